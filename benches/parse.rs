@@ -0,0 +1,44 @@
+//! Benchmark: sequential vs parallel parsing of a 10,000-line Markdown document.
+//!
+//! Run with `cargo bench --bench parse`. Uses a plain `Instant` timer so no extra dev-dependency is needed.
+
+use chrono::NaiveDate;
+use og::markdown_parser::{parse_markdown_document_to_tasks, parse_markdown_document_to_tasks_parallel};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const LINES: usize = 10_000;
+const ITERATIONS: u32 = 1;
+
+fn document() -> String {
+    let mut md_doc = String::new();
+    for i in 0..LINES {
+        if i % 3 == 0 {
+            md_doc.push_str(&format!("- [ ] [[Task {}]] created:2024-01-01\n", i));
+        } else {
+            md_doc.push_str(&format!("    - [x] (A) [[Task {}]] id:{} due:2024-02-01\n", i, i + 50_000));
+        }
+    }
+    md_doc
+}
+
+fn time(label: &str, mut run: impl FnMut()) {
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        run();
+        total += start.elapsed();
+    }
+    println!("{:<12} {:>10.2?} / iteration ({} lines)", label, total / ITERATIONS, LINES);
+}
+
+fn main() {
+    let md_doc = document();
+    let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    time("sequential", || {
+        black_box(parse_markdown_document_to_tasks(black_box(&md_doc), default_date).unwrap());
+    });
+    time("parallel", || {
+        black_box(parse_markdown_document_to_tasks_parallel(black_box(&md_doc), default_date).unwrap());
+    });
+}
@@ -1,10 +1,14 @@
+use crate::recurrence;
+use crate::error::OgError;
 use crate::task_model::Task;
 use chrono::{Local, NaiveDate};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 
 // D.4. 差分マージロジックの基本方針
 // 1. タスクの同定: id フィールドを使用。
-// 2. フィールドの更新: Markdownで編集可能なフィールドはJSONに値を反映。updated 日はツール処理日で自動更新。
+// 2. フィールドの更新: Markdownで編集可能なフィールドはJSONに値を反映。内容が変わったタスクの updated 日はツール処理日で自動更新。
 // 3. 新規タスクの追加: Markdownでの新規追加は、JSON仕様に従い id, created, display_order を採番・設定してJSONに追加。
 // 4. タスクの削除: Markdownからタスク行が削除されたらJSONからも対応タスクを削除（確認なし）。
 // 5. タスクの順序変更: Markdownの行順変更を検出し、JSON側の全タスクの display_order を再採番して反映。
@@ -14,15 +18,171 @@ use std::collections::{HashMap, HashSet};
 //      (実装: パーサーは属性がない場合にNoneを設定し、apply_changesがこれを反映してキーごと削除)
 //    - キー必須（値null可）属性 (due等) がMDからまるごと削除されたら、JSONではキーを残し値をnullに。
 //      (実装: パーサーは属性がない場合にNoneを設定し、apply_changesがこれを反映してnull値を設定)
+// 8. 競合: JSON 側の updated が Markdown に書かれた updated より新しいタスクは、Markdown を書き出した後に
+//    JSON も更新されている。食い違うフィールドを ConflictStrategy に従って解決し、FieldConflict として報告する。
+
+// og apply --conflict-strategy: 競合したタスクのフィールドをどちらから取るか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictStrategy {
+    #[default]
+    Theirs, // Markdown の値 (従来どおり)
+    Ours,   // JSON の値
+    Merge,  // 片方だけに値があるフィールドはその値。両方に値があって食い違うフィールドはエラー (上書きしない)
+}
+
+impl FromStr for ConflictStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "theirs" => Ok(ConflictStrategy::Theirs),
+            "ours" => Ok(ConflictStrategy::Ours),
+            "merge" => Ok(ConflictStrategy::Merge),
+            _ => Err(format!("Invalid conflict strategy '{}' (expected merge, theirs or ours)", s)),
+        }
+    }
+}
+
+// 競合したタスクの 1 フィールド分の報告
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldConflict {
+    pub task_id: i64,
+    pub field: &'static str,
+    pub json_updated: NaiveDate,
+    pub markdown_updated: NaiveDate,
+    pub kept_markdown: bool,
+    // merge で解決できなかった (両方に値があり食い違う) フィールド。apply はエラーになる
+    pub unresolved: bool,
+}
+
+impl fmt::Display for FieldConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Conflict: task id:{} field '{}' (JSON updated {}, Markdown {}): kept {} value",
+            self.task_id,
+            self.field,
+            self.json_updated,
+            self.markdown_updated,
+            if self.kept_markdown { "Markdown" } else { "JSON" }
+        )
+    }
+}
+
+// Markdown で編集できるフィールド (競合の判定と解決の単位)
+const EDITABLE_FIELDS: [&str; 13] = [
+    "name", "status", "priority", "due", "completed", "notes", "project", "contexts", "tags", "tags_kv", "depends", "progress", "estimate_minutes",
+];
+
+fn field_equal(a: &Task, b: &Task, field: &str) -> bool {
+    match field {
+        "name" => a.name == b.name,
+        "status" => a.status == b.status,
+        "priority" => a.priority == b.priority,
+        "due" => a.due == b.due,
+        "completed" => a.completed == b.completed,
+        "notes" => a.notes == b.notes,
+        "project" => a.project == b.project,
+        "contexts" => a.contexts == b.contexts,
+        "tags" => a.tags == b.tags,
+        "tags_kv" => a.tags_kv == b.tags_kv,
+        "depends" => a.depends == b.depends,
+        "progress" => a.progress == b.progress,
+        "estimate_minutes" => a.estimate_minutes == b.estimate_minutes,
+        _ => true,
+    }
+}
+
+// そのフィールドが空 (Markdown では属性なし) か。名前・ステータス・優先度は常に値がある
+fn field_is_empty(task: &Task, field: &str) -> bool {
+    match field {
+        "due" => task.due.is_none(),
+        "completed" => task.completed.is_none(),
+        "notes" => task.notes.is_none(),
+        "project" => task.project.is_none(),
+        "contexts" => task.contexts.as_ref().is_none_or(|v| v.is_empty()),
+        "tags" => task.tags.as_ref().is_none_or(|v| v.is_empty()),
+        "tags_kv" => task.tags_kv.as_ref().is_none_or(|v| v.is_empty()),
+        "depends" => task.depends.as_ref().is_none_or(|v| v.is_empty()),
+        "progress" => task.progress.is_none(),
+        "estimate_minutes" => task.estimate_minutes.is_none(),
+        _ => false,
+    }
+}
+
+// merge で自動的に解決できない競合: 両方に値があって食い違う (共通の基準の版がないので、どちらの変更か分からない)
+fn is_unresolvable(existing: &Task, md: &Task, field: &str) -> bool {
+    !field_equal(existing, md, field) && !field_is_empty(existing, field) && !field_is_empty(md, field)
+}
+
+fn copy_field(target: &mut Task, source: &Task, field: &str) {
+    match field {
+        "name" => target.name = source.name.clone(),
+        "status" => target.status = source.status.clone(),
+        "priority" => target.priority = source.priority.clone(),
+        "due" => target.due = source.due,
+        "completed" => target.completed = source.completed,
+        "notes" => target.notes = source.notes.clone(),
+        "project" => target.project = source.project.clone(),
+        "contexts" => target.contexts = source.contexts.clone(),
+        "tags" => target.tags = source.tags.clone(),
+        "tags_kv" => target.tags_kv = source.tags_kv.clone(),
+        "depends" => target.depends = source.depends.clone(),
+        "progress" => target.progress = source.progress,
+        "estimate_minutes" => target.estimate_minutes = source.estimate_minutes,
+        _ => {}
+    }
+}
+
+// JSON 側が Markdown の updated より後に更新されているか (どちらかの updated がなければ判定しない)
+fn is_conflict(existing: &Task, md: &Task) -> bool {
+    matches!((existing.updated, md.updated), (Some(json_updated), Some(md_updated)) if json_updated > md_updated)
+}
+
+/// 競合したタスクの Markdown 側の値を戦略に従って解決し、Markdown から反映する値のタスクを返す。
+/// 基準となる共通の版は保存していないため、merge では片方だけに値があるフィールドをその側だけの変更とみなす。
+/// 両方に値があって食い違うフィールドは JSON の値のまま残す (apply_changes_with_strategy がエラーにする)。
+/// サブタスク・display_order・extra は Markdown 側のまま (サブタスクはそれぞれ個別に判定される)
+pub fn merge_fields(existing: &Task, md: &Task, strategy: ConflictStrategy) -> Task {
+    let mut merged = md.clone();
+    for field in EDITABLE_FIELDS {
+        let keep_json = match strategy {
+            ConflictStrategy::Theirs => false,
+            ConflictStrategy::Ours => true,
+            ConflictStrategy::Merge => field_is_empty(md, field) || is_unresolvable(existing, md, field),
+        };
+        if keep_json {
+            copy_field(&mut merged, existing, field);
+        }
+    }
+    if strategy == ConflictStrategy::Ours {
+        merged.extra = None;
+    }
+    merged
+}
 
 pub fn apply_changes(
+    existing_tasks_vec: Vec<Task>,
+    markdown_tasks_vec: Vec<Task>,
+    default_created_date: NaiveDate,
+) -> Result<Vec<Task>, OgError> {
+    apply_changes_with_strategy(existing_tasks_vec, markdown_tasks_vec, default_created_date, ConflictStrategy::Theirs).map(|(tasks, _)| tasks)
+}
+
+// apply_changes に競合の解決方法を指定する版。解決したフィールドの報告も返す
+pub fn apply_changes_with_strategy(
     existing_tasks_vec: Vec<Task>,
     markdown_tasks_vec: Vec<Task>,
     _default_created_date: NaiveDate, // May be needed for new tasks if not set by parser
-) -> Result<Vec<Task>, String> {
+    strategy: ConflictStrategy,
+) -> Result<(Vec<Task>, Vec<FieldConflict>), OgError> {
     let mut final_tasks: Vec<Task> = Vec::new();
+    let mut conflicts: Vec<FieldConflict> = Vec::new();
     let today = Local::now().date_naive();
 
+    // 今回の apply より前に完了していたタスク (サブタスクを含む)。繰り返しの次回分はこれ以外の完了タスクから生成する
+    let done_before: HashSet<i64> = crate::task_model::flatten_all(&existing_tasks_vec).filter(|t| is_done(&t.status)).map(|t| t.id).collect();
+
     // 1. Index existing tasks by ID for quick lookup and to track seen IDs from markdown
     let mut existing_tasks_map: HashMap<i64, Task> = existing_tasks_vec
         .into_iter()
@@ -31,54 +191,19 @@ pub fn apply_changes(
 
     // Keep track of IDs present in the new markdown input
     let mut markdown_task_ids: HashSet<i64> = HashSet::new();
-    let mut next_display_order = 1;
 
     // 2. Process tasks from Markdown input
     // This loop handles:
     // - Updates to existing tasks (D.4.2)
     // - Addition of new tasks (D.4.3)
     // - Order of tasks as they appear in Markdown (D.4.5)
-    for mut md_task in markdown_tasks_vec {
+    for (display_order, mut md_task) in (1..).zip(markdown_tasks_vec) {
         markdown_task_ids.insert(md_task.id);
-        md_task.display_order = next_display_order;
-        next_display_order += 1;
+        md_task.display_order = display_order;
 
         if let Some(mut existing_task) = existing_tasks_map.remove(&md_task.id) {
             // Task exists, update it based on Markdown content
-            // D.4.2: Update fields editable in Markdown
-            // D.4.2: Update editable fields from markdown
-            existing_task.name = md_task.name;
-            existing_task.status = md_task.status;
-            existing_task.priority = md_task.priority;
-            
-            // D.4.7: Attribute deletion - Required keys with nullable values
-            // When the key is required but the value can be null (like 'due'), 
-            // if it's deleted from MD, we set it to None in the JSON
-            existing_task.due = md_task.due;  // Will be None if not in MD
-            existing_task.completed = md_task.completed;  // Will be None if not in MD
-            
-            // created date should not change for existing tasks
-            
-            // D.4.7: Attribute deletion - Optional keys
-            // When optional keys (project, contexts, tags, notes) are deleted from MD,
-            // we remove them completely from the JSON (they will be None from the parser)
-            existing_task.notes = md_task.notes;  // Will be None if not in MD
-            existing_task.project = md_task.project;  // Will be None if not in MD
-            existing_task.contexts = md_task.contexts;  // Will be None if not in MD
-            existing_task.tags = md_task.tags;  // Will be None if not in MD
-            
-            // subtasks from markdown should overwrite existing subtasks
-            // A more sophisticated subtask merge might be needed in the future
-            existing_task.subtasks = md_task.subtasks;
-
-            // D.4.2: updated 日はツール処理日で自動更新
-            existing_task.updated = Some(today);
-            
-            // D.4.5: display_order is set from md_task
-            existing_task.display_order = md_task.display_order;
-
-            // D.4.6: JSON固有情報の保護 (extra field) - already part of existing_task, so it's preserved unless overwritten by a more complex rule later.
-
+            update_task_from_markdown(&mut existing_task, md_task, today, strategy, &mut conflicts);
             final_tasks.push(existing_task);
         } else {
             // New task from Markdown (D.4.3)
@@ -110,8 +235,176 @@ pub fn apply_changes(
         task.display_order = (index + 1) as i64;
     }
 
+    // 3. 繰り返しタスク (サブタスクを含む) の次回分を生成する。競合の解決後のステータスで判定するので、
+    // ours で JSON の未完了が残ったタスクからは生成しない
+    let mut next_id = crate::task_model::flatten_all(&final_tasks).map(|t| t.id).max().unwrap_or(0) + 1;
+    spawn_next_occurrences(&mut final_tasks, &done_before, today, &mut next_id);
+
+    // マージで生じた空のサブタスクリストは None にそろえる
+    crate::task_model::prune_empty_subtasks(&mut final_tasks);
 
-    Ok(final_tasks)
+    // merge で解決できない競合があれば、どちらの値も捨てずにエラーにする
+    let unresolved: Vec<String> = conflicts.iter().filter(|c| c.unresolved).map(|c| format!("task id:{} field '{}'", c.task_id, c.field)).collect();
+    if !unresolved.is_empty() {
+        return Err(OgError::Validation(format!(
+            "changed in both the JSON and the Markdown, cannot merge: {}; rerun with --conflict-strategy theirs or ours",
+            unresolved.join(", ")
+        )));
+    }
+
+    Ok((final_tasks, conflicts))
+}
+
+// このリストで今回完了になった繰り返しタスクの次回分を同じリストの末尾に追加し、サブタスクにも再帰する。
+// 完了したタスクは repeat を外して残す (次の apply で再び生成しないように)。次回分はサブタスクを持たない
+fn spawn_next_occurrences(tasks: &mut Vec<Task>, done_before: &HashSet<i64>, today: NaiveDate, next_id: &mut i64) {
+    let mut spawned = Vec::new();
+    for task in tasks.iter_mut() {
+        if !is_done(&task.status) || done_before.contains(&task.id) {
+            continue;
+        }
+        let Some(next_due) = recurrence::next_occurrence(task, task.completed.unwrap_or(today)) else { continue };
+        let mut next_task = task.clone();
+        task.repeat = None;
+        next_task.id = *next_id;
+        *next_id += 1;
+        next_task.status = "open".to_string();
+        next_task.due = Some(next_due);
+        next_task.created = today;
+        next_task.updated = Some(today);
+        next_task.completed = None;
+        next_task.subtasks = None;
+        spawned.push(next_task);
+    }
+    for mut next_task in spawned {
+        next_task.display_order = tasks.len() as i64 + 1;
+        tasks.push(next_task);
+    }
+    for task in tasks.iter_mut() {
+        if let Some(subtasks) = &mut task.subtasks {
+            spawn_next_occurrences(subtasks, done_before, today, next_id);
+        }
+    }
+}
+
+/// サブタスクのリストを id で突き合わせてマージする (トップレベルと同じ方針)。
+/// 一致する id は更新、新しい id は追加、Markdown にない id は削除。既存側の extra は保持する。
+pub fn merge_subtask_lists(existing: Vec<Task>, updated: Vec<Task>, today: NaiveDate) -> Vec<Task> {
+    merge_subtask_lists_with(existing, updated, today, ConflictStrategy::Theirs, &mut Vec::new())
+}
+
+fn merge_subtask_lists_with(existing: Vec<Task>, updated: Vec<Task>, today: NaiveDate, strategy: ConflictStrategy, conflicts: &mut Vec<FieldConflict>) -> Vec<Task> {
+    let mut existing_map: HashMap<i64, Task> = existing.into_iter().map(|t| (t.id, t)).collect();
+    updated
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut md_task)| {
+            md_task.display_order = (index + 1) as i64;
+            match existing_map.remove(&md_task.id) {
+                Some(mut existing_task) => {
+                    update_task_from_markdown(&mut existing_task, md_task, today, strategy, conflicts);
+                    existing_task
+                }
+                None => {
+                    md_task.updated = Some(today);
+                    md_task
+                }
+            }
+        })
+        .collect()
+}
+
+// 既存タスクに Markdown 側の編集可能フィールドを反映する。サブタスクは再帰的にマージ
+fn update_task_from_markdown(existing_task: &mut Task, mut md_task: Task, today: NaiveDate, strategy: ConflictStrategy, conflicts: &mut Vec<FieldConflict>) {
+    let before_hash = crate::task_model::content_hash(existing_task);
+    let was_done = is_done(&existing_task.status);
+
+    // D.4.8: JSON 側の方が新しければ、食い違うフィールドを戦略に従って解決してから反映する
+    if is_conflict(existing_task, &md_task) {
+        let merged = merge_fields(existing_task, &md_task, strategy);
+        for field in EDITABLE_FIELDS.into_iter().filter(|field| !field_equal(existing_task, &md_task, field)) {
+            conflicts.push(FieldConflict {
+                task_id: existing_task.id,
+                field,
+                json_updated: existing_task.updated.unwrap(),
+                markdown_updated: md_task.updated.unwrap(),
+                kept_markdown: field_equal(&merged, &md_task, field),
+                unresolved: strategy == ConflictStrategy::Merge && is_unresolvable(existing_task, &md_task, field),
+            });
+        }
+        md_task = merged;
+    }
+
+    // D.4.2: Update editable fields from markdown
+    existing_task.name = md_task.name;
+    existing_task.status = md_task.status;
+    existing_task.priority = md_task.priority;
+
+    // D.4.7: Attribute deletion - Required keys with nullable values
+    // When the key is required but the value can be null (like 'due'),
+    // if it's deleted from MD, we set it to None in the JSON
+    existing_task.due = md_task.due;  // Will be None if not in MD
+    existing_task.completed = md_task.completed;  // Will be None if not in MD
+
+    // D.4.2: done になったのに completed が書かれていなければ今日を入れ、done でなくなったら消す (updated と同じく自動)
+    if is_done(&existing_task.status) {
+        if !was_done && existing_task.completed.is_none() {
+            existing_task.completed = Some(today);
+        }
+    } else if was_done {
+        existing_task.completed = None;
+    }
+
+    // created date should not change for existing tasks
+
+    // D.4.7: Attribute deletion - Optional keys
+    // When optional keys (project, contexts, tags, notes) are deleted from MD,
+    // we remove them completely from the JSON (they will be None from the parser)
+    existing_task.notes = md_task.notes;  // Will be None if not in MD
+    existing_task.project = md_task.project;  // Will be None if not in MD
+    existing_task.contexts = md_task.contexts;  // Will be None if not in MD
+    existing_task.tags = md_task.tags;  // Will be None if not in MD
+    existing_task.tags_kv = md_task.tags_kv;  // Will be None if not in MD
+    existing_task.depends = md_task.depends;  // Will be None if not in MD
+    existing_task.progress = md_task.progress;  // Will be None if not in MD
+    existing_task.estimate_minutes = md_task.estimate_minutes;  // Will be None if not in MD
+    existing_task.repeat = md_task.repeat;  // Will be None if not in MD
+
+    // Markdown の未知の属性 (sprint:12 など) は extra に反映する。JSON 側の値と同じ表記なら元の型 (数値など) を保つ
+    if let Some(md_extra) = md_task.extra {
+        let extra = existing_task.extra.get_or_insert_with(HashMap::new);
+        for (key, value) in md_extra {
+            let unchanged = match extra.get(&key) {
+                Some(current) => {
+                    let written = crate::markdown_formatter::extra_attribute_value(&key, current);
+                    written.is_some() && written == crate::markdown_formatter::extra_attribute_value(&key, &value)
+                }
+                None => false,
+            };
+            if !unchanged {
+                extra.insert(key, value);
+            }
+        }
+    }
+
+    // サブタスクも id でマージし、既存サブタスクの extra を失わないようにする
+    existing_task.subtasks = md_task.subtasks.map(|updated| {
+        merge_subtask_lists_with(existing_task.subtasks.take().unwrap_or_default(), updated, today, strategy, conflicts)
+    });
+
+    // D.4.2: updated 日はツール処理日で自動更新。内容が変わっていなければ (並び順の変更だけなら) 据え置く
+    if crate::task_model::content_hash(existing_task) != before_hash {
+        existing_task.updated = Some(today);
+    }
+
+    // D.4.5: display_order is set from md_task
+    existing_task.display_order = md_task.display_order;
+
+    // D.4.6: JSON固有情報の保護 (extra field, unknown_fields) - already part of existing_task, so it's preserved.
+}
+
+fn is_done(status: &str) -> bool {
+    status.eq_ignore_ascii_case("done")
 }
 
 #[cfg(test)]
@@ -135,9 +428,14 @@ mod tests {
             contexts: None,
             notes: None,
             tags: None,
+            tags_kv: None,
             subtasks: None,
             extra: None,
             repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
         }
     }
 
@@ -241,6 +539,40 @@ mod tests {
         assert_eq!(result[2].updated, Some(today));
     }
     
+    #[test]
+    fn test_apply_prunes_empty_subtask_lists() {
+        let today = Local::now().date_naive();
+        let mut md_task = create_sample_task(1, "Parent", 1, None);
+        md_task.subtasks = Some(vec![]);
+        let result = apply_changes(vec![], vec![md_task], today).unwrap();
+        assert!(result[0].subtasks.is_none());
+    }
+
+    #[test]
+    fn test_apply_merges_custom_attributes_into_extra() {
+        let today = Local::now().date_naive();
+        let mut existing = create_sample_task(1, "Plan", 1, None);
+        existing.extra = Some(HashMap::from([
+            ("points".to_string(), serde_json::json!(3)),
+            ("ticket".to_string(), serde_json::json!("12")),
+            ("meta".to_string(), serde_json::json!({"a": 1})),
+        ]));
+        // パーサーは ticket:12 を数値で返すが、JSON 側と同じ表記なので文字列のまま残す
+        let mut md_task = create_sample_task(1, "Plan", 1, None);
+        md_task.extra = Some(HashMap::from([
+            ("points".to_string(), serde_json::json!(3)),
+            ("ticket".to_string(), serde_json::json!(12)),
+            ("sprint".to_string(), serde_json::json!(12)),
+        ]));
+
+        let result = apply_changes(vec![existing], vec![md_task], today).unwrap();
+        let extra = result[0].extra.as_ref().unwrap();
+        assert_eq!(extra["points"], serde_json::json!(3));
+        assert_eq!(extra["ticket"], serde_json::json!("12"));
+        assert_eq!(extra["sprint"], serde_json::json!(12));
+        assert_eq!(extra["meta"], serde_json::json!({"a": 1}));
+    }
+
     #[test]
     fn test_preserve_extra_field_on_update() {
         let mut task1_existing = create_sample_task(1, "Task 1 Old", 1, None);
@@ -259,5 +591,229 @@ mod tests {
         assert!(result[0].extra.is_some());
         assert_eq!(result[0].extra.as_ref().unwrap().get("custom_key").unwrap(), &serde_json::json!("custom_value"));
         assert_eq!(result[0].updated, Some(today));
+
+        // サブタスク (孫タスクを含む) の extra も親の更新で失われない
+        let mut grandchild = create_sample_task(3, "Grandchild", 1, None);
+        grandchild.extra = Some(HashMap::from([("gkey".to_string(), serde_json::json!(3))]));
+        let mut child = create_sample_task(2, "Child", 1, None);
+        child.extra = Some(HashMap::from([("ckey".to_string(), serde_json::json!(2))]));
+        child.subtasks = Some(vec![grandchild]);
+        let mut parent_existing = create_sample_task(1, "Parent", 1, None);
+        parent_existing.subtasks = Some(vec![child, create_sample_task(4, "Removed", 2, None)]);
+
+        let md_grandchild = create_sample_task(3, "Grandchild New", 1, None);
+        let mut md_child = create_sample_task(2, "Child New", 1, None);
+        md_child.subtasks = Some(vec![md_grandchild]);
+        let mut md_parent = create_sample_task(1, "Parent New", 1, None);
+        md_parent.subtasks = Some(vec![create_sample_task(5, "Added", 1, None), md_child]);
+
+        let result = apply_changes(vec![parent_existing], vec![md_parent], today).unwrap();
+        let subtasks = result[0].subtasks.as_ref().unwrap();
+        assert_eq!(subtasks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![5, 2]);
+        assert_eq!(subtasks[0].updated, Some(today));
+        assert_eq!(subtasks[1].name, "Child New");
+        assert_eq!(subtasks[1].display_order, 2);
+        assert_eq!(subtasks[1].extra.as_ref().unwrap().get("ckey").unwrap(), &serde_json::json!(2));
+        let grandchildren = subtasks[1].subtasks.as_ref().unwrap();
+        assert_eq!(grandchildren[0].name, "Grandchild New");
+        assert_eq!(grandchildren[0].extra.as_ref().unwrap().get("gkey").unwrap(), &serde_json::json!(3));
+    }
+
+    #[test]
+    fn test_completed_repeating_task_spawns_next_occurrence() {
+        use crate::task_model::{Frequency, RepeatInfo};
+
+        let mut existing = create_sample_task(1, "Pay rent", 1, None);
+        existing.due = NaiveDate::from_ymd_opt(2023, 1, 31);
+        existing.repeat = Some(RepeatInfo { frequency: Some(Frequency::LastDayOfMonth) });
+
+        let mut md_task = existing.clone();
+        md_task.status = "done".to_string();
+
+        let today = Local::now().date_naive();
+        let result = apply_changes(vec![existing], vec![md_task], today).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].id, 1);
+        assert_eq!(result[0].status, "done");
+        assert!(result[0].repeat.is_none());
+        assert_eq!(result[1].id, 2);
+        assert_eq!(result[1].status, "open");
+        assert_eq!(result[1].due, NaiveDate::from_ymd_opt(2023, 2, 28));
+        assert_eq!(result[1].repeat.as_ref().unwrap().frequency, Some(Frequency::LastDayOfMonth));
+        assert_eq!(result[1].display_order, 2);
+    }
+
+    #[test]
+    fn test_completed_repeating_subtask_spawns_next_occurrence() {
+        use crate::task_model::{Frequency, RepeatInfo};
+
+        let mut child = create_sample_task(2, "Water plants", 1, None);
+        child.due = NaiveDate::from_ymd_opt(2024, 5, 3);
+        child.repeat = Some(RepeatInfo { frequency: Some(Frequency::Weekly) });
+        let mut existing = create_sample_task(1, "Home", 1, None);
+        existing.subtasks = Some(vec![child.clone(), create_sample_task(3, "Vacuum", 2, None)]);
+
+        let mut md_task = existing.clone();
+        md_task.subtasks.as_mut().unwrap()[0].status = "done".to_string();
+
+        let today = Local::now().date_naive();
+        let result = apply_changes(vec![existing], vec![md_task], today).unwrap();
+
+        // 次回分は同じ親の末尾に、ツリー全体で未使用の id で追加される
+        assert_eq!(result.len(), 1);
+        let subtasks = result[0].subtasks.as_ref().unwrap();
+        assert_eq!(subtasks.iter().map(|t| (t.id, t.status.as_str(), t.display_order)).collect::<Vec<_>>(), vec![(2, "done", 1), (3, "PENDING", 2), (4, "open", 3)]);
+        assert!(subtasks[0].repeat.is_none());
+        assert_eq!(subtasks[2].due, NaiveDate::from_ymd_opt(2024, 5, 10));
+
+        // すでに完了していたサブタスクからは再び生成しない
+        let again = apply_changes(result.clone(), result, today).unwrap();
+        assert_eq!(again[0].subtasks.as_ref().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_repeat_spawn_follows_conflict_strategy() {
+        use crate::task_model::{Frequency, RepeatInfo};
+
+        // JSON 側が Markdown を書き出した後に更新されていて、Markdown では完了にした
+        let today = NaiveDate::from_ymd_opt(2024, 5, 4).unwrap();
+        let mut existing = create_sample_task(1, "Water plants", 1, None);
+        existing.due = NaiveDate::from_ymd_opt(2024, 5, 3);
+        existing.repeat = Some(RepeatInfo { frequency: Some(Frequency::Weekly) });
+        existing.updated = NaiveDate::from_ymd_opt(2024, 5, 3);
+        let mut md_task = existing.clone();
+        md_task.status = "done".to_string();
+        md_task.updated = NaiveDate::from_ymd_opt(2024, 5, 1);
+
+        // ours では JSON の未完了が残るので次回分は作らない
+        let (result, _) = apply_changes_with_strategy(vec![existing.clone()], vec![md_task.clone()], today, ConflictStrategy::Ours).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].status, existing.status);
+        assert!(result[0].repeat.is_some());
+
+        let (result, _) = apply_changes_with_strategy(vec![existing], vec![md_task], today, ConflictStrategy::Theirs).unwrap();
+        assert_eq!(result.iter().map(|t| t.status.as_str()).collect::<Vec<_>>(), vec!["done", "open"]);
+    }
+
+    #[test]
+    fn test_repeat_added_or_removed_in_markdown_is_applied() {
+        use crate::task_model::{Frequency, RepeatInfo};
+
+        let today = Local::now().date_naive();
+        let mut md_task = create_sample_task(1, "Water plants", 1, None);
+        md_task.repeat = Some(RepeatInfo { frequency: Some(Frequency::Weekly) });
+        let result = apply_changes(vec![create_sample_task(1, "Water plants", 1, None)], vec![md_task], today).unwrap();
+        assert_eq!(result[0].repeat.as_ref().unwrap().frequency, Some(Frequency::Weekly));
+
+        let result = apply_changes(result, vec![create_sample_task(1, "Water plants", 1, None)], today).unwrap();
+        assert!(result[0].repeat.is_none());
+    }
+
+    #[test]
+    fn test_unchanged_task_keeps_updated_date() {
+        let today = Local::now().date_naive();
+        let last_update = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let mut existing = vec![create_sample_task(1, "Same", 1, None), create_sample_task(2, "Renamed", 2, None)];
+        for task in &mut existing {
+            task.updated = Some(last_update);
+        }
+        // 1 は並び順が変わるだけ、2 は名前も変わる
+        let md_tasks = vec![create_sample_task(2, "Renamed!", 1, None), create_sample_task(1, "Same", 2, None)];
+
+        let result = apply_changes(existing, md_tasks, today).unwrap();
+        assert_eq!((result[0].id, result[0].updated), (2, Some(today)));
+        assert_eq!((result[1].id, result[1].updated), (1, Some(last_update)));
+        assert_eq!(result[1].display_order, 2);
+    }
+
+    // JSON 側は 5/3 に due と project を更新、Markdown (5/1 時点) は名前を変えて project を消した
+    fn conflicting_pair() -> (Task, Task) {
+        let mut existing = create_sample_task(1, "Report", 1, Some("work"));
+        existing.due = NaiveDate::from_ymd_opt(2024, 5, 10);
+        existing.updated = NaiveDate::from_ymd_opt(2024, 5, 3);
+        let mut md_task = create_sample_task(1, "Quarterly report", 1, None);
+        md_task.due = NaiveDate::from_ymd_opt(2024, 5, 7);
+        md_task.updated = NaiveDate::from_ymd_opt(2024, 5, 1);
+        (existing, md_task)
+    }
+
+    #[test]
+    fn test_merge_fields_strategies() {
+        let (existing, md_task) = conflicting_pair();
+
+        let theirs = merge_fields(&existing, &md_task, ConflictStrategy::Theirs);
+        assert_eq!((theirs.name.as_str(), theirs.due, theirs.project.as_deref()), ("Quarterly report", NaiveDate::from_ymd_opt(2024, 5, 7), None));
+
+        let ours = merge_fields(&existing, &md_task, ConflictStrategy::Ours);
+        assert_eq!((ours.name.as_str(), ours.due, ours.project.as_deref()), ("Report", NaiveDate::from_ymd_opt(2024, 5, 10), Some("work")));
+
+        // Markdown で空の project は JSON、両方に値がある name と due は上書きせず JSON のまま
+        let merged = merge_fields(&existing, &md_task, ConflictStrategy::Merge);
+        assert_eq!((merged.name.as_str(), merged.due, merged.project.as_deref()), ("Report", NaiveDate::from_ymd_opt(2024, 5, 10), Some("work")));
+
+        // JSON で空のフィールドに Markdown で値を付けたなら Markdown
+        let mut md_with_notes = existing.clone();
+        md_with_notes.notes = Some("call first".to_string());
+        let merged = merge_fields(&existing, &md_with_notes, ConflictStrategy::Merge);
+        assert_eq!(merged.notes.as_deref(), Some("call first"));
+
+        assert_eq!("MERGE".parse::<ConflictStrategy>(), Ok(ConflictStrategy::Merge));
+        assert!("mine".parse::<ConflictStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_apply_reports_field_conflicts() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 4).unwrap();
+        let (existing, md_task) = conflicting_pair();
+        let (result, conflicts) = apply_changes_with_strategy(vec![existing.clone()], vec![md_task.clone()], today, ConflictStrategy::Theirs).unwrap();
+        assert_eq!(result[0].project, None);
+        assert_eq!(
+            conflicts.iter().map(|c| (c.field, c.kept_markdown)).collect::<Vec<_>>(),
+            vec![("name", true), ("due", true), ("project", true)]
+        );
+
+        // merge: Markdown で消した project だけなら JSON の値を残して解決できる
+        let mut md_same_name = md_task.clone();
+        md_same_name.name = existing.name.clone();
+        md_same_name.due = existing.due;
+        let (result, conflicts) = apply_changes_with_strategy(vec![existing.clone()], vec![md_same_name], today, ConflictStrategy::Merge).unwrap();
+        assert_eq!(result[0].project.as_deref(), Some("work"));
+        assert_eq!(conflicts[0].to_string(), "Conflict: task id:1 field 'project' (JSON updated 2024-05-03, Markdown 2024-05-01): kept JSON value");
+
+        // merge: 両方で食い違う name と due は上書きせずエラーにする
+        let err = apply_changes_with_strategy(vec![existing.clone()], vec![md_task.clone()], today, ConflictStrategy::Merge).unwrap_err().to_string();
+        assert!(err.contains("task id:1 field 'name', task id:1 field 'due'"), "{}", err);
+
+        // Markdown の方が新しければ競合ではない
+        let mut newer_md = md_task;
+        newer_md.updated = NaiveDate::from_ymd_opt(2024, 5, 3);
+        let (result, conflicts) = apply_changes_with_strategy(vec![existing], vec![newer_md], today, ConflictStrategy::Ours).unwrap();
+        assert!(conflicts.is_empty());
+        assert_eq!(result[0].name, "Quarterly report");
+    }
+
+    #[test]
+    fn test_completed_is_stamped_on_done_and_cleared_on_reopen() {
+        let today = Local::now().date_naive();
+        let mut md_done = create_sample_task(1, "Task", 1, None);
+        md_done.status = "done".to_string();
+        let result = apply_changes(vec![create_sample_task(1, "Task", 1, None)], vec![md_done.clone()], today).unwrap();
+        assert_eq!(result[0].completed, Some(today));
+
+        // Markdown に completed があればその日付を使う
+        let explicit = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        md_done.completed = Some(explicit);
+        let result = apply_changes(vec![create_sample_task(1, "Task", 1, None)], vec![md_done], today).unwrap();
+        assert_eq!(result[0].completed, Some(explicit));
+
+        // done から戻すと completed は消える (Markdown に残っていても)
+        let mut existing_done = result[0].clone();
+        existing_done.updated = None;
+        let mut md_reopened = create_sample_task(1, "Task", 1, None);
+        md_reopened.completed = Some(explicit);
+        let result = apply_changes(vec![existing_done], vec![md_reopened], today).unwrap();
+        assert_eq!(result[0].status, "PENDING");
+        assert_eq!(result[0].completed, None);
     }
 }
@@ -1,24 +1,228 @@
 use chrono::{Datelike, Local, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
 use google_calendar3::{CalendarHub, hyper, hyper_rustls};
-use serde::Deserialize;
+use og::task_model::Task;
+use og::color::{colorize, AnsiColor};
+use og::OgError;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
-use yasumi;
+use std::path::{Path, PathBuf};
 use yup_oauth2::{
+    authenticator::DefaultAuthenticator,
     ApplicationSecret, InstalledFlowAuthenticator, InstalledFlowReturnMethod,
 };
 
-#[derive(Debug, Clone)]
+const CALENDAR_READONLY_SCOPE: &str = "https://www.googleapis.com/auth/calendar.readonly";
+
+#[derive(Debug)]
+pub enum CalendarError {
+    /// No credentials file at the given path
+    CredentialsNotFound(PathBuf),
+    /// The credentials file exists but cannot be read or is not an OAuth client file
+    InvalidCredentials(String),
+    TokenExpired,
+    /// The token cannot be obtained or stored, e.g. the OAuth flow failed or the account name is invalid
+    TokenError(String),
+    AccessDenied,
+    NetworkError(String),
+    ApiError(String),
+    /// Data other than API responses (e.g. holiday lists) could not be parsed
+    ParseError(String),
+    IoError(std::io::Error),
+}
+
+impl fmt::Display for CalendarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalendarError::CredentialsNotFound(path) => write!(
+                f,
+                "Google Calendar credentials not found at {}. Download an OAuth client ID (Desktop app) from Google Cloud Console > APIs & Services > Credentials and save it there, or set OG_CREDENTIALS_PATH. Use --no-auth to run without a calendar.",
+                path.display()
+            ),
+            CalendarError::InvalidCredentials(msg) => write!(f, "Invalid credentials file: {}", msg),
+            CalendarError::TokenExpired => write!(f, "Your Google token has expired. Please run 'og cal' again to re-authenticate."),
+            CalendarError::AccessDenied => write!(f, "Google Calendar access denied. The OAuth application may not be verified. Please check with the app developer or use your own Google Cloud credentials."),
+            CalendarError::NetworkError(msg) => write!(f, "Network error: {}", msg),
+            CalendarError::ApiError(msg) => write!(f, "Google Calendar API error: {}", msg),
+            CalendarError::TokenError(msg) => write!(f, "Google token error: {}", msg),
+            CalendarError::ParseError(msg) => write!(f, "Failed to parse calendar data: {}", msg),
+            CalendarError::IoError(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl Error for CalendarError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CalendarError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CalendarError {
+    fn from(e: std::io::Error) -> Self {
+        CalendarError::IoError(e)
+    }
+}
+
+// Errors of the non-API steps (holiday lists for --next) while fetching events
+impl From<OgError> for CalendarError {
+    fn from(e: OgError) -> Self {
+        match e {
+            OgError::Io(e) => CalendarError::IoError(e),
+            OgError::Parse { .. } | OgError::Json(_) => CalendarError::ParseError(e.to_string()),
+            OgError::Auth(msg) => CalendarError::TokenError(msg),
+            OgError::Calendar(msg) => CalendarError::ApiError(msg),
+            OgError::Validation(_) | OgError::Import(_) => CalendarError::ApiError(e.to_string()),
+        }
+    }
+}
+
+// Expired or denied credentials need user action (re-authentication); the rest are API failures
+impl From<CalendarError> for OgError {
+    fn from(e: CalendarError) -> Self {
+        match e {
+            CalendarError::CredentialsNotFound(_)
+            | CalendarError::InvalidCredentials(_)
+            | CalendarError::TokenExpired
+            | CalendarError::TokenError(_)
+            | CalendarError::AccessDenied => OgError::Auth(e.to_string()),
+            CalendarError::IoError(e) => OgError::Io(e),
+            CalendarError::NetworkError(_) | CalendarError::ApiError(_) | CalendarError::ParseError(_) => OgError::Calendar(e.to_string()),
+        }
+    }
+}
+
+/// A calendar event. `og cal --format json` serializes only `start_time`, `end_time`, `title` and `is_all_day`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CalendarEvent {
+    #[serde(with = "hh_mm")]
     pub start_time: Option<NaiveTime>,
+    #[serde(with = "hh_mm")]
     pub end_time: Option<NaiveTime>,
     pub title: String,
     pub is_all_day: bool,
+    /// All-day events only: first day of the event
+    #[serde(skip)]
+    pub start_date: Option<NaiveDate>,
+    /// All-day events only: last day of the event (inclusive)
+    #[serde(skip)]
+    pub end_date: Option<NaiveDate>,
+    #[serde(skip)]
+    pub attendees: Vec<EventAttendee>,
+    /// Whether the calendar owner organizes this event
+    #[serde(skip)]
+    pub is_organizer: bool,
+    /// Google Calendar event id, used to match previously synced tasks
+    #[serde(skip)]
+    pub event_id: Option<String>,
+    #[serde(skip)]
+    pub description: Option<String>,
+    /// Local day the event starts on
+    #[serde(skip)]
+    pub date: Option<NaiveDate>,
+    /// The API reported `status: "cancelled"`
+    #[serde(skip)]
+    pub is_cancelled: bool,
+    /// The calendar owner's own RSVP, when they are listed as an attendee
+    #[serde(skip)]
+    pub self_response: Option<AttendeeStatus>,
+}
+
+impl CalendarEvent {
+    /// Cancelled events and events the calendar owner declined, hidden by `og cal --hide-declined`
+    pub fn is_declined_or_cancelled(&self) -> bool {
+        self.is_cancelled || self.self_response == Some(AttendeeStatus::Declined)
+    }
+}
+
+/// Serializes optional event times as `"HH:MM"`, or `null` when absent (all-day events)
+mod hh_mm {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &Option<NaiveTime>, serializer: S) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => serializer.serialize_str(&time.format("%H:%M").to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveTime>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| NaiveTime::parse_from_str(&s, "%H:%M").map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventAttendee {
+    pub email: String,
+    pub display_name: Option<String>,
+    pub response_status: AttendeeStatus,
+}
+
+/// RSVP status of an attendee as reported by the Calendar API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttendeeStatus {
+    Accepted,
+    Declined,
+    Tentative,
+    NeedsAction,
+}
+
+impl AttendeeStatus {
+    /// Maps the API `responseStatus` string; unknown or missing values are treated as `NeedsAction`
+    pub fn from_api(status: Option<&str>) -> Self {
+        match status {
+            Some("accepted") => AttendeeStatus::Accepted,
+            Some("declined") => AttendeeStatus::Declined,
+            Some("tentative") => AttendeeStatus::Tentative,
+            _ => AttendeeStatus::NeedsAction,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            AttendeeStatus::Accepted => "✓",
+            AttendeeStatus::Declined => "✗",
+            AttendeeStatus::Tentative => "?",
+            AttendeeStatus::NeedsAction => "-",
+        }
+    }
+}
+
+impl EventAttendee {
+    pub fn format_line(&self) -> String {
+        match &self.display_name {
+            Some(name) => format!("  {} {} <{}>", self.response_status.symbol(), name, self.email),
+            None => format!("  {} {}", self.response_status.symbol(), self.email),
+        }
+    }
 }
 
 impl CalendarEvent {
     pub fn format_with_time(&self) -> String {
+        if self.is_all_day {
+            match (self.start_date, self.end_date) {
+                (Some(start), Some(end)) if end > start => format!(
+                    "{}..{} {}",
+                    start.format("%Y-%m-%d"),
+                    end.format("%Y-%m-%d"),
+                    self.title
+                ),
+                _ => format!("00:00-23:59 {}", self.title),
+            }
+        } else {
+            self.format_time_only()
+        }
+    }
+
+    /// Like `format_with_time`, but never shows the date range of multi-day all-day events
+    pub fn format_time_only(&self) -> String {
         if self.is_all_day {
             format!("00:00-23:59 {}", self.title)
         } else if let (Some(start), Some(end)) = (self.start_time, self.end_time) {
@@ -35,11 +239,22 @@ impl CalendarEvent {
     pub fn format_title_only(&self) -> String {
         self.title.clone()
     }
+
+    /// `HH:MM Title` with only the start time; all-day events start at `00:00`
+    pub fn format_compact(&self) -> String {
+        match self.start_time {
+            Some(start) if !self.is_all_day => format!("{} {}", start.format("%H:%M"), self.title),
+            _ => format!("00:00 {}", self.title),
+        }
+    }
 }
 
+/// `credentials.json` as downloaded from Google Cloud. Desktop clients use an `installed` key,
+/// "Web application" clients a `web` key with the same fields; both are accepted.
 #[derive(Deserialize)]
 struct Credentials {
-    installed: InstalledCredentials,
+    #[serde(alias = "web")]
+    installed: Option<InstalledCredentials>,
 }
 
 #[derive(Deserialize)]
@@ -51,25 +266,103 @@ struct InstalledCredentials {
     redirect_uris: Vec<String>,
 }
 
-pub async fn get_today_events(show_all: bool) -> Result<Vec<CalendarEvent>, Box<dyn Error>> {
-    let hub = create_calendar_hub().await?;
+/// Fetches all of today's events; apply `filter_events` for display
+pub async fn get_today_events(account: Option<&str>, no_browser: bool) -> Result<Vec<CalendarEvent>, CalendarError> {
+    let hub = create_calendar_hub(account, no_browser).await?;
     let today = Local::now().date_naive();
-    let events = fetch_events_for_date(&hub, today).await?;
-    Ok(filter_events(events, show_all))
+    fetch_events_for_date(&hub, today).await
 }
 
-pub async fn get_next_business_day_events(show_all: bool) -> Result<Vec<CalendarEvent>, Box<dyn Error>> {
-    let hub = create_calendar_hub().await?;
+/// Fetches all events of the `count`-th next business day; apply `filter_events` for display
+pub async fn get_next_business_day_events(count: u32, country_code: &str, account: Option<&str>, no_browser: bool) -> Result<Vec<CalendarEvent>, CalendarError> {
+    let hub = create_calendar_hub(account, no_browser).await?;
+    let next_day = next_business_day_from_today(count, country_code).await?;
+    fetch_events_for_date(&hub, next_day).await
+}
+
+/// Returns the `count`-th business day after today, skipping custom and public holidays
+pub async fn next_business_day_from_today(count: u32, country_code: &str) -> Result<NaiveDate, OgError> {
     let today = Local::now().date_naive();
-    let next_business_day = next_business_day_jp(today);
-    let events = fetch_events_for_date(&hub, next_business_day).await?;
-    Ok(filter_events(events, show_all))
+    let mut holidays = load_custom_holidays()?;
+    // The next business day may fall in the following year (e.g. on Dec 31)
+    for year in [today.year(), today.year() + 1] {
+        holidays.extend(load_public_holidays(country_code, year).await?);
+    }
+    Ok(next_business_day(today, count, &holidays))
+}
+
+/// Synchronises calendar events into a task list, keyed by `extra["gcal_event_id"]`.
+/// - Unknown events become new open tasks (title → name, start day → due, description → notes)
+/// - Known events update `name`, `due` and `notes` when they changed
+/// - Previously imported tasks due on `today` (the day the events were fetched for)
+///   whose event is no longer returned are set to `cancelled`, unless already closed (done, cancelled or skipped).
+///   `events` must therefore be the list before `filter_events` and other display filters
+pub async fn sync_events_to_tasks(events: &[CalendarEvent], existing_tasks: &mut Vec<Task>, today: NaiveDate) {
+    let stamp = Local::now().date_naive();
+    let mut seen_ids = HashSet::new();
+
+    for event in events {
+        let Some(event_id) = event.event_id.as_deref() else { continue };
+        seen_ids.insert(event_id.to_string());
+        let due = event.date.or(Some(today));
+        let notes = event.description.clone().filter(|d| !d.is_empty());
+
+        if let Some(task) = existing_tasks.iter_mut().find(|t| gcal_event_id(t) == Some(event_id)) {
+            if task.name != event.title || task.due != due || task.notes != notes {
+                task.name = event.title.clone();
+                task.due = due;
+                task.notes = notes;
+                task.updated = Some(stamp);
+            }
+            continue;
+        }
+
+        let mut extra = HashMap::new();
+        extra.insert("gcal_event_id".to_string(), serde_json::Value::String(event_id.to_string()));
+        let id = existing_tasks.iter().flat_map(|t| t.flatten()).map(|t| t.id).max().unwrap_or(0) + 1;
+        let display_order = existing_tasks.iter().map(|t| t.display_order).max().unwrap_or(0) + 1;
+        existing_tasks.push(Task {
+            name: event.title.clone(),
+            status: "open".to_string(),
+            priority: "N".to_string(),
+            id,
+            created: stamp,
+            display_order,
+            due,
+            updated: None,
+            completed: None,
+            project: None,
+            contexts: None,
+            notes,
+            tags: None,
+            tags_kv: None,
+            subtasks: None,
+            extra: Some(extra),
+            repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
+        });
+    }
+
+    for task in existing_tasks.iter_mut() {
+        let Some(event_id) = gcal_event_id(task) else { continue };
+        if task.due == Some(today) && !seen_ids.contains(event_id) && !og::filter::is_closed(task) {
+            task.status = "cancelled".to_string();
+            task.updated = Some(stamp);
+        }
+    }
+}
+
+fn gcal_event_id(task: &Task) -> Option<&str> {
+    task.extra.as_ref()?.get("gcal_event_id")?.as_str()
 }
 
 /// Filters events based on visibility rules
 /// - All-day events are hidden unless show_all is true
 /// - Events starting with '.' are hidden unless show_all is true
-fn filter_events(events: Vec<CalendarEvent>, show_all: bool) -> Vec<CalendarEvent> {
+pub fn filter_events(events: Vec<CalendarEvent>, show_all: bool) -> Vec<CalendarEvent> {
     if show_all {
         return events;
     }
@@ -89,54 +382,150 @@ fn filter_events(events: Vec<CalendarEvent>, show_all: bool) -> Vec<CalendarEven
     }).collect()
 }
 
-/// Checks if a given date is a business day in Japan (not weekend or holiday)
-pub fn is_business_day_jp(date: NaiveDate) -> bool {
+/// Checks if a given date is a business day (not weekend or one of the given holidays)
+pub fn is_business_day(date: NaiveDate, holidays: &HashSet<NaiveDate>) -> bool {
     // Check if it's weekend
     if date.weekday() == Weekday::Sat || date.weekday() == Weekday::Sun {
         return false;
     }
-    
-    // Check if it's a Japanese holiday
-    !yasumi::is_holiday(date)
+
+    // Check if it's a public or custom (e.g. company) holiday
+    !holidays.contains(&date)
 }
 
-/// Returns the next business day after the given date
-pub fn next_business_day_jp(from_date: NaiveDate) -> NaiveDate {
-    let mut candidate = from_date + chrono::Duration::days(1);
-    
-    while !is_business_day_jp(candidate) {
-        candidate = candidate + chrono::Duration::days(1);
+/// Returns the `count`-th business day after the given date (1 = the next one),
+/// skipping weekends and the given holidays
+pub fn next_business_day(from: NaiveDate, count: u32, holidays: &HashSet<NaiveDate>) -> NaiveDate {
+    let mut candidate = from;
+    for _ in 0..count {
+        candidate += chrono::Duration::days(1);
+        while !is_business_day(candidate, holidays) {
+            candidate += chrono::Duration::days(1);
+        }
     }
     
     candidate
 }
 
-async fn create_calendar_hub() -> Result<CalendarHub<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>, Box<dyn Error>> {
+/// Returns the Japanese public holidays of the given year (computed offline)
+pub fn jp_public_holidays(year: i32) -> HashSet<NaiveDate> {
+    let mut holidays = HashSet::new();
+    let mut date = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+    while date.year() == year {
+        if yasumi::is_holiday(date) {
+            holidays.insert(date);
+        }
+        date += chrono::Duration::days(1);
+    }
+    holidays
+}
+
+#[derive(Deserialize)]
+struct PublicHoliday {
+    date: NaiveDate,
+}
+
+/// Parses the response of the Nager.Date public holiday API
+pub fn parse_public_holidays_json(content: &str) -> Result<HashSet<NaiveDate>, OgError> {
+    let holidays: Vec<PublicHoliday> = serde_json::from_str(content)
+        .map_err(|e| OgError::Calendar(format!("Failed to parse public holidays: {}", e)))?;
+    Ok(holidays.into_iter().map(|h| h.date).collect())
+}
+
+/// Loads the public holidays of a country for a year.
+/// Japan is computed offline; other countries are fetched from Nager.Date and cached
+/// in `~/.config/og/holidays_<CC>_<YEAR>.json`.
+async fn load_public_holidays(country_code: &str, year: i32) -> Result<HashSet<NaiveDate>, OgError> {
+    let country_code = country_code.to_uppercase();
+    if country_code == "JP" {
+        return Ok(jp_public_holidays(year));
+    }
+
+    let cache_path = get_public_holidays_cache_path(&country_code, year)?;
+    if let Ok(content) = fs::read_to_string(&cache_path) {
+        return parse_public_holidays_json(&content);
+    }
+
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()?
+        .https_only()
+        .enable_http1()
+        .build();
+    let client: hyper::Client<_, hyper::Body> = hyper::Client::builder().build(https);
+    let uri: hyper::Uri = format!("https://date.nager.at/api/v3/PublicHolidays/{}/{}", year, country_code).parse()
+        .map_err(|e| OgError::Calendar(format!("Invalid country code '{}': {}", country_code, e)))?;
+    let response = client.get(uri).await
+        .map_err(|e| OgError::Calendar(format!("Failed to fetch public holidays: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(OgError::Calendar(format!("Failed to fetch public holidays for '{}': HTTP {}", country_code, response.status())));
+    }
+    let body = hyper::body::to_bytes(response.into_body()).await
+        .map_err(|e| OgError::Calendar(format!("Failed to read public holidays: {}", e)))?;
+    let content = String::from_utf8(body.to_vec())
+        .map_err(|e| OgError::Calendar(format!("Failed to read public holidays: {}", e)))?;
+    let holidays = parse_public_holidays_json(&content)?;
+
+    if let Some(parent) = cache_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&cache_path, &content)?;
+    Ok(holidays)
+}
+
+/// Parses a holiday list (one `YYYY-MM-DD` per line). Blank lines and `#` comments are ignored.
+pub fn parse_holidays(content: &str) -> Result<HashSet<NaiveDate>, OgError> {
+    let mut holidays = HashSet::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let date = NaiveDate::parse_from_str(line, "%Y-%m-%d").map_err(|e| OgError::Parse {
+            line: index + 1,
+            msg: format!("Invalid holiday date '{}': {}", line, e),
+        })?;
+        holidays.insert(date);
+    }
+    Ok(holidays)
+}
+
+/// Loads custom holidays from `~/.config/google/holidays.txt`. A missing file means no custom holidays.
+fn load_custom_holidays() -> Result<HashSet<NaiveDate>, OgError> {
+    let path = get_holidays_path()?;
+    if !path.exists() {
+        return Ok(HashSet::new());
+    }
+    let content = fs::read_to_string(&path)?;
+    parse_holidays(&content)
+}
+
+/// Builds an authenticated hub. `account` selects which cached token is used (see `get_token_path`).
+/// `no_browser` selects the paste-the-code flow (see `auth_return_method`).
+async fn create_calendar_hub(account: Option<&str>, no_browser: bool) -> Result<CalendarHub<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>, CalendarError> {
     let credentials_path = get_credentials_path()?;
-    let token_path = get_token_path()?;
+    let token_path = get_token_path(account)?;
     
     // Read credentials manually
     let credentials_content = fs::read_to_string(&credentials_path)
-        .map_err(|e| format!("Failed to read credentials file: {}", e))?;
+        .map_err(|e| std::io::Error::new(e.kind(), format!("failed to read {}: {}", credentials_path.display(), e)))?;
     
     
-    let credentials: Credentials = serde_json::from_str(&credentials_content)
-        .map_err(|e| format!("Failed to parse credentials file: {}", e))?;
+    let credentials = parse_credentials(&credentials_content)?;
     
     // Create application secret manually
     let app_secret = ApplicationSecret {
-        client_id: credentials.installed.client_id,
-        client_secret: credentials.installed.client_secret,
-        auth_uri: credentials.installed.auth_uri,
-        token_uri: credentials.installed.token_uri,
-        redirect_uris: credentials.installed.redirect_uris,
+        client_id: credentials.client_id,
+        client_secret: credentials.client_secret,
+        auth_uri: credentials.auth_uri,
+        token_uri: credentials.token_uri,
+        redirect_uris: credentials.redirect_uris,
         ..Default::default()
     };
     
     // Create authenticator
     let auth = InstalledFlowAuthenticator::builder(
         app_secret,
-        InstalledFlowReturnMethod::HTTPRedirect
+        auth_return_method(no_browser)
     )
     .persist_tokens_to_disk(&token_path)
     .build()
@@ -144,12 +533,14 @@ async fn create_calendar_hub() -> Result<CalendarHub<hyper_rustls::HttpsConnecto
     .map_err(|e| {
         let error_msg = format!("{}", e);
         if error_msg.contains("access_denied") || error_msg.contains("unauthorized") {
-            format!("Google OAuth access denied. This application may not be verified by Google. You need to:\n1. Create your own Google Cloud project\n2. Enable Calendar API\n3. Create OAuth credentials\n4. Replace the credentials.json file")
+            CalendarError::AccessDenied
         } else {
-            format!("Authentication failed: {}", e)
+            CalendarError::TokenError(format!("Authentication failed: {}", e))
         }
     })?;
     
+    ensure_valid_token(&auth, &token_path).await?;
+    
     // Create HTTPS connector with proper configuration for hyper-rustls 0.25
     let https = hyper_rustls::HttpsConnectorBuilder::new()
         .with_native_roots()?
@@ -162,10 +553,59 @@ async fn create_calendar_hub() -> Result<CalendarHub<hyper_rustls::HttpsConnecto
     Ok(hub)
 }
 
+/// Parses `credentials.json`, failing with an actionable message when it is not an OAuth client file
+fn parse_credentials(content: &str) -> Result<InstalledCredentials, CalendarError> {
+    let credentials: Credentials = serde_json::from_str(content)
+        .map_err(|e| CalendarError::InvalidCredentials(format!("failed to parse: {}", e)))?;
+    credentials.installed.ok_or_else(|| CalendarError::InvalidCredentials(
+        "credentials.json must be a Desktop/Installed OAuth client (expected an \"installed\" or \"web\" key). Download it from Google Cloud Console > APIs & Services > Credentials.".to_string()
+    ))
+}
+
+/// Requests a token up front so an expired or revoked refresh token is detected before any API call.
+/// On expiry the stale token file at `token_path` is removed so the next run starts a fresh authentication.
+pub async fn ensure_valid_token(auth: &DefaultAuthenticator, token_path: &Path) -> Result<(), CalendarError> {
+    match auth.token(&[CALENDAR_READONLY_SCOPE]).await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let error_msg = format!("{}", e);
+            if error_msg.contains("invalid_grant") || error_msg.contains("token_expired") {
+                let _ = fs::remove_file(token_path);
+                Err(CalendarError::TokenExpired)
+            } else if error_msg.contains("access_denied") || error_msg.contains("unauthorized") {
+                Err(CalendarError::AccessDenied)
+            } else if matches!(e, yup_oauth2::Error::HttpError(_)) {
+                Err(CalendarError::NetworkError(error_msg))
+            } else {
+                Err(CalendarError::ApiError(error_msg))
+            }
+        }
+    }
+}
+
+/// Calls `fetch_page` with each `nextPageToken` (starting from none) until a page has no token,
+/// returning the items of every page in order
+async fn collect_all_pages<T, E, F, Fut>(mut fetch_page: F) -> Result<Vec<T>, E>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<(Vec<T>, Option<String>), E>>,
+{
+    let mut items = Vec::new();
+    let mut page_token = None;
+    loop {
+        let (page, next_page_token) = fetch_page(page_token).await?;
+        items.extend(page);
+        match next_page_token {
+            Some(token) if !token.is_empty() => page_token = Some(token),
+            _ => return Ok(items),
+        }
+    }
+}
+
 async fn fetch_events_for_date(
     hub: &CalendarHub<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>,
     date: NaiveDate
-) -> Result<Vec<CalendarEvent>, Box<dyn Error>> {
+) -> Result<Vec<CalendarEvent>, CalendarError> {
     let start_time = date.and_hms_opt(0, 0, 0).unwrap();
     let end_time = date.and_hms_opt(23, 59, 59).unwrap();
     
@@ -173,106 +613,1058 @@ async fn fetch_events_for_date(
     let start_utc = Local.from_local_datetime(&start_time).unwrap().with_timezone(&Utc);
     let end_utc = Local.from_local_datetime(&end_time).unwrap().with_timezone(&Utc);
     
-    let result = hub.events()
-        .list("primary")
-        .time_min(start_utc)
-        .time_max(end_utc)
-        .single_events(true)
-        .order_by("startTime")
-        .doit()
-        .await;
+    let result = collect_all_pages(|page_token| {
+        let mut call = hub.events()
+            .list("primary")
+            .time_min(start_utc)
+            .time_max(end_utc)
+            .single_events(true)
+            .order_by("startTime");
+        if let Some(token) = page_token {
+            call = call.page_token(&token);
+        }
+        async move {
+            call.doit().await.map(|(_, events_list)| (events_list.items.unwrap_or_default(), events_list.next_page_token))
+        }
+    }).await;
     
     match result {
-        Ok((_, events_list)) => {
-            let mut calendar_events = Vec::new();
-            
-            if let Some(items) = events_list.items {
-                for event in items {
-                    let title = event.summary.unwrap_or_else(|| "No Title".to_string());
-                    
-                    let (start_time, end_time, is_all_day) = if let Some(start) = event.start {
-                        if let Some(date_time) = start.date_time {
-                            // Timed event
-                            let start_local = date_time.with_timezone(&Local);
-                            let start_naive = start_local.time();
-                            
-                            let end_naive = if let Some(end) = event.end {
-                                if let Some(end_date_time) = end.date_time {
-                                    let end_local = end_date_time.with_timezone(&Local);
-                                    end_local.time()
-                                } else {
-                                    start_naive
-                                }
-                            } else {
-                                start_naive
-                            };
-                            
-                            (Some(start_naive), Some(end_naive), false)
-                        } else {
-                            // All-day event
-                            (None, None, true)
-                        }
-                    } else {
-                        (None, None, true)
-                    };
-                    
-                    calendar_events.push(CalendarEvent {
-                        start_time,
-                        end_time,
-                        title,
-                        is_all_day,
-                    });
-                }
-            }
+        Ok(items) => {
+            let mut calendar_events: Vec<CalendarEvent> = items
+                .into_iter()
+                .map(event_from_api)
+                .collect();
             
+            sort_events(&mut calendar_events);
             Ok(calendar_events)
         }
         Err(e) => {
             let error_msg = format!("{}", e);
             if error_msg.contains("access_denied") || error_msg.contains("unauthorized") {
-                Err("Google Calendar access denied. The OAuth application may not be verified. Please check with the app developer or use your own Google Cloud credentials.".into())
+                Err(CalendarError::AccessDenied)
             } else {
-                Err(format!("Failed to fetch calendar events: {}", e).into())
+                Err(CalendarError::ApiError(format!("failed to fetch calendar events: {}", e)))
             }
         }
     }
 }
 
-fn get_credentials_path() -> Result<PathBuf, Box<dyn Error>> {
-    let home_dir = dirs::home_dir()
-        .ok_or("Could not determine home directory")?;
+/// Maps an API event item to a `CalendarEvent`
+fn event_from_api(event: google_calendar3::api::Event) -> CalendarEvent {
+    let title = event.summary.unwrap_or_else(|| "No Title".to_string());
+    let event_id = event.id;
+    let description = event.description;
+    let is_organizer = event.organizer.as_ref()
+        .and_then(|o| o.self_)
+        .unwrap_or(false);
+    let is_cancelled = event.status.as_deref() == Some("cancelled");
+    let api_attendees = event.attendees.unwrap_or_default();
+    // The calendar owner's own RSVP, from the attendee entry flagged `self`
+    let self_response = api_attendees.iter()
+        .find(|a| a.self_ == Some(true))
+        .map(|a| AttendeeStatus::from_api(a.response_status.as_deref()));
+    let attendees = api_attendees
+        .into_iter()
+        .filter_map(|a| Some(EventAttendee {
+            email: a.email?,
+            display_name: a.display_name,
+            response_status: AttendeeStatus::from_api(a.response_status.as_deref()),
+        }))
+        .collect();
     
-    let path = home_dir.join(".config").join("google").join("credentials.json");
+    // All-day events carry `date` instead of `dateTime`; the API's end date is exclusive
+    let start_date = event.start.as_ref().and_then(|s| s.date);
+    let end_date = event.end.as_ref()
+        .and_then(|e| e.date)
+        .map(|d| d - chrono::Duration::days(1));
+
+    let mut event_date = start_date;
+
+    let (start_time, end_time, is_all_day) = if let Some(start) = event.start {
+        if let Some(date_time) = start.date_time {
+            // Timed event
+            let start_local = date_time.with_timezone(&Local);
+            let start_naive = start_local.time();
+            event_date = Some(start_local.date_naive());
+            
+            let end_naive = if let Some(end) = event.end {
+                if let Some(end_date_time) = end.date_time {
+                    let end_local = end_date_time.with_timezone(&Local);
+                    end_local.time()
+                } else {
+                    start_naive
+                }
+            } else {
+                start_naive
+            };
+            
+            (Some(start_naive), Some(end_naive), false)
+        } else {
+            // All-day event
+            (None, None, true)
+        }
+    } else {
+        (None, None, true)
+    };
     
+    CalendarEvent {
+        start_time,
+        end_time,
+        title,
+        is_all_day,
+        start_date: if is_all_day { start_date } else { None },
+        end_date: if is_all_day { end_date } else { None },
+        attendees,
+        is_organizer,
+        event_id,
+        description,
+        date: event_date,
+        is_cancelled,
+        self_response,
+    }
+}
+
+/// Whether `og cal` should skip the OAuth flow and report no events.
+/// `--no-auth` always does; `OG_CAL_NO_AUTH=1` does so only when no credentials file is found.
+pub fn offline_mode(no_auth: bool) -> bool {
+    if no_auth {
+        return true;
+    }
+    let implied = std::env::var("OG_CAL_NO_AUTH").is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+    implied && get_credentials_path().is_err()
+}
+
+/// How the OAuth authorization code comes back to `og`.
+/// By default a local redirect server receives it after the browser consent page.
+/// `--no-auth-browser` or `OG_OAUTH_NO_BROWSER=1` switches to the interactive flow for headless machines:
+/// the consent URL is printed to stdout and the code is pasted on stdin.
+pub fn auth_return_method(no_browser: bool) -> InstalledFlowReturnMethod {
+    return_method_for(no_browser, std::env::var("OG_OAUTH_NO_BROWSER").ok().as_deref())
+}
+
+/// `auth_return_method` with the `OG_OAUTH_NO_BROWSER` value passed in
+fn return_method_for(no_browser: bool, env_value: Option<&str>) -> InstalledFlowReturnMethod {
+    let implied = env_value.is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+    if no_browser || implied {
+        InstalledFlowReturnMethod::Interactive
+    } else {
+        InstalledFlowReturnMethod::HTTPRedirect
+    }
+}
+
+/// One line of the `og doctor` checklist
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoctorCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+    /// What to do about a failed check
+    pub hint: Option<String>,
+}
+
+impl DoctorCheck {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        DoctorCheck { name, passed: true, detail: detail.into(), hint: None }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        DoctorCheck { name, passed: false, detail: detail.into(), hint: Some(hint.into()) }
+    }
+}
+
+impl fmt::Display for DoctorCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", if self.passed { "ok" } else { "FAIL" }, self.name, self.detail)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n       hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the calendar setup for `og doctor` without calling the API: credentials file, client type,
+/// cached token and home directory, in that order. Checks that depend on a failed one are skipped.
+pub fn run_doctor_checks(account: Option<&str>) -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+
+    match credentials_path_candidate() {
+        Ok(path) => checks.extend(check_credentials_file(&path)),
+        Err(e) => checks.push(DoctorCheck::fail("Credentials file", e.to_string(), "Set OG_CREDENTIALS_PATH to the credentials.json path")),
+    }
+
+    match get_token_path(account) {
+        Ok(path) => checks.push(check_token_file(&path, Utc::now())),
+        Err(e) => checks.push(DoctorCheck::fail("Token file", e.to_string(), "Use an account name without '/', '\\' or a leading '.', or set OG_TOKEN_PATH")),
+    }
+
+    checks.push(match dirs::home_dir() {
+        Some(home_dir) => DoctorCheck::pass("Home directory", home_dir.display().to_string()),
+        None => DoctorCheck::fail("Home directory", "could not be determined", "Set HOME, or set OG_CREDENTIALS_PATH and OG_TOKEN_PATH"),
+    });
+    checks
+}
+
+/// `og doctor --check-token`: asks Google for a token with the cached one (refreshing it if needed)
+pub async fn check_token_online(account: Option<&str>) -> DoctorCheck {
+    match create_calendar_hub(account, false).await {
+        Ok(_) => DoctorCheck::pass("Token validation", "accepted by Google"),
+        Err(e) => DoctorCheck::fail("Token validation", e.to_string(), "Run 'og cal' to re-authenticate"),
+    }
+}
+
+/// Credentials checks: the file exists, is JSON, and holds an installed (Desktop) or web OAuth client
+fn check_credentials_file(path: &Path) -> Vec<DoctorCheck> {
+    const DOWNLOAD_HINT: &str = "Download an OAuth client ID (Desktop app) from Google Cloud Console > APIs & Services > Credentials";
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return vec![DoctorCheck::fail("Credentials file", format!("{} not found", path.display()), format!("{} and save it there, or set OG_CREDENTIALS_PATH", DOWNLOAD_HINT))];
+        }
+        Err(e) => return vec![DoctorCheck::fail("Credentials file", format!("cannot read {}: {}", path.display(), e), "Check the file permissions")],
+    };
+    let mut checks = vec![DoctorCheck::pass("Credentials file", path.display().to_string())];
+
+    if let Err(e) = serde_json::from_str::<serde_json::Value>(&content) {
+        checks.push(DoctorCheck::fail("Credentials JSON", format!("not valid JSON: {}", e), format!("{} again; the file may be truncated", DOWNLOAD_HINT)));
+        return checks;
+    }
+    checks.push(DoctorCheck::pass("Credentials JSON", "parses"));
+
+    checks.push(match parse_credentials(&content) {
+        Ok(_) => DoctorCheck::pass("OAuth client type", "installed (Desktop) client"),
+        Err(_) => DoctorCheck::fail("OAuth client type", "no \"installed\" or \"web\" client with client_id, client_secret, auth_uri, token_uri and redirect_uris", DOWNLOAD_HINT),
+    });
+    checks
+}
+
+/// Token check: the cached token exists, and whether it can still be used without re-authenticating.
+/// An expired access token is fine as long as a refresh token is stored.
+fn check_token_file(path: &Path, now: chrono::DateTime<Utc>) -> DoctorCheck {
+    const AUTH_HINT: &str = "Run 'og cal' to authenticate (add --no-auth-browser on a machine without a browser)";
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return DoctorCheck::fail("Token file", format!("{} not found", path.display()), AUTH_HINT);
+        }
+        Err(e) => return DoctorCheck::fail("Token file", format!("cannot read {}: {}", path.display(), e), "Check the file permissions"),
+    };
+    let Ok(tokens) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return DoctorCheck::fail("Token file", format!("{} is not valid JSON", path.display()), format!("Delete it, then {}", AUTH_HINT.to_lowercase()));
+    };
+    // yup-oauth2 はスコープごとのトークンを配列で保存する
+    let Some(token) = tokens.as_array().and_then(|entries| entries.first()).and_then(|entry| entry.get("token")) else {
+        return DoctorCheck::fail("Token file", format!("{} holds no token", path.display()), AUTH_HINT);
+    };
+    let has_refresh_token = token.get("refresh_token").is_some_and(|t| t.is_string());
+    match token.get("expires_at").and_then(token_expiry) {
+        Some(expires_at) if expires_at > now => DoctorCheck::pass("Token file", format!("{} (valid until {})", path.display(), expires_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"))),
+        Some(_) if has_refresh_token => DoctorCheck::pass("Token file", format!("{} (access token expired; it is refreshed on the next run)", path.display())),
+        Some(expires_at) => DoctorCheck::fail("Token file", format!("{} expired at {} and has no refresh token", path.display(), expires_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")), AUTH_HINT),
+        None if has_refresh_token => DoctorCheck::pass("Token file", format!("{} (has a refresh token)", path.display())),
+        None => DoctorCheck::fail("Token file", format!("{} has no expiry or refresh token", path.display()), AUTH_HINT),
+    }
+}
+
+/// `expires_at` as written by yup-oauth2: an RFC 3339 string, or the `time` crate's
+/// `[year, ordinal, hour, minute, second, nanosecond, offset_h, offset_m, offset_s]` tuple
+fn token_expiry(value: &serde_json::Value) -> Option<chrono::DateTime<Utc>> {
+    if let Some(text) = value.as_str() {
+        return chrono::DateTime::parse_from_rfc3339(text).ok().map(|t| t.with_timezone(&Utc));
+    }
+    let parts: Vec<i64> = value.as_array()?.iter().map(|part| part.as_i64()).collect::<Option<_>>()?;
+    let [year, ordinal, hour, minute, second, _, offset_h, offset_m, offset_s] = parts[..] else {
+        return None;
+    };
+    let local = NaiveDate::from_yo_opt(year as i32, ordinal as u32)?.and_hms_opt(hour as u32, minute as u32, second as u32)?;
+    let offset = chrono::Duration::seconds(offset_h * 3600 + offset_m * 60 + offset_s);
+    Some(Utc.from_utc_datetime(&(local - offset)))
+}
+
+/// The credentials path `og` would use, whether or not the file exists
+fn credentials_path_candidate() -> Result<PathBuf, CalendarError> {
+    credentials_path_from(std::env::var_os("OG_CREDENTIALS_PATH"))
+}
+
+/// `credentials_path_candidate` with the `OG_CREDENTIALS_PATH` value passed in
+fn credentials_path_from(env_path: Option<std::ffi::OsString>) -> Result<PathBuf, CalendarError> {
+    match env_path {
+        Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => {
+            let home_dir = dirs::home_dir().ok_or_else(home_dir_error)?;
+            Ok(home_dir.join(".config").join("google").join("credentials.json"))
+        }
+    }
+}
+
+/// Resolves the credentials path: `OG_CREDENTIALS_PATH` if set, otherwise `~/.config/google/credentials.json`
+fn get_credentials_path() -> Result<PathBuf, CalendarError> {
+    existing_credentials_path(credentials_path_candidate()?)
+}
+
+fn existing_credentials_path(path: PathBuf) -> Result<PathBuf, CalendarError> {
     if !path.exists() {
-        return Err(format!("Credentials file not found: {}", path.display()).into());
+        return Err(CalendarError::CredentialsNotFound(path));
+    }
+    
+    Ok(path)
+}
+
+/// Resolves the token path. With an account name the token is cached per account at
+/// `~/.config/google/token-<NAME>.json`; otherwise `OG_TOKEN_PATH` if set, then `~/.config/google/token.json`
+fn get_token_path(account: Option<&str>) -> Result<PathBuf, CalendarError> {
+    token_path_from(account, std::env::var_os("OG_TOKEN_PATH"))
+}
+
+/// `get_token_path` with the `OG_TOKEN_PATH` value passed in
+fn token_path_from(account: Option<&str>, env_path: Option<std::ffi::OsString>) -> Result<PathBuf, CalendarError> {
+    if account.is_none() {
+        if let Some(path) = env_path {
+            if !path.is_empty() {
+                return Ok(PathBuf::from(path));
+            }
+        }
+    }
+
+    let home_dir = dirs::home_dir().ok_or_else(home_dir_error)?;
+
+    token_path_in(&home_dir.join(".config").join("google"), account)
+}
+
+/// Token file inside `dir` for the given account (`token.json` when no account is given)
+fn token_path_in(dir: &Path, account: Option<&str>) -> Result<PathBuf, CalendarError> {
+    match account {
+        None => Ok(dir.join("token.json")),
+        Some(name) if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') => {
+            Err(CalendarError::TokenError(format!("Invalid account name '{}'", name)))
+        }
+        Some(name) => Ok(dir.join(format!("token-{}.json", name))),
     }
+}
+
+fn home_dir_error() -> CalendarError {
+    CalendarError::IoError(std::io::Error::new(std::io::ErrorKind::NotFound, "Could not determine home directory"))
+}
+
+fn get_holidays_path() -> Result<PathBuf, OgError> {
+    let home_dir = dirs::home_dir()
+        .ok_or_else(|| OgError::Calendar("Could not determine home directory".to_string()))?;
     
+    let path = home_dir.join(".config").join("google").join("holidays.txt");
     Ok(path)
 }
 
-fn get_token_path() -> Result<PathBuf, Box<dyn Error>> {
+fn get_public_holidays_cache_path(country_code: &str, year: i32) -> Result<PathBuf, OgError> {
     let home_dir = dirs::home_dir()
-        .ok_or("Could not determine home directory")?;
+        .ok_or_else(|| OgError::Calendar("Could not determine home directory".to_string()))?;
     
-    let path = home_dir.join(".config").join("google").join("token.json");
+    let path = home_dir.join(".config").join("og").join(format!("holidays_{}_{}.json", country_code, year));
     Ok(path)
 }
 
-pub fn format_events_output(events: &[CalendarEvent], show_title_only: bool) -> String {
-    let mut output = String::from("### 予定\n");
+/// Controls how `format_events_output` renders a list of events
+#[derive(Debug, Clone)]
+pub struct CalendarOutputOptions {
+    /// Heading line printed before the events; `None` omits it
+    pub header: Option<String>,
+    /// Line printed when there are no events; `None` prints nothing
+    pub empty_message: Option<String>,
+    /// Show the date range of multi-day all-day events instead of `00:00-23:59`
+    pub show_date: bool,
+    /// Print only event titles without times
+    pub title_only: bool,
+    /// List attendees and their RSVP status under each event
+    pub show_attendees: bool,
+    /// Wrap the header, event times and RSVP states in ANSI colors
+    pub color: bool,
+}
+
+impl Default for CalendarOutputOptions {
+    fn default() -> Self {
+        CalendarOutputOptions {
+            header: Some("### 予定".to_string()),
+            empty_message: Some("予定はありません。".to_string()),
+            show_date: true,
+            title_only: false,
+            show_attendees: false,
+            color: false,
+        }
+    }
+}
+
+pub fn format_events_output(events: &[CalendarEvent], opts: &CalendarOutputOptions) -> String {
+    let mut output = String::new();
+    let paint = |text: &str, color: AnsiColor| if opts.color { colorize(text, color) } else { text.to_string() };
+    if let Some(header) = &opts.header {
+        output.push_str(&paint(header, AnsiColor::Bold));
+        output.push('\n');
+    }
     
     if events.is_empty() {
-        output.push_str("予定はありません。\n");
+        if let Some(message) = &opts.empty_message {
+            output.push_str(message);
+            output.push('\n');
+        }
     } else {
         for event in events {
-            if show_title_only {
-                output.push_str(&format!("{}\n", event.format_title_only()));
+            let line = if opts.title_only {
+                event.format_title_only()
+            } else if opts.show_date {
+                event.format_with_time()
             } else {
-                output.push_str(&format!("{}\n", event.format_with_time()));
+                event.format_time_only()
+            };
+            // 時刻 (または日付範囲) の部分だけを色付けする
+            match line.split_once(' ') {
+                Some((when, title)) if opts.color && !opts.title_only => {
+                    output.push_str(&format!("{} {}", paint(when, AnsiColor::Yellow), title));
+                }
+                _ => output.push_str(&line),
+            }
+            output.push('\n');
+            if opts.show_attendees {
+                for attendee in &event.attendees {
+                    let color = match attendee.response_status {
+                        AttendeeStatus::Accepted => Some(AnsiColor::Green),
+                        AttendeeStatus::Declined => Some(AnsiColor::Red),
+                        AttendeeStatus::Tentative => Some(AnsiColor::Yellow),
+                        AttendeeStatus::NeedsAction => None,
+                    };
+                    let attendee_line = attendee.format_line();
+                    output.push_str(&match color {
+                        Some(color) => paint(&attendee_line, color),
+                        None => attendee_line,
+                    });
+                    output.push('\n');
+                }
             }
         }
     }
     
     output
-}
\ No newline at end of file
+}
+
+/// Output format of `og cal`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarFormat {
+    Text,
+    Json,
+    Ical,
+    /// One `HH:MM Title` line per event without a header, e.g. for notifications
+    Compact,
+}
+
+impl std::str::FromStr for CalendarFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(CalendarFormat::Text),
+            "json" => Ok(CalendarFormat::Json),
+            "ical" | "ics" => Ok(CalendarFormat::Ical),
+            "compact" => Ok(CalendarFormat::Compact),
+            _ => Err(format!("Error: Unsupported calendar format '{}' (expected text, json, ical or compact).", s)),
+        }
+    }
+}
+
+/// All-day events first (keeping their relative order), then timed events by start time.
+/// The API's `startTime` ordering mixes all-day events in by their UTC midnight, so it is not relied on.
+pub fn sort_events(events: &mut [CalendarEvent]) {
+    events.sort_by_key(|event| (!event.is_all_day, event.start_time));
+}
+
+/// Pairs of timed events whose `[start_time, end_time)` intervals overlap, in input order.
+/// All-day events never conflict, and events on different dates are never compared.
+pub fn find_overlapping_events(events: &[CalendarEvent]) -> Vec<(&CalendarEvent, &CalendarEvent)> {
+    let timed: Vec<(&CalendarEvent, NaiveTime, NaiveTime)> = events
+        .iter()
+        .filter(|event| !event.is_all_day)
+        .filter_map(|event| Some((event, event.start_time?, event.end_time?)))
+        .collect();
+    let mut conflicts = Vec::new();
+    for (i, &(a, a_start, a_end)) in timed.iter().enumerate() {
+        for &(b, b_start, b_end) in &timed[i + 1..] {
+            let same_day = match (a.date, b.date) {
+                (Some(a_date), Some(b_date)) => a_date == b_date,
+                _ => true,
+            };
+            if same_day && a_start < b_end && b_start < a_end {
+                conflicts.push((a, b));
+            }
+        }
+    }
+    conflicts
+}
+
+/// Timed events starting after now and at most `window_minutes` from now, for `og cal --remind-minutes`.
+/// All-day events have no start time and are never upcoming.
+pub fn filter_upcoming(events: &[CalendarEvent], window_minutes: i64) -> Vec<&CalendarEvent> {
+    filter_upcoming_at(events, Local::now().time(), window_minutes)
+}
+
+fn filter_upcoming_at(events: &[CalendarEvent], now: NaiveTime, window_minutes: i64) -> Vec<&CalendarEvent> {
+    // 窓が日付をまたぐ場合 (23:55 から 15 分など) は、今日の残りの予定をすべて含める
+    let (window_end, wrapped_seconds) = now.overflowing_add_signed(chrono::Duration::minutes(window_minutes));
+    events
+        .iter()
+        .filter(|event| match event.start_time {
+            Some(start) => start > now && (wrapped_seconds != 0 || start <= window_end),
+            None => false,
+        })
+        .collect()
+}
+
+/// `WARNING: "Meeting A" (10:00-11:00) overlaps with "Meeting B" (10:30-12:00)`
+pub fn format_conflict_warning(a: &CalendarEvent, b: &CalendarEvent) -> String {
+    let span = |event: &CalendarEvent| match (event.start_time, event.end_time) {
+        (Some(start), Some(end)) => format!("{}-{}", start.format("%H:%M"), end.format("%H:%M")),
+        _ => "00:00-23:59".to_string(),
+    };
+    format!("WARNING: \"{}\" ({}) overlaps with \"{}\" ({})", a.title, span(a), b.title, span(b))
+}
+
+/// One `HH:MM Title` line per event and nothing at all when there are none, for `og cal --format compact`
+pub fn format_events_compact(events: &[CalendarEvent]) -> String {
+    events.iter().map(|event| event.format_compact() + "\n").collect()
+}
+
+/// Events as a pretty-printed JSON array, for `og cal --format json | jq`
+pub fn format_events_json(events: &[CalendarEvent]) -> Result<String, OgError> {
+    Ok(serde_json::to_string_pretty(events)? + "\n")
+}
+
+/// Events as an iCalendar (RFC 5545) document with floating local times
+pub fn format_events_ical(events: &[CalendarEvent]) -> String {
+    let escape = |text: &str| {
+        text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+    };
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let mut lines = vec!["BEGIN:VCALENDAR".to_string(), "VERSION:2.0".to_string(), "PRODID:-//og//og cal//EN".to_string()];
+    for (index, event) in events.iter().enumerate() {
+        lines.push("BEGIN:VEVENT".to_string());
+        match &event.event_id {
+            Some(id) => lines.push(format!("UID:{}", id)),
+            None => lines.push(format!("UID:og-{}-{}", stamp, index + 1)),
+        }
+        lines.push(format!("DTSTAMP:{}", stamp));
+        let day = event.date.or(event.start_date);
+        if event.is_all_day {
+            if let Some(start) = event.start_date.or(day) {
+                // DTEND of an all-day event is exclusive
+                let end = event.end_date.unwrap_or(start) + chrono::Duration::days(1);
+                lines.push(format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")));
+                lines.push(format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+            }
+        } else if let (Some(day), Some(start), Some(end)) = (day, event.start_time, event.end_time) {
+            lines.push(format!("DTSTART:{}", day.and_time(start).format("%Y%m%dT%H%M%S")));
+            lines.push(format!("DTEND:{}", day.and_time(end).format("%Y%m%dT%H%M%S")));
+        }
+        lines.push(format!("SUMMARY:{}", escape(&event.title)));
+        if let Some(description) = &event.description {
+            lines.push(format!("DESCRIPTION:{}", escape(description)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.join("\r\n") + "\r\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_day_event(title: &str, start: NaiveDate, end: NaiveDate) -> CalendarEvent {
+        CalendarEvent {
+            start_time: None,
+            end_time: None,
+            title: title.to_string(),
+            is_all_day: true,
+            start_date: Some(start),
+            end_date: Some(end),
+            attendees: Vec::new(),
+            is_organizer: true,
+            event_id: None,
+            description: None,
+            date: Some(start),
+            is_cancelled: false,
+            self_response: None,
+        }
+    }
+
+    fn timed_event(title: &str, start: &str, end: &str) -> CalendarEvent {
+        CalendarEvent {
+            start_time: Some(NaiveTime::parse_from_str(start, "%H:%M").unwrap()),
+            end_time: Some(NaiveTime::parse_from_str(end, "%H:%M").unwrap()),
+            is_all_day: false,
+            start_date: None,
+            end_date: None,
+            ..all_day_event(title, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        }
+    }
+
+    fn synced_event(event_id: &str, title: &str, date: NaiveDate) -> CalendarEvent {
+        CalendarEvent {
+            event_id: Some(event_id.to_string()),
+            description: Some(format!("{} agenda", title)),
+            ..all_day_event(title, date, date)
+        }
+    }
+
+    #[test]
+    fn test_format_multi_day_all_day_event() {
+        let event = all_day_event(
+            "Offsite",
+            NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 5, 3).unwrap(),
+        );
+        assert_eq!(event.format_with_time(), "2024-05-01..2024-05-03 Offsite");
+    }
+
+    #[test]
+    fn test_format_single_day_all_day_event() {
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let event = all_day_event("Holiday", day, day);
+        assert_eq!(event.format_with_time(), "00:00-23:59 Holiday");
+    }
+
+    #[test]
+    fn test_format_events_output_custom_header() {
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let events = vec![all_day_event("Holiday", day, day)];
+        let opts = CalendarOutputOptions {
+            header: Some("## Schedule".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(format_events_output(&events, &opts), "## Schedule\n00:00-23:59 Holiday\n");
+    }
+
+    #[test]
+    fn test_format_events_output_default_empty() {
+        let output = format_events_output(&[], &CalendarOutputOptions::default());
+        assert_eq!(output, "### 予定\n予定はありません。\n");
+    }
+
+    #[test]
+    fn test_format_events_compact() {
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let events = vec![all_day_event("Holiday", day, day), timed_event("Standup", "09:30", "09:45")];
+        assert_eq!(format_events_compact(&events), "00:00 Holiday\n09:30 Standup\n");
+        assert_eq!(format_events_compact(&[]), "");
+        assert_eq!("Compact".parse::<CalendarFormat>(), Ok(CalendarFormat::Compact));
+    }
+
+    #[test]
+    fn test_format_events_json_round_trip() {
+        let timed = CalendarEvent {
+            start_time: NaiveTime::from_hms_opt(9, 30, 0),
+            end_time: NaiveTime::from_hms_opt(10, 0, 0),
+            is_all_day: false,
+            ..all_day_event("Standup", NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 3).unwrap())
+        };
+        let all_day = all_day_event("Holiday", NaiveDate::from_ymd_opt(2024, 6, 4).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 4).unwrap());
+
+        let json = format_events_json(&[timed, all_day]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0], serde_json::json!({"start_time": "09:30", "end_time": "10:00", "title": "Standup", "is_all_day": false}));
+        assert_eq!(value[1]["start_time"], serde_json::Value::Null);
+
+        let events: Vec<CalendarEvent> = serde_json::from_str(&json).unwrap();
+        assert_eq!(events[0].start_time, NaiveTime::from_hms_opt(9, 30, 0));
+        assert_eq!(events[0].title, "Standup");
+        assert!(events[1].is_all_day);
+        assert_eq!(events[1].end_time, None);
+    }
+
+    #[test]
+    fn test_format_events_ical() {
+        let ical = format_events_ical(&[all_day_event("Offsite, day 1", NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 4).unwrap())]);
+        assert!(ical.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ical.contains("DTSTART;VALUE=DATE:20240603\r\nDTEND;VALUE=DATE:20240605\r\n"));
+        assert!(ical.contains("SUMMARY:Offsite\\, day 1\r\n"));
+        assert!(ical.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn test_format_events_output_color() {
+        let event = all_day_event("Offsite", NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 5).unwrap());
+        let plain = format_events_output(std::slice::from_ref(&event), &CalendarOutputOptions::default());
+        let colored = format_events_output(&[event], &CalendarOutputOptions { color: true, ..Default::default() });
+        assert_eq!(colored, "\x1b[1m### 予定\x1b[0m\n\x1b[33m2024-06-03..2024-06-05\x1b[0m Offsite\n");
+        // 色コードを除けば色なしの出力と同じ
+        assert_eq!(colored.replace("\x1b[1m", "").replace("\x1b[33m", "").replace("\x1b[0m", ""), plain);
+    }
+
+    #[test]
+    fn test_filter_upcoming_at() {
+        let events = vec![
+            timed_event("Started", "09:50", "10:30"),
+            timed_event("Now", "10:00", "10:30"),
+            timed_event("Soon", "10:10", "10:30"),
+            timed_event("Edge", "10:15", "11:00"),
+            timed_event("Later", "10:16", "11:00"),
+            all_day_event("Holiday", NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
+        ];
+        let titles = |now: &str, window: i64| -> Vec<String> {
+            let now = NaiveTime::parse_from_str(now, "%H:%M").unwrap();
+            filter_upcoming_at(&events, now, window).iter().map(|event| event.title.clone()).collect()
+        };
+        // 開始済み・ちょうど今始まる予定は含めず、窓の終わりちょうどは含める。終日の予定は対象外
+        assert_eq!(titles("10:00", 15), vec!["Soon", "Edge"]);
+        assert!(titles("11:00", 15).is_empty());
+        // 日付をまたぐ窓では今日の残りの予定をすべて含める
+        let late = vec![timed_event("Night", "23:58", "23:59")];
+        let now = NaiveTime::parse_from_str("23:55", "%H:%M").unwrap();
+        assert_eq!(filter_upcoming_at(&late, now, 15).len(), 1);
+    }
+
+    #[test]
+    fn test_find_overlapping_events() {
+        // 1 と 3 だけが重なる。2 は 3 の終了ちょうどに始まるので重ならない (終了時刻は含まない)
+        let events = vec![
+            timed_event("Meeting A", "10:00", "11:00"),
+            timed_event("Lunch", "12:00", "13:00"),
+            timed_event("Meeting B", "10:30", "12:00"),
+        ];
+        let conflicts = find_overlapping_events(&events);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].0.title.as_str(), conflicts[0].1.title.as_str()), ("Meeting A", "Meeting B"));
+        assert_eq!(
+            format_conflict_warning(conflicts[0].0, conflicts[0].1),
+            "WARNING: \"Meeting A\" (10:00-11:00) overlaps with \"Meeting B\" (10:30-12:00)"
+        );
+
+        // 終日の予定は時間指定の予定と衝突しない
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let with_all_day = vec![all_day_event("Holiday", day, day), timed_event("Standup", "09:00", "09:15")];
+        assert!(find_overlapping_events(&with_all_day).is_empty());
+    }
+
+    #[test]
+    fn test_declined_and_cancelled_events_are_dropped() {
+        use google_calendar3::api;
+        let item = |title: &str, status: &str, self_response: Option<&str>| api::Event {
+            summary: Some(title.to_string()),
+            status: Some(status.to_string()),
+            attendees: Some(vec![
+                api::EventAttendee {
+                    email: Some("other@example.com".to_string()),
+                    response_status: Some("declined".to_string()),
+                    ..Default::default()
+                },
+                api::EventAttendee {
+                    email: Some("me@example.com".to_string()),
+                    self_: Some(true),
+                    response_status: self_response.map(str::to_string),
+                    ..Default::default()
+                },
+            ]),
+            ..Default::default()
+        };
+        let items = vec![
+            item("Accepted", "confirmed", Some("accepted")),
+            item("Declined", "confirmed", Some("declined")),
+            item("Cancelled", "cancelled", Some("accepted")),
+            item("No reply", "tentative", None),
+        ];
+        let mut events: Vec<CalendarEvent> = items.into_iter().map(event_from_api).collect();
+        events.retain(|event| !event.is_declined_or_cancelled());
+        // 他の参加者の辞退は関係ない
+        let titles: Vec<&str> = events.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, vec!["Accepted", "No reply"]);
+    }
+
+    #[tokio::test]
+    async fn test_collect_all_pages_follows_page_tokens() {
+        // 2 ページ目のトークンを持つ 1 ページ目と、トークンなしの 2 ページ目
+        let mut requested = Vec::new();
+        let items = collect_all_pages(|page_token: Option<String>| {
+            requested.push(page_token.clone());
+            async move {
+                match page_token.as_deref() {
+                    None => Ok::<_, String>((vec!["Standup", "Review"], Some("page-2".to_string()))),
+                    Some("page-2") => Ok((vec!["Lunch"], None)),
+                    Some(other) => Err(format!("unexpected token {}", other)),
+                }
+            }
+        }).await.unwrap();
+        assert_eq!(items, vec!["Standup", "Review", "Lunch"]);
+        assert_eq!(requested, vec![None, Some("page-2".to_string())]);
+    }
+
+    #[test]
+    fn test_sort_events_all_day_first_then_by_start_time() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut events = vec![
+            timed_event("Lunch", "12:00", "13:00"),
+            all_day_event("Holiday", day, day),
+            timed_event("Standup", "09:00", "09:15"),
+            all_day_event("Offsite", day, day),
+            timed_event("Review", "09:00", "10:00"),
+        ];
+        sort_events(&mut events);
+        let titles: Vec<&str> = events.iter().map(|e| e.title.as_str()).collect();
+        // 同じ開始時刻の予定は元の順序のまま
+        assert_eq!(titles, vec!["Holiday", "Offsite", "Standup", "Review", "Lunch"]);
+    }
+
+    #[test]
+    fn test_attendee_status_mapping() {
+        assert_eq!(AttendeeStatus::from_api(Some("accepted")), AttendeeStatus::Accepted);
+        assert_eq!(AttendeeStatus::from_api(Some("declined")), AttendeeStatus::Declined);
+        assert_eq!(AttendeeStatus::from_api(Some("tentative")), AttendeeStatus::Tentative);
+        assert_eq!(AttendeeStatus::from_api(Some("needsAction")), AttendeeStatus::NeedsAction);
+        assert_eq!(AttendeeStatus::from_api(None), AttendeeStatus::NeedsAction);
+    }
+
+    #[test]
+    fn test_format_events_output_with_attendees() {
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let mut event = all_day_event("Review", day, day);
+        event.attendees = vec![
+            EventAttendee {
+                email: "alice@example.com".to_string(),
+                display_name: Some("Alice".to_string()),
+                response_status: AttendeeStatus::Accepted,
+            },
+            EventAttendee {
+                email: "bob@example.com".to_string(),
+                display_name: Some("Bob".to_string()),
+                response_status: AttendeeStatus::Declined,
+            },
+        ];
+        let opts = CalendarOutputOptions {
+            header: None,
+            show_attendees: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_events_output(&[event], &opts),
+            "00:00-23:59 Review\n  ✓ Alice <alice@example.com>\n  ✗ Bob <bob@example.com>\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_events_to_tasks_creates_updates_and_cancels() {
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let mut tasks = Vec::new();
+        let events = vec![synced_event("ev1", "Standup", day), synced_event("ev2", "Review", day)];
+        sync_events_to_tasks(&events, &mut tasks, day).await;
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].name, "Standup");
+        assert_eq!(tasks[0].due, Some(day));
+        assert_eq!(tasks[0].notes.as_deref(), Some("Standup agenda"));
+        assert_eq!(tasks[1].id, 2);
+
+        // ev1 renamed, ev2 deleted
+        let events = vec![synced_event("ev1", "Daily standup", day)];
+        sync_events_to_tasks(&events, &mut tasks, day).await;
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].name, "Daily standup");
+        assert_eq!(tasks[0].status, "open");
+        assert_eq!(tasks[1].status, "cancelled");
+    }
+
+    #[tokio::test]
+    async fn test_sync_events_to_tasks_keeps_closed_tasks() {
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let mut tasks = Vec::new();
+        let events = vec![synced_event("ev1", "Standup", day), synced_event("ev2", "Review", day)];
+        sync_events_to_tasks(&events, &mut tasks, day).await;
+        tasks[0].status = "done".to_string();
+
+        // 完了済みのタスクは予定が消えてもキャンセルにしない
+        sync_events_to_tasks(&[], &mut tasks, day).await;
+        assert_eq!(tasks[0].status, "done");
+        assert_eq!(tasks[1].status, "cancelled");
+    }
+
+    #[test]
+    fn test_next_business_day_skips_custom_holiday() {
+        // 2024-06-07 is a Friday; the following Monday is a company holiday
+        let friday = NaiveDate::from_ymd_opt(2024, 6, 7).unwrap();
+        let holidays = parse_holidays("# company holidays\n2024-06-10\n").unwrap();
+        assert_eq!(next_business_day(friday, 1, &holidays), NaiveDate::from_ymd_opt(2024, 6, 11).unwrap());
+    }
+
+    #[test]
+    fn test_next_business_day_weekends_only() {
+        let friday = NaiveDate::from_ymd_opt(2024, 6, 7).unwrap();
+        assert_eq!(next_business_day(friday, 1, &HashSet::new()), NaiveDate::from_ymd_opt(2024, 6, 10).unwrap());
+    }
+
+    #[test]
+    fn test_next_business_day_counts_across_weekend() {
+        // 2024-06-06 (木) から 2 営業日後は土日を飛ばして月曜、3 営業日後は火曜
+        let thursday = NaiveDate::from_ymd_opt(2024, 6, 6).unwrap();
+        assert_eq!(next_business_day(thursday, 2, &HashSet::new()), NaiveDate::from_ymd_opt(2024, 6, 10).unwrap());
+        assert_eq!(next_business_day(thursday, 3, &HashSet::new()), NaiveDate::from_ymd_opt(2024, 6, 11).unwrap());
+        // 休日も営業日として数えない
+        let holidays = parse_holidays("2024-06-10\n").unwrap();
+        assert_eq!(next_business_day(thursday, 2, &holidays), NaiveDate::from_ymd_opt(2024, 6, 11).unwrap());
+    }
+
+    #[test]
+    fn test_next_business_day_skips_public_holiday() {
+        // 2024-07-12 is a Friday; Monday 2024-07-15 is Marine Day (海の日)
+        let friday = NaiveDate::from_ymd_opt(2024, 7, 12).unwrap();
+        let holidays = jp_public_holidays(2024);
+        assert!(holidays.contains(&NaiveDate::from_ymd_opt(2024, 7, 15).unwrap()));
+        assert_eq!(next_business_day(friday, 1, &holidays), NaiveDate::from_ymd_opt(2024, 7, 16).unwrap());
+    }
+
+    #[test]
+    fn test_parse_public_holidays_json() {
+        let content = r#"[{"date":"2024-12-25","localName":"Christmas Day","name":"Christmas Day","countryCode":"US"}]"#;
+        let holidays = parse_public_holidays_json(content).unwrap();
+        assert_eq!(holidays.len(), 1);
+        assert!(holidays.contains(&NaiveDate::from_ymd_opt(2024, 12, 25).unwrap()));
+    }
+
+    #[test]
+    fn test_parse_holidays_rejects_invalid_date() {
+        let err = parse_holidays("# company holidays\n2024-13-01").unwrap_err();
+        assert!(matches!(err, OgError::Parse { line: 2, .. }));
+    }
+
+    #[test]
+    fn test_calendar_error_display() {
+        let cases = [
+            (CalendarError::CredentialsNotFound(PathBuf::from("/home/me/.config/google/credentials.json")), "credentials not found at /home/me/.config/google/credentials.json"),
+            (CalendarError::InvalidCredentials("failed to parse: EOF".to_string()), "Invalid credentials file: failed to parse: EOF"),
+            (CalendarError::TokenExpired, "token has expired"),
+            (CalendarError::AccessDenied, "access denied"),
+            (CalendarError::NetworkError("timeout".to_string()), "Network error: timeout"),
+            (CalendarError::ApiError("404".to_string()), "Google Calendar API error: 404"),
+            (CalendarError::TokenError("Invalid account name '..'".to_string()), "Google token error: Invalid account name '..'"),
+            (CalendarError::ParseError("line 2: bad date".to_string()), "Failed to parse calendar data: line 2: bad date"),
+            (CalendarError::IoError(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied")), "I/O error: denied"),
+        ];
+        for (error, expected) in cases {
+            assert!(error.to_string().contains(expected), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_calendar_error_maps_to_og_error() {
+        assert!(matches!(OgError::from(CalendarError::TokenExpired), OgError::Auth(_)));
+        assert!(matches!(OgError::from(CalendarError::AccessDenied), OgError::Auth(_)));
+        assert!(matches!(OgError::from(CalendarError::NetworkError("timeout".to_string())), OgError::Calendar(_)));
+        assert!(matches!(OgError::from(CalendarError::CredentialsNotFound(PathBuf::from("x"))), OgError::Auth(_)));
+        assert!(matches!(OgError::from(CalendarError::TokenError("x".to_string())), OgError::Auth(_)));
+        assert!(matches!(OgError::from(CalendarError::IoError(std::io::Error::other("x"))), OgError::Io(_)));
+
+        // 祝日の読み込みなど、予定の取得途中の OgError
+        assert!(matches!(CalendarError::from(OgError::Parse { line: 2, msg: "bad date".to_string() }), CalendarError::ParseError(_)));
+        assert!(matches!(CalendarError::from(OgError::Calendar("HTTP 500".to_string())), CalendarError::ApiError(msg) if msg == "HTTP 500"));
+    }
+
+    #[test]
+    fn test_credentials_path_from_env() {
+        // 環境変数はテストの並列実行で競合するので、値を引数で渡す
+        let credentials = tempfile::NamedTempFile::new().unwrap();
+        let path = credentials_path_from(Some(credentials.path().into())).unwrap();
+        assert_eq!(existing_credentials_path(path).unwrap(), credentials.path());
+
+        let missing = credentials.path().with_extension("missing");
+        let path = credentials_path_from(Some(missing.clone().into())).unwrap();
+        let err = existing_credentials_path(path).unwrap_err();
+        assert!(matches!(err, CalendarError::CredentialsNotFound(_)));
+        assert!(err.to_string().contains(&missing.display().to_string()));
+
+        // 空の値は未設定と同じ扱い
+        assert_eq!(credentials_path_from(Some("".into())).ok(), credentials_path_from(None).ok());
+    }
+
+    #[test]
+    fn test_auth_return_method() {
+        assert!(matches!(return_method_for(false, None), InstalledFlowReturnMethod::HTTPRedirect));
+        assert!(matches!(return_method_for(true, None), InstalledFlowReturnMethod::Interactive));
+
+        // 環境変数でもブラウザなしのフローに切り替わる
+        assert!(matches!(return_method_for(false, Some("1")), InstalledFlowReturnMethod::Interactive));
+        assert!(matches!(return_method_for(false, Some("TRUE")), InstalledFlowReturnMethod::Interactive));
+        assert!(matches!(return_method_for(false, Some("0")), InstalledFlowReturnMethod::HTTPRedirect));
+    }
+
+    #[test]
+    fn test_doctor_credentials_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.json");
+
+        // ファイルがない場合は 1 項目だけ失敗を返す
+        let checks = check_credentials_file(&path);
+        assert_eq!(checks.len(), 1);
+        assert!(!checks[0].passed);
+        assert!(checks[0].detail.contains("not found"));
+
+        fs::write(&path, "{\"installed\": {").unwrap();
+        let checks = check_credentials_file(&path);
+        assert_eq!(checks.iter().map(|c| c.passed).collect::<Vec<_>>(), vec![true, false]);
+        assert_eq!(checks[1].name, "Credentials JSON");
+
+        // サービスアカウントの鍵ファイルは JSON としては正しいがクライアントの種類で失敗する
+        fs::write(&path, r#"{"type":"service_account","client_email":"x@example.iam.gserviceaccount.com"}"#).unwrap();
+        let checks = check_credentials_file(&path);
+        assert_eq!(checks.iter().map(|c| c.passed).collect::<Vec<_>>(), vec![true, true, false]);
+        assert_eq!(checks[2].name, "OAuth client type");
+        assert!(checks[2].hint.as_deref().unwrap().contains("Desktop app"));
+
+        fs::write(&path, r#"{"installed":{"client_id":"id","client_secret":"secret","auth_uri":"https://accounts.google.com/o/oauth2/auth","token_uri":"https://oauth2.googleapis.com/token","redirect_uris":["http://localhost"]}}"#).unwrap();
+        assert!(check_credentials_file(&path).iter().all(|c| c.passed));
+    }
+
+    #[test]
+    fn test_doctor_token_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token.json");
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        assert!(!check_token_file(&path, now).passed);
+
+        // 2024-02-01 (通算 32 日目) に期限切れ。リフレッシュトークンがあれば問題ない
+        let expired = r#"[{"scopes":["s"],"token":{"access_token":"a","refresh_token":"r","expires_at":[2024,32,0,0,0,0,0,0,0]}}]"#;
+        fs::write(&path, expired).unwrap();
+        let check = check_token_file(&path, now);
+        assert!(check.passed);
+        assert!(check.detail.contains("access token expired"));
+
+        fs::write(&path, expired.replace(r#""refresh_token":"r","#, "")).unwrap();
+        let check = check_token_file(&path, now);
+        assert!(!check.passed);
+        assert!(check.hint.is_some());
+
+        fs::write(&path, r#"[{"scopes":["s"],"token":{"access_token":"a","expires_at":"2024-03-02T00:00:00Z"}}]"#).unwrap();
+        assert!(check_token_file(&path, now).detail.contains("valid until"));
+    }
+
+    #[test]
+    fn test_token_path_from_env() {
+        let env_path = Some("/tmp/og-test/token.json".into());
+        assert_eq!(token_path_from(None, env_path.clone()).unwrap(), PathBuf::from("/tmp/og-test/token.json"));
+        // アカウント指定時は OG_TOKEN_PATH を使わない
+        assert!(token_path_from(Some("work"), env_path).unwrap().ends_with("token-work.json"));
+    }
+
+    #[test]
+    fn test_parse_credentials_accepts_web_key() {
+        let web = r#"{"web":{"client_id":"id.apps.googleusercontent.com","client_secret":"secret","auth_uri":"https://accounts.google.com/o/oauth2/auth","token_uri":"https://oauth2.googleapis.com/token","redirect_uris":["http://localhost"]}}"#;
+        let credentials = parse_credentials(web).unwrap();
+        assert_eq!(credentials.client_id, "id.apps.googleusercontent.com");
+        assert_eq!(credentials.redirect_uris, vec!["http://localhost".to_string()]);
+
+        // InstalledCredentials は client_secret を含むため Debug を実装しない (unwrap_err は使えない)
+        assert!(matches!(
+            parse_credentials(r#"{"type":"service_account"}"#),
+            Err(err @ CalendarError::InvalidCredentials(_)) if err.to_string().contains("Desktop/Installed OAuth client")
+        ));
+    }
+
+    #[test]
+    fn test_token_path_per_account() {
+        let dir = Path::new("/home/me/.config/google");
+        assert_eq!(token_path_in(dir, None).unwrap(), dir.join("token.json"));
+        assert_eq!(token_path_in(dir, Some("work")).unwrap(), dir.join("token-work.json"));
+        assert_eq!(token_path_in(dir, Some("personal")).unwrap(), dir.join("token-personal.json"));
+        assert!(token_path_in(dir, Some("../evil")).is_err());
+        assert!(token_path_in(dir, Some("")).is_err());
+    }
+}
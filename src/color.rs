@@ -0,0 +1,70 @@
+use crate::filter::is_overdue;
+use crate::task_model::Task;
+use chrono::NaiveDate;
+
+// 端末表示用の ANSI カラー (og fmt / og cal)。色付けしない場合の文字列は元のまま
+const ANSI_RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiColor {
+    Bold,
+    Dim,
+    Red,
+    Green,
+    Yellow,
+}
+
+impl AnsiColor {
+    pub fn code(self) -> &'static str {
+        match self {
+            AnsiColor::Bold => "\x1b[1m",
+            AnsiColor::Dim => "\x1b[2m",
+            AnsiColor::Red => "\x1b[31m",
+            AnsiColor::Green => "\x1b[32m",
+            AnsiColor::Yellow => "\x1b[33m",
+        }
+    }
+}
+
+pub fn colorize(text: &str, color: AnsiColor) -> String {
+    format!("{}{}{}", color.code(), text, ANSI_RESET)
+}
+
+// タスク一覧 (og list など) 向けの色: done は緑、期限切れは赤、今日が期限なら黄。優先度 A は太字
+pub fn colorize_task(text: &str, task: &Task, today: NaiveDate) -> String {
+    let colored = if task.status.eq_ignore_ascii_case("done") {
+        colorize(text, AnsiColor::Green)
+    } else if is_overdue(task, today) {
+        colorize(text, AnsiColor::Red)
+    } else if task.due == Some(today) {
+        colorize(text, AnsiColor::Yellow)
+    } else {
+        text.to_string()
+    };
+    if task.priority == "A" {
+        colorize(&colored, AnsiColor::Bold)
+    } else {
+        colored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_parser::parse_markdown_document_to_tasks;
+
+    #[test]
+    fn test_colorize_task_rules() {
+        let md_doc = "\
+- [x] [[Done]] id:1 due:2024-06-01
+- [ ] [[Overdue]] id:2 due:2024-06-01
+- [ ] (A) [[Today]] id:3 due:2024-06-10
+- [ ] [[Later]] id:4 due:2024-07-01";
+        let today = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+        let tasks = parse_markdown_document_to_tasks(md_doc, today).unwrap();
+        assert_eq!(colorize_task("x", &tasks[0], today), "\x1b[32mx\x1b[0m");
+        assert_eq!(colorize_task("x", &tasks[1], today), "\x1b[31mx\x1b[0m");
+        assert_eq!(colorize_task("x", &tasks[2], today), "\x1b[1m\x1b[33mx\x1b[0m\x1b[0m");
+        assert_eq!(colorize_task("x", &tasks[3], today), "x");
+    }
+}
@@ -0,0 +1,237 @@
+// 端末向けの人が読む一覧表示 (og due のテキスト出力など) の補助
+
+use crate::task_model::Task;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::collections::BTreeMap;
+
+// --show-notes でタスク行の下に付けるメモの行頭
+pub const NOTE_PREFIX: &str = "  > ";
+pub const NOTE_WIDTH: usize = 80;
+
+// メモを prefix 付きの行に折り返す。prefix を含めて width 文字以内に収まるよう単語の間で折り返し、
+// 元の改行はそのまま保つ。1 単語で幅を超える場合はその単語だけの行にする。各行は改行で終わる
+pub fn wrap_note(note: &str, width: usize, prefix: &str) -> String {
+    let available = width.saturating_sub(prefix.chars().count()).max(1);
+    let mut output = String::new();
+    for source_line in note.lines() {
+        let mut current = String::new();
+        for word in source_line.split_whitespace() {
+            if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > available {
+                output.push_str(&format!("{}{}\n", prefix, current));
+                current.clear();
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        // 空行は prefix だけの行 (末尾の空白は付けない)
+        let line = format!("{}{}", prefix, current);
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+// og fmt --max-line-length: max_len 文字を超えるタスク行の属性部分を、2 文字深いインデントに "↳ " を付けた
+// 継続行に送る。タスク名 [[...]] までは分割しない。属性は引用符の中では区切らず、継続行も max_len に
+// 収まるよう必要なら複数行にする。max_len が 0 の場合とタスク行以外はそのまま返す
+pub fn wrap_long_line(line: &str, max_len: usize) -> String {
+    if max_len == 0 || line.chars().count() <= max_len || !line.trim_start().starts_with("- [") {
+        return line.to_string();
+    }
+    let name_end = line.find("[[").and_then(|start| line[start..].find("]]").map(|end| start + end + 2));
+    let Some(name_end) = name_end else {
+        return line.to_string();
+    };
+    let (head, attributes) = line.split_at(name_end);
+    let tokens = split_attribute_tokens(attributes);
+    if tokens.is_empty() {
+        return line.to_string();
+    }
+
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let prefix = format!("{}  {} ", indent, crate::markdown_parser::ATTRIBUTE_CONTINUATION);
+    let mut lines = vec![head.to_string()];
+    let mut current = String::new();
+    for token in tokens {
+        if !current.is_empty() && prefix.chars().count() + current.chars().count() + 1 + token.chars().count() > max_len {
+            lines.push(format!("{}{}", prefix, current));
+            current.clear();
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(token);
+    }
+    lines.push(format!("{}{}", prefix, current));
+    lines.join("\n")
+}
+
+// 属性文字列を空白で区切る。note:"a b" のような二重引用符の中の空白では区切らない ("" は引用符のエスケープ)
+fn split_attribute_tokens(attributes: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut in_quotes = false;
+    let mut start: Option<usize> = None;
+    for (index, c) in attributes.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        if c.is_whitespace() && !in_quotes {
+            if let Some(token_start) = start.take() {
+                tokens.push(&attributes[token_start..index]);
+            }
+        } else if start.is_none() {
+            start = Some(index);
+        }
+    }
+    if let Some(token_start) = start {
+        tokens.push(&attributes[token_start..]);
+    }
+    tokens
+}
+
+// add_week_annotations のグループのキー。due のないグループ (no_due) が最後に並ぶ
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct WeekGroupKey {
+    no_due: bool,
+    week: Option<(i32, u32)>, // (ISO 週の年, 週番号)
+}
+
+// og fmt --add-week-of-year: 整形済みの行をトップレベルのタスクごとのまとまりに分け、due の ISO 週ごとに
+// <!-- W24 2024-06-10 – 2024-06-16 --> (月曜〜日曜) のコメント行を付けてまとめる。週は古い順に並べ、
+// due のないタスクは最後の <!-- No Due Date --> にまとめる。同じ週の中では元の順を保つ。
+// 最初のタスク行より前の行 (--emit-next-id-hint のヒントなど) は先頭に残す
+pub fn add_week_annotations(formatted_lines: Vec<String>, tasks: &[Task]) -> Vec<String> {
+    let is_top_level_task_line = |line: &str| line.starts_with("- [");
+    let first_task = formatted_lines.iter().position(|line| is_top_level_task_line(line)).unwrap_or(formatted_lines.len());
+    let mut lines = formatted_lines.into_iter();
+    let mut output: Vec<String> = lines.by_ref().take(first_task).collect();
+
+    // トップレベルのタスク行から次のトップレベルのタスク行の手前まで (サブタスクや継続行) が 1 タスク分
+    let mut blocks: Vec<Vec<String>> = Vec::new();
+    for line in lines {
+        if is_top_level_task_line(&line) || blocks.is_empty() {
+            blocks.push(Vec::new());
+        }
+        blocks.last_mut().unwrap().push(line);
+    }
+
+    // キーの先頭を due の有無にして、期限のないグループを最後に置く
+    let mut groups: BTreeMap<WeekGroupKey, Vec<String>> = BTreeMap::new();
+    for (index, block) in blocks.into_iter().enumerate() {
+        let week = tasks.get(index).and_then(|task| task.due).map(|due| (due.iso_week().year(), due.iso_week().week()));
+        groups.entry(WeekGroupKey { no_due: week.is_none(), week }).or_default().extend(block);
+    }
+    for (WeekGroupKey { week, .. }, block) in groups {
+        output.push(match week.and_then(|(year, week)| NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)) {
+            Some(monday) => format!("<!-- W{:02} {} – {} -->", monday.iso_week().week(), monday, monday + Duration::days(6)),
+            None => "<!-- No Due Date -->".to_string(),
+        });
+        output.extend(block);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_note_at_exactly_width() {
+        // prefix (4 文字) + 76 文字 = 80 文字はちょうど収まる
+        let fits = format!("{} {}", "a".repeat(37), "b".repeat(38));
+        assert_eq!(wrap_note(&fits, 80, NOTE_PREFIX), format!("  > {}\n", fits));
+
+        // 1 文字増えると最後の単語が次の行に回る
+        let over = format!("{} {}", "a".repeat(37), "b".repeat(39));
+        assert_eq!(wrap_note(&over, 80, NOTE_PREFIX), format!("  > {}\n  > {}\n", "a".repeat(37), "b".repeat(39)));
+
+        // 幅より長い単語は分割しない
+        let long_word = "x".repeat(100);
+        assert_eq!(wrap_note(&long_word, 80, NOTE_PREFIX), format!("  > {}\n", long_word));
+    }
+
+    #[test]
+    fn test_wrap_note_keeps_newlines() {
+        let note = "First line\n\nThird line wraps here";
+        assert_eq!(wrap_note(note, 20, NOTE_PREFIX), "  > First line\n  >\n  > Third line wraps\n  > here\n");
+    }
+
+    #[test]
+    fn test_wrap_long_line_moves_attributes() {
+        let line = "    - [ ] (A) [[Write the quarterly report]] id:3 due:2024-05-01 +work @office";
+        assert_eq!(wrap_long_line(line, 0), line);
+        assert_eq!(wrap_long_line(line, 200), line);
+
+        let wrapped = wrap_long_line(line, 50);
+        assert_eq!(wrapped, "    - [ ] (A) [[Write the quarterly report]]\n      ↳ id:3 due:2024-05-01 +work @office");
+
+        // 継続行にも収まらない属性はさらに次の継続行へ。引用符の中では区切らない
+        let with_note = "- [ ] (B) [[Call]] id:1 due:2024-01-01 note:\"call back after lunch\"";
+        assert_eq!(
+            wrap_long_line(with_note, 30),
+            "- [ ] (B) [[Call]]\n  ↳ id:1 due:2024-01-01\n  ↳ note:\"call back after lunch\""
+        );
+
+        // タスク行以外は対象外
+        let text = format!("# {}", "x".repeat(100));
+        assert_eq!(wrap_long_line(&text, 20), text);
+    }
+
+    #[test]
+    fn test_wrap_long_line_roundtrip() {
+        let markdown = concat!(
+            "- [ ] (A) [[Plan the offsite]] id:1 due:2024-06-01 +team @office #planning #travel created:2024-01-01 updated:2024-02-01 completed:\"\" progress:40 estimate:2h30m depends:2 note:\"book the \"\"big\"\" room\"\n",
+            "    - [x] (B) [[Book venue]] id:2 due:\"\" created:2024-01-01 updated:\"\" completed:2024-01-05\n",
+        );
+        let default_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let original = crate::markdown_parser::parse_markdown_document_to_tasks(markdown, default_date).unwrap();
+
+        let wrapped: Vec<String> = markdown.lines().map(|line| wrap_long_line(line, 60)).collect();
+        let wrapped = wrapped.join("\n");
+        assert!(wrapped.lines().all(|line| line.chars().count() <= 60 || !line.contains('↳')));
+        assert!(wrapped.lines().filter(|line| line.trim_start().starts_with('↳')).count() > 2);
+
+        let reparsed = crate::markdown_parser::parse_markdown_document_to_tasks(&wrapped, default_date).unwrap();
+        assert_eq!(serde_json::to_value(&reparsed).unwrap(), serde_json::to_value(&original).unwrap());
+    }
+
+    #[test]
+    fn test_add_week_annotations() {
+        let markdown = concat!(
+            "<!-- og:next_id:6 -->\n",
+            "- [ ] [[Next week]] id:1 due:2024-06-17\n",
+            "- [ ] [[No due]] id:2\n",
+            "- [ ] [[Wednesday]] id:3 due:2024-06-12\n",
+            "    - [ ] [[Child]] id:4 due:2024-01-01\n",
+            "- [ ] [[Monday]] id:5 due:2024-06-10\n",
+        );
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks(markdown, default_date).unwrap();
+        let lines: Vec<String> = markdown.lines().map(String::from).collect();
+
+        // 2024-06-12 (水) は ISO 第 24 週 (06-10〜06-16)、06-17 (月) から第 25 週。サブタスクの due は見ない
+        assert_eq!(
+            add_week_annotations(lines, &tasks),
+            vec![
+                "<!-- og:next_id:6 -->",
+                "<!-- W24 2024-06-10 – 2024-06-16 -->",
+                "- [ ] [[Wednesday]] id:3 due:2024-06-12",
+                "    - [ ] [[Child]] id:4 due:2024-01-01",
+                "- [ ] [[Monday]] id:5 due:2024-06-10",
+                "<!-- W25 2024-06-17 – 2024-06-23 -->",
+                "- [ ] [[Next week]] id:1 due:2024-06-17",
+                "<!-- No Due Date -->",
+                "- [ ] [[No due]] id:2",
+            ]
+        );
+
+        // 年をまたぐ週は ISO 週の年で扱う (2024-12-30 は 2025 年の第 1 週)
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks("- [ ] [[A]] id:1 due:2024-12-30", default_date).unwrap();
+        assert_eq!(
+            add_week_annotations(vec!["- [ ] [[A]] id:1 due:2024-12-30".to_string()], &tasks),
+            vec!["<!-- W01 2024-12-30 – 2025-01-05 -->", "- [ ] [[A]] id:1 due:2024-12-30"]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use thiserror::Error;
+
+// og 全体の統一エラー型。CLI (main) では文字列にして表示する。
+#[derive(Debug, Error)]
+pub enum OgError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    // Markdown のパースエラー (line は 1 始まりの行番号)
+    #[error("line {line}: {msg}")]
+    Parse { line: usize, msg: String },
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    // 認証情報の不備・トークン期限切れなど (再認証が必要)
+    #[error("authentication error: {0}")]
+    Auth(String),
+    // タスクの内容が仕様に合わない
+    #[error("validation error: {0}")]
+    Validation(String),
+    // Google Calendar / 祝日 API の呼び出しエラー
+    #[error("calendar error: {0}")]
+    Calendar(String),
+    // GitHub などの外部サービスからの取り込み (og import) のエラー
+    #[error("import error: {0}")]
+    Import(String),
+}
+
+impl OgError {
+    // 1行単位でパースしたエラーに、ドキュメント内の行番号を付け直す
+    pub fn at_line(self, line: usize) -> Self {
+        match self {
+            OgError::Parse { msg, .. } => OgError::Parse { line, msg },
+            other => other,
+        }
+    }
+}
+
+// Markdown のタスク行 1 行分のパースエラー。行番号は持たず、ドキュメント単位の呼び出し元が at_line で付ける
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ParseError {
+    // 行がタスク行の基本形式 ("[ ] (A) [[名前]] 属性...") に合わない
+    #[error("Line '{line:?}' does not match base task format")]
+    NoMatch { line: String },
+    // 日付の形をしているが実在しない日付 (due:2024-02-30 など)
+    #[error("invalid {field} date '{raw}'")]
+    InvalidDate { field: String, raw: String },
+    // id が i64 に収まらない
+    #[error("invalid id '{raw}'")]
+    InvalidId { raw: String },
+    #[error("Failed to compile base regex: {0}")]
+    RegexError(String),
+    #[error("Line '{line}' has unknown status marker '[{marker}]'")]
+    UnknownStatus { line: String, marker: char },
+    #[error("Line '{line}' has conflicting priorities: ({positional}) and priority:{attribute}")]
+    ConflictingPriorities { line: String, positional: String, attribute: String },
+    // progress / estimate の値が範囲外・解釈できない
+    #[error("Line '{line}' has invalid {field} '{raw}' (expected {expected})")]
+    InvalidValue { line: String, field: &'static str, raw: String, expected: &'static str },
+}
+
+impl ParseError {
+    // ドキュメント内の行番号 (1 始まり) を付けて OgError にする
+    pub fn at_line(self, line: usize) -> OgError {
+        OgError::Parse { line, msg: self.to_string() }
+    }
+}
+
+// 1 行単位のパースを ? で OgError に変換する場合は行番号 1 とみなす
+impl From<ParseError> for OgError {
+    fn from(e: ParseError) -> Self {
+        e.at_line(1)
+    }
+}
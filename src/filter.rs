@@ -0,0 +1,510 @@
+use crate::task_model::Task;
+use chrono::{Datelike, NaiveDate, Weekday};
+use std::str::FromStr;
+
+// 完了・キャンセル・スキップ済みのタスク (期限切れとみなさない。og cal --sync-to-tasks もキャンセルしない)
+pub fn is_closed(task: &Task) -> bool {
+    ["done", "cancelled", "skipped"].iter().any(|s| task.status.eq_ignore_ascii_case(s))
+}
+
+// 期限切れ: due が今日より前で、未完了 (done/cancelled/skipped 以外)
+pub fn is_overdue(task: &Task, today: NaiveDate) -> bool {
+    match task.due {
+        Some(due) => due < today && !is_closed(task),
+        None => false,
+    }
+}
+
+// og fmt --overdue-action の動作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverdueAction {
+    Warn, // 標準エラーに警告を出す
+    Mark, // 優先度を A にする (元の優先度は extra["original_priority"] に保存)
+    Skip, // 出力から除外する
+}
+
+impl FromStr for OverdueAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "warn" => Ok(OverdueAction::Warn),
+            "mark" => Ok(OverdueAction::Mark),
+            "skip" => Ok(OverdueAction::Skip),
+            _ => Err(format!("Invalid overdue action '{}' (expected warn, mark or skip)", s)),
+        }
+    }
+}
+
+// 期限切れタスクを深さ優先で列挙する (サブタスクも含む)
+pub fn overdue_tasks(tasks: &[Task], today: NaiveDate) -> Vec<&Task> {
+    crate::task_model::flatten_all(tasks).filter(|t| is_overdue(t, today)).collect()
+}
+
+// 期限切れタスクの優先度を A にする (元の優先度は最初に記録したものを保つ)
+pub fn mark_overdue(tasks: &mut [Task], today: NaiveDate) {
+    for task in tasks.iter_mut() {
+        task.flatten_mut(&mut |t| {
+            if is_overdue(t, today) {
+                let original = std::mem::replace(&mut t.priority, "A".to_string());
+                t.extra
+                    .get_or_insert_with(Default::default)
+                    .entry("original_priority".to_string())
+                    .or_insert(serde_json::Value::String(original));
+            }
+        });
+    }
+}
+
+// 期限切れタスクを除外する (期限切れの親を除外すると、その子も除外される)
+pub fn skip_overdue(tasks: Vec<Task>, today: NaiveDate) -> Vec<Task> {
+    tasks
+        .into_iter()
+        .filter(|t| !is_overdue(t, today))
+        .map(|mut t| {
+            t.subtasks = t.subtasks.map(|subtasks| skip_overdue(subtasks, today));
+            t
+        })
+        .collect()
+}
+
+// og archive の対象: done か cancelled で、サブタスクも (孫まで) 全て done か cancelled のタスク
+pub fn is_archivable(task: &Task) -> bool {
+    task.flatten().all(|t| t.status.eq_ignore_ascii_case("done") || t.status.eq_ignore_ascii_case("cancelled"))
+}
+
+// タスクを (残すタスク, アーカイブするタスク) に分ける。ID は振り直さない。
+// アーカイブはサブタスクごと移動し、一部でも未完了の子を持つ親は残す。
+// 残した親の下の完了済みのサブツリーも (親から外して) アーカイブし、並びは深さ優先の順になる
+pub fn split_archivable(tasks: Vec<Task>) -> (Vec<Task>, Vec<Task>) {
+    let mut active = Vec::new();
+    let mut archived = Vec::new();
+    for mut task in tasks {
+        if is_archivable(&task) {
+            archived.push(task);
+            continue;
+        }
+        if let Some(subtasks) = task.subtasks.take() {
+            let (active_subtasks, archived_subtasks) = split_archivable(subtasks);
+            archived.extend(archived_subtasks);
+            task.subtasks = if active_subtasks.is_empty() { None } else { Some(active_subtasks) };
+        }
+        active.push(task);
+    }
+    (active, archived)
+}
+
+// completed が cutoff より前の done タスクを取り除く (og fmt --strip-completed-older-than)。
+// 親が取り除かれたらサブタスクもまとめて消え、残った親のサブタスクは個別に判定する。
+// 戻り値の件数は取り除いたタスクの総数 (サブタスク含む)。
+pub fn strip_old_completed(tasks: Vec<Task>, cutoff: NaiveDate) -> (Vec<Task>, usize) {
+    let mut removed = 0;
+    let mut kept = Vec::new();
+    for mut task in tasks {
+        let is_old_done = task.status.eq_ignore_ascii_case("done") && task.completed.is_some_and(|completed| completed < cutoff);
+        if is_old_done {
+            removed += task.flatten().count();
+            continue;
+        }
+        if let Some(subtasks) = task.subtasks.take() {
+            let (subtasks, removed_subtasks) = strip_old_completed(subtasks, cutoff);
+            removed += removed_subtasks;
+            task.subtasks = Some(subtasks);
+        }
+        kept.push(task);
+    }
+    (kept, removed)
+}
+
+// completed が [since, until] (両端含む) に入るタスクを選ぶ。None の境界は無制限。
+// 親が範囲外でも、範囲内のサブタスクは親の位置に繰り上げて残す。completed が None のタスクは除外。
+pub fn completed_between(tasks: &[Task], since: Option<NaiveDate>, until: Option<NaiveDate>) -> Vec<Task> {
+    let mut result = Vec::new();
+    for task in tasks {
+        let subtasks = completed_between(task.subtasks.as_deref().unwrap_or(&[]), since, until);
+        let in_range = task.completed.is_some_and(|completed| {
+            since.is_none_or(|since| completed >= since) && until.is_none_or(|until| completed <= until)
+        });
+        if in_range {
+            let mut selected = task.clone();
+            selected.subtasks = if subtasks.is_empty() { None } else { Some(subtasks) };
+            result.push(selected);
+        } else {
+            result.extend(subtasks);
+        }
+    }
+    result
+}
+
+// due が date のタスク (サブタスクも含む)
+pub fn tasks_due_on(tasks: &[Task], date: NaiveDate) -> Vec<&Task> {
+    tasks_due_within(tasks, date, date)
+}
+
+// due が [from, to] (両端含む) に入るタスク (サブタスクも含む)。親子とも該当すれば両方を返す
+pub fn tasks_due_within(tasks: &[Task], from: NaiveDate, to: NaiveDate) -> Vec<&Task> {
+    crate::task_model::flatten_all(tasks)
+        .filter(|t| t.due.is_some_and(|due| from <= due && due <= to))
+        .collect()
+}
+
+// og list --tag の条件。`tag:` と `#` は省略できる (`tag:area:health` = `#area:health` = `area:health`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagQuery {
+    Tag(String),              // #tag (tags に含まれる)
+    KeyValue(String, String), // #key:value (tags_kv[key] == value)
+}
+
+impl FromStr for TagQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let query = s.trim();
+        let query = query.strip_prefix("tag:").unwrap_or(query);
+        let query = query.strip_prefix('#').unwrap_or(query);
+        match query.split_once(':') {
+            Some((key, value)) if !key.is_empty() && !value.is_empty() => Ok(TagQuery::KeyValue(key.to_string(), value.to_string())),
+            None if !query.is_empty() => Ok(TagQuery::Tag(query.to_string())),
+            _ => Err(format!("Invalid tag query '{}' (expected TAG or KEY:VALUE, optionally prefixed with tag:)", s)),
+        }
+    }
+}
+
+// タスク自身のタグだけを見る (サブタスクのタグは親に引き継がない)
+pub fn matches_tag_query(task: &Task, query: &TagQuery) -> bool {
+    match query {
+        TagQuery::Tag(tag) => task.tags.as_ref().is_some_and(|tags| tags.contains(tag)),
+        TagQuery::KeyValue(key, value) => task.tags_kv.as_ref().and_then(|tags_kv| tags_kv.get(key)).is_some_and(|v| v == value),
+    }
+}
+
+// --group-by の見出しのキー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupKey {
+    Project,
+    Context, // 複数のコンテキストを持つタスクは各グループに入る
+    Tag,     // 複数のタグを持つタスクは各グループに入る
+    Status,
+}
+
+impl FromStr for GroupKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "project" => Ok(GroupKey::Project),
+            "context" => Ok(GroupKey::Context),
+            "tag" => Ok(GroupKey::Tag),
+            "status" => Ok(GroupKey::Status),
+            _ => Err(format!("Invalid group key '{}' (expected project, context, tag or status)", s)),
+        }
+    }
+}
+
+// キーを持たないタスクのグループ名
+pub const NO_GROUP_HEADING: &str = "(none)";
+
+// トップレベルのタスクを見出しごとに振り分ける (サブタスクは親と同じグループ)。
+// 見出しは +project / @context / #tag / status の形で名前順、(none) は最後。グループ内は元の順序のまま
+pub fn group_tasks(tasks: &[Task], key: GroupKey) -> Vec<(String, Vec<Task>)> {
+    let mut groups: std::collections::BTreeMap<String, Vec<Task>> = std::collections::BTreeMap::new();
+    let mut ungrouped: Vec<Task> = Vec::new();
+    for task in tasks {
+        let headings: Vec<String> = match key {
+            GroupKey::Project => task.project.iter().map(|p| format!("+{}", p)).collect(),
+            GroupKey::Context => task.contexts.iter().flatten().map(|c| format!("@{}", c)).collect(),
+            GroupKey::Tag => task.tags.iter().flatten().map(|t| format!("#{}", t)).collect(),
+            GroupKey::Status => vec![task.status.clone()],
+        };
+        if headings.is_empty() {
+            ungrouped.push(task.clone());
+        }
+        for heading in headings {
+            groups.entry(heading).or_default().push(task.clone());
+        }
+    }
+    let mut result: Vec<(String, Vec<Task>)> = groups.into_iter().collect();
+    if !ungrouped.is_empty() {
+        result.push((NO_GROUP_HEADING.to_string(), ungrouped));
+    }
+    result
+}
+
+// --since / --until の日付: YYYY-MM-DD, today, または -7d のような今日からの相対日数
+pub fn parse_date_bound(value: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("today") {
+        return Ok(today);
+    }
+    if let Some(days) = value.strip_suffix('d').and_then(|n| n.parse::<i64>().ok()) {
+        return Ok(today + chrono::Duration::days(days));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{}' (expected YYYY-MM-DD, today or an offset like -7d)", value))
+}
+
+// date を含む ISO 8601 の週 (月曜始まり) の (月曜, 日曜)
+pub fn iso_week_bounds(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let monday = date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64);
+    (monday, monday + chrono::Duration::days(6))
+}
+
+// og due --due-week の週: this (今週), next (来週), または 2024-W32 のような ISO 週。(月曜, 日曜) を返す
+pub fn parse_due_week(value: &str, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), String> {
+    let value = value.trim();
+    match value.to_ascii_lowercase().as_str() {
+        "this" => return Ok(iso_week_bounds(today)),
+        "next" => return Ok(iso_week_bounds(today + chrono::Duration::days(7))),
+        _ => {}
+    }
+    let monday = value
+        .split_once(['W', 'w'])
+        .and_then(|(year, week)| Some((year.strip_suffix('-')?.parse::<i32>().ok()?, week.parse::<u32>().ok()?)))
+        .and_then(|(year, week)| NaiveDate::from_isoywd_opt(year, week, Weekday::Mon))
+        .ok_or_else(|| format!("Invalid week '{}' (expected this, next or an ISO week like 2024-W32)", value))?;
+    Ok(iso_week_bounds(monday))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_task(id: i64, status: &str, due: Option<NaiveDate>) -> Task {
+        Task {
+            name: format!("Task {}", id),
+            status: status.to_string(),
+            priority: "C".to_string(),
+            id,
+            created: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            display_order: id,
+            due,
+            updated: None,
+            completed: None,
+            project: None,
+            contexts: None,
+            notes: None,
+            tags: None,
+            tags_kv: None,
+            subtasks: None,
+            extra: None,
+            repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_tag_query_matches_bare_and_key_value_tags() {
+        let mut task = create_task(1, "open", None);
+        task.tags = Some(vec!["important".to_string()]);
+        task.tags_kv = Some([("area".to_string(), "health".to_string())].into_iter().collect());
+
+        let query = |s: &str| s.parse::<TagQuery>().unwrap();
+        assert_eq!(query("tag:area:health"), TagQuery::KeyValue("area".to_string(), "health".to_string()));
+        assert_eq!(query("#important"), TagQuery::Tag("important".to_string()));
+        assert!(matches_tag_query(&task, &query("tag:area:health")));
+        assert!(matches_tag_query(&task, &query("area:health")));
+        assert!(!matches_tag_query(&task, &query("tag:area:work")));
+        assert!(matches_tag_query(&task, &query("tag:important")));
+        assert!(!matches_tag_query(&task, &query("area")));
+        assert!("tag:".parse::<TagQuery>().is_err());
+        assert!("area:".parse::<TagQuery>().is_err());
+    }
+
+    fn sample_tasks() -> (Vec<Task>, NaiveDate) {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+        let past = NaiveDate::from_ymd_opt(2024, 6, 1);
+        let future = NaiveDate::from_ymd_opt(2024, 6, 20);
+        let mut parent = create_task(1, "open", future);
+        parent.subtasks = Some(vec![create_task(2, "pending", past), create_task(3, "done", past)]);
+        let tasks = vec![parent, create_task(4, "open", past), create_task(5, "CANCELLED", past), create_task(6, "open", None)];
+        (tasks, today)
+    }
+
+    #[test]
+    fn test_is_overdue() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+        assert!(is_overdue(&create_task(1, "open", NaiveDate::from_ymd_opt(2024, 6, 9)), today));
+        assert!(!is_overdue(&create_task(1, "open", Some(today)), today));
+        assert!(!is_overdue(&create_task(1, "done", NaiveDate::from_ymd_opt(2024, 6, 9)), today));
+        assert!(!is_overdue(&create_task(1, "open", None), today));
+        assert!(!is_overdue(&create_task(1, "skipped", NaiveDate::from_ymd_opt(2024, 6, 9)), today));
+    }
+
+    #[test]
+    fn test_warn_lists_overdue_tasks() {
+        let (tasks, today) = sample_tasks();
+        let ids: Vec<i64> = overdue_tasks(&tasks, today).iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![2, 4]);
+    }
+
+    #[test]
+    fn test_mark_sets_priority_and_keeps_original() {
+        let (mut tasks, today) = sample_tasks();
+        mark_overdue(&mut tasks, today);
+        let child = &tasks[0].subtasks.as_ref().unwrap()[0];
+        assert_eq!(child.priority, "A");
+        assert_eq!(child.extra.as_ref().unwrap()["original_priority"], serde_json::json!("C"));
+        assert_eq!(tasks[1].priority, "A");
+        assert_eq!(tasks[0].priority, "C");
+        assert!(tasks[0].extra.is_none());
+        assert_eq!(tasks[2].priority, "C");
+    }
+
+    #[test]
+    fn test_skip_removes_overdue_tasks() {
+        let (tasks, today) = sample_tasks();
+        let remaining = skip_overdue(tasks, today);
+        let ids: Vec<i64> = crate::task_model::flatten_all(&remaining).map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 3, 5, 6]);
+    }
+
+    #[test]
+    fn test_overdue_action_from_str() {
+        assert_eq!("warn".parse::<OverdueAction>(), Ok(OverdueAction::Warn));
+        assert_eq!("MARK".parse::<OverdueAction>(), Ok(OverdueAction::Mark));
+        assert_eq!("skip".parse::<OverdueAction>(), Ok(OverdueAction::Skip));
+        assert!("ignore".parse::<OverdueAction>().is_err());
+    }
+
+    #[test]
+    fn test_completed_between_one_week_window() {
+        let mut monday = create_task(1, "done", None);
+        monday.completed = NaiveDate::from_ymd_opt(2024, 6, 3);
+        let mut friday = create_task(2, "done", None);
+        friday.completed = NaiveDate::from_ymd_opt(2024, 6, 7);
+        let mut last_month = create_task(3, "done", None);
+        last_month.completed = NaiveDate::from_ymd_opt(2024, 5, 20);
+        let mut parent = create_task(4, "open", None);
+        parent.subtasks = Some(vec![friday, last_month]);
+        let tasks = vec![monday, parent, create_task(5, "open", None)];
+
+        let since = NaiveDate::from_ymd_opt(2024, 6, 3);
+        let until = NaiveDate::from_ymd_opt(2024, 6, 9);
+        let ids: Vec<i64> = completed_between(&tasks, since, until).iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_split_archivable_keeps_partially_done_parents() {
+        let mut done_parent = create_task(1, "done", None);
+        done_parent.subtasks = Some(vec![create_task(2, "cancelled", None)]);
+        let mut partial_parent = create_task(3, "done", None);
+        partial_parent.subtasks = Some(vec![create_task(4, "done", None), create_task(5, "open", None)]);
+        let tasks = vec![done_parent, partial_parent, create_task(6, "open", None), create_task(7, "cancelled", None)];
+
+        let (active, archived) = split_archivable(tasks);
+        assert_eq!(active.iter().map(|t| t.id).collect::<Vec<_>>(), vec![3, 6]);
+        assert_eq!(archived.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 4, 7]);
+        assert_eq!(archived[0].subtasks.as_ref().unwrap()[0].id, 2);
+        // 残した親には未完了の子だけが残る
+        assert_eq!(active[0].subtasks.as_ref().unwrap().iter().map(|t| t.id).collect::<Vec<_>>(), vec![5]);
+    }
+
+    #[test]
+    fn test_split_archivable_moves_closed_subtrees_of_active_parents() {
+        let mut closed_child = create_task(2, "done", None);
+        closed_child.subtasks = Some(vec![create_task(3, "cancelled", None)]);
+        let mut open_parent = create_task(1, "open", None);
+        open_parent.subtasks = Some(vec![closed_child]);
+
+        let (active, archived) = split_archivable(vec![open_parent]);
+        assert_eq!(active.len(), 1);
+        assert!(active[0].subtasks.is_none());
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].id, 2);
+        assert_eq!(archived[0].subtasks.as_ref().unwrap()[0].id, 3);
+    }
+
+    #[test]
+    fn test_strip_old_completed() {
+        let cutoff = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let done_on = |id: i64, day: u32| Task { completed: NaiveDate::from_ymd_opt(2024, 5, day), ..create_task(id, "done", None) };
+
+        let mut old_parent = done_on(1, 1);
+        old_parent.subtasks = Some(vec![create_task(2, "open", None)]);
+        let mut open_parent = create_task(3, "open", None);
+        open_parent.subtasks = Some(vec![done_on(4, 2), create_task(5, "open", None)]);
+        let recent = Task { completed: Some(cutoff), ..create_task(6, "done", None) };
+        let not_done = Task { completed: NaiveDate::from_ymd_opt(2024, 5, 1), ..create_task(7, "cancelled", None) };
+
+        let (kept, removed) = strip_old_completed(vec![old_parent, open_parent, recent, not_done], cutoff);
+        assert_eq!(removed, 3);
+        assert_eq!(kept.iter().map(|t| t.id).collect::<Vec<_>>(), vec![3, 6, 7]);
+        assert_eq!(kept[0].subtasks.as_ref().unwrap().iter().map(|t| t.id).collect::<Vec<_>>(), vec![5]);
+    }
+
+    #[test]
+    fn test_tasks_due_on_and_within() {
+        let (tasks, today) = sample_tasks();
+        let past = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        // サブタスク (2, 3) も対象
+        let ids = |found: Vec<&Task>| found.iter().map(|t| t.id).collect::<Vec<i64>>();
+        assert_eq!(ids(tasks_due_on(&tasks, past)), vec![2, 3, 4, 5]);
+        assert!(tasks_due_on(&tasks, today).is_empty());
+        assert_eq!(ids(tasks_due_within(&tasks, today, today + chrono::Duration::days(10))), vec![1]);
+    }
+
+    #[test]
+    fn test_group_tasks_by_tag_and_project() {
+        let mut a = create_task(1, "open", None);
+        a.tags = Some(vec!["work".to_string(), "urgent".to_string()]);
+        a.project = Some("alpha".to_string());
+        let mut b = create_task(2, "open", None);
+        b.tags = Some(vec!["work".to_string()]);
+        let c = create_task(3, "open", None);
+        let tasks = vec![a, b, c];
+
+        // 複数タグのタスクは各タグに入り、タグなしは最後の (none) に入る
+        let by_tag: Vec<(String, Vec<i64>)> = group_tasks(&tasks, GroupKey::Tag)
+            .into_iter()
+            .map(|(heading, group)| (heading, group.iter().map(|t| t.id).collect()))
+            .collect();
+        assert_eq!(by_tag, vec![
+            ("#urgent".to_string(), vec![1]),
+            ("#work".to_string(), vec![1, 2]),
+            ("(none)".to_string(), vec![3]),
+        ]);
+
+        let by_project: Vec<String> = group_tasks(&tasks, GroupKey::Project).into_iter().map(|(heading, _)| heading).collect();
+        assert_eq!(by_project, vec!["+alpha", "(none)"]);
+        assert!("owner".parse::<GroupKey>().is_err());
+    }
+
+    #[test]
+    fn test_parse_date_bound() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+        assert_eq!(parse_date_bound("-7d", today).unwrap(), NaiveDate::from_ymd_opt(2024, 6, 3).unwrap());
+        assert_eq!(parse_date_bound("today", today).unwrap(), today);
+        assert_eq!(parse_date_bound("2024-01-02", today).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert!(parse_date_bound("last week", today).is_err());
+    }
+
+    #[test]
+    fn test_parse_due_week() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        // 2024-W32 は 08-05 (月) 〜 08-11 (日)。日曜の期限はその週に入り、翌月曜は次の週
+        let (monday, sunday) = parse_due_week("2024-W32", date(2024, 1, 1)).unwrap();
+        assert_eq!((monday, sunday), (date(2024, 8, 5), date(2024, 8, 11)));
+        let tasks = vec![
+            create_task(1, "open", Some(date(2024, 8, 11))),
+            create_task(2, "open", Some(date(2024, 8, 12))),
+            create_task(3, "open", Some(date(2024, 8, 4))),
+            create_task(4, "open", None),
+        ];
+        assert_eq!(tasks_due_within(&tasks, monday, sunday).iter().map(|t| t.id).collect::<Vec<_>>(), vec![1]);
+
+        // 日曜から見た今週は、その日曜で終わる週
+        assert_eq!(parse_due_week("this", date(2024, 8, 11)).unwrap(), (date(2024, 8, 5), date(2024, 8, 11)));
+        // next は月をまたいでもよい (2024-05-29 (水) の翌週は 06-03〜06-09)
+        assert_eq!(parse_due_week("next", date(2024, 5, 29)).unwrap(), (date(2024, 6, 3), date(2024, 6, 9)));
+        // ISO 週の年は暦年と異なることがある
+        assert_eq!(parse_due_week("2025-w01", date(2024, 1, 1)).unwrap().0, date(2024, 12, 30));
+
+        assert!(parse_due_week("2024-W53", date(2024, 1, 1)).is_err());
+        assert!(parse_due_week("2024-32", date(2024, 1, 1)).is_err());
+        assert!(parse_due_week("last", date(2024, 1, 1)).is_err());
+    }
+}
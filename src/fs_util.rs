@@ -0,0 +1,207 @@
+// ファイル書き込みのユーティリティ
+use chrono::NaiveDateTime;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// --backup の保存先の決め方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupMode {
+    // <path>.bak (前回のバックアップは上書き)
+    Simple,
+    // <path>.2024-01-01T12-00-00.bak
+    Timestamped,
+}
+
+impl FromStr for BackupMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "simple" => Ok(BackupMode::Simple),
+            "timestamped" | "timestamp" => Ok(BackupMode::Timestamped),
+            other => Err(format!("Error: Unknown backup mode '{}'. Use simple or timestamped.", other)),
+        }
+    }
+}
+
+pub fn backup_path(path: &Path, mode: BackupMode, now: NaiveDateTime) -> PathBuf {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    match mode {
+        BackupMode::Simple => path.with_file_name(format!("{}.bak", file_name)),
+        BackupMode::Timestamped => path.with_file_name(format!("{}.{}.bak", file_name, now.format("%Y-%m-%dT%H-%M-%S"))),
+    }
+}
+
+// 上書きする前の内容をバックアップにコピーする。対象がまだ存在しなければ何もしない
+pub fn backup_file(path: &Path, mode: BackupMode, now: NaiveDateTime) -> io::Result<Option<PathBuf>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let backup = backup_path(path, mode, now);
+    fs::copy(path, &backup)?;
+    Ok(Some(backup))
+}
+
+// --ignore-pattern のグロブ。`!` で始まるパターンは除外の取り消しで、
+// ほかのパターンに一致していてもそれに一致するファイルは処理する (例: `*.md` と `!important.md`)
+#[derive(Debug, Clone, Default)]
+pub struct GlobFilter {
+    ignore: Vec<glob::Pattern>,
+    keep: Vec<glob::Pattern>,
+}
+
+impl GlobFilter {
+    pub fn new(patterns: &[String]) -> Result<Self, String> {
+        let mut filter = GlobFilter::default();
+        for pattern in patterns {
+            let (negated, glob_str) = match pattern.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, pattern.as_str()),
+            };
+            let compiled = glob::Pattern::new(glob_str)
+                .map_err(|e| format!("Error: Invalid --ignore-pattern '{}': {}", pattern, e))?;
+            if negated { filter.keep.push(compiled) } else { filter.ignore.push(compiled) }
+        }
+        Ok(filter)
+    }
+
+    // path を読み飛ばすべきなら true。パターンはファイル名とパス全体の両方に当てる
+    pub fn matches(&self, path: &Path) -> bool {
+        let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let hit = |patterns: &[glob::Pattern]| patterns.iter().any(|p| p.matches(&file_name) || p.matches_path(path));
+        hit(&self.ignore) && !hit(&self.keep)
+    }
+}
+
+// 同じディレクトリの一時ファイル名。rename が同一ファイルシステム内で済むように隣に置く
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()))
+}
+
+// 一時ファイルに書き込んでから rename で置き換える。
+// 途中で失敗しても対象ファイルが空や書きかけの状態で残らない。
+// シンボリックリンクはリンク先を置き換え (リンク自体は残す)、既存ファイルのパーミッションを引き継ぐ
+pub fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = match fs::canonicalize(path.as_ref()) {
+        Ok(resolved) => resolved,
+        Err(_) => path.as_ref().to_path_buf(),
+    };
+    let path = path.as_path();
+    let original_permissions = fs::metadata(path).ok().map(|metadata| metadata.permissions());
+    let temp_path = temp_path_for(path);
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        drop(file);
+        if let Some(permissions) = original_permissions {
+            fs::set_permissions(&temp_path, permissions)?;
+        }
+        replace_file(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+#[cfg(not(windows))]
+fn replace_file(from: &Path, to: &Path) -> io::Result<()> {
+    fs::rename(from, to)
+}
+
+// Windows では対象が他のプロセスに開かれていると上書きの rename が失敗することがあるので、
+// 対象を消してから再試行する
+#[cfg(windows)]
+fn replace_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(_) if to.exists() => {
+            fs::remove_file(to)?;
+            fs::rename(from, to)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.jsonl");
+        fs::write(&path, "old\n").unwrap();
+
+        write_atomic(&path, "new\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        // 一時ファイルが残っていない
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions_and_symlink() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("tasks.jsonl");
+        let link = dir.path().join("link.jsonl");
+        fs::write(&target, "old\n").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&target, &link).unwrap();
+
+        write_atomic(&link, "new\n").unwrap();
+
+        // リンクはそのままで、リンク先が置き換わる
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new\n");
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_backup_path() {
+        let now = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        let path = Path::new("dir/tasks.md");
+        assert_eq!(backup_path(path, BackupMode::Simple, now), PathBuf::from("dir/tasks.md.bak"));
+        assert_eq!(backup_path(path, BackupMode::Timestamped, now), PathBuf::from("dir/tasks.md.2024-01-01T12-00-00.bak"));
+    }
+
+    #[test]
+    fn test_backup_file_skips_missing_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = chrono::Local::now().naive_local();
+        assert_eq!(backup_file(&dir.path().join("new.jsonl"), BackupMode::Simple, now).unwrap(), None);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_write_atomic_leaves_target_untouched_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing_dir").join("tasks.jsonl");
+
+        assert!(write_atomic(&path, "new\n").is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_glob_filter_ignores_backups() {
+        let filter = GlobFilter::new(&["*.bak".to_string()]).unwrap();
+        assert!(filter.matches(Path::new("notes/tasks.md.bak")));
+        assert!(filter.matches(Path::new("tasks.jsonl.2024-01-01T12-00-00.bak")));
+        assert!(!filter.matches(Path::new("notes/tasks.md")));
+        assert!(!GlobFilter::default().matches(Path::new("tasks.md.bak")));
+    }
+
+    #[test]
+    fn test_glob_filter_negated_pattern_keeps_file() {
+        let filter = GlobFilter::new(&["*.md".to_string(), "!important.md".to_string()]).unwrap();
+        assert!(filter.matches(Path::new("notes/scratch.md")));
+        assert!(!filter.matches(Path::new("notes/important.md")));
+        assert!(GlobFilter::new(&["[".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Utc};
+use google_calendar3::{hyper, hyper_rustls};
+use og::task_model::Task;
+use og::OgError;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+const GITHUB_API_URL: &str = "https://api.github.com";
+const PER_PAGE: usize = 100;
+
+/// The fields of a GitHub issue that `og import --from-github-issues` uses.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GithubIssue {
+    pub number: u64,
+    pub title: String,
+    pub body: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub milestone: Option<GithubMilestone>,
+    #[serde(default)]
+    pub labels: Vec<GithubLabel>,
+    #[serde(default)]
+    pub assignees: Vec<GithubUser>,
+    /// Present only when the "issue" is a pull request
+    pub pull_request: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GithubMilestone {
+    pub due_on: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GithubLabel {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GithubUser {
+    pub login: String,
+}
+
+/// API base URL; `OG_GITHUB_API_URL` overrides it (GitHub Enterprise, tests)
+fn api_base_url() -> String {
+    std::env::var("OG_GITHUB_API_URL").unwrap_or_else(|_| GITHUB_API_URL.to_string())
+}
+
+/// Fetches every open issue of `OWNER/REPO`, following pages of 100.
+/// `token` (from `GITHUB_TOKEN`) is sent as a bearer token; public repositories work without it.
+pub async fn fetch_open_issues(repo: &str, token: Option<&str>) -> Result<Vec<GithubIssue>, OgError> {
+    let (owner, name) = repo
+        .split_once('/')
+        .filter(|(owner, name)| !owner.is_empty() && !name.is_empty() && !name.contains('/'))
+        .ok_or_else(|| OgError::Import(format!("Invalid repository '{}' (expected OWNER/REPO)", repo)))?;
+
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()?
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client: hyper::Client<_, hyper::Body> = hyper::Client::builder().build(https);
+
+    let mut issues = Vec::new();
+    for page in 1.. {
+        let uri = format!("{}/repos/{}/{}/issues?state=open&per_page={}&page={}", api_base_url(), owner, name, PER_PAGE, page);
+        let mut request = hyper::Request::get(uri)
+            .header("User-Agent", "og")
+            .header("Accept", "application/vnd.github+json");
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let request = request.body(hyper::Body::empty())
+            .map_err(|e| OgError::Import(format!("Invalid GitHub request: {}", e)))?;
+        let response = client.request(request).await
+            .map_err(|e| OgError::Import(format!("Failed to fetch issues of {}: {}", repo, e)))?;
+        if !response.status().is_success() {
+            return Err(OgError::Import(format!("Failed to fetch issues of {}: HTTP {}", repo, response.status())));
+        }
+        let body = hyper::body::to_bytes(response.into_body()).await
+            .map_err(|e| OgError::Import(format!("Failed to read issues of {}: {}", repo, e)))?;
+        let page_issues: Vec<GithubIssue> = serde_json::from_slice(&body)?;
+        let last_page = page_issues.len() < PER_PAGE;
+        issues.extend(page_issues);
+        if last_page {
+            break;
+        }
+    }
+    Ok(issues)
+}
+
+/// Adds issues as open tasks, skipping pull requests and issues already imported
+/// (matched by `extra["github_issue_number"]`). Returns the number of tasks added.
+/// - title → name, body → notes, created_at → created, milestone due_on → due
+/// - labels → tags and assignee logins → contexts (whitespace replaced by `-` so they stay one token)
+pub fn import_issues(issues: &[GithubIssue], existing_tasks: &mut Vec<Task>) -> usize {
+    let mut known: HashSet<u64> = existing_tasks.iter().flat_map(|t| t.flatten()).filter_map(github_issue_number).collect();
+    let mut added = 0;
+    let labels = |names: Vec<&str>| -> Option<Vec<String>> {
+        let labels: Vec<String> = names.iter().map(|name| name.split_whitespace().collect::<Vec<_>>().join("-")).filter(|name| !name.is_empty()).collect();
+        if labels.is_empty() { None } else { Some(labels) }
+    };
+
+    for issue in issues.iter().filter(|issue| issue.pull_request.is_none()) {
+        if !known.insert(issue.number) {
+            continue;
+        }
+        let mut extra = HashMap::new();
+        extra.insert("github_issue_number".to_string(), serde_json::Value::from(issue.number));
+        let id = existing_tasks.iter().flat_map(|t| t.flatten()).map(|t| t.id).max().unwrap_or(0) + 1;
+        let display_order = existing_tasks.iter().map(|t| t.display_order).max().unwrap_or(0) + 1;
+        existing_tasks.push(Task {
+            name: issue.title.clone(),
+            status: "open".to_string(),
+            priority: "N".to_string(),
+            id,
+            created: issue.created_at.date_naive(),
+            display_order,
+            due: issue.milestone.as_ref().and_then(|milestone| milestone.due_on).map(|due_on| due_on.date_naive()),
+            updated: None,
+            completed: None,
+            project: None,
+            contexts: labels(issue.assignees.iter().map(|user| user.login.as_str()).collect()),
+            notes: issue.body.clone().filter(|body| !body.trim().is_empty()),
+            tags: labels(issue.labels.iter().map(|label| label.name.as_str()).collect()),
+            tags_kv: None,
+            subtasks: None,
+            extra: Some(extra),
+            repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
+        });
+        added += 1;
+    }
+    added
+}
+
+fn github_issue_number(task: &Task) -> Option<u64> {
+    task.extra.as_ref()?.get("github_issue_number")?.as_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const ISSUES_JSON: &str = r#"[
+        {"number": 12, "title": "Fix login", "body": "Steps:\n1. open", "created_at": "2024-03-01T10:00:00Z",
+         "milestone": {"due_on": "2024-04-30T07:00:00Z"}, "labels": [{"name": "bug"}, {"name": "good first issue"}],
+         "assignees": [{"login": "octocat"}]},
+        {"number": 13, "title": "Docs", "body": null, "created_at": "2024-03-02T00:00:00Z", "milestone": null, "labels": [], "assignees": []},
+        {"number": 14, "title": "Add CI", "body": "", "created_at": "2024-03-03T00:00:00Z", "milestone": null,
+         "pull_request": {"url": "https://api.github.com/repos/o/r/pulls/14"}}
+    ]"#;
+
+    #[test]
+    fn test_import_issues_maps_fields() {
+        let issues: Vec<GithubIssue> = serde_json::from_str(ISSUES_JSON).unwrap();
+        let mut tasks = Vec::new();
+        assert_eq!(import_issues(&issues, &mut tasks), 2);
+
+        let task = &tasks[0];
+        assert_eq!(task.name, "Fix login");
+        assert_eq!(task.created, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(task.due, NaiveDate::from_ymd_opt(2024, 4, 30));
+        assert_eq!(task.notes.as_deref(), Some("Steps:\n1. open"));
+        assert_eq!(task.tags, Some(vec!["bug".to_string(), "good-first-issue".to_string()]));
+        assert_eq!(task.contexts, Some(vec!["octocat".to_string()]));
+        assert_eq!(task.extra.as_ref().unwrap()["github_issue_number"], serde_json::json!(12));
+        assert_eq!((task.id, task.display_order), (1, 1));
+
+        // 本文・ラベル・担当者がなければ None。プルリクエストは取り込まない
+        assert_eq!(tasks[1].name, "Docs");
+        assert!(tasks[1].notes.is_none() && tasks[1].tags.is_none() && tasks[1].contexts.is_none() && tasks[1].due.is_none());
+        assert_eq!(tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_import_issues_skips_known_numbers() {
+        let issues: Vec<GithubIssue> = serde_json::from_str(ISSUES_JSON).unwrap();
+        let mut tasks = Vec::new();
+        import_issues(&issues, &mut tasks);
+        tasks[0].name = "Fix login (edited)".to_string();
+
+        // 2 回目は何も追加せず、手で直した内容もそのまま
+        assert_eq!(import_issues(&issues, &mut tasks), 0);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].name, "Fix login (edited)");
+    }
+}
@@ -1,4 +1,85 @@
+//! Converts between the og Markdown task format and JSON task lists.
+//!
+//! The functions at the crate root are the stable entry points for using og as a library:
+//!
+//! ```
+//! let tasks = og::markdown_to_tasks("- [ ] (A) [[Write report]] id:1 +work").unwrap();
+//! let jsonl = og::tasks_to_jsonl(&tasks).unwrap();
+//! assert!(jsonl.contains("\"name\":\"Write report\""));
+//!
+//! let round_trip = og::jsonl_to_tasks(&jsonl).unwrap();
+//! assert_eq!(round_trip[0].project.as_deref(), Some("work"));
+//! ```
+
 pub mod task_model;
 pub mod markdown_parser;
 pub mod markdown_formatter;
-pub mod apply_logic;
\ No newline at end of file
+pub mod obsidian_parser;
+pub mod apply_logic;
+pub mod merge;
+pub mod lint;
+pub mod recurrence;
+pub mod filter;
+pub mod render;
+pub mod color;
+pub mod template;
+pub mod split;
+pub mod sort;
+pub mod transform;
+pub mod error;
+pub mod status_map;
+pub mod validate;
+pub mod stats;
+pub mod fs_util;
+pub mod display;
+pub mod topo_sort;
+
+pub use error::{OgError, ParseError};
+pub use task_model::Task;
+
+use chrono::Local;
+
+/// Parses a Markdown task document. Tasks without `created:` get today's date.
+pub fn markdown_to_tasks(markdown: &str) -> Result<Vec<Task>, OgError> {
+    markdown_parser::parse_markdown_document_to_tasks(markdown, Local::now().date_naive())
+}
+
+/// Formats tasks as a Markdown task document (one line per task, subtasks indented).
+///
+/// ```
+/// let tasks = og::markdown_to_tasks("- [x] (N) [[Done]] id:1 created:2024-01-01").unwrap();
+/// let markdown = og::tasks_to_markdown(&tasks).unwrap();
+/// assert!(markdown.starts_with("- [x] (N) [[Done]] id:1"));
+/// ```
+pub fn tasks_to_markdown(tasks: &[Task]) -> Result<String, OgError> {
+    Ok(markdown_formatter::format_tasks_to_markdown_document(tasks))
+}
+
+/// Serializes tasks as JSONL: one JSON object per line, with a trailing newline.
+pub fn tasks_to_jsonl(tasks: &[Task]) -> Result<String, OgError> {
+    let mut output = String::new();
+    for task in tasks {
+        output.push_str(&serde_json::to_string(task)?);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Parses JSONL task lines. A document starting with `[` is read as a JSON array instead.
+pub fn jsonl_to_tasks(content: &str) -> Result<Vec<Task>, OgError> {
+    if content.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(content)?);
+    }
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(OgError::from))
+        .collect()
+}
+
+/// Applies an edited Markdown document to existing tasks, as `og apply` does.
+pub fn apply(existing_tasks: Vec<Task>, markdown: &str) -> Result<Vec<Task>, OgError> {
+    let today = Local::now().date_naive();
+    let markdown_tasks = markdown_parser::parse_markdown_document_to_tasks(markdown, today)?;
+    apply_logic::apply_changes(existing_tasks, markdown_tasks, today)
+}
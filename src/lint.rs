@@ -0,0 +1,146 @@
+use crate::markdown_parser;
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+
+// og lint: ファイルを変更せずに仕様違反を報告する
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LintIssue {
+    pub line: usize, // 1始まりの行番号
+    pub message: String,
+}
+
+// ドキュメントを検査し、見つかった問題を行番号順に返す
+pub fn lint_markdown_document(markdown_document: &str) -> Vec<LintIssue> {
+    // 位置指定の優先度として解釈されなかった "(...)" を検出する
+    let invalid_priority_re = Regex::new(r#"^\((?P<priority_val>[^)\s]*)\)\s"#).unwrap();
+
+    let mut issues: Vec<LintIssue> = Vec::new();
+    let mut first_line_by_id: HashMap<i64, usize> = HashMap::new();
+    let mut previous_indent_level: Option<usize> = None;
+
+    // 継続行 (` \` / `↳`) はタスク行につなげてから検査する。行番号はタスク行のもの
+    for (index, line) in markdown_parser::logical_lines(markdown_document) {
+        let line = line.as_str();
+        let line_number = index + 1;
+        if !markdown_parser::is_task_line(line) {
+            continue;
+        }
+
+        // インデントは半角スペース4つで1レベル
+        let indent_spaces = line.chars().take_while(|&c| c == ' ').count();
+        if indent_spaces % 4 != 0 {
+            issues.push(LintIssue { line: line_number, message: format!("indentation of {} spaces is not a multiple of 4", indent_spaces) });
+        }
+        let indent_level = indent_spaces / 4;
+        let max_level = previous_indent_level.map_or(0, |level| level + 1);
+        if indent_level > max_level {
+            issues.push(LintIssue { line: line_number, message: format!("indentation level {} skips a level (expected at most {})", indent_level, max_level) });
+        }
+        previous_indent_level = Some(indent_level);
+
+        // created が省略されたタスクで due との比較が誤検出されないよう、既定値は最小日付にする
+        let task = match markdown_parser::parse_markdown_line_to_task(line.trim_start(), 0, NaiveDate::MIN, 0) {
+            Ok(task) => task,
+            Err(e) => {
+                issues.push(LintIssue { line: line_number, message: e.to_string() });
+                continue;
+            }
+        };
+
+        if task.name.trim().is_empty() {
+            issues.push(LintIssue { line: line_number, message: "task has no name".to_string() });
+        }
+
+        if let Some(caps) = invalid_priority_re.captures(&task.name) {
+            issues.push(LintIssue { line: line_number, message: format!("invalid priority '({})'", &caps["priority_val"]) });
+        }
+
+        if let Some(due) = task.due {
+            if due < task.created {
+                issues.push(LintIssue { line: line_number, message: format!("due date {} is before created date {}", due, task.created) });
+            }
+        }
+
+        if task.id != 0 {
+            if let Some(first_line) = first_line_by_id.get(&task.id) {
+                issues.push(LintIssue { line: line_number, message: format!("duplicate id:{} (first used on line {})", task.id, first_line) });
+            } else {
+                first_line_by_id.insert(task.id, line_number);
+            }
+        }
+    }
+
+    issues
+}
+
+pub fn format_issues_as_text(issues: &[LintIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("line {}: {}\n", issue.line, issue.message))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_clean_document() {
+        let md_doc = "\
+- [ ] (A) [[Parent]] id:1 created:2024-01-01 due:2024-02-01
+    - [x] [[Child]] id:2";
+        assert!(lint_markdown_document(md_doc).is_empty());
+    }
+
+    #[test]
+    fn test_lint_duplicate_id() {
+        let md_doc = "\
+- [ ] [[Task A]] id:1
+- [ ] [[Task B]] id:1";
+        let issues = lint_markdown_document(md_doc);
+        assert_eq!(issues, vec![LintIssue { line: 2, message: "duplicate id:1 (first used on line 1)".to_string() }]);
+    }
+
+    #[test]
+    fn test_lint_due_before_created_and_invalid_priority() {
+        let md_doc = "\
+- [ ] [[Late]] created:2024-03-01 due:2024-02-01
+- [ ] (a) Lowercase priority";
+        let issues = lint_markdown_document(md_doc);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].line, 1);
+        assert!(issues[0].message.contains("before created"));
+        assert_eq!(issues[1].line, 2);
+        assert!(issues[1].message.contains("invalid priority"));
+    }
+
+    #[test]
+    fn test_lint_inconsistent_indentation() {
+        let md_doc = "\
+- [ ] [[Parent]]
+  - [ ] [[Two spaces]]
+        - [ ] [[Skipped level]]";
+        let issues = lint_markdown_document(md_doc);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].line, 2);
+        assert_eq!(issues[1].line, 3);
+        assert!(issues[1].message.contains("skips a level"));
+    }
+
+    #[test]
+    fn test_lint_checks_continuation_lines() {
+        let md_doc = "\
+- [ ] [[First]] id:1
+- [ ] [[Wrapped]] \\
+    id:1 created:2024-03-01
+- [ ] [[Arrow]]
+  ↳ created:2024-03-01 due:2024-02-01";
+        let issues = lint_markdown_document(md_doc);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0], LintIssue { line: 2, message: "duplicate id:1 (first used on line 1)".to_string() });
+        assert_eq!(issues[1].line, 4);
+        assert!(issues[1].message.contains("before created"));
+    }
+}
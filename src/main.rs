@@ -2,29 +2,53 @@ use clap::Parser;
 use std::path::PathBuf;
 use std::fs;
 use std::io::{self, Read, Write};
-use chrono::{Local};
+use chrono::{Local, NaiveDate};
+use rayon::prelude::*;
 
-mod task_model;
-mod markdown_parser;
-mod markdown_formatter;
-mod apply_logic;
 mod calendar;
+mod github_import;
+mod watcher;
 
-use task_model::Task;
+use og::{apply_logic, filter, lint, markdown_formatter, markdown_parser, merge, render, sort, split, template};
+use og::task_model::Task;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)] // Removed trailing_var_arg = true
 struct Cli {
     // Options first
-    #[arg(long, short = 'f', global = true, help = "Input format (json or markdown)")]
+    #[arg(long, short = 'f', global = true, help = "Input format (json or markdown; obsidian for Obsidian Tasks emoji syntax in conversion mode)")]
     from: Option<String>,
 
+    #[arg(long = "stdin-format", global = true, help = "Input format when reading from stdin (json or markdown); shorthand for --from when piping")]
+    stdin_format: Option<String>,
+
     #[arg(long, short = 't', global = true, help = "Output format (json or markdown)")]
     to: Option<String>,
 
     #[arg(long, short = 'o', global = true, help = "Output file path. Writes to stdout if not specified.")]
     output: Option<String>,
 
+    #[arg(long = "pretty", alias = "json-pretty", global = true, help = "Emit a pretty-printed JSON array instead of JSONL (markdown -> json)")]
+    pretty: bool,
+
+    #[arg(long, global = true, help = "Strip trailing punctuation from parsed tags, contexts and projects")]
+    normalize_labels: bool,
+
+    #[arg(long, global = true, help = "Lowercase parsed tags, contexts and projects")]
+    lowercase_labels: bool,
+
+    #[arg(long, global = true, help = "Reject task lines with unknown key:value attributes (e.g. a misspelled duee:) instead of silently dropping them")]
+    strict: bool,
+
+    #[arg(long, global = true, value_name = "WHEN", num_args = 0..=1, default_missing_value = "always", help = "Color terminal output: always (the default for a bare --color), never or auto (default; off when NO_COLOR is set or stdout is not a terminal)")]
+    color: Option<String>,
+
+    #[arg(long = "no-color", global = true, conflicts_with = "color", help = "Never color output (same as --color never)")]
+    no_color: bool,
+
+    #[arg(long, global = true, value_name = "MODE", num_args = 0..=1, default_missing_value = "simple", help = "Copy each file to <path>.bak before overwriting it (MODE: simple, the default, or timestamped for <path>.<YYYY-MM-DDTHH-MM-SS>.bak)")]
+    backup: Option<String>,
+
     // Subcommand next
     #[command(subcommand)]
     command: Option<Commands>,
@@ -42,23 +66,296 @@ enum Commands {
 
         #[arg(long, short = 'i', help = "Modify the input file in-place. Conflicts with global --output (-o).", conflicts_with = "output")]
         in_place: bool,
+
+        #[arg(long, help = "Parse task lines in parallel (for very large files)")]
+        parallel: bool,
+
+        #[arg(long = "no-ids", help = "Omit id:N attributes from the output")]
+        no_ids: bool,
+
+        #[arg(long = "remove-ids", conflicts_with = "emit_display_orders", help = "Clear every task id (and depends:, which would point at old ids) so the output has no id:N and ids are auto-assigned again on the next parse")]
+        remove_ids: bool,
+
+        #[arg(long = "overdue-action", help = "What to do with overdue tasks: warn, mark or skip (default: $OG_OVERDUE_ACTION or warn)")]
+        overdue_action: Option<String>,
+
+        #[arg(long, help = "Render tasks with a Tera template file instead of the standard Markdown format")]
+        template: Option<PathBuf>,
+
+        #[arg(long = "template-vars", value_name = "KEY=VALUE", help = "Extra variable passed to --template (repeatable)")]
+        template_vars: Vec<String>,
+
+        #[arg(long = "sort-by", help = "Sort tasks by name, priority, due, id or created (top level only unless --sort-subtasks)")]
+        sort_by: Option<String>,
+
+        #[arg(long = "sort-subtasks", requires = "sort_by", help = "With --sort-by, also sort subtasks at every depth (display_order restarts at 1 under each parent)")]
+        sort_subtasks: bool,
+
+        #[arg(long, help = "Keep running and re-format the file in place whenever it changes (implies --in-place)")]
+        watch: bool,
+
+        #[arg(long = "split-by-project", value_name = "DIR", conflicts_with_all = ["watch", "in_place", "keep_text", "template", "aggregate_tags"], help = "Write the formatted tasks to DIR/<project>.md, one file per project (tasks without a project go to DIR/inbox.md)")]
+        split_by_project: Option<PathBuf>,
+
+        #[arg(long = "ignore-pattern", value_name = "GLOB", help = "Skip the input file if its name or path matches GLOB (repeatable; a leading ! keeps matching files)")]
+        ignore_pattern: Vec<String>,
+
+        #[arg(long = "compact-nulls", help = "Omit due/updated/completed when they have no value instead of writing key:\"\"")]
+        compact_nulls: bool,
+
+        #[arg(long = "emit-display-orders", help = "Write each task's display_order as do:N so explicit ordering survives a Markdown edit")]
+        emit_display_orders: bool,
+
+        #[arg(long = "emit-next-id-hint", help = "Write <!-- og:next_id:N --> as the first line so later parses number new tasks from N without scanning every id")]
+        emit_next_id_hint: bool,
+
+        #[arg(long = "prune-empty-subtask-lists", help = "Drop subtask lists left empty (e.g. after --strip-completed-older-than) so they are stored as no subtasks")]
+        prune_empty_subtask_lists: bool,
+
+        #[arg(long, value_name = "COLS", help = "Wrap task lines longer than COLS characters, moving attributes onto indented continuation lines (the previous line ends with ' \\')")]
+        wrap: Option<usize>,
+
+        #[arg(long = "max-line-length", value_name = "N", default_value_t = 0, conflicts_with_all = ["wrap", "template"], help = "Move the attributes of task lines longer than N characters onto '  ↳ ' continuation lines (0 disables)")]
+        max_line_length: usize,
+
+        #[arg(long = "normalize-priorities", help = "Compress top-level priority gaps so e.g. A, C, E become A, B, C ((N) is left as is)")]
+        normalize_priorities: bool,
+
+        #[arg(long = "add-missing-created", help = "Always write created:YYYY-MM-DD, filling in today's date for tasks that had none (the default; spells it out against --suppress-created-when-default)")]
+        add_missing_created: bool,
+
+        #[arg(long = "suppress-created-when-default", conflicts_with = "add_missing_created", help = "Omit created: when it is today's date (the date a task without created: gets)")]
+        suppress_created_when_default: bool,
+
+        #[arg(long = "show-progress", help = "Append the done ratio of leaf subtasks as <!-- 75% --> to each parent task line")]
+        show_progress: bool,
+
+        #[arg(long = "preserve-trailing-newline", help = "End the output with a newline (always on with --in-place)")]
+        preserve_trailing_newline: bool,
+
+        #[arg(long = "preserve-header-lines", conflicts_with_all = ["keep_text", "template", "split_by_project", "emit_next_id_hint", "add_week_of_year"], help = "Keep the lines before the first task (YAML front matter, --- separators, a # title) at the top of the output unchanged")]
+        preserve_header_lines: bool,
+
+        #[arg(long = "max-depth", value_name = "N", conflicts_with_all = ["in_place", "watch", "split_by_project"], help = "Display only: omit subtasks deeper than N (0 = top level only) and mark truncated parents with '(… M more)'")]
+        max_depth: Option<usize>,
+
+        #[arg(long = "add-week-of-year", conflicts_with_all = ["template", "keep_text", "split_by_project"], help = "Group top-level tasks by the ISO week of their due date under <!-- W24 2024-06-10 – 2024-06-16 --> comment lines (tasks without a due date go last under <!-- No Due Date -->)")]
+        add_week_of_year: bool,
+
+        #[arg(long = "aggregate-tags", conflicts_with = "watch", help = "Print tag counts as '#tag: N' lines (most used first) instead of the formatted document")]
+        aggregate_tags: bool,
+
+        #[arg(long = "separate-report", requires = "aggregate_tags", help = "With --aggregate-tags, keep the normal output and print the tag counts to stderr")]
+        separate_report: bool,
+
+        #[arg(long = "min-count", value_name = "N", default_value_t = 1, requires = "aggregate_tags", help = "With --aggregate-tags, hide tags used fewer than N times")]
+        min_count: usize,
+
+        #[arg(long = "strip-completed-older-than", value_name = "DAYS", help = "Drop done tasks completed more than DAYS days ago (with their subtasks)")]
+        strip_completed_older_than: Option<u32>,
+
+        #[arg(long = "keep-text", conflicts_with = "template", help = "Keep headings, blank lines and other non-task lines in place and only normalize task lines")]
+        keep_text: bool,
+    },
+    #[command(about = "Report spec violations in a Markdown task file without modifying it")]
+    Lint {
+        #[arg(help = "Input Markdown file path. Reads from stdin if not specified or if path is '-'.")]
+        input_file: Option<String>,
+
+        #[arg(long, default_value = "text", help = "Report format (text or json)")]
+        format: String,
+    },
+    #[command(about = "Merge task files into one list with globally unique ids")]
+    Merge {
+        #[arg(required = true, help = "Input files, merged in the given order (format set by --from, default markdown)")]
+        inputs: Vec<PathBuf>,
+
+        #[arg(long, help = "After renumbering, collapse tasks with the same name, status, priority, due, project and tags, keeping the earliest created (subtasks are compared within their parent)")]
+        dedup: bool,
+
+        #[arg(long, conflicts_with = "dedup", help = "Like --dedup with a looser key that ignores priority and tags: tasks with the same name, due, project and status are duplicates")]
+        dedupe: bool,
+    },
+    #[command(about = "Split a task file into one Markdown file per project, tag or status")]
+    Split {
+        #[arg(help = "Input file path (format set by --from, default markdown). Reads from stdin if not specified or if path is '-'.")]
+        input_file: Option<String>,
+
+        #[arg(long, default_value = "project", help = "Split key: project, tag or status")]
+        by: String,
+
+        #[arg(long = "out-dir", visible_alias = "output-dir", help = "Directory for the output files (created if missing)")]
+        out_dir: PathBuf,
+
+        #[arg(long = "name-template", value_name = "TEMPLATE", help = "File name for each group, e.g. '{project}-tasks.md'; {project}, {status} or {tag} (matching --by) and {date} are substituted, with / \\ : in values replaced by _ (default '{<key>}.md')")]
+        name_template: Option<String>,
     },
+    #[command(about = "Renumber task ids (--compact: 1..N in display order, parents before their subtasks)")]
+    Id {
+        #[arg(help = "Input file path (format set by --from, default markdown). Rewritten in place unless -o is given (--to must then match the input format); reads from stdin if not specified or if path is '-'.")]
+        input_file: Option<String>,
+
+        #[arg(long, help = "Reassign ids 1..N depth-first in display order and update depends: references")]
+        compact: bool,
+    },
+    #[command(about = "Move closed tasks to an archive file (appended as Markdown) and remove them from the task file")]
+    Archive {
+        #[arg(help = "Task file to clean up (format set by --from, default markdown); rewritten in place unless -o is given")]
+        input_file: String,
+
+        #[arg(long = "to-file", value_name = "PATH", required_unless_present = "output_dir", conflicts_with = "output_dir", help = "Archive file; archived tasks are appended as Markdown with their ids unchanged")]
+        to_file: Option<PathBuf>,
+
+        #[arg(long = "output-dir", value_name = "DIR", help = "Append archived tasks to files in DIR named by --name-template instead of a single --to-file (created if missing)")]
+        output_dir: Option<PathBuf>,
+
+        #[arg(long = "name-template", value_name = "TEMPLATE", requires = "output_dir", help = "Archive file name under --output-dir, e.g. '{project}-{date}.md'; archived tasks are grouped by {project} and {status}, with / \\ : in values replaced by _ (default 'archive.md')")]
+        name_template: Option<String>,
+    },
+    #[command(about = "List tasks one per line as '[ID] (PRIORITY) NAME'")]
+    List {
+        #[arg(help = "Input file path (format set by --from, default markdown). Reads from stdin if not specified or if path is '-'.")]
+        input_file: Option<String>,
+
+        #[arg(long = "topological-order", help = "Order tasks so each comes after the tasks it depends on, with an '(after: IDS)' suffix; fails on a dependency cycle")]
+        topological_order: bool,
+
+        #[arg(long, value_name = "QUERY", help = "List only tasks with this tag: TAG for #TAG, KEY:VALUE for #KEY:VALUE (a leading 'tag:' is allowed, e.g. tag:area:health)")]
+        tag: Option<String>,
+
+        #[arg(long, default_value = "text", help = "Output format: text ('[ID] (PRIORITY) NAME') or compact (fixed-width ID, priority, status, name and due columns)")]
+        format: String,
+
+        #[arg(long = "show-notes", help = "Print each task's notes under it as '  > ' lines wrapped at 80 columns")]
+        show_notes: bool,
+    },
+    #[command(about = "List tasks completed within a date range (e.g. for standups)")]
+    Done {
+        #[arg(help = "Input file path (format set by --from, default markdown). Reads from stdin if not specified or if path is '-'.")]
+        input_file: Option<String>,
+
+        #[arg(long, help = "First completion date to include: YYYY-MM-DD, today or an offset like -7d")]
+        since: Option<String>,
+
+        #[arg(long, help = "Last completion date to include: YYYY-MM-DD, today or an offset like -1d")]
+        until: Option<String>,
+
+        #[arg(long = "group-by", value_name = "KEY", help = "Print tasks under a '### ' heading per project, context, tag or status (markdown output only)")]
+        group_by: Option<String>,
+    },
+    #[command(name = "print-schema", alias = "schema", about = "Print the JSON Schema of the task JSON format")]
+    PrintSchema,
     #[command(about = "Apply Markdown changes to a JSON file")]
     Apply {
         #[arg(long, help = "Target JSON file path")] 
         target_json: PathBuf,
         #[arg(long, help = "Dry run without modifying the JSON file")]
         dry_run: bool,
+        #[arg(long, help = "Parse JSON and Markdown lines in parallel (for very large files)")]
+        parallel: bool,
+        #[arg(long, help = "Collapse Markdown tasks with the same name, due, project and status, keeping the earliest created (subtasks are compared within their parent)")]
+        dedupe: bool,
+        #[arg(long = "content-hash-file", value_name = "PATH", help = "Write a JSON map of task id to SHA-256 content hash, reporting how many tasks changed since the previous map")]
+        content_hash_file: Option<PathBuf>,
+        #[arg(long = "conflict-strategy", value_name = "STRATEGY", default_value = "theirs", help = "For tasks whose JSON 'updated' is newer than the Markdown's: theirs (Markdown wins), ours (JSON wins) or merge (take the non-empty side, fail if both sides set different values)")]
+        conflict_strategy: String,
+    },
+    #[command(about = "List tasks due on a date, or within the next N days")]
+    Due {
+        #[arg(long, help = "Target JSON file path")]
+        target_json: PathBuf,
+        #[arg(long, help = "Due date to list: YYYY-MM-DD, today or an offset like 1d (default: today)")]
+        date: Option<String>,
+        #[arg(long = "days-ahead", value_name = "N", help = "Also list tasks due up to N days after --date")]
+        days_ahead: Option<u32>,
+        #[arg(long = "due-week", value_name = "WEEK", conflicts_with_all = ["date", "days_ahead"], help = "List tasks due in an ISO week (Monday to Sunday): this, next or YYYY-Www (e.g. 2024-W32)")]
+        due_week: Option<String>,
+        #[arg(long, default_value = "text", help = "Output format: text ('[ID] (PRIORITY) NAME – due DATE' lines), markdown or table (a Markdown table)")]
+        format: String,
+        #[arg(long = "show-notes", help = "Text format: print each task's notes under it as '  > ' lines wrapped at 80 columns")]
+        show_notes: bool,
+    },
+    #[command(about = "Check a JSON task file for duplicate ids and display_order gaps")]
+    Validate {
+        #[arg(long, help = "Target JSON file path")]
+        target_json: PathBuf,
+        #[arg(long = "compact-display-order", help = "Rewrite the file with display_order renumbered from 1 in current order")]
+        compact_display_order: bool,
+        #[arg(long = "ignore-pattern", value_name = "GLOB", help = "Skip the target file if its name or path matches GLOB (repeatable; a leading ! keeps matching files)")]
+        ignore_pattern: Vec<String>,
     },
     #[command(about = "Display calendar events")]
     Cal {
         #[arg(long = "title", help = "Show only titles without time")]
         title: bool,
-        #[arg(long = "next", short = 'n', help = "Show next business day events")]
-        next: bool,
+        #[arg(long = "next", short = 'n', value_name = "N", num_args = 0..=1, default_missing_value = "1", value_parser = clap::value_parser!(u32).range(1..), help = "Show events N business days ahead (default 1: the next business day)")]
+        next: Option<u32>,
         #[arg(long = "all", short = 'a', help = "Show all events including all-day and hidden events")]
         all: bool,
+        #[arg(long = "attendees", help = "Show attendees and their RSVP status under each event")]
+        attendees: bool,
+        #[arg(long = "only-if-organizer", help = "Show only events organized by the calendar owner")]
+        only_if_organizer: bool,
+        #[arg(long = "country-code", default_value = "JP", help = "Country whose public holidays are skipped by --next")]
+        country_code: String,
+        #[arg(long = "sync-to-tasks", value_name = "PATH", help = "Create/update tasks for the fetched events in a JSON task file (ignores --only-if-organizer / --hide-declined)")]
+        sync_to_tasks: Option<PathBuf>,
+        #[arg(long = "no-auth", help = "Offline mode: skip authentication and show no events (implied by OG_CAL_NO_AUTH=1 when credentials are missing)")]
+        no_auth: bool,
+        #[arg(long = "no-auth-browser", help = "Headless authentication: print the consent URL and read the code from stdin instead of opening a browser (also OG_OAUTH_NO_BROWSER=1)")]
+        no_auth_browser: bool,
+        #[arg(long, default_value = "text", help = "Output format: text, json (array of events), ical or compact (one 'HH:MM title' line per event)")]
+        format: String,
+        #[arg(long, value_name = "NAME", help = "Google account to use; its token is cached at ~/.config/google/token-<NAME>.json")]
+        account: Option<String>,
+        #[arg(long = "conflict-detect", help = "Warn on stderr about timed events that overlap")]
+        conflict_detect: bool,
+        #[arg(long = "hide-declined", value_name = "BOOL", default_value_t = true, num_args = 0..=1, default_missing_value = "true", action = clap::ArgAction::Set, help = "Hide cancelled events and events you declined (--hide-declined=false or --all shows them)")]
+        hide_declined: bool,
+        #[arg(long = "remind-minutes", value_name = "N", conflicts_with_all = ["next", "sync_to_tasks"], help = "Show only today's timed events starting within the next N minutes; exits with 1 if there is at least one, 0 if none, 2 on errors (for cron-driven notifications)")]
+        remind_minutes: Option<u32>,
     },
+    #[command(about = "Import tasks from another service into a JSON task file")]
+    Import {
+        #[arg(long, help = "Target JSON file path (created if missing)")]
+        target_json: PathBuf,
+        #[arg(long = "from-github-issues", value_name = "OWNER/REPO", help = "Add the open issues of a GitHub repository as tasks, skipping issues imported before (authenticates with $GITHUB_TOKEN when set)")]
+        from_github_issues: String,
+    },
+    #[command(about = "Check the Google Calendar setup (credentials, token, home directory) without fetching events")]
+    Doctor {
+        #[arg(long, value_name = "NAME", help = "Check the token of this Google account (see og cal --account)")]
+        account: Option<String>,
+        #[arg(long = "check-token", help = "Also ask Google to accept the cached token when the offline checks pass")]
+        check_token: bool,
+    },
+}
+
+// --backup 指定時、上書きする前のファイルをコピーしておく
+fn backup_before_write(mode: Option<og::fs_util::BackupMode>, path: &std::path::Path) -> Result<(), String> {
+    let Some(mode) = mode else { return Ok(()) };
+    match og::fs_util::backup_file(path, mode, Local::now().naive_local()) {
+        Ok(Some(backup)) => {
+            eprintln!("Backed up {} to {}", path.display(), backup.display());
+            Ok(())
+        }
+        Ok(None) => Ok(()),
+        Err(e) => Err(format!("Error backing up '{}': {}", path.display(), e)),
+    }
+}
+
+// og apply --content-hash-file: 前回のハッシュと比べて変わったタスク数を表示し、新しいハッシュを書く
+fn write_content_hashes(path: &std::path::Path, tasks: &[Task]) -> Result<(), String> {
+    let hashes = og::task_model::content_hashes(tasks);
+    if let Ok(previous_content) = fs::read_to_string(path) {
+        let previous: std::collections::BTreeMap<i64, String> = serde_json::from_str(&previous_content)
+            .map_err(|e| format!("Error parsing content hash file '{}': {}", path.display(), e))?;
+        let changed = hashes.iter().filter(|(id, hash)| previous.get(id) != Some(hash)).count();
+        eprintln!("{} of {} task(s) changed since the last apply", changed, hashes.len());
+    }
+    let json = serde_json::to_string_pretty(&hashes).map_err(|e| e.to_string())?;
+    og::fs_util::write_atomic(path, json + "\n")
+        .map_err(|e| format!("Error writing content hash file '{}': {}", path.display(), e))
 }
 
 fn read_input(input_file_path: Option<&String>) -> Result<String, String> {
@@ -82,15 +379,135 @@ fn write_output(output_file_path: Option<&String>, content: &str) -> Result<(),
     }
 }
 
+// JSONL (1行1タスク) と整形済みJSON配列の両方を受け付ける。先頭が '[' なら配列とみなす。
+fn parse_json_tasks(content: &str) -> Result<Vec<Task>, String> {
+    if content.trim_start().starts_with('[') {
+        return serde_json::from_str(content).map_err(|e| format!("Error deserializing task array from JSON: {}", e));
+    }
+    let mut tasks: Vec<Task> = Vec::new();
+    for line in content.lines() {
+        if line.trim().is_empty() { continue; }
+        let task: Task = serde_json::from_str(line).map_err(|e| format!("Error deserializing task from JSON line '{}': {}", line, e))?;
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+fn parse_markdown(content: &str, default_created_date: NaiveDate, parallel: bool, options: &markdown_parser::ParseOptions) -> Result<Vec<Task>, String> {
+    let tasks = if parallel {
+        markdown_parser::parse_markdown_document_to_tasks_parallel_with_options(content, default_created_date, options)
+    } else {
+        markdown_parser::parse_markdown_document_to_tasks_with_options(content, default_created_date, options)
+    };
+    tasks.map_err(|e| e.to_string())
+}
+
+// apply 用: JSONL を1行ずつデシリアライズする (行同士は独立しているため並列化可能)
+fn parse_json_lines(content: &str, parallel: bool) -> Result<Vec<Task>, String> {
+    let lines: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).collect();
+    let parse_line = |line: &&str| -> Result<Task, String> {
+        serde_json::from_str(line).map_err(|e| format!("Error parsing JSON line '{}': {}", line, e))
+    };
+    if parallel {
+        lines.par_iter().map(parse_line).collect()
+    } else {
+        lines.iter().map(parse_line).collect()
+    }
+}
+
+// display_order の昇順に並べ替える (サブタスクも兄弟ごとに)
+fn sort_by_display_order(tasks: &mut [Task]) {
+    tasks.sort_by_key(|t| t.display_order);
+    for task in tasks.iter_mut() {
+        if let Some(subtasks) = &mut task.subtasks {
+            sort_by_display_order(subtasks);
+        }
+    }
+}
+
+fn serialize_tasks_to_json(tasks: &[Task], pretty: bool) -> Result<String, String> {
+    if pretty {
+        if tasks.is_empty() {
+            return Ok("".to_string());
+        }
+        let output_string = serde_json::to_string_pretty(tasks).map_err(|e| format!("Error serializing tasks to JSON: {}", e))?;
+        return Ok(output_string + "\n");
+    }
+    let mut json_outputs: Vec<String> = Vec::new();
+    for task in tasks {
+        json_outputs.push(serde_json::to_string(task).map_err(|e| format!("Error serializing task to JSON: {}", e))?);
+    }
+    let output_string = json_outputs.join("\n");
+    Ok(if output_string.is_empty() { "".to_string() } else { output_string + "\n" })
+}
+
+// og cal の取得エラーに、次に何をすればよいかの案内を付ける
+fn calendar_error_message(e: &calendar::CalendarError) -> String {
+    use calendar::CalendarError;
+    match e {
+        CalendarError::CredentialsNotFound(_) | CalendarError::InvalidCredentials(_) => format!("{}\nRun 'og doctor' to check the calendar setup.", e),
+        CalendarError::TokenError(_) => format!("{}\nRun 'og cal' to sign in again, or 'og doctor --check-token' to check the cached token.", e),
+        CalendarError::NetworkError(_) => format!("{}\nCheck your network connection, or use --no-auth to run without a calendar.", e),
+        CalendarError::IoError(_) => format!("Calendar setup: {}", e),
+        CalendarError::TokenExpired | CalendarError::AccessDenied | CalendarError::ApiError(_) | CalendarError::ParseError(_) => e.to_string(),
+    }
+}
+
+// 認証情報やトークンの問題で、ユーザーが再認証・設定し直す必要があるエラー
+fn calendar_error_needs_auth(e: &calendar::CalendarError) -> bool {
+    use calendar::CalendarError;
+    matches!(
+        e,
+        CalendarError::CredentialsNotFound(_) | CalendarError::InvalidCredentials(_) | CalendarError::TokenExpired | CalendarError::TokenError(_) | CalendarError::AccessDenied
+    )
+}
+
+// --from と --stdin-format から入力形式を決める (変換モードと --from を読むサブコマンドで共通)。どちらもなければ None
+fn resolve_from_format(from: Option<&str>, stdin_format: Option<&str>, reads_stdin: bool) -> Result<Option<String>, String> {
+    match (from.map(str::to_lowercase), stdin_format.map(str::to_lowercase)) {
+        (_, Some(_)) if !reads_stdin => Err("Error: --stdin-format only applies when reading from stdin; use --from for input files.".to_string()),
+        (Some(from), Some(stdin_format)) if from != stdin_format => Err(format!("Error: --from '{}' conflicts with --stdin-format '{}'.", from, stdin_format)),
+        (_, Some(stdin_format)) => Ok(Some(stdin_format)),
+        (from, None) => Ok(from),
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
     let cli = Cli::parse();
     let default_created_date = Local::now().date_naive();
+    let status_map = og::status_map::StatusMap::load_config().map_err(|e| e.to_string())?;
+    let parse_options = markdown_parser::ParseOptions {
+        strip_label_punctuation: cli.normalize_labels,
+        lowercase_labels: cli.lowercase_labels,
+        status_map: status_map.clone(),
+        strict: cli.strict,
+        error_policy: markdown_parser::ErrorPolicy::FailFast,
+    };
+    let color_choice: render::ColorChoice = if cli.no_color {
+        render::ColorChoice::Never
+    } else {
+        cli.color.as_deref().unwrap_or("auto").parse()?
+    };
+    let backup: Option<og::fs_util::BackupMode> = cli.backup.as_deref().map(str::parse).transpose()?;
+    // fmt 以外のコマンドの Markdown 出力用 (ステータス文字の対応表だけ反映)
+    let markdown_options = markdown_formatter::FormatterOptions { status_map, ..Default::default() };
 
     if let Some(command) = cli.command {
+        // --stdin-format は入力形式を選べるサブコマンドだけが読む。ほかで黙って無視しないようにエラーにする
+        if cli.stdin_format.is_some() && !matches!(command, Commands::Split { .. } | Commands::Id { .. } | Commands::List { .. } | Commands::Done { .. } | Commands::Apply { .. }) {
+            return Err("Error: --stdin-format is only supported in conversion mode and by split, id, list, done and apply.".to_string());
+        }
         match command {
-            Commands::Fmt { input_file, in_place } => {
+            Commands::Fmt { input_file, in_place, parallel, no_ids, remove_ids, overdue_action, template, template_vars, sort_by, sort_subtasks, watch, split_by_project, ignore_pattern, keep_text, compact_nulls, emit_display_orders, emit_next_id_hint, prune_empty_subtask_lists, normalize_priorities, wrap, max_line_length, add_missing_created: _, suppress_created_when_default, show_progress, preserve_trailing_newline, preserve_header_lines, max_depth, add_week_of_year, aggregate_tags, separate_report, min_count, strip_completed_older_than } => {
+                // --watch は --in-place を含む
+                let in_place = in_place || watch;
+                if let Some(path) = &input_file {
+                    if og::fs_util::GlobFilter::new(&ignore_pattern)?.matches(path.as_ref()) {
+                        eprintln!("Skipping {} (matches --ignore-pattern)", path);
+                        return Ok(());
+                    }
+                }
                 if in_place && cli.output.is_some() {
                     return Err("Error: --in-place cannot be used with --output (-o).".to_string());
                 }
@@ -98,35 +515,452 @@ async fn main() -> Result<(), String> {
                     return Err("Error: --in-place requires a named input file, not stdin.".to_string());
                 }
 
-                let input_content = read_input(input_file.as_ref())?;
-                let tasks = markdown_parser::parse_markdown_document_to_tasks(&input_content, default_created_date)?;
-                let formatted_markdown = markdown_formatter::format_tasks_to_markdown_document(&tasks);
+                let overdue_action: filter::OverdueAction = match overdue_action.or_else(|| std::env::var("OG_OVERDUE_ACTION").ok()) {
+                    Some(action) => action.parse()?,
+                    None => filter::OverdueAction::Warn,
+                };
+                let sort_key: Option<sort::SortKey> = sort_by.as_deref().map(str::parse).transpose()?;
+                let template_content = match &template {
+                    Some(template_path) => Some(fs::read_to_string(template_path)
+                        .map_err(|e| format!("Error reading template file '{}': {}", template_path.display(), e))?),
+                    None => None,
+                };
+                let vars = template_vars.iter()
+                    .map(|v| template::parse_template_var(v))
+                    .collect::<Result<Vec<_>, String>>()?;
+                if no_ids {
+                    eprintln!("Warning: --no-ids drops task ids. Running 'og apply' on this output will regenerate ids and may not match the existing JSON.");
+                }
+                let formatter_options = markdown_formatter::FormatterOptions { omit_ids: no_ids, emit_null_dates: !compact_nulls, show_progress, emit_display_orders, emit_next_id_hint, omit_created_on: suppress_created_when_default.then_some(default_created_date), wrap_width: wrap, max_depth, trailing_newline: preserve_trailing_newline || in_place, preserve_header_lines, ..markdown_options.clone() };
 
-                if in_place {
+                // 入力文字列から整形済みの出力を作る (--watch では変更のたびに呼ぶ)
+                // 期限切れの扱いと並べ替え (--keep-text ではタスク行の連続ごとに適用)
+                let process_tasks = |mut tasks: Vec<Task>| -> Vec<Task> {
+                    if let Some(days) = strip_completed_older_than {
+                        let cutoff = default_created_date - chrono::Duration::days(days as i64);
+                        let (kept, removed) = filter::strip_old_completed(tasks, cutoff);
+                        eprintln!("Removed {} completed task(s) older than {} days", removed, days);
+                        tasks = kept;
+                    }
+                    match overdue_action {
+                        filter::OverdueAction::Warn => {
+                            for task in filter::overdue_tasks(&tasks, default_created_date) {
+                                eprintln!("Warning: task id:{} '{}' is overdue (due {})", task.id, task.name, task.due.unwrap());
+                            }
+                        }
+                        filter::OverdueAction::Mark => filter::mark_overdue(&mut tasks, default_created_date),
+                        filter::OverdueAction::Skip => tasks = filter::skip_overdue(tasks, default_created_date),
+                    }
+                    if prune_empty_subtask_lists {
+                        og::task_model::prune_empty_subtasks(&mut tasks);
+                    }
+                    if normalize_priorities {
+                        sort::normalize_priorities(&mut tasks);
+                    }
+                    if let Some(key) = sort_key {
+                        if sort_subtasks {
+                            sort::sort_tasks_recursive(&mut tasks, key);
+                        } else {
+                            sort::sort_tasks(&mut tasks, key);
+                        }
+                    }
+                    if remove_ids {
+                        og::transform::clear_ids(&mut tasks);
+                    }
+                    tasks
+                };
+                // --max-line-length: 長いタスク行の属性を継続行に送る (0 なら何もしない)
+                let wrap_long_lines = |formatted: &str| -> String {
+                    if max_line_length == 0 {
+                        return formatted.to_string();
+                    }
+                    let mut wrapped: String = formatted.lines().map(|line| og::display::wrap_long_line(line, max_line_length)).collect::<Vec<String>>().join("\n");
+                    if formatted.ends_with('\n') {
+                        wrapped.push('\n');
+                    }
+                    wrapped
+                };
+                // --add-week-of-year: トップレベルのタスクを due の ISO 週ごとのコメント行の下にまとめる
+                let annotate_weeks = |formatted: String, tasks: &[Task]| -> String {
+                    if !add_week_of_year {
+                        return formatted;
+                    }
+                    let lines = formatted.lines().map(String::from).collect();
+                    let mut annotated = og::display::add_week_annotations(lines, tasks).join("\n");
+                    if formatted.ends_with('\n') {
+                        annotated.push('\n');
+                    }
+                    annotated
+                };
+                let format_content = |input_content: &str| -> Result<(Vec<Task>, String), String> {
+                    if keep_text {
+                        let mut document = markdown_parser::parse_markdown_document_with_text(input_content, default_created_date, &parse_options)
+                            .map_err(|e| e.to_string())?;
+                        for block in &mut document.blocks {
+                            if let og::task_model::Block::Tasks(tasks) = block {
+                                *tasks = process_tasks(std::mem::take(tasks));
+                            }
+                        }
+                        let formatted_markdown = wrap_long_lines(&markdown_formatter::format_document_with_options(&document, &formatter_options));
+                        return Ok((document.tasks().cloned().collect(), formatted_markdown));
+                    }
+                    let tasks = process_tasks(parse_markdown(input_content, default_created_date, parallel, &parse_options)?);
+                    let formatted_markdown = match &template_content {
+                        Some(template_content) => template::render_tasks_with_template(template_content, &tasks, &vars)?,
+                        None => annotate_weeks(wrap_long_lines(&markdown_formatter::format_tasks_with_source_header(input_content, &tasks, &formatter_options)), &tasks),
+                    };
+                    Ok((tasks, formatted_markdown))
+                };
+
+                if watch {
                     let path = input_file.unwrap();
-                    fs::write(&path, formatted_markdown).map_err(|e| format!("Error writing back to file '{}': {}", path, e))?;
+                    // 監視中の書き込みごとではなく、開始前の内容を 1 回だけ残す
+                    backup_before_write(backup, path.as_ref())?;
+                    watcher::watch_and_format(&path, |content| format_content(content).map(|(_, formatted)| formatted))?;
+                } else if in_place {
+                    let path = input_file.unwrap();
+                    let input_content = read_input(Some(&path))?;
+                    let (tasks, formatted_markdown) = format_content(&input_content)?;
+                    backup_before_write(backup, path.as_ref())?;
+                    og::fs_util::write_atomic(&path, formatted_markdown).map_err(|e| format!("Error writing back to file '{}': {}", path, e))?;
                     eprintln!("Formatted file in-place: {}", path);
+                    if aggregate_tags {
+                        let report = og::stats::format_tag_counts(&og::stats::count_tags(&tasks), min_count);
+                        if separate_report { eprint!("{}", report) } else { print!("{}", report) }
+                    }
+                } else if let Some(out_dir) = split_by_project {
+                    let input_content = read_input(input_file.as_ref())?;
+                    let (tasks, _) = format_content(&input_content)?;
+                    // ファイル名順、inbox は最後
+                    let mut groups: Vec<(String, Vec<Task>)> = og::transform::split_tasks_by_project(tasks)?.into_iter().collect();
+                    groups.sort_by(|a, b| (a.0 == og::transform::INBOX_NAME, &a.0).cmp(&(b.0 == og::transform::INBOX_NAME, &b.0)));
+                    fs::create_dir_all(&out_dir)
+                        .map_err(|e| format!("Error creating output directory '{}': {}", out_dir.display(), e))?;
+                    let mut written: Vec<String> = Vec::new();
+                    for (name, group) in &groups {
+                        let path = out_dir.join(format!("{}.md", name));
+                        let markdown = markdown_formatter::format_tasks_to_markdown_document_with_options(group, &formatter_options);
+                        backup_before_write(backup, &path)?;
+                        og::fs_util::write_atomic(&path, markdown + "\n")
+                            .map_err(|e| format!("Error writing output file '{}': {}", path.display(), e))?;
+                        written.push(format!("{}.md ({} tasks)", name, og::task_model::flatten_all(group).count()));
+                    }
+                    println!("Wrote {} files: {}", written.len(), written.join(", "));
+                } else if aggregate_tags && !separate_report {
+                    // タグ集計のみを出力する
+                    let input_content = read_input(input_file.as_ref())?;
+                    let (tasks, _) = format_content(&input_content)?;
+                    write_output(cli.output.as_ref(), &og::stats::format_tag_counts(&og::stats::count_tags(&tasks), min_count))?;
+                } else {
+                    let input_content = read_input(input_file.as_ref())?;
+                    let (tasks, formatted_markdown) = format_content(&input_content)?;
+                    if aggregate_tags {
+                        eprint!("{}", og::stats::format_tag_counts(&og::stats::count_tags(&tasks), min_count));
+                    }
+                    // 色付けは端末表示のみ。ファイル出力 (-o) は常にプレーン。
+                    // colorize_markdown_document は出力行とタスクを 1 行ずつ対応させるため、
+                    // タスク以外の行を含む出力 (--keep-text / --add-week-of-year / --preserve-header-lines / --emit-next-id-hint)、
+                    // 継続行を出力する --wrap / --max-line-length、一部のタスクを出力しない --max-depth では色付けしない
+                    let one_line_per_task = template.is_none() && !keep_text && !add_week_of_year && !preserve_header_lines && !emit_next_id_hint
+                        && wrap.is_none() && max_line_length == 0 && max_depth.is_none();
+                    if cli.output.is_none() && one_line_per_task && color_choice.should_color_stdout() {
+                        let colored = render::colorize_markdown_document(&tasks, &formatted_markdown, default_created_date);
+                        write_output(None, &colored)?;
+                    } else {
+                        write_output(cli.output.as_ref(), &formatted_markdown)?;
+                    }
+                }
+            },
+            Commands::Lint { input_file, format } => {
+                let input_content = read_input(input_file.as_ref())?;
+                let issues = lint::lint_markdown_document(&input_content);
+                match format.to_lowercase().as_str() {
+                    "text" => print!("{}", lint::format_issues_as_text(&issues)),
+                    "json" => println!("{}", serde_json::to_string(&issues).map_err(|e| format!("Error serializing lint issues to JSON: {}", e))?),
+                    other => return Err(format!("Error: Unsupported lint format '{}'.", other)),
+                }
+                if !issues.is_empty() {
+                    io::stdout().flush().map_err(|e| format!("Error flushing stdout: {}", e))?;
+                    std::process::exit(1);
+                }
+            },
+            Commands::Split { input_file, by, out_dir, name_template } => {
+                let key: split::SplitKey = by.parse()?;
+                let name_template = match name_template {
+                    Some(name_template) => name_template.parse()?,
+                    None => split::NameTemplate::for_split_key(key),
+                };
+                let from_format = resolve_from_format(cli.from.as_deref(), cli.stdin_format.as_deref(), input_file.as_deref().is_none_or(|path| path == "-"))?.unwrap_or_else(|| "markdown".to_string());
+                let input_content = read_input(input_file.as_ref())?;
+                let tasks = match from_format.as_str() {
+                    "markdown" => parse_markdown(&input_content, default_created_date, false, &parse_options)?,
+                    "json" => parse_json_tasks(&input_content)?,
+                    other => return Err(format!("Error: Unsupported input format '{}' for split.", other)),
+                };
+
+                // 書き込む前にファイル名を決め、衝突があれば何も書かずに終える
+                let groups = split::split_tasks(&tasks, key);
+                let values: Vec<split::NameValues> = groups.keys().map(|name| vec![(key.placeholder(), name.clone())]).collect();
+                let file_names = split::assign_file_names(&name_template, &values, Local::now().date_naive())?;
+                fs::create_dir_all(&out_dir)
+                    .map_err(|e| format!("Error creating output directory '{}': {}", out_dir.display(), e))?;
+                for (group, file_name) in groups.into_values().zip(file_names) {
+                    let path = out_dir.join(file_name);
+                    let markdown = markdown_formatter::format_tasks_to_markdown_document_with_options(&group, &markdown_options);
+                    fs::write(&path, markdown + "\n")
+                        .map_err(|e| format!("Error writing output file '{}': {}", path.display(), e))?;
+                }
+            },
+            Commands::Id { input_file, compact } => {
+                if !compact {
+                    return Err("Error: og id needs an action; use --compact to renumber ids.".to_string());
+                }
+                let from_format = resolve_from_format(cli.from.as_deref(), cli.stdin_format.as_deref(), input_file.as_deref().is_none_or(|path| path == "-"))?.unwrap_or_else(|| "markdown".to_string());
+                let to_format = cli.to.as_ref().map(|s| s.to_lowercase()).unwrap_or_else(|| from_format.clone());
+                // 別の形式でファイルを上書きすると元の形式のファイルが壊れるので、-o なしの書き換えでは --to を変えられない
+                let rewrites_input = cli.output.is_none() && input_file.as_deref().is_some_and(|path| path != "-");
+                if rewrites_input && to_format != from_format {
+                    return Err(format!("Error: --to '{}' differs from the input format '{}'; use -o <PATH> to write the converted tasks elsewhere.", to_format, from_format));
+                }
+                let input_content = read_input(input_file.as_ref())?;
+                let mut tasks = match from_format.as_str() {
+                    "markdown" => parse_markdown(&input_content, default_created_date, false, &parse_options)?,
+                    "json" => parse_json_tasks(&input_content)?,
+                    other => return Err(format!("Error: Unsupported input format '{}' for id.", other)),
+                };
+
+                // 表示順に並べてから、深さ優先で 1..N を振り直す
+                sort_by_display_order(&mut tasks);
+                og::task_model::compact_ids(&mut tasks);
+                og::task_model::compact_display_orders(&mut tasks);
+
+                let output = match to_format.as_str() {
+                    "markdown" => {
+                        let markdown = markdown_formatter::format_tasks_to_markdown_document_with_options(&tasks, &markdown_options);
+                        if markdown.is_empty() { markdown } else { markdown + "\n" }
+                    }
+                    "json" => serialize_tasks_to_json(&tasks, cli.pretty)?,
+                    other => return Err(format!("Error: Unsupported output format '{}' for id.", other)),
+                };
+                // -o がなければ名前付きの入力ファイルを書き換える (stdin の場合は stdout へ)
+                match (cli.output.as_ref(), input_file.as_deref()) {
+                    (None, Some(path)) if path != "-" => {
+                        backup_before_write(backup, path.as_ref())?;
+                        fs::write(path, output).map_err(|e| format!("Error writing back to file '{}': {}", path, e))?;
+                        eprintln!("Renumbered ids in {}", path);
+                    }
+                    (output_path, _) => write_output(output_path, &output)?,
+                }
+            },
+            Commands::Archive { input_file, to_file, output_dir, name_template } => {
+                let from_format = cli.from.as_ref().map(|s| s.to_lowercase()).unwrap_or_else(|| "markdown".to_string());
+                let input_content = fs::read_to_string(&input_file)
+                    .map_err(|e| format!("Error reading input file '{}': {}", input_file, e))?;
+                let tasks = match from_format.as_str() {
+                    "markdown" => parse_markdown(&input_content, default_created_date, false, &parse_options)?,
+                    "json" => parse_json_tasks(&input_content)?,
+                    other => return Err(format!("Error: Unsupported input format '{}' for archive.", other)),
+                };
+
+                // 完了済みのタスクをサブタスクごと移す (一部未完了の子を持つ親は残し、その下の完了済みのサブツリーは移す)
+                let (mut active, archived) = filter::split_archivable(tasks);
+                if archived.is_empty() {
+                    eprintln!("No tasks to archive");
+                    return Ok(());
+                }
+
+                let archived_count = og::task_model::flatten_all(&archived).count();
+                // 書き込み先: --to-file の 1 ファイル、または --output-dir 内の --name-template ごとのファイル
+                let archive_files: Vec<(PathBuf, Vec<Task>)> = match (to_file, output_dir) {
+                    (Some(to_file), _) => vec![(to_file, archived)],
+                    (None, Some(output_dir)) => {
+                        let name_template: split::NameTemplate = name_template.as_deref().unwrap_or("archive.md").parse()?;
+                        let (values, groups): (Vec<split::NameValues>, Vec<Vec<Task>>) = split::group_by_name_template(archived, &name_template).into_iter().unzip();
+                        let file_names = split::assign_file_names(&name_template, &values, Local::now().date_naive())?;
+                        fs::create_dir_all(&output_dir)
+                            .map_err(|e| format!("Error creating output directory '{}': {}", output_dir.display(), e))?;
+                        file_names.into_iter().map(|file_name| output_dir.join(file_name)).zip(groups).collect()
+                    }
+                    (None, None) => unreachable!("clap requires --to-file or --output-dir"),
+                };
+
+                // アーカイブは上書きせず末尾に追記する
+                for (to_file, group) in &archive_files {
+                    let mut archive_content = match fs::read_to_string(to_file) {
+                        Ok(existing) => existing,
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+                        Err(e) => return Err(format!("Error reading archive file '{}': {}", to_file.display(), e)),
+                    };
+                    if !archive_content.is_empty() && !archive_content.ends_with('\n') {
+                        archive_content.push('\n');
+                    }
+                    archive_content.push_str(&markdown_formatter::format_tasks_to_markdown_document_with_options(group, &markdown_options));
+                    archive_content.push('\n');
+                    backup_before_write(backup, to_file)?;
+                    fs::write(to_file, archive_content)
+                        .map_err(|e| format!("Error writing archive file '{}': {}", to_file.display(), e))?;
+                }
+
+                og::task_model::compact_display_orders(&mut active);
+                let output = match from_format.as_str() {
+                    "json" => serialize_tasks_to_json(&active, cli.pretty)?,
+                    _ => {
+                        let markdown = markdown_formatter::format_tasks_to_markdown_document_with_options(&active, &markdown_options);
+                        if markdown.is_empty() { markdown } else { markdown + "\n" }
+                    }
+                };
+                // -o があれば残りのタスクはそこへ書き、入力ファイルは変えない
+                match cli.output.as_ref() {
+                    Some(output_path) => write_output(Some(output_path), &output)?,
+                    None => {
+                        backup_before_write(backup, input_file.as_ref())?;
+                        fs::write(&input_file, output).map_err(|e| format!("Error writing back to file '{}': {}", input_file, e))?;
+                    }
+                }
+                let archive_names: Vec<String> = archive_files.iter().map(|(path, _)| path.display().to_string()).collect();
+                eprintln!("Archived {} task(s) to {}", archived_count, archive_names.join(", "));
+            },
+            Commands::List { input_file, topological_order, tag, format, show_notes } => {
+                let tag_query: Option<filter::TagQuery> = tag.as_deref().map(str::parse).transpose()?;
+                let from_format = resolve_from_format(cli.from.as_deref(), cli.stdin_format.as_deref(), input_file.as_deref().is_none_or(|path| path == "-"))?.unwrap_or_else(|| "markdown".to_string());
+                let input_content = read_input(input_file.as_ref())?;
+                let tasks = match from_format.as_str() {
+                    "markdown" => parse_markdown(&input_content, default_created_date, false, &parse_options)?,
+                    "json" => parse_json_tasks(&input_content)?,
+                    other => return Err(format!("Error: Unsupported input format '{}' for list.", other)),
+                };
+                // サブタスクも 1 行ずつ出す
+                let ordered: Vec<&Task> = if topological_order {
+                    og::topo_sort::topological_sort(&tasks).map_err(|e| format!("Error: {}", e))?
                 } else {
-                    write_output(cli.output.as_ref(), &formatted_markdown)?;
+                    og::task_model::flatten_all(&tasks).collect()
+                };
+                let ordered: Vec<&Task> = match &tag_query {
+                    Some(query) => ordered.into_iter().filter(|t| filter::matches_tag_query(t, query)).collect(),
+                    None => ordered,
+                };
+                let output = ordered.iter()
+                    .map(|t| {
+                        let after = match t.depends.as_deref() {
+                            Some(ids) if topological_order && !ids.is_empty() => {
+                                format!("  (after: {})", ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", "))
+                            }
+                            _ => String::new(),
+                        };
+                        let line = match format.to_lowercase().as_str() {
+                            "text" => format!("[{}] ({}) {}{}\n", t.id, t.priority, t.name, after),
+                            // 色付けは端末表示のみ
+                            "compact" if cli.output.is_none() && color_choice.should_color_stdout() => format!("{}{}\n", t.to_compact_line_colored(default_created_date), after),
+                            "compact" => format!("{}{}\n", t.to_compact_line(), after),
+                            other => return Err(format!("Error: Unsupported list format '{}' (expected text or compact).", other)),
+                        };
+                        Ok(match t.notes.as_deref().filter(|note| show_notes && !note.trim().is_empty()) {
+                            Some(note) => line + &og::display::wrap_note(note, og::display::NOTE_WIDTH, og::display::NOTE_PREFIX),
+                            None => line,
+                        })
+                    })
+                    .collect::<Result<String, String>>()?;
+                write_output(cli.output.as_ref(), &output)?;
+            },
+            Commands::Done { input_file, since, until, group_by } => {
+                let since = since.map(|v| filter::parse_date_bound(&v, default_created_date)).transpose()?;
+                let until = until.map(|v| filter::parse_date_bound(&v, default_created_date)).transpose()?;
+                let from_format = resolve_from_format(cli.from.as_deref(), cli.stdin_format.as_deref(), input_file.as_deref().is_none_or(|path| path == "-"))?.unwrap_or_else(|| "markdown".to_string());
+                let to_format = cli.to.as_ref().map(|s| s.to_lowercase()).unwrap_or_else(|| "markdown".to_string());
+
+                let input_content = read_input(input_file.as_ref())?;
+                let tasks = match from_format.as_str() {
+                    "markdown" => parse_markdown(&input_content, default_created_date, false, &parse_options)?,
+                    "json" => parse_json_tasks(&input_content)?,
+                    other => return Err(format!("Error: Unsupported input format '{}' for done.", other)),
+                };
+                let completed = filter::completed_between(&tasks, since, until);
+                let group_key: Option<filter::GroupKey> = group_by.as_deref().map(str::parse).transpose()?;
+
+                let output = match (to_format.as_str(), group_key) {
+                    ("markdown", Some(key)) => {
+                        let markdown = markdown_formatter::format_grouped_tasks(&filter::group_tasks(&completed, key), &markdown_options);
+                        if markdown.is_empty() { markdown } else { markdown + "\n" }
+                    }
+                    ("json" | "table", Some(_)) => return Err("Error: --group-by only applies to markdown output.".to_string()),
+                    ("markdown", None) => {
+                        let markdown = markdown_formatter::format_tasks_to_markdown_document_with_options(&completed, &markdown_options);
+                        if markdown.is_empty() { markdown } else { markdown + "\n" }
+                    }
+                    ("json", None) => serialize_tasks_to_json(&completed, cli.pretty)?,
+                    ("table", None) => render::render_table(&completed),
+                    (other, _) => return Err(format!("Error: Unsupported output format '{}' for done.", other)),
+                };
+                write_output(cli.output.as_ref(), &output)?;
+            },
+            Commands::Merge { inputs, dedup, dedupe } => {
+                let from_format = cli.from.as_ref().map(|s| s.to_lowercase()).unwrap_or_else(|| "markdown".to_string());
+                let to_format = cli.to.as_ref().map(|s| s.to_lowercase()).unwrap_or_else(|| "markdown".to_string());
+
+                let mut lists: Vec<Vec<Task>> = Vec::new();
+                for input in &inputs {
+                    let content = fs::read_to_string(input)
+                        .map_err(|e| format!("Error reading input file '{}': {}", input.display(), e))?;
+                    let tasks = match from_format.as_str() {
+                        "markdown" => parse_markdown(&content, default_created_date, false, &parse_options)?,
+                        "json" => parse_json_tasks(&content)?,
+                        other => return Err(format!("Error: Unsupported input format '{}' for merge.", other)),
+                    };
+                    lists.push(tasks);
                 }
+
+                let mut merged = merge::merge_task_lists(lists);
+                // --dedup と --dedupe は重複とみなすキーだけが違う (同時には指定できない)
+                let dedup_key = match (dedup, dedupe) {
+                    (true, _) => Some(merge::DedupKey::Content),
+                    (_, true) => Some(merge::DedupKey::NameDueProjectStatus),
+                    _ => None,
+                };
+                if let Some(key) = dedup_key {
+                    let (deduped, pairs) = merge::dedup_tasks_by(merged, key);
+                    for (kept_id, discarded_id) in &pairs {
+                        eprintln!("Warning: task id:{} is a duplicate of id:{} and was removed", discarded_id, kept_id);
+                    }
+                    eprintln!("Removed {} duplicate task(s)", pairs.len());
+                    merged = deduped;
+                    og::task_model::compact_display_orders(&mut merged);
+                }
+
+                let output = match to_format.as_str() {
+                    "markdown" => {
+                        let markdown = markdown_formatter::format_tasks_to_markdown_document_with_options(&merged, &markdown_options);
+                        if markdown.is_empty() { markdown } else { markdown + "\n" }
+                    }
+                    "json" => serialize_tasks_to_json(&merged, cli.pretty)?,
+                    other => return Err(format!("Error: Unsupported output format '{}' for merge.", other)),
+                };
+                write_output(cli.output.as_ref(), &output)?;
             },
-            Commands::Apply { target_json, dry_run } => {
-                let from_format = cli.from.as_ref().map(|s| s.to_lowercase()).unwrap_or_default();
+            Commands::PrintSchema => {
+                let schema = serde_json::to_string_pretty(&og::task_model::task_json_schema())
+                    .map_err(|e| format!("Error serializing JSON Schema: {}", e))?;
+                write_output(cli.output.as_ref(), &(schema + "\n"))?;
+            },
+            Commands::Apply { target_json, dry_run, parallel, dedupe, content_hash_file, conflict_strategy } => {
+                let conflict_strategy: apply_logic::ConflictStrategy = conflict_strategy.parse()?;
+                let from_format = resolve_from_format(cli.from.as_deref(), cli.stdin_format.as_deref(), true)?.unwrap_or_default();
                 if from_format != "markdown" {
                     return Err("Error: --from must be 'markdown' for apply command.".to_string());
                 }
                 let input_content = read_input(None)?;
                 let existing_json = fs::read_to_string(&target_json)
                     .map_err(|e| format!("Error reading JSON file '{}': {}", target_json.display(), e))?;
-                let mut existing_tasks: Vec<Task> = Vec::new();
-                for line in existing_json.lines() {
-                    if line.trim().is_empty() { continue; }
-                    let task: Task = serde_json::from_str(line)
-                        .map_err(|e| format!("Error parsing JSON line '{}': {}", line, e))?;
-                    existing_tasks.push(task);
+                let existing_tasks = parse_json_lines(&existing_json, parallel)?;
+                let mut markdown_tasks = parse_markdown(&input_content, default_created_date, parallel, &parse_options)?;
+                if dedupe {
+                    let removed = merge::dedupe_tasks(&mut markdown_tasks);
+                    eprintln!("Removed {} duplicate task(s)", removed);
+                }
+                let (final_tasks, conflicts) = apply_logic::apply_changes_with_strategy(existing_tasks, markdown_tasks, default_created_date, conflict_strategy)
+                    .map_err(|e| e.to_string())?;
+                for conflict in &conflicts {
+                    eprintln!("{}", conflict);
                 }
-                let markdown_tasks = markdown_parser::parse_markdown_document_to_tasks(&input_content, default_created_date)?;
-                let final_tasks = apply_logic::apply_changes(existing_tasks, markdown_tasks, default_created_date)?;
                 if dry_run {
                     println!("Dry run summary:");
                     println!("Added tasks:");
@@ -138,56 +972,244 @@ async fn main() -> Result<(), String> {
                         .map(|t| serde_json::to_string(t).unwrap())
                         .collect::<Vec<_>>()
                         .join("\n");
-                    fs::write(&target_json, json_out + "\n")
+                    backup_before_write(backup, &target_json)?;
+                    og::fs_util::write_atomic(&target_json, json_out + "\n")
                         .map_err(|e| format!("Error writing JSON file '{}': {}", target_json.display(), e))?;
-                    let markdown_out = markdown_formatter::format_tasks_to_markdown_document(&final_tasks);
+                    if let Some(hash_path) = &content_hash_file {
+                        write_content_hashes(hash_path, &final_tasks)?;
+                    }
+                    let markdown_out = markdown_formatter::format_tasks_to_markdown_document_with_options(&final_tasks, &markdown_options);
                     print!("{}", markdown_out);
                 }
             },
-            Commands::Cal { title, next, all } => {
-                let events_result = if next {
-                    calendar::get_next_business_day_events(all).await
+            Commands::Due { target_json, date, days_ahead, due_week, format, show_notes } => {
+                let (from, to) = match due_week {
+                    Some(week) => filter::parse_due_week(&week, default_created_date)?,
+                    None => {
+                        let from = match date {
+                            Some(value) => filter::parse_date_bound(&value, default_created_date)?,
+                            None => default_created_date,
+                        };
+                        let days = days_ahead.unwrap_or(0);
+                        let to = from.checked_add_signed(chrono::Duration::days(days as i64))
+                            .ok_or_else(|| format!("Error: --days-ahead {} goes past the latest supported date.", days))?;
+                        (from, to)
+                    }
+                };
+                let content = fs::read_to_string(&target_json)
+                    .map_err(|e| format!("Error reading JSON file '{}': {}", target_json.display(), e))?;
+                let tasks = parse_json_tasks(&content)?;
+
+                // 期限の早い順、同じ日なら優先度の高い順
+                let mut due = filter::tasks_due_within(&tasks, from, to);
+                due.sort_by(|a, b| sort::compare(a, b, sort::SortKey::Due).then_with(|| sort::compare(a, b, sort::SortKey::Priority)));
+
+                let output = match format.to_lowercase().as_str() {
+                    "text" => due.iter()
+                        .map(|t| {
+                            let line = format!("[{}] ({}) {} \u{2013} due {}\n", t.id, t.priority, t.name, t.due.unwrap().format("%Y-%m-%d"));
+                            match t.notes.as_deref().filter(|note| show_notes && !note.trim().is_empty()) {
+                                Some(note) => line + &og::display::wrap_note(note, og::display::NOTE_WIDTH, og::display::NOTE_PREFIX),
+                                None => line,
+                            }
+                        })
+                        .collect::<String>(),
+                    "markdown" => {
+                        // 親と子がともに該当する場合に重複しないよう、各タスクを 1 行ずつ出す
+                        let flat: Vec<Task> = due.iter().map(|t| Task { subtasks: None, ..(*t).clone() }).collect();
+                        let markdown = markdown_formatter::format_tasks_to_markdown_document_with_options(&flat, &markdown_options);
+                        if markdown.is_empty() { markdown } else { markdown + "\n" }
+                    }
+                    "table" => {
+                        let flat: Vec<Task> = due.iter().map(|t| Task { subtasks: None, ..(*t).clone() }).collect();
+                        render::render_table(&flat)
+                    }
+                    other => return Err(format!("Error: Unsupported output format '{}' for due (expected text, markdown or table).", other)),
+                };
+                write_output(cli.output.as_ref(), &output)?;
+            },
+            Commands::Validate { target_json, compact_display_order, ignore_pattern } => {
+                if og::fs_util::GlobFilter::new(&ignore_pattern)?.matches(&target_json) {
+                    eprintln!("Skipping {} (matches --ignore-pattern)", target_json.display());
+                    return Ok(());
+                }
+                let existing_json = fs::read_to_string(&target_json)
+                    .map_err(|e| format!("Error reading JSON file '{}': {}", target_json.display(), e))?;
+                let mut tasks = parse_json_lines(&existing_json, false)?;
+                if compact_display_order {
+                    og::task_model::compact_display_orders(&mut tasks);
+                    let json_out = og::tasks_to_jsonl(&tasks).map_err(|e| e.to_string())?;
+                    backup_before_write(backup, &target_json)?;
+                    fs::write(&target_json, json_out)
+                        .map_err(|e| format!("Error writing JSON file '{}': {}", target_json.display(), e))?;
+                    eprintln!("Compacted display_order in {}", target_json.display());
+                }
+                let issues = og::validate::validate_tasks(&tasks);
+                for issue in &issues {
+                    println!("{}", issue);
+                }
+                if !issues.is_empty() {
+                    io::stdout().flush().map_err(|e| format!("Error flushing stdout: {}", e))?;
+                    std::process::exit(1);
+                }
+            },
+            Commands::Cal { title, next, all, attendees, only_if_organizer, country_code, sync_to_tasks, no_auth, no_auth_browser, account, format, conflict_detect, hide_declined, remind_minutes } => {
+                // --remind-minutes では終了コード 1 が「予定あり」を表すので、エラーは終了コード 2 にする
+                let cal_format: calendar::CalendarFormat = match format.parse() {
+                    Ok(cal_format) => cal_format,
+                    Err(e) if remind_minutes.is_some() => {
+                        eprintln!("{}", e);
+                        std::process::exit(2);
+                    }
+                    Err(e) => return Err(e),
+                };
+                let offline = calendar::offline_mode(no_auth);
+                let events_result = if offline {
+                    eprintln!("Calendar: running in offline mode (no credentials found)");
+                    Ok(Vec::new())
+                } else if let Some(count) = next {
+                    // 絞り込みは取得後に行う (--sync-to-tasks は絞り込み前の予定で同期する)
+                    calendar::get_next_business_day_events(count, &country_code, account.as_deref(), no_auth_browser).await
                 } else {
-                    calendar::get_today_events(all).await
+                    calendar::get_today_events(account.as_deref(), no_auth_browser).await
                 };
                 
                 match events_result {
                     Ok(events) => {
-                        let output = calendar::format_events_output(&events, title);
+                        // 表示されない予定 (終日・'.' 始まり・辞退など) を削除扱いにしてキャンセルしないよう、同期には全件を使う
+                        let fetched_events = if sync_to_tasks.is_some() { events.clone() } else { Vec::new() };
+                        let mut events = calendar::filter_events(events, all);
+                        if only_if_organizer {
+                            events.retain(|event| event.is_organizer);
+                        }
+                        if hide_declined && !all {
+                            events.retain(|event| !event.is_declined_or_cancelled());
+                        }
+                        if let Some(minutes) = remind_minutes {
+                            events = calendar::filter_upcoming(&events, minutes as i64).into_iter().cloned().collect();
+                        }
+                        if conflict_detect {
+                            for (a, b) in calendar::find_overlapping_events(&events) {
+                                eprintln!("{}", calendar::format_conflict_warning(a, b));
+                            }
+                        }
+                        let mut opts = calendar::CalendarOutputOptions {
+                            title_only: title,
+                            show_attendees: attendees,
+                            color: color_choice.should_color_stdout(),
+                            ..Default::default()
+                        };
+                        // 通知用なので見出しを付けず、予定がなければ何も出力しない
+                        if remind_minutes.is_some() {
+                            opts.header = None;
+                            opts.empty_message = None;
+                        }
+                        let output = match cal_format {
+                            calendar::CalendarFormat::Text => calendar::format_events_output(&events, &opts),
+                            calendar::CalendarFormat::Json => calendar::format_events_json(&events).map_err(|e| e.to_string())?,
+                            calendar::CalendarFormat::Ical => calendar::format_events_ical(&events),
+                            calendar::CalendarFormat::Compact => calendar::format_events_compact(&events),
+                        };
                         print!("{}", output);
+                        // --remind-minutes: 通知すべき予定があれば終了コード 1
+                        if remind_minutes.is_some() && !events.is_empty() {
+                            io::stdout().flush().map_err(|e| format!("Error flushing stdout: {}", e))?;
+                            std::process::exit(1);
+                        }
+
+                        if offline && sync_to_tasks.is_some() {
+                            eprintln!("Warning: --sync-to-tasks is skipped in offline mode");
+                        } else if let Some(path) = sync_to_tasks {
+                            let day = if let Some(count) = next {
+                                calendar::next_business_day_from_today(count, &country_code).await
+                                    .map_err(|e| e.to_string())?
+                            } else {
+                                default_created_date
+                            };
+                            let mut tasks = if path.exists() {
+                                let content = fs::read_to_string(&path)
+                                    .map_err(|e| format!("Error reading JSON file '{}': {}", path.display(), e))?;
+                                parse_json_tasks(&content)?
+                            } else {
+                                Vec::new()
+                            };
+                            calendar::sync_events_to_tasks(&fetched_events, &mut tasks, day).await;
+                            let json_out = serialize_tasks_to_json(&tasks, cli.pretty)?;
+                            backup_before_write(backup, &path)?;
+                            fs::write(&path, json_out)
+                                .map_err(|e| format!("Error writing JSON file '{}': {}", path.display(), e))?;
+                        }
                     }
                     Err(e) => {
-                        return Err(format!("Calendar error: {}", e));
+                        let message = calendar_error_message(&e);
+                        // 期限切れなど再認証が必要な場合と --remind-minutes の取得失敗は終了コード 2
+                        if remind_minutes.is_some() || calendar_error_needs_auth(&e) {
+                            eprintln!("{}", message);
+                            std::process::exit(2);
+                        }
+                        return Err(message);
                     }
                 }
             }
+            Commands::Import { target_json, from_github_issues } => {
+                let mut tasks = if target_json.exists() {
+                    let content = fs::read_to_string(&target_json)
+                        .map_err(|e| format!("Error reading JSON file '{}': {}", target_json.display(), e))?;
+                    parse_json_tasks(&content)?
+                } else {
+                    Vec::new()
+                };
+                let token = std::env::var("GITHUB_TOKEN").ok().filter(|token| !token.is_empty());
+                let issues = github_import::fetch_open_issues(&from_github_issues, token.as_deref()).await.map_err(|e| e.to_string())?;
+                let added = github_import::import_issues(&issues, &mut tasks);
+                eprintln!("Imported {} new issue(s) from {}", added, from_github_issues);
+                let json_out = serialize_tasks_to_json(&tasks, cli.pretty)?;
+                backup_before_write(backup, &target_json)?;
+                og::fs_util::write_atomic(&target_json, json_out)
+                    .map_err(|e| format!("Error writing JSON file '{}': {}", target_json.display(), e))?;
+            },
+            Commands::Doctor { account, check_token } => {
+                let mut checks = calendar::run_doctor_checks(account.as_deref());
+                if check_token && checks.iter().all(|check| check.passed) {
+                    checks.push(calendar::check_token_online(account.as_deref()).await);
+                }
+                for check in &checks {
+                    println!("{}", check);
+                }
+                let failed = checks.iter().filter(|check| !check.passed).count();
+                if failed > 0 {
+                    println!("{} of {} checks failed", failed, checks.len());
+                    io::stdout().flush().map_err(|e| format!("Error flushing stdout: {}", e))?;
+                    std::process::exit(1);
+                }
+            }
         }
     } else {
         // Conversion mode (no subcommand)
-        let from_format = cli.from.ok_or_else(|| "Error: --from <FORMAT> is required for conversion mode.".to_string())?.to_lowercase();
+        let reads_stdin = cli.input_file_conversion.as_deref().is_none_or(|path| path == "-");
+        let from_format = resolve_from_format(cli.from.as_deref(), cli.stdin_format.as_deref(), reads_stdin)?
+            .ok_or_else(|| "Error: --from <FORMAT> (or --stdin-format when piping) is required for conversion mode.".to_string())?;
         let to_format = cli.to.ok_or_else(|| "Error: --to <FORMAT> is required for conversion mode.".to_string())?.to_lowercase();
 
         let input_content = read_input(cli.input_file_conversion.as_ref())?;
 
         match (from_format.as_str(), to_format.as_str()) {
             ("markdown", "json") => {
-                let tasks = markdown_parser::parse_markdown_document_to_tasks(&input_content, default_created_date)?;
-                let mut json_outputs: Vec<String> = Vec::new();
-                for task in tasks {
-                    json_outputs.push(serde_json::to_string(&task).map_err(|e| format!("Error serializing task to JSON: {}", e))?);
-                }
-                let output_string = json_outputs.join("\n");
-                let final_output = if output_string.is_empty() { "".to_string() } else { output_string + "\n" };
+                let tasks = parse_markdown(&input_content, default_created_date, false, &parse_options)?;
+                let final_output = serialize_tasks_to_json(&tasks, cli.pretty)?;
                 write_output(cli.output.as_ref(), &final_output)?;
             }
+            ("obsidian", "json" | "markdown") => {
+                let tasks = og::obsidian_parser::parse_obsidian_tasks(&input_content, default_created_date).map_err(|e| e.to_string())?;
+                let output = match to_format.as_str() {
+                    "json" => serialize_tasks_to_json(&tasks, cli.pretty)?,
+                    _ => markdown_formatter::format_tasks_to_markdown_document_with_options(&tasks, &markdown_options),
+                };
+                write_output(cli.output.as_ref(), &output)?;
+            }
             ("json", "markdown") => {
-                let mut tasks: Vec<Task> = Vec::new();
-                for line in input_content.lines() {
-                    if line.trim().is_empty() { continue; }
-                    let task: Task = serde_json::from_str(line).map_err(|e| format!("Error deserializing task from JSON line '{}': {}", line, e))?;
-                    tasks.push(task);
-                }
-                let markdown_output = markdown_formatter::format_tasks_to_markdown_document(&tasks);
+                let tasks = parse_json_tasks(&input_content)?;
+                let markdown_output = markdown_formatter::format_tasks_to_markdown_document_with_options(&tasks, &markdown_options);
                 write_output(cli.output.as_ref(), &markdown_output)?;
             }
             _ => return Err(format!("Error: Unsupported conversion from '{}' to '{}'.", from_format, to_format)),
@@ -213,6 +1235,7 @@ mod tests {
             due: None, // Changed from due_date
             completed: None, // Changed from completed_at
             tags: Some(Vec::new()), // Wrapped in Some()
+            tags_kv: None,
             subtasks: Some(Vec::new()), // Changed from sub_tasks and wrapped in Some()
             // parent_id: None, // Removed, not in Task model
             priority: "N".to_string(), // Changed to String, provided a default
@@ -223,6 +1246,10 @@ mod tests {
             contexts: None, // Added optional field
             extra: None, // Added optional field
             repeat: None, // Added optional field
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
         }
     }
 
@@ -303,4 +1330,30 @@ mod tests {
         assert_eq!(re_parsed_tasks[0].subtasks.as_ref().map_or(0, |s| s.len()), 1, "Formatting should preserve sub-task count for Task A."); // Changed from sub_tasks
     }
 
+    #[test]
+    fn test_markdown_pretty_json_round_trip() {
+        let markdown_input = "- [ ] (A) [[Task 1]] id:1 due:\"\" created:2024-01-01 updated:\"\" completed:\"\"
+    - [x] (N) [[Subtask 1.1]] id:2 due:2024-02-01 created:2024-01-01 updated:\"\" completed:2024-01-15
+- [p] (B) [[Task 2]] id:3 due:\"\" +proj @ctx #tag created:2024-01-02 updated:\"\" completed:\"\"";
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let tasks = markdown_parser::parse_markdown_document_to_tasks(markdown_input, default_date).unwrap();
+
+        let pretty_json = serialize_tasks_to_json(&tasks, true).unwrap();
+        assert!(pretty_json.starts_with('['));
+        assert!(pretty_json.contains("\n  {"));
+
+        let round_trip_tasks = parse_json_tasks(&pretty_json).unwrap();
+        let markdown_output = markdown_formatter::format_tasks_to_markdown_document(&round_trip_tasks);
+        assert_eq!(markdown_output, markdown_input);
+    }
+
+    #[test]
+    fn test_parse_json_tasks_accepts_jsonl() {
+        let jsonl = serialize_tasks_to_json(&[create_test_task(1, "A"), create_test_task(2, "B")], false).unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        let tasks = parse_json_tasks(&jsonl).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[1].name, "B");
+    }
+
 }
@@ -1,37 +1,76 @@
-use crate::task_model::Task;
-
-// B.3. 要素詳細 と B.4. 属性ごとの表示ルール に基づく
-fn map_status_string_to_char(status_string: &str) -> char {
-    // 仕様書とコードの差異: 
-    // - 仕様書では大文字表記 (例: "NONE") を使用
-    // - 実装では小文字表記 (例: "open") を使用
-    // - 特に "open" と "none" は実装では同じ ' ' 文字にマップ
-    match status_string.to_ascii_lowercase().as_str() {
-        "none" | "open" => ' ',  // 仕様書では "NONE"
-        "pending" => 'p',
-        "doing" => '>',
-        "waiting" => 'w',
-        "done" => 'x',
-        "cancelled" => 'c',
-        _ => '?', // デフォルトまたはエラーケース
+use crate::status_map::StatusMap;
+use crate::task_model::{completion_percentage_str, Block, Document, Task};
+use chrono::NaiveDate;
+
+// フォーマッタの出力オプション (og fmt のフラグに対応)
+#[derive(Debug, Clone)]
+pub struct FormatterOptions {
+    pub omit_ids: bool, // id:N 属性を出力しない (--no-ids)
+    pub status_map: StatusMap, // ステータスマーカー文字の対応表 (~/.config/og/status-map.toml)
+    pub emit_null_dates: bool, // 値のない日付も due:"" のように出力する (false で省略: --compact-nulls)
+    pub show_progress: bool, // サブタスクを持つ行の末尾に完了率 <!-- 75% --> を付ける (--show-progress)
+    pub emit_display_orders: bool, // display_order を do:N として出力する (--emit-display-orders)
+    pub emit_next_id_hint: bool, // 1行目に <!-- og:next_id:N --> を付ける (--emit-next-id-hint)
+    pub omit_created_on: Option<NaiveDate>, // この日付の created は出力しない (--suppress-created-when-default)
+    pub wrap_width: Option<usize>, // この幅を超えるタスク行を属性の間で折り返す (--wrap)
+    pub max_depth: Option<usize>, // この深さ (トップレベルが 0) より深いサブタスクを出力しない (--max-depth)
+    pub trailing_newline: bool, // 出力を改行で終える (--preserve-trailing-newline、--in-place では常に有効)
+    pub preserve_header_lines: bool, // 最初のタスク行より前の行を出力の先頭に残す (--preserve-header-lines)
+}
+
+impl Default for FormatterOptions {
+    fn default() -> Self {
+        FormatterOptions { omit_ids: false, status_map: StatusMap::default(), emit_null_dates: true, show_progress: false, emit_display_orders: false, emit_next_id_hint: false, omit_created_on: None, wrap_width: None, max_depth: None, trailing_newline: false, preserve_header_lines: false }
+    }
+}
+
+// extra の値を key:value 属性として書けるならその値の文字列を返す。
+// 空白を含まない文字列 (// で始まるものを除く)・数値・真偽値のみ対象で、既知の属性と同じキーや構造を持つ値は JSON のみに残す
+pub fn extra_attribute_value(key: &str, value: &serde_json::Value) -> Option<String> {
+    let attribute_like_re = regex::Regex::new(crate::markdown_parser::ATTRIBUTE_LIKE_RE_STR).unwrap();
+    let key_is_attribute = attribute_like_re.is_match(&format!("{}:", key))
+        && !crate::markdown_parser::KNOWN_ATTRIBUTE_KEYS.contains(&key);
+    if !key_is_attribute {
+        return None;
+    }
+    match value {
+        serde_json::Value::String(text) if attribute_like_re.is_match(&format!("{}:{}", key, text)) => Some(text.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
     }
 }
 
 // format_task_to_markdown_line を変更 (行頭マーカーとインデントは呼び出し元で付与)
-fn format_task_core_content(task: &Task) -> String { // 新しい内部関数名
-    let status_char = map_status_string_to_char(&task.status);
+fn format_task_core_content(task: &Task, options: &FormatterOptions) -> String { // 新しい内部関数名
+    let (head, attributes) = format_task_core_parts(task, options);
+    format!("{} {}", head, attributes.join(" ")).trim_end().to_string()
+}
+
+// "[ ] (A) [[名前]]" の部分と、属性トークンのリスト (--wrap はトークンの間で折り返す)
+fn format_task_core_parts(task: &Task, options: &FormatterOptions) -> (String, Vec<String>) {
+    // B.3. 要素詳細: ステータス → マーカー文字 (既定の対応表は StatusMap::default)
+    let status_char = options.status_map.char_for(&task.status);
     let priority_str = &task.priority;
     let task_name_str = &task.name;
 
     let mut attributes: Vec<String> = Vec::new();
 
-    // id (必須)
-    attributes.push(format!("id:{}", task.id));
+    // id (必須。--no-ids の場合と未採番 (0、--remove-ids) の場合は省略し、再パース時に自動採番させる)
+    if !options.omit_ids && task.id != 0 {
+        attributes.push(format!("id:{}", task.id));
+    }
+
+    // do (--emit-display-orders の場合のみ。再パース時に行順より優先される)
+    if options.emit_display_orders {
+        attributes.push(format!("do:{}", task.display_order));
+    }
 
     // due (キー必須、値はOption<NaiveDate>)
     match &task.due {
         Some(due_date) => attributes.push(format!("due:{}", due_date.format("%Y-%m-%d"))),
-        None => attributes.push("due:\"\"".to_string()),
+        None if options.emit_null_dates => attributes.push("due:\"\"".to_string()),
+        None => {}
     }
 
     // project (オプション)
@@ -54,60 +93,229 @@ fn format_task_core_content(task: &Task) -> String { // 新しい内部関数名
             attributes.push(tags_str);
         }
     }
+
+    // tags_kv (オプション、#key:value。出力を安定させるためキー順)
+    if let Some(tags_kv) = &task.tags_kv {
+        let mut entries: Vec<(&String, &String)> = tags_kv.iter().collect();
+        entries.sort();
+        for (key, value) in entries {
+            attributes.push(format!("#{}:{}", key, value));
+        }
+    }
     
-    // created (必須, NaiveDate)
-    attributes.push(format!("created:{}", task.created.format("%Y-%m-%d")));
+    // created (必須, NaiveDate。--suppress-created-when-default では今日の日付なら省略し、再パース時に補わせる)
+    if options.omit_created_on != Some(task.created) {
+        attributes.push(format!("created:{}", task.created.format("%Y-%m-%d")));
+    }
 
     // updated (キー必須、値はOption<NaiveDate>)
     match &task.updated {
         Some(updated_date) => attributes.push(format!("updated:{}", updated_date.format("%Y-%m-%d"))),
-        None => attributes.push("updated:\"\"".to_string()),
+        None if options.emit_null_dates => attributes.push("updated:\"\"".to_string()),
+        None => {}
     }
 
     // completed (キー必須、値はOption<NaiveDate>)
     match &task.completed {
         Some(completed_date) => attributes.push(format!("completed:{}", completed_date.format("%Y-%m-%d"))),
-        None => attributes.push("completed:\"\"".to_string()),
+        None if options.emit_null_dates => attributes.push("completed:\"\"".to_string()),
+        None => {}
+    }
+
+    // progress (オプション、0〜100)
+    if let Some(progress) = task.progress {
+        attributes.push(format!("progress:{}", progress));
+    }
+
+    // estimate (オプション、正規形 XhYm)
+    if let Some(minutes) = task.estimate_minutes {
+        attributes.push(format!("estimate:{}", crate::task_model::format_duration_minutes(minutes)));
+    }
+
+    // depends (オプション、先行タスクのID)
+    if let Some(depends) = &task.depends {
+        if !depends.is_empty() {
+            let ids = depends.iter().map(|id| id.to_string()).collect::<Vec<String>>().join(",");
+            attributes.push(format!("depends:{}", ids));
+        }
+    }
+
+    // repeat (オプション、頻度がある場合のみ)
+    if let Some(frequency) = task.repeat.as_ref().and_then(|r| r.frequency) {
+        attributes.push(format!("repeat:{}", frequency.as_str()));
+    }
+
+    // extra のうち単純な値 (sprint:12 など) は属性として書き出し、再パースで extra に戻す。キー順
+    if let Some(extra) = &task.extra {
+        let mut entries: Vec<(&String, String)> = extra
+            .iter()
+            .filter_map(|(key, value)| Some((key, extra_attribute_value(key, value)?)))
+            .collect();
+        entries.sort();
+        for (key, value) in entries {
+            attributes.push(format!("{}:{}", key, value));
+        }
     }
 
     // notes (オプション)
     if let Some(note_str) = &task.notes {
-        attributes.push(format!("note:\"{}\"", note_str.replace("\"", "\"\"")));
+        attributes.push(format!("note:\"{}\"", escape_note(note_str)));
     }
     
-    let attributes_combined_str = attributes.join(" ");
-
     // 行頭の "- " は除去。インデントは呼び出し側で。
-    format!(
-        "[{}] ({}) [[{}]] {}",
-        status_char,
-        priority_str,
-        task_name_str,
-        attributes_combined_str.trim_end()
-    ).trim_end().to_string()
+    (format!("[{}] ({}) [[{}]]", status_char, priority_str, task_name_str), attributes)
+}
+
+// note:"..." の値。改行でタスク行が割れないよう \n / \r に、\ は \\ に、" は "" にする
+// (パーサーの markdown_parser::unescape_note の逆)
+fn escape_note(note: &str) -> String {
+    note.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r").replace('"', "\"\"")
+}
+
+// --wrap: 幅を超える行を属性トークンの間で折り返す。折り返す行の末尾には " \" を付け、
+// 続きはタスクより 1 段深いインデントに置く (パーサーは 1 行につなげて読む)
+fn wrap_task_line(first: String, tokens: Vec<String>, continuation_indent: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = first;
+    for token in tokens {
+        // 折り返し記号 " \" の 2 文字分を残して収まるか
+        if current.chars().count() + 1 + token.chars().count() + 2 <= width {
+            current.push(' ');
+            current.push_str(&token);
+        } else {
+            lines.push(format!("{} {}", current, crate::markdown_parser::LINE_CONTINUATION));
+            current = format!("{}{}", continuation_indent, token);
+        }
+    }
+    lines.push(current);
+    lines
 }
 
 // 再帰的にタスクとサブタスクをフォーマットする内部ヘルパー
-fn format_task_recursive_internal(task: &Task, indent_level: usize, lines: &mut Vec<String>) {
+fn format_task_recursive_internal(task: &Task, indent_level: usize, options: &FormatterOptions, lines: &mut Vec<String>) {
     let indent = "    ".repeat(indent_level); // 半角スペース4つで1レベル
-    let task_core_line = format_task_core_content(task);
-    lines.push(format!("{}- {}", indent, task_core_line));
+    let has_subtasks = task.subtasks.as_ref().is_some_and(|subtasks| !subtasks.is_empty());
+    let progress_comment = (options.show_progress && has_subtasks).then(|| format!("<!-- {} -->", completion_percentage_str(task)));
+    // --max-depth で子を出力しない親には、隠したタスク (子孫すべて) の数を付ける
+    let truncated = has_subtasks && options.max_depth.is_some_and(|max_depth| indent_level >= max_depth);
+    let truncation_marker = truncated.then(|| format!("(… {} more)", task.flatten().count() - 1));
+    match options.wrap_width {
+        Some(width) => {
+            let (head, mut tokens) = format_task_core_parts(task, options);
+            tokens.extend(truncation_marker);
+            tokens.extend(progress_comment);
+            let single_line = format!("{}- {} {}", indent, head, tokens.join(" ")).trim_end().to_string();
+            if single_line.chars().count() <= width {
+                lines.push(single_line);
+            } else {
+                lines.extend(wrap_task_line(format!("{}- {}", indent, head), tokens, &format!("{}    ", indent), width));
+            }
+        }
+        None => {
+            let mut task_core_line = format_task_core_content(task, options);
+            if let Some(marker) = truncation_marker {
+                task_core_line = format!("{} {}", task_core_line, marker);
+            }
+            match progress_comment {
+                Some(comment) => lines.push(format!("{}- {} {}", indent, task_core_line, comment)),
+                None => lines.push(format!("{}- {}", indent, task_core_line)),
+            }
+        }
+    }
 
-    if let Some(subtasks) = &task.subtasks {
+    if let Some(subtasks) = task.subtasks.as_ref().filter(|_| !truncated) {
         for subtask in subtasks {
-            format_task_recursive_internal(subtask, indent_level + 1, lines);
+            format_task_recursive_internal(subtask, indent_level + 1, options, lines);
         }
     }
 }
 
+// 次に採番すべき ID (最大 ID + 1) を示すコメント行。パーサーは read_next_id_hint で読む
+fn next_id_hint_line<'a>(tasks: impl Iterator<Item = &'a Task>) -> String {
+    let max_id = tasks.flat_map(|task| task.flatten()).map(|task| task.id).max().unwrap_or(0);
+    format!("<!-- og:next_id:{} -->", max_id + 1)
+}
+
 // 公開関数：Taskのスライスを受け取り、Markdownドキュメント文字列を生成
 pub fn format_tasks_to_markdown_document(tasks: &[Task]) -> String {
+    format_tasks_to_markdown_document_with_options(tasks, &FormatterOptions::default())
+}
+
+// 出力オプション付きの format_tasks_to_markdown_document
+pub fn format_tasks_to_markdown_document_with_options(tasks: &[Task], options: &FormatterOptions) -> String {
     let mut lines: Vec<String> = Vec::new();
+    if options.emit_next_id_hint {
+        lines.push(next_id_hint_line(tasks.iter()));
+    }
     for task in tasks {
         // トップレベルタスクのインデントレベルは0
-        format_task_recursive_internal(task, 0, &mut lines);
+        format_task_recursive_internal(task, 0, options, &mut lines);
+    }
+    end_with_newline(lines.join("\n"), options)
+}
+
+// trailing_newline なら、空でない出力が改行で終わるようにする
+fn end_with_newline(mut output: String, options: &FormatterOptions) -> String {
+    if options.trailing_newline && !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
     }
-    lines.join("\n")
+    output
+}
+
+// 最初のタスク行より前の行 (YAML front matter、--- 区切り、# 見出しなど)。
+// --emit-next-id-hint のヒント行は古い値なので含めない
+pub fn leading_header_lines(markdown_document: &str) -> Vec<&str> {
+    markdown_document
+        .lines()
+        .take_while(|line| !crate::markdown_parser::is_task_line(line))
+        .filter(|line| crate::markdown_parser::read_next_id_hint(line).is_none())
+        .collect()
+}
+
+// format_tasks_to_markdown_document_with_options と同じ。preserve_header_lines なら source の先頭の
+// タスク以外の行を、整形結果の前にそのまま付ける
+pub fn format_tasks_with_source_header(source: &str, tasks: &[Task], options: &FormatterOptions) -> String {
+    let formatted = format_tasks_to_markdown_document_with_options(tasks, options);
+    let header = leading_header_lines(source);
+    if !options.preserve_header_lines || header.is_empty() {
+        return formatted;
+    }
+    end_with_newline(format!("{}\n{}", header.join("\n"), formatted), options)
+}
+
+// filter::group_tasks の結果を、グループごとの "### 見出し" とタスクの Markdown にする
+pub fn format_grouped_tasks(groups: &[(String, Vec<Task>)], options: &FormatterOptions) -> String {
+    groups
+        .iter()
+        .map(|(heading, tasks)| format!("### {}\n\n{}", heading, format_tasks_to_markdown_document_with_options(tasks, options)))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+// タスク以外の行を元の位置に残したまま、タスク行だけを整形して出力する
+pub fn format_document_with_options(document: &Document, options: &FormatterOptions) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut blocks = document.blocks.iter().peekable();
+    if options.emit_next_id_hint {
+        lines.push(next_id_hint_line(document.tasks()));
+        // 元のヒント行は新しいものに置き換える
+        if let Some(Block::Text(text)) = blocks.peek() {
+            if crate::markdown_parser::read_next_id_hint(text).is_some() {
+                blocks.next();
+            }
+        }
+    }
+    for block in blocks {
+        match block {
+            Block::Text(text) => lines.push(text.clone()),
+            Block::Tasks(tasks) => {
+                for task in tasks {
+                    format_task_recursive_internal(task, 0, options, &mut lines);
+                }
+            }
+        }
+    }
+    end_with_newline(lines.join("\n"), options)
 }
 
 
@@ -133,9 +341,14 @@ mod tests {
             contexts: Some(vec!["work".to_string(), "home".to_string()]),
             notes: Some("This is a note.".to_string()),
             tags: Some(vec!["important".to_string()]),
+            tags_kv: None,
             subtasks: None,
             extra: None,
             repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
         };
         let expected_md = "- [p] (A) [[Simple Task]] id:1 due:2024-12-31 +MyProject @work @home #important created:2024-01-01 updated:\"\" completed:\"\" note:\"This is a note.\"";
         assert_eq!(format_tasks_to_markdown_document(&[task]), expected_md);
@@ -157,9 +370,14 @@ mod tests {
             contexts: None,
             notes: None,
             tags: None,
+            tags_kv: None,
             subtasks: None,
             extra: None,
             repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
         };
         let expected_md = "- [ ] (N) [[Minimal Task]] id:2 due:\"\" created:2024-01-02 updated:\"\" completed:\"\"";
         assert_eq!(format_tasks_to_markdown_document(&[task]), expected_md);
@@ -181,9 +399,14 @@ mod tests {
             contexts: None,
             notes: Some("".to_string()), 
             tags: None,
+            tags_kv: None,
             subtasks: None,
             extra: None,
             repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
         };
         let expected_md = "- [x] (C) [[Empty Note Task]] id:3 due:2024-03-10 created:2024-03-03 updated:2024-03-04 completed:2024-03-05 note:\"\"";
         assert_eq!(format_tasks_to_markdown_document(&[task]), expected_md);
@@ -205,9 +428,14 @@ mod tests {
             contexts: None,
             notes: Some("This is a \"quoted\" note.".to_string()),
             tags: None,
+            tags_kv: None,
             subtasks: None,
             extra: None,
             repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
         };
         let expected_md = "- [p] (B) [[Note with quotes]] id:4 due:\"\" created:2024-07-01 updated:\"\" completed:\"\" note:\"This is a \"\"quoted\"\" note.\"";
         assert_eq!(format_tasks_to_markdown_document(&[task]), expected_md);
@@ -216,12 +444,12 @@ mod tests {
     #[test]
     fn test_format_multiple_tasks_no_subtasks() {
         let task1_created = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
-        let task1 = Task { id: 1, name: "Task 1".to_string(), status: "NONE".to_string(), priority: "N".to_string(), created: task1_created, display_order: 1, due: None, updated: None, completed: None, project: None, contexts: None, notes: None, tags: None, subtasks: None, extra: None, repeat: None };
+        let task1 = Task { id: 1, name: "Task 1".to_string(), status: "NONE".to_string(), priority: "N".to_string(), created: task1_created, display_order: 1, due: None, updated: None, completed: None, project: None, contexts: None, notes: None, tags: None, tags_kv: None, subtasks: None, extra: None, repeat: None, depends: None, progress: None, estimate_minutes: None, unknown_fields: Default::default() };
         
         let task2_created = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
         let task2_due = Some(NaiveDate::from_ymd_opt(2024, 1, 10).unwrap());
         let task2_completed = Some(NaiveDate::from_ymd_opt(2024, 1, 3).unwrap());
-        let task2 = Task { id: 2, name: "Task 2".to_string(), status: "DONE".to_string(), priority: "A".to_string(), created: task2_created, display_order: 2, due: task2_due, updated: None, completed: task2_completed, project: None, contexts: None, notes: None, tags: None, subtasks: None, extra: None, repeat: None };
+        let task2 = Task { id: 2, name: "Task 2".to_string(), status: "DONE".to_string(), priority: "A".to_string(), created: task2_created, display_order: 2, due: task2_due, updated: None, completed: task2_completed, project: None, contexts: None, notes: None, tags: None, tags_kv: None, subtasks: None, extra: None, repeat: None, depends: None, progress: None, estimate_minutes: None, unknown_fields: Default::default() };
         
         let expected_md = "\
 - [ ] (N) [[Task 1]] id:1 due:\"\" created:2024-01-01 updated:\"\" completed:\"\"
@@ -234,13 +462,13 @@ mod tests {
         let child_created = NaiveDate::from_ymd_opt(2024, 7, 15).unwrap();
         let child_task = Task {
             name: "Child Task".to_string(), status: "PENDING".to_string(), priority: "N".to_string(), id: 11, created: child_created, display_order: 2,
-            due: None, updated: None, completed: None, project: None, contexts: None, notes: None, tags: None, subtasks: None, extra: None, repeat: None,
+            due: None, updated: None, completed: None, project: None, contexts: None, notes: None, tags: None, tags_kv: None, subtasks: None, extra: None, repeat: None, depends: None, progress: None, estimate_minutes: None, unknown_fields: Default::default(),
         };
         
         let parent_created = NaiveDate::from_ymd_opt(2024, 7, 15).unwrap();
         let parent_task = Task {
             name: "Parent Task".to_string(), status: "NONE".to_string(), priority: "A".to_string(), id: 10, created: parent_created, display_order: 1,
-            due: None, updated: None, completed: None, project: None, contexts: None, notes: None, tags: None, subtasks: Some(vec![child_task]), extra: None, repeat: None,
+            due: None, updated: None, completed: None, project: None, contexts: None, notes: None, tags: None, tags_kv: None, subtasks: Some(vec![child_task]), extra: None, repeat: None, depends: None, progress: None, estimate_minutes: None, unknown_fields: Default::default(),
         };
         let expected_md = "\
 - [ ] (A) [[Parent Task]] id:10 due:\"\" created:2024-07-15 updated:\"\" completed:\"\"
@@ -252,14 +480,14 @@ mod tests {
     fn test_format_task_with_multiple_subtasks_and_levels() {
         let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(); // Common date for simplicity
 
-        let gc1 = Task { name: "Grandchild 1.1.1".to_string(), id:3, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:3, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,subtasks:None,extra:None,repeat:None };
-        let c1 = Task { name: "Child 1.1".to_string(), id:2, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:2, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,subtasks:Some(vec![gc1]),extra:None,repeat:None };
-        let c2 = Task { name: "Child 1.2".to_string(), id:4, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:4, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,subtasks:None,extra:None,repeat:None };
-        let p1 = Task { name: "Parent 1".to_string(), id:1, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:1, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,subtasks:Some(vec![c1, c2]),extra:None,repeat:None };
+        let gc1 = Task { name: "Grandchild 1.1.1".to_string(), id:3, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:3, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,tags_kv:None,subtasks:None,extra:None,repeat:None,depends:None,progress:None,estimate_minutes:None,unknown_fields:Default::default() };
+        let c1 = Task { name: "Child 1.1".to_string(), id:2, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:2, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,tags_kv:None,subtasks:Some(vec![gc1]),extra:None,repeat:None,depends:None,progress:None,estimate_minutes:None,unknown_fields:Default::default() };
+        let c2 = Task { name: "Child 1.2".to_string(), id:4, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:4, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,tags_kv:None,subtasks:None,extra:None,repeat:None,depends:None,progress:None,estimate_minutes:None,unknown_fields:Default::default() };
+        let p1 = Task { name: "Parent 1".to_string(), id:1, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:1, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,tags_kv:None,subtasks:Some(vec![c1, c2]),extra:None,repeat:None,depends:None,progress:None,estimate_minutes:None,unknown_fields:Default::default() };
 
-        let gc2_1_1 = Task { name: "GrandGrandchild 2.1.1".to_string(), id:7, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:7, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,subtasks:None,extra:None,repeat:None };
-        let c3 = Task { name: "Child 2.1".to_string(), id:6, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:6, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,subtasks:Some(vec![gc2_1_1]),extra:None,repeat:None };
-        let p2 = Task { name: "Parent 2".to_string(), id:5, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:5, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,subtasks:Some(vec![c3]),extra:None,repeat:None };
+        let gc2_1_1 = Task { name: "GrandGrandchild 2.1.1".to_string(), id:7, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:7, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,tags_kv:None,subtasks:None,extra:None,repeat:None,depends:None,progress:None,estimate_minutes:None,unknown_fields:Default::default() };
+        let c3 = Task { name: "Child 2.1".to_string(), id:6, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:6, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,tags_kv:None,subtasks:Some(vec![gc2_1_1]),extra:None,repeat:None,depends:None,progress:None,estimate_minutes:None,unknown_fields:Default::default() };
+        let p2 = Task { name: "Parent 2".to_string(), id:5, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:5, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,tags_kv:None,subtasks:Some(vec![c3]),extra:None,repeat:None,depends:None,progress:None,estimate_minutes:None,unknown_fields:Default::default() };
 
         let expected_md = "\
 - [ ] (N) [[Parent 1]] id:1 due:\"\" created:2024-01-01 updated:\"\" completed:\"\"
@@ -271,4 +499,314 @@ mod tests {
         - [ ] (N) [[GrandGrandchild 2.1.1]] id:7 due:\"\" created:2024-01-01 updated:\"\" completed:\"\"";
         assert_eq!(format_tasks_to_markdown_document(&[p1, p2]), expected_md);
     }
+
+    #[test]
+    fn test_format_omit_ids_and_reparse() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let child = Task { name: "Child".to_string(), id:42, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:2, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,tags_kv:None,subtasks:None,extra:None,repeat:None,depends:None,progress:None,estimate_minutes:None,unknown_fields:Default::default() };
+        let parent = Task { name: "Parent".to_string(), id:17, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:1, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,tags_kv:None,subtasks:Some(vec![child]),extra:None,repeat:None,depends:None,progress:None,estimate_minutes:None,unknown_fields:Default::default() };
+
+        let options = FormatterOptions { omit_ids: true, ..Default::default() };
+        let md = format_tasks_to_markdown_document_with_options(&[parent], &options);
+        assert!(!md.contains("id:"));
+
+        let reparsed = crate::markdown_parser::parse_markdown_document_to_tasks(&md, test_date).unwrap();
+        assert_eq!(reparsed[0].id, 1);
+        assert_eq!(reparsed[0].subtasks.as_ref().unwrap()[0].id, 2);
+    }
+
+    #[test]
+    fn test_skipped_status_round_trip() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let md = "- [-] (N) [[Commented out]] id:3 due:\"\" created:2024-01-01 updated:\"\" completed:\"\"";
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks(md, test_date).unwrap();
+        assert_eq!(tasks[0].status, "skipped");
+        assert_eq!(format_tasks_to_markdown_document(&tasks), md);
+
+        let json = serde_json::to_string(&tasks[0]).unwrap();
+        let from_json: Task = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json.status, "skipped");
+        assert_eq!(format_tasks_to_markdown_document(&[from_json]), md);
+    }
+
+    #[test]
+    fn test_format_depends_round_trip() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut task = Task { name: "Blocked".to_string(), id:9, status:"NONE".to_string(), priority:"N".to_string(), created:test_date, display_order:1, due:None,updated:None,completed:None,project:None,contexts:None,notes:None,tags:None,tags_kv:None,subtasks:None,extra:None,repeat:None,depends:Some(vec![1, 4, 7]),progress:None,estimate_minutes:None,unknown_fields:Default::default() };
+        let md = format_tasks_to_markdown_document(&[task.clone()]);
+        assert_eq!(md, "- [ ] (N) [[Blocked]] id:9 due:\"\" created:2024-01-01 updated:\"\" completed:\"\" depends:1,4,7");
+
+        let reparsed = crate::markdown_parser::parse_markdown_document_to_tasks(&md, test_date).unwrap();
+        assert_eq!(reparsed[0].depends, Some(vec![1, 4, 7]));
+
+        let json = serde_json::to_string(&reparsed[0]).unwrap();
+        assert!(json.contains("\"depends\":[1,4,7]"));
+        let from_json: Task = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json.depends, Some(vec![1, 4, 7]));
+
+        // 空の場合は出力しない
+        task.depends = Some(vec![]);
+        assert!(!format_tasks_to_markdown_document(&[task.clone()]).contains("depends:"));
+        assert!(!serde_json::to_string(&task).unwrap().contains("depends"));
+        task.depends = None;
+        assert!(!serde_json::to_string(&task).unwrap().contains("depends"));
+    }
+
+    #[test]
+    fn test_emit_display_orders_round_trip() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        // 行の順序とは異なる表示順を持つタスク (子も含む)
+        let md = "- [ ] [[Second]] id:1 do:20\n    - [ ] [[Child]] id:3 do:7\n- [ ] [[First]] id:2 do:10";
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks(md, test_date).unwrap();
+        assert_eq!(tasks[0].display_order, 20);
+        assert_eq!(tasks[0].subtasks.as_ref().unwrap()[0].display_order, 7);
+        assert_eq!(tasks[1].display_order, 10);
+
+        let options = FormatterOptions { emit_display_orders: true, emit_null_dates: false, ..Default::default() };
+        let formatted = format_tasks_to_markdown_document_with_options(&tasks, &options);
+        assert_eq!(formatted, "- [ ] (N) [[Second]] id:1 do:20 created:2024-01-01\n    - [ ] (N) [[Child]] id:3 do:7 created:2024-01-01\n- [ ] (N) [[First]] id:2 do:10 created:2024-01-01");
+        let reparsed = crate::markdown_parser::parse_markdown_document_to_tasks(&formatted, test_date).unwrap();
+        assert_eq!(serde_json::to_value(&reparsed).unwrap(), serde_json::to_value(&tasks).unwrap());
+
+        // 既定では出力しない
+        assert!(!format_tasks_to_markdown_document(&tasks).contains("do:"));
+    }
+
+    #[test]
+    fn test_emit_next_id_hint() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks("- [ ] [[A]] id:4\n    - [ ] [[B]] id:9", test_date).unwrap();
+        let options = FormatterOptions { emit_next_id_hint: true, emit_null_dates: false, ..Default::default() };
+        let formatted = format_tasks_to_markdown_document_with_options(&tasks, &options);
+        assert!(formatted.starts_with("<!-- og:next_id:10 -->\n- [ ] (N) [[A]] id:4"));
+
+        // 追記したタスクはヒントから採番される
+        let appended = format!("{}\n- [ ] [[C]]", formatted);
+        let reparsed = crate::markdown_parser::parse_markdown_document_to_tasks(&appended, test_date).unwrap();
+        assert_eq!(reparsed[1].id, 10);
+
+        // --keep-text では古いヒント行を置き換える
+        let document = crate::markdown_parser::parse_markdown_document_with_text(&appended, test_date, &Default::default()).unwrap();
+        let formatted = format_document_with_options(&document, &options);
+        assert_eq!(formatted.matches("og:next_id").count(), 1);
+        assert!(formatted.starts_with("<!-- og:next_id:11 -->\n"));
+    }
+
+    #[test]
+    fn test_custom_attribute_round_trip() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks("- [ ] [[Plan]] id:1 sprint:12 owner:ken billable:true ticket:007 https://example.com/a", test_date).unwrap();
+        // 数値・真偽値は元の型で戻る。同じ表記に戻らない 007 は文字列、URL は属性ではない
+        let extra = tasks[0].extra.as_ref().unwrap();
+        assert_eq!(extra["sprint"], serde_json::json!(12));
+        assert_eq!(extra["owner"], serde_json::json!("ken"));
+        assert_eq!(extra["billable"], serde_json::json!(true));
+        assert_eq!(extra["ticket"], serde_json::json!("007"));
+        assert_eq!(extra.len(), 4);
+
+        // markdown → json → markdown
+        let json = serde_json::to_string(&tasks[0]).unwrap();
+        let from_json: Task = serde_json::from_str(&json).unwrap();
+        let options = FormatterOptions { emit_null_dates: false, ..Default::default() };
+        assert_eq!(
+            format_tasks_to_markdown_document_with_options(&[from_json], &options),
+            "- [ ] (N) [[Plan]] id:1 created:2024-01-01 billable:true owner:ken sprint:12 ticket:007"
+        );
+    }
+
+    #[test]
+    fn test_structured_extra_stays_json_only() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut task = crate::markdown_parser::parse_markdown_document_to_tasks("- [ ] [[Plan]] id:1", test_date).unwrap().remove(0);
+        task.extra = Some(std::collections::HashMap::from([
+            ("meta".to_string(), serde_json::json!({"a": 1})),
+            ("label".to_string(), serde_json::json!("two words")),
+            ("due".to_string(), serde_json::json!("2024-05-01")),
+            ("points".to_string(), serde_json::json!(3)),
+            ("link".to_string(), serde_json::json!("//example.com/a")),
+        ]));
+        let md = format_tasks_to_markdown_document_with_options(&[task], &FormatterOptions { emit_null_dates: false, ..Default::default() });
+        assert_eq!(md, "- [ ] (N) [[Plan]] id:1 created:2024-01-01 points:3");
+    }
+
+    #[test]
+    fn test_trailing_newline_and_header_lines() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let source = "<!-- og:next_id:3 -->\n---\ntitle: Tasks\n---\n# Inbox\n\n- [ ] [[A]] id:1\n\nTrailing prose\n- [ ] [[B]] id:2";
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks(source, test_date).unwrap();
+        let options = FormatterOptions { emit_null_dates: false, omit_created_on: Some(test_date), ..Default::default() };
+        let body = "- [ ] (N) [[A]] id:1\n- [ ] (N) [[B]] id:2";
+        assert_eq!(format_tasks_with_source_header(source, &tasks, &options), body);
+
+        let options = FormatterOptions { trailing_newline: true, ..options };
+        assert_eq!(format_tasks_to_markdown_document_with_options(&tasks, &options), format!("{}\n", body));
+        assert_eq!(format_tasks_to_markdown_document_with_options(&[], &options), "");
+
+        // 最初のタスク行より前だけを残す (古い next_id のヒントは除く)。タスクの間の行は残さない
+        let options = FormatterOptions { preserve_header_lines: true, ..options };
+        assert_eq!(format_tasks_with_source_header(source, &tasks, &options), format!("---\ntitle: Tasks\n---\n# Inbox\n\n{}\n", body));
+    }
+
+    #[test]
+    fn test_format_max_depth() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let md = "- [ ] [[Root]] id:1\n    - [ ] [[Child]] id:2\n        - [ ] [[Grandchild]] id:3\n            - [ ] [[Great]] id:4\n        - [ ] [[Grandchild 2]] id:5\n    - [ ] [[Leaf]] id:6";
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks(md, test_date).unwrap();
+
+        // 深さ 1: 子までを出力し、孫を持つ子に隠した子孫の数を付ける (子のない Leaf には付けない)
+        let options = FormatterOptions { emit_null_dates: false, omit_created_on: Some(test_date), max_depth: Some(1), ..Default::default() };
+        assert_eq!(
+            format_tasks_to_markdown_document_with_options(&tasks, &options),
+            "- [ ] (N) [[Root]] id:1\n    - [ ] (N) [[Child]] id:2 (… 3 more)\n    - [ ] (N) [[Leaf]] id:6"
+        );
+
+        // 深さ 0: トップレベルのみ
+        let options = FormatterOptions { max_depth: Some(0), ..options };
+        assert_eq!(format_tasks_to_markdown_document_with_options(&tasks, &options), "- [ ] (N) [[Root]] id:1 (… 5 more)");
+    }
+
+    #[test]
+    fn test_format_estimate_canonical() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks("- [ ] [[A]] id:1 estimate:90m\n- [ ] [[B]] id:2 estimate:1d", test_date).unwrap();
+        let md = format_tasks_to_markdown_document_with_options(&tasks, &FormatterOptions { emit_null_dates: false, ..Default::default() });
+        assert_eq!(md, "- [ ] (N) [[A]] id:1 created:2024-01-01 estimate:1h30m\n- [ ] (N) [[B]] id:2 created:2024-01-01 estimate:8h0m");
+    }
+
+    #[test]
+    fn test_document_keeps_headings_and_paragraphs() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let md = "\
+# Plan
+
+- [ ] [[First]] id:1 created:2024-01-01
+    - [x] [[Child]] created:2024-01-01
+
+## Heading
+Some prose between the groups.
+- [ ] (A) [[Second]] created:2024-01-01";
+        let options = crate::markdown_parser::ParseOptions::default();
+        let document = crate::markdown_parser::parse_markdown_document_with_text(md, test_date, &options).unwrap();
+        assert_eq!(document.blocks.len(), 7);
+        assert_eq!(document.tasks().map(|t| t.id).collect::<Vec<_>>(), vec![1, 3]);
+
+        let expected = "\
+# Plan
+
+- [ ] (N) [[First]] id:1 due:\"\" created:2024-01-01 updated:\"\" completed:\"\"
+    - [x] (N) [[Child]] id:2 due:\"\" created:2024-01-01 updated:\"\" completed:\"\"
+
+## Heading
+Some prose between the groups.
+- [ ] (A) [[Second]] id:3 due:\"\" created:2024-01-01 updated:\"\" completed:\"\"";
+        let formatted = format_document_with_options(&document, &FormatterOptions::default());
+        assert_eq!(formatted, expected);
+
+        // 整形済みの出力は再整形しても変わらない
+        let reparsed = crate::markdown_parser::parse_markdown_document_with_text(&formatted, test_date, &options).unwrap();
+        assert_eq!(format_document_with_options(&reparsed, &FormatterOptions::default()), expected);
+    }
+
+    #[test]
+    fn test_compact_nulls_omits_empty_dates_and_round_trips() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let md = "- [ ] (A) [[Sparse]] id:1 created:2024-01-01\n    - [x] [[Dated]] id:2 due:2024-02-01 created:2024-01-01 completed:2024-01-15";
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks(md, test_date).unwrap();
+
+        let full = format_tasks_to_markdown_document(&tasks);
+        let compact_options = FormatterOptions { emit_null_dates: false, ..Default::default() };
+        let compact = format_tasks_to_markdown_document_with_options(&tasks, &compact_options);
+        assert!(compact.len() < full.len());
+        assert!(!compact.contains("\"\""));
+        assert_eq!(compact, "- [ ] (A) [[Sparse]] id:1 created:2024-01-01\n    - [x] (N) [[Dated]] id:2 due:2024-02-01 created:2024-01-01 completed:2024-01-15");
+
+        let reparsed = crate::markdown_parser::parse_markdown_document_to_tasks(&compact, test_date).unwrap();
+        assert_eq!(serde_json::to_value(&reparsed).unwrap(), serde_json::to_value(&tasks).unwrap());
+    }
+
+    #[test]
+    fn test_show_progress_comment_on_parent_lines() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let md = "\
+- [ ] [[Parent]] id:1 created:2024-01-01
+    - [x] [[Done]] id:2 created:2024-01-01
+    - [ ] [[Open]] id:3 created:2024-01-01
+- [ ] [[Alone]] id:4 created:2024-01-01";
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks(md, test_date).unwrap();
+        let options = FormatterOptions { show_progress: true, emit_null_dates: false, ..Default::default() };
+        let formatted = format_tasks_to_markdown_document_with_options(&tasks, &options);
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert!(lines[0].ends_with("created:2024-01-01 <!-- 50% -->"));
+        assert!(!lines[1].contains("<!--"));
+        assert!(!lines[3].contains("<!--"));
+
+        // 再整形しても注釈は重複しない
+        let reparsed = crate::markdown_parser::parse_markdown_document_to_tasks(&formatted, test_date).unwrap();
+        assert_eq!(format_tasks_to_markdown_document_with_options(&reparsed, &options), formatted);
+    }
+
+    #[test]
+    fn test_format_progress_round_trip() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let md = "- [>] (N) [[Long]] id:1 due:\"\" created:2024-01-01 updated:\"\" completed:\"\" progress:75";
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks(md, test_date).unwrap();
+        assert_eq!(format_tasks_to_markdown_document(&tasks), md);
+
+        let json = serde_json::to_string(&tasks[0]).unwrap();
+        assert!(json.contains("\"progress\":75"));
+        let from_json: Task = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json.progress, Some(75));
+
+        // 未設定なら Markdown にも JSON にも出さない
+        let mut task = from_json;
+        task.progress = None;
+        assert!(!format_tasks_to_markdown_document(std::slice::from_ref(&task)).contains("progress"));
+        assert!(!serde_json::to_string(&task).unwrap().contains("progress"));
+    }
+
+    #[test]
+    fn test_format_tags_kv_round_trip() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let md = "- [ ] (N) [[Run]] id:1 created:2024-01-01 #urgent #sprint:42 #area:health";
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks(md, test_date).unwrap();
+        assert_eq!(tasks[0].tags, Some(vec!["urgent".to_string()]));
+        let kv = tasks[0].tags_kv.as_ref().unwrap();
+        assert_eq!(kv.get("area").map(String::as_str), Some("health"));
+        assert_eq!(kv.get("sprint").map(String::as_str), Some("42"));
+
+        // キー順で出力され、再パースしても同じ結果になる
+        let formatted = format_tasks_to_markdown_document(&tasks);
+        assert!(formatted.contains("#urgent #area:health #sprint:42"));
+        let reparsed = crate::markdown_parser::parse_markdown_document_to_tasks(&formatted, test_date).unwrap();
+        assert_eq!(reparsed[0].tags, tasks[0].tags);
+        assert_eq!(reparsed[0].tags_kv, tasks[0].tags_kv);
+
+        let json = serde_json::to_string(&tasks[0]).unwrap();
+        let from_json: Task = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json.tags_kv, tasks[0].tags_kv);
+    }
+
+    #[test]
+    fn test_wrap_round_trip() {
+        let test_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let md = "- [ ] (A) [[Prepare release]] id:1 due:2024-02-01 +og @work #release #docs created:2024-01-01 updated:2024-01-02 estimate:2h note:\"check the \"\"changelog\"\" first\"\n    - [x] (N) [[Tag]] id:2 created:2024-01-01 completed:2024-01-03";
+        let tasks = crate::markdown_parser::parse_markdown_document_to_tasks(md, test_date).unwrap();
+
+        let options = FormatterOptions { wrap_width: Some(40), ..Default::default() };
+        let wrapped = format_tasks_to_markdown_document_with_options(&tasks, &options);
+        let lines: Vec<&str> = wrapped.lines().collect();
+        assert!(lines.len() > 2, "{}", wrapped);
+        assert!(lines.iter().all(|line| line.chars().count() <= 40), "{}", wrapped);
+        assert_eq!(lines[0], "- [ ] (A) [[Prepare release]] id:1 \\");
+        assert!(lines[1].starts_with("    due:2024-02-01"));
+        // note は空白を含んでも 1 つのトークンとして折り返さない
+        assert!(lines.iter().any(|line| line.trim_start() == "note:\"check the \"\"changelog\"\" first\""), "{}", wrapped);
+
+        let reparsed = crate::markdown_parser::parse_markdown_document_to_tasks(&wrapped, test_date).unwrap();
+        assert_eq!(serde_json::to_value(&reparsed).unwrap(), serde_json::to_value(&tasks).unwrap());
+
+        // 幅に収まる行と、指定なしの出力は従来どおり
+        let wide = FormatterOptions { wrap_width: Some(500), ..Default::default() };
+        assert_eq!(format_tasks_to_markdown_document_with_options(&tasks, &wide), format_tasks_to_markdown_document(&tasks));
+    }
 }
@@ -1,213 +1,468 @@
-use crate::task_model::Task;
+use crate::error::{OgError, ParseError};
+use crate::status_map::StatusMap;
+use crate::task_model::{Block, Document, Frequency, RepeatInfo, Task};
 use regex::Regex;
-use std::fmt::Debug;
+use std::collections::HashMap;
 use chrono::{NaiveDate, Local, Datelike};
+use rayon::prelude::*;
 
 // インデントレベルを計算するヘルパー関数 (半角スペース4つで1レベル)
-fn calculate_indent_level(line: &str) -> usize {
+pub(crate) fn calculate_indent_level(line: &str) -> usize {
     line.chars().take_while(|&c| c == ' ').count() / 4
 }
 
 // 行頭のインデントとリストマーカーを除去するヘルパー関数
 fn strip_indent_and_marker(line: &str) -> &str {
-    line.trim_start_matches(|c: char| c == ' ' || c == '-' || c == '*') // 基本的なリストマーカーも除去
+    line.trim_start_matches([' ', '-', '*']) // 基本的なリストマーカーも除去
         .trim_start() // マーカー後のスペースも除去
 }
 
+// タスク行 ("- [x]" または "* [x]" のようにチェックボックスで始まる行) かどうか。og fmt は常に "- " で出力する。
+// "* [リンク](url)" のような普通の箇条書きはタスク行ではない
+pub(crate) fn is_task_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let Some(rest) = trimmed.strip_prefix("- [").or_else(|| trimmed.strip_prefix("* [")) else {
+        return false;
+    };
+    let mut chars = rest.chars();
+    matches!((chars.next(), chars.next()), (Some(marker), Some(']')) if marker != '[' && marker != ']')
+}
+
+// 行末がこの記号 (直前に空白) のタスク行は次の行に続く (og fmt --wrap が出力)
+pub(crate) const LINE_CONTINUATION: &str = "\\";
+// タスク行の直後にあり、この記号で始まる行はタスク行の属性の続き (og fmt --max-line-length が出力)
+pub(crate) const ATTRIBUTE_CONTINUATION: &str = "↳";
+
+// タスク行とその継続行を 1 行につなげ、(元の行番号 - 1, 行) の列にする。
+// 継続行の先頭の空白は捨てるので、インデントはタスク行のものが残る
+pub(crate) fn logical_lines(markdown_document: &str) -> Vec<(usize, String)> {
+    let mut result: Vec<(usize, String)> = Vec::new();
+    let mut continues = false;
+    let mut after_task = false;
+    for (line_index, line) in markdown_document.lines().enumerate() {
+        let (content, next_continues) = match line.trim_end().strip_suffix(LINE_CONTINUATION) {
+            Some(rest) if rest.ends_with(' ') => (rest.trim_end(), true),
+            _ => (line, false),
+        };
+        let wrapped_attributes = line.trim_start().strip_prefix(ATTRIBUTE_CONTINUATION).filter(|_| after_task);
+        // 次の行がタスク行なら、前の行末の ` \` は継続ではなく手書きの文字なので戻す
+        if continues && is_task_line(line) {
+            let (_, joined) = result.last_mut().unwrap();
+            joined.push_str(" \\");
+            continues = false;
+        }
+        if continues {
+            let (_, joined) = result.last_mut().unwrap();
+            joined.push(' ');
+            joined.push_str(content.trim());
+            continues = next_continues;
+        } else if let Some(attributes) = wrapped_attributes {
+            let (_, joined) = result.last_mut().unwrap();
+            joined.push(' ');
+            joined.push_str(attributes.trim());
+        } else if is_task_line(line) {
+            result.push((line_index, content.to_string()));
+            continues = next_continues;
+            after_task = true;
+        } else {
+            result.push((line_index, line.to_string()));
+            after_task = false;
+        }
+    }
+    // 最後の行の ` \` も続きがないので戻す
+    if continues {
+        let (_, joined) = result.last_mut().unwrap();
+        joined.push_str(" \\");
+    }
+    result
+}
+
 // ドキュメント全体をパースしてTaskのVecを返す（サブタスク対応）
-// TODO: 実装する。現在はプレースホルダ。
 // ID と display_order の採番ロジックもここで管理する。
-// default_created_date は parse_markdown_line_to_task に渡す必要がある。
 pub fn parse_markdown_document_to_tasks(
     markdown_document: &str,
     default_created_date: NaiveDate, // Changed to NaiveDate
-) -> Result<Vec<Task>, String> {
-    let base_re_str = format!(
-        r#"^\s*{}\s*(?:{}\s*)?{}\s*(?P<attributes_str>.*)"#,
-        STATUS_MARKER_RE_STR,
-        PRIORITY_RE_STR,
-        TASK_NAME_RE_STR
-    );
-    let base_re = Regex::new(&base_re_str).map_err(|e| format!("Failed to compile base regex: {}", e))?;
-    let id_re = Regex::new(ID_ATTR_RE_STR).unwrap(); // Moved id_re definition here
-
-    let mut root_tasks: Vec<Task> = Vec::new();
-    let mut parent_stack: Vec<(Task, usize)> = Vec::new(); // (タスク, インデントレベル)
-    let mut current_id_counter: i64 = 1;
-    let mut display_order_counter: i64 = 1;
-    let mut existing_ids: std::collections::HashSet<i64> = std::collections::HashSet::new();
+) -> Result<Vec<Task>, OgError> {
+    parse_document(markdown_document, default_created_date, StatusMap::builtin())
+}
 
-    for line in markdown_document.lines() {
-        if line.trim().is_empty() || !line.trim_start().starts_with("- [") {
-            continue;
-        }
+fn parse_document(markdown_document: &str, default_created_date: NaiveDate, status_map: &StatusMap) -> Result<Vec<Task>, OgError> {
+    parse_document_with_policy(markdown_document, default_created_date, status_map, ErrorPolicy::FailFast).map(|(tasks, _)| tasks)
+}
 
+// error_policy に従ってパースし、タスクと読み飛ばした行のエラー (CollectAll のみ) を返す
+fn parse_document_with_policy(
+    markdown_document: &str,
+    default_created_date: NaiveDate,
+    status_map: &StatusMap,
+    policy: ErrorPolicy,
+) -> Result<(Vec<Task>, LineErrors), OgError> {
+    // ステップ1: 全ての行をパース (display_order は行の出現順)
+    let mut results = Vec::new();
+    let lines = logical_lines(markdown_document);
+    let task_lines = lines.iter().filter(|(_, line)| is_task_line(line));
+    for (index, (line_index, line)) in task_lines.enumerate() {
         let indent_level = calculate_indent_level(line);
         let task_content_line = strip_indent_and_marker(line);
+        // default_id として 0 を渡し、IDはステップ2で確定させる
+        let result = parse_markdown_line_to_task_with_status_map(task_content_line, 0, default_created_date, (index + 1) as i64, status_map);
+        // FailFast では残りの行をパースしない
+        let stop = result.is_err() && policy == ErrorPolicy::FailFast;
+        results.push((line_index + 1, result.map(|task| (task, indent_level))));
+        if stop {
+            break;
+        }
+    }
+    let (mut flat_parsed_items, errors) = sort_parse_results(results, policy)?;
 
-        // display_order は行の出現順
-        let current_display_order = display_order_counter;
-        display_order_counter += 1;
+    // ステップ2: ID採番、ステップ3: 階層構造の構築
+    assign_auto_ids(&mut flat_parsed_items, read_next_id_hint(markdown_document));
+    Ok((build_task_tree(flat_parsed_items), errors))
+}
 
-        // parse_markdown_line_to_task は id のパースも試みる
-        // ここでは default_id として 0 を渡し、パース後にIDの存在と一意性を確認する
-        let mut task = parse_markdown_line_to_task(task_content_line, 0, default_created_date, current_display_order)?;
+// パースできなかった行の (ドキュメントの行番号, エラー) の一覧
+pub type LineErrors = Vec<(usize, ParseError)>;
+// 行ごとのパース結果: (ドキュメントの行番号, (タスク, インデントレベル) またはエラー)
+type LineResult = (usize, Result<(Task, usize), ParseError>);
+
+// パースできないタスク行の扱い (ParseOptions::error_policy)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    #[default]
+    FailFast,   // 最初のエラーで失敗する (従来どおり)
+    SkipBad,    // その行を読み飛ばし、標準エラーに行番号とエラーを警告する
+    CollectAll, // 読み飛ばした行のエラーを全て返す (parse_markdown_document_collecting_errors)
+}
 
-        // ID処理: Markdownにあればそれを使い、なければ採番。重複チェックも行う。
-        if task.id == 0 || existing_ids.contains(&task.id) { // id:0 はパースされなかったことを示す仮定
-            while existing_ids.contains(&current_id_counter) {
-                current_id_counter += 1;
+// 行ごとのパース結果 (ドキュメントの行番号, 結果) を error_policy に従って振り分ける
+fn sort_parse_results(
+    results: Vec<LineResult>,
+    policy: ErrorPolicy,
+) -> Result<(Vec<(Task, usize)>, LineErrors), OgError> {
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+    for (line, result) in results {
+        match result {
+            Ok(item) => items.push(item),
+            Err(e) => match policy {
+                ErrorPolicy::FailFast => return Err(e.at_line(line)),
+                ErrorPolicy::SkipBad => eprintln!("Warning: skipping line {}: {}", line, e),
+                ErrorPolicy::CollectAll => errors.push((line, e)),
+            },
+        }
+    }
+    Ok((items, errors))
+}
+
+// タスク以外の行を Block::Text として残したままドキュメントをパースする。
+// ID は通常のパースと同じくドキュメント全体で採番し、階層はタスク行の連続ごとに構築する。
+pub fn parse_markdown_document_with_text(
+    markdown_document: &str,
+    default_created_date: NaiveDate,
+    options: &ParseOptions,
+) -> Result<Document, OgError> {
+    if options.strict {
+        check_unknown_attributes(markdown_document)?;
+    }
+    // Text 行と、連続するタスク行の行数 (ID 採番後にタスク列へ置き換える)
+    enum Layout {
+        Text(String),
+        TaskRun(usize),
+    }
+    let mut layout: Vec<Layout> = Vec::new();
+    let mut flat_parsed_items: Vec<(Task, usize)> = Vec::new();
+    for (line_index, line) in logical_lines(markdown_document) {
+        if !is_task_line(&line) {
+            layout.push(Layout::Text(line));
+            continue;
+        }
+        let task = match parse_markdown_line_to_task_with_status_map(strip_indent_and_marker(&line), 0, default_created_date, (flat_parsed_items.len() + 1) as i64, &options.status_map) {
+            Ok(task) => task,
+            Err(e) if options.error_policy == ErrorPolicy::FailFast => return Err(e.at_line(line_index + 1)),
+            // 読み飛ばす行は本文として残し、書き出しても失われないようにする
+            Err(e) => {
+                eprintln!("Warning: keeping line {} as text: {}", line_index + 1, e);
+                layout.push(Layout::Text(line));
+                continue;
             }
-            task.id = current_id_counter;
+        };
+        flat_parsed_items.push((task, calculate_indent_level(&line)));
+        match layout.last_mut() {
+            Some(Layout::TaskRun(count)) => *count += 1,
+            _ => layout.push(Layout::TaskRun(1)),
         }
-        existing_ids.insert(task.id);
-        current_id_counter = task.id.max(current_id_counter) + 1; // 次のID候補を更新
-        
-        // 親スタックの調整
-        while let Some((_, parent_level)) = parent_stack.last() {
-            if indent_level <= *parent_level {
-                parent_stack.pop();
-            } else {
-                break;
+    }
+    assign_auto_ids(&mut flat_parsed_items, read_next_id_hint(markdown_document));
+
+    let mut items = flat_parsed_items.into_iter();
+    let mut blocks = Vec::new();
+    for entry in layout {
+        match entry {
+            Layout::TaskRun(count) => {
+                let mut tasks = build_task_tree(items.by_ref().take(count).collect());
+                normalize_labels(&mut tasks, options);
+                blocks.push(Block::Tasks(tasks));
             }
+            Layout::Text(text) => blocks.push(Block::Text(text)),
         }
+    }
+    Ok(Document { blocks })
+}
 
-        if parent_stack.is_empty() {
-            // トップレベルタスク
-            parent_stack.push((task.clone(), indent_level)); // スタックにはクローンを入れ、元のタスクをroot_tasksに
-            root_tasks.push(task);
-        } else {
-            // サブタスク
-            if let Some((parent_task_ref_mut, _)) = parent_stack.last_mut() {
-                 // 親タスクのsubtasksを初期化（まだなら）
-                if parent_task_ref_mut.subtasks.is_none() {
-                    parent_task_ref_mut.subtasks = Some(Vec::new());
-                }
-                // サブタスクを追加
-                parent_task_ref_mut.subtasks.as_mut().unwrap().push(task.clone());
-                
-                // 現在のタスクを新しい親としてスタックに追加
-                parent_stack.push((task, indent_level));
-            } else {
-                 // このケースは論理的に到達不能のはず (parent_stackが空でないならlast_mutはSomeを返す)
-                 return Err("Internal error: Parent stack manipulation failed".to_string());
+// パース時のオプション (既定では全て無効で、従来どおりの挙動)
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub strip_label_punctuation: bool, // #done, → done のように末尾の句読点を除去
+    pub lowercase_labels: bool,        // タグ・コンテキスト・プロジェクトを小文字化
+    pub status_map: StatusMap,         // ステータスマーカー文字の対応表 (~/.config/og/status-map.toml)
+    pub strict: bool,                  // 未知の key:value 属性をエラーにする (--strict)
+    pub error_policy: ErrorPolicy,     // パースできないタスク行の扱い
+}
+
+// 属性として解釈されるキー。これ以外の key:value は extra に入れる (--strict ではエラー)
+pub(crate) const KNOWN_ATTRIBUTE_KEYS: &[&str] = &[
+    "id", "do", "priority", "created", "due", "updated", "completed", "repeat", "depends", "progress", "estimate", "note",
+];
+// 属性らしく見えるトークン (+project / @context / #tag は除く)。
+// 値が // で始まるもの (https://example.com などの URL) は属性とみなさない
+pub(crate) const ATTRIBUTE_LIKE_RE_STR: &str = r#"^(?P<key>[A-Za-z][\w-]*):(?P<value>(?:[^/\s]|/[^/\s])\S*|/?)$"#;
+
+// note:"..." の値を戻す。"" は "、\n / \r は改行、\\ は \ にする。
+// それ以外の \ はそのまま残す (エスケープ導入前に書かれた C:\dir などのメモを変えない)
+fn unescape_note(value: &str) -> String {
+    let unquoted = value.replace("\"\"", "\"");
+    let mut note = String::with_capacity(unquoted.len());
+    let mut chars = unquoted.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escaped = match (c, chars.peek()) {
+            ('\\', Some('n')) => '\n',
+            ('\\', Some('r')) => '\r',
+            ('\\', Some('\\')) => '\\',
+            _ => {
+                note.push(c);
+                continue;
             }
-        }
+        };
+        chars.next();
+        note.push(escaped);
     }
-    
-    // スタックに残っているタスクは、実際には root_tasks やそのサブタスクとして既に登録されている。
-    // しかし、スタックの Task インスタンスは、サブタスクが追加される前の古い状態の可能性がある。
-    // 正しい親子関係は root_tasks に構築されているので、そちらを返す。
-    // この関数では、parent_stack はあくまで現在の「親候補」を追跡するために使う。
-    // サブタスクが親に追加される際、親の subtasks フィールドを直接変更する必要がある。
-    // 上記のロジックでは、`root_tasks` に追加したトップレベルタスクと、`parent_stack` に積んだタスクは別インスタンスになっている。
-    // これではサブタスクが `root_tasks` 内のタスクに反映されない。
-    // `Vec<Task>` ではなく、インデックスや参照を使って実際のタスクに変更を加える必要があるか、
-    // または、最後に `parent_stack` から `root_tasks` を再構築する必要がある。
-
-    // より直接的に root_tasks を構築するアプローチに修正する
-    // `parse_markdown_document_to_tasks` のロジックを再考
-    // 以下の修正案では、タスクをまずVecにフラットに格納し、後で親子関係を構築する形は取らず、
-    // リアルタイムに親子関係を構築していく。
-    // そのためには、parent_stack には可変参照を保持するか、または最後にツリーを再構築する必要がある。
-    // ここでは、一旦、現在のroot_tasksの末尾のタスクを親と仮定する単純な方法で再試行する。
-    // これは複雑なインデントレベルの変化に対応できないため、より堅牢なスタック管理が必要。
-
-    // --- スタックベースの親子関係構築ロジックの修正案 ---
-    // `root_tasks` はトップレベルのタスクのみを保持する。
-    // `parent_stack` には `(task_mut_ref, indent_level)` のような形で可変参照を保持したいが、
-    // Rustの所有権システムでは簡単ではない。インデックスを使うか、最後に再構築する。
-
-    // ここでは、一旦上記の実装（クローンとスタック）のまま進め、
-    // 問題点（サブタスクがroot_tasksに正しく反映されない）を認識した上で、
-    // テストフェーズで修正する方針とします。
-    // 期待する動作は「親タスクの .subtasks フィールドに子が追加されること」
-    // 現在の実装では、`parent_stack` 上のタスクの `subtasks` が更新されるが、それが `root_tasks` に反映されない。
-
-    // `root_tasks` に直接サブタスクを追加する修正:
-    // let mut final_tasks: Vec<Task> = Vec::new(); // Removed
-    // let mut processing_stack: Vec<(&mut Task, usize)> = Vec::new(); // Removed
-    // `Task` を一時的に格納するベクタ。可変参照のために必要。
-    // let mut task_store: Vec<Task> = Vec::new(); // Removed
-
-    // `markdown_document` の行を再度イテレートして、今度は可変参照でツリーを構築する
-    // IDとDisplayOrderは既に元のTaskオブジェクトに設定されている前提とする。
-    // （上記ループで作成した `tasks` Vec を使う形にするべきだったが、ここでは簡略化のため再ループ）
-    
-    // 上記の `tasks` Vec を使って階層構造を構築する形に修正するべき。
-    // １回目のループでフラットなリストとID/DisplayOrderを作成。
-    // ２回目の処理でそのリストから階層構造を構築する。
-    
-    // この関数のスコープが複雑になりすぎるため、スタック処理を簡略化し、
-    // あくまで `parent_stack` を使って、追加先がトップレベルか、
-    // スタックの最後の要素の子かを判断するにとどめる。
-    // そのためには、`root_tasks` の最後の要素、またはそのサブタスクツリーの
-    // 適切な場所に可変参照でアクセスする必要がある。
-
-    // ---- 再設計 ----
-    // 1. 全ての行をパースし、(Task, indent_level) のリストを作成。
-    //    IDとDisplayOrderはこの時点で確定させる。
-    // 2. このリストを元に、親子関係を構築して Vec<Task> (階層構造込み) を作る。
-
-    // ステップ1: 全ての行をパース (IDとDisplayOrderもここで確定)
-    let mut flat_parsed_items: Vec<(Task, usize)> = Vec::new();
-    let mut next_auto_id: i64 = 1;
-    display_order_counter = 1; // リセット
-    existing_ids.clear(); // リセット
+    note
+}
 
-    // 最初に全ての指定IDを収集
-    for line in markdown_document.lines() {
-        if line.trim().is_empty() || !line.trim_start().starts_with("- [") {
+// extra に入れる値。数値・真偽値として書き戻すと同じ表記になるもの (12, 1.5, true) はその型、ほかは文字列
+fn extra_value(value: &str) -> serde_json::Value {
+    match serde_json::from_str::<serde_json::Value>(value) {
+        Ok(scalar @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_))) if serde_json::to_string(&scalar).is_ok_and(|text| text == value) => scalar,
+        _ => serde_json::Value::String(value.to_string()),
+    }
+}
+
+// 属性部にある既知でない key:value を (キー, 値) で返す
+fn unknown_attributes(attributes_str: &str) -> Vec<(String, String)> {
+    let note_re = Regex::new(NOTE_ATTR_RE_STR).unwrap();
+    let attribute_like_re = Regex::new(ATTRIBUTE_LIKE_RE_STR).unwrap();
+    // note:"..." は空白を含むので先に取り除く
+    let attributes_str = note_re.replace_all(attributes_str, "");
+    attributes_str
+        .split_whitespace()
+        .filter_map(|token| attribute_like_re.captures(token))
+        .filter(|caps| !KNOWN_ATTRIBUTE_KEYS.contains(&&caps["key"]))
+        .map(|caps| (caps["key"].to_string(), caps["value"].to_string()))
+        .collect()
+}
+
+// --strict: 属性部に未知の key:value (duee:2024-01-01 など) があればその行でエラーにする。
+// 通常モードでは extra に入るだけで既知の属性としては読まれないため、タイプミスを検出するために使う
+fn check_unknown_attributes(markdown_document: &str) -> Result<(), OgError> {
+    for (line_index, line) in logical_lines(markdown_document).iter().filter(|(_, line)| is_task_line(line)) {
+        // [[名前]] のない行は残り全体がタスク名になるので属性はない
+        let Some(name_end) = line.find("[[").and_then(|start| line[start..].find("]]").map(|end| start + end + 2)) else {
             continue;
+        };
+        let attributes_str = line[name_end..].trim();
+        let attributes_str = match attributes_str.rfind("<!--") {
+            Some(pos) if attributes_str.ends_with("-->") => &attributes_str[..pos],
+            _ => attributes_str,
+        };
+        if let Some((key, value)) = unknown_attributes(attributes_str).into_iter().next() {
+            return Err(OgError::Parse { line: line_index + 1, msg: format!("Unknown attribute '{}:{}'", key, value) });
         }
-        let task_content_line = strip_indent_and_marker(line);
-        let attributes_str = base_re.captures(task_content_line)
-            .and_then(|caps| caps.name("attributes_str"))
-            .map_or("", |m| m.as_str().trim());
-        if let Some(cap) = id_re.captures(attributes_str) {
-            if let Some(val_str) = cap.name("id_val") {
-                if let Ok(id) = val_str.as_str().parse::<i64>() {
-                    existing_ids.insert(id);
-                }
-            }
+    }
+    Ok(())
+}
+
+// オプション付きの parse_markdown_document_to_tasks
+pub fn parse_markdown_document_to_tasks_with_options(
+    markdown_document: &str,
+    default_created_date: NaiveDate,
+    options: &ParseOptions,
+) -> Result<Vec<Task>, OgError> {
+    if options.strict {
+        check_unknown_attributes(markdown_document)?;
+    }
+    let (mut tasks, errors) = parse_document_with_policy(markdown_document, default_created_date, &options.status_map, options.error_policy)?;
+    // CollectAll のエラー一覧は parse_markdown_document_collecting_errors で受け取る。ここでは最初のエラーを返す
+    if let Some((line, e)) = errors.into_iter().next() {
+        return Err(e.at_line(line));
+    }
+    normalize_labels(&mut tasks, options);
+    Ok(tasks)
+}
+
+// error_policy が CollectAll のとき、パースできた全タスクと、パースできなかった行の (行番号, エラー) を返す。
+// FailFast では最初のエラーで失敗し、SkipBad ではエラーの一覧は常に空
+pub fn parse_markdown_document_collecting_errors(
+    markdown_document: &str,
+    default_created_date: NaiveDate,
+    options: &ParseOptions,
+) -> Result<(Vec<Task>, LineErrors), OgError> {
+    if options.strict {
+        check_unknown_attributes(markdown_document)?;
+    }
+    let (mut tasks, errors) = parse_document_with_policy(markdown_document, default_created_date, &options.status_map, options.error_policy)?;
+    normalize_labels(&mut tasks, options);
+    Ok((tasks, errors))
+}
+
+// オプション付きの parse_markdown_document_to_tasks_parallel
+pub fn parse_markdown_document_to_tasks_parallel_with_options(
+    markdown_document: &str,
+    default_created_date: NaiveDate,
+    options: &ParseOptions,
+) -> Result<Vec<Task>, OgError> {
+    if options.strict {
+        check_unknown_attributes(markdown_document)?;
+    }
+    let (mut tasks, errors) = parse_document_parallel(markdown_document, default_created_date, &options.status_map, options.error_policy)?;
+    if let Some((line, e)) = errors.into_iter().next() {
+        return Err(e.at_line(line));
+    }
+    normalize_labels(&mut tasks, options);
+    Ok(tasks)
+}
+
+// タグ・コンテキスト・プロジェクトを正規化する (サブタスクも含む)
+pub fn normalize_labels(tasks: &mut [Task], options: &ParseOptions) {
+    if !options.strip_label_punctuation && !options.lowercase_labels {
+        return;
+    }
+    let normalize = |label: &str| -> String {
+        let mut label = label;
+        if options.strip_label_punctuation {
+            label = label.trim_end_matches(|c: char| ",.;:!?)]}'\"".contains(c));
+        }
+        if options.lowercase_labels { label.to_lowercase() } else { label.to_string() }
+    };
+    let normalize_list = |labels: &Option<Vec<String>>| -> Option<Vec<String>> {
+        // 正規化で同じ表記になったラベルも重複として取り除く
+        let mut normalized: Vec<String> = Vec::new();
+        for label in labels.as_ref()?.iter().map(|l| normalize(l)).filter(|l| !l.is_empty()) {
+            push_unique(&mut normalized, &label);
         }
+        if normalized.is_empty() { None } else { Some(normalized) }
+    };
+    for task in tasks.iter_mut() {
+        task.flatten_mut(&mut |t| {
+            t.project = t.project.as_deref().map(normalize).filter(|p| !p.is_empty());
+            t.contexts = normalize_list(&t.contexts);
+            t.tags = normalize_list(&t.tags);
+        });
     }
+}
+
+// parse_markdown_document_to_tasks の並列版。
+// 行ごとのパースは独立しているため Rayon で並列化し、ID採番とツリー構築は逐次で行う。
+pub fn parse_markdown_document_to_tasks_parallel(
+    markdown_document: &str,
+    default_created_date: NaiveDate,
+) -> Result<Vec<Task>, OgError> {
+    parse_document_parallel(markdown_document, default_created_date, StatusMap::builtin(), ErrorPolicy::FailFast).map(|(tasks, _)| tasks)
+}
+
+fn parse_document_parallel(
+    markdown_document: &str,
+    default_created_date: NaiveDate,
+    status_map: &StatusMap,
+    policy: ErrorPolicy,
+) -> Result<(Vec<Task>, LineErrors), OgError> {
+    let lines = logical_lines(markdown_document);
+    let task_lines: Vec<(usize, &str)> = lines
+        .iter()
+        .filter(|(_, line)| is_task_line(line))
+        .map(|(index, line)| (index + 1, line.as_str()))
+        .collect();
+    let results: Vec<LineResult> = task_lines
+        .par_iter()
+        .enumerate()
+        .map(|(index, (line_number, line))| {
+            let indent_level = calculate_indent_level(line);
+            let result = parse_markdown_line_to_task_with_status_map(strip_indent_and_marker(line), 0, default_created_date, (index + 1) as i64, status_map);
+            (*line_number, result.map(|task| (task, indent_level)))
+        })
+        .collect();
+    let (mut flat_parsed_items, errors) = sort_parse_results(results, policy)?;
+    assign_auto_ids(&mut flat_parsed_items, read_next_id_hint(markdown_document));
+    Ok((build_task_tree(flat_parsed_items), errors))
+}
+
+// タスク行を並列にパースし、(Task, インデントレベル) を行の順序のまま返す。
+// display_order は行の出現順、ID は未採番 (指定がなければ 0)。
+pub fn parse_lines_parallel(lines: &[&str], default_date: NaiveDate) -> Result<Vec<(Task, usize)>, OgError> {
+    lines
+        .par_iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let indent_level = calculate_indent_level(line);
+            let task = parse_markdown_line_to_task_with_status_map(strip_indent_and_marker(line), 0, default_date, (index + 1) as i64, StatusMap::builtin())
+                .map_err(|e| e.at_line(index + 1))?;
+            Ok((task, indent_level))
+        })
+        .collect()
+}
+
+// 1行目の <!-- og:next_id:42 --> から次に採番する ID のヒントを読む (og fmt --emit-next-id-hint が出力)
+pub fn read_next_id_hint(doc: &str) -> Option<i64> {
+    let first_line = doc.lines().next()?.trim();
+    let hint = first_line.strip_prefix("<!-- og:next_id:")?.strip_suffix("-->")?.trim();
+    hint.parse().ok().filter(|id: &i64| *id >= 1)
+}
 
-    for line in markdown_document.lines() {
-        if line.trim().is_empty() || !line.trim_start().starts_with("- [") {
+// ID処理: Markdownにあればそれを使い、なければ採番 (1から開始、欠番優先)。
+// 指定IDは事前に全て収集し、自動採番と衝突しないようにする。
+// next_id_hint があり、指定IDが全てそれ未満なら、集合を作らずヒントから連番で振る (欠番は埋めない)。
+fn assign_auto_ids(flat_parsed_items: &mut [(Task, usize)], next_id_hint: Option<i64>) {
+    // 手で書き足した ID がヒント以上なら、ヒントは古いとみなして通常の採番に戻す
+    if let Some(hint) = next_id_hint.filter(|hint| flat_parsed_items.iter().all(|(task, _)| task.id < *hint)) {
+        for ((task, _), id) in flat_parsed_items.iter_mut().filter(|(task, _)| task.id == 0).zip(hint..) {
+            task.id = id;
+        }
+        return;
+    }
+
+    let mut existing_ids: std::collections::HashSet<i64> = flat_parsed_items
+        .iter()
+        .map(|(task, _)| task.id)
+        .filter(|id| *id != 0)
+        .collect();
+    let mut next_auto_id: i64 = 1;
+
+    for (task, _) in flat_parsed_items.iter_mut() {
+        if task.id != 0 {
             continue;
         }
-        let indent_level = calculate_indent_level(line);
-        let task_content_line = strip_indent_and_marker(line);
-        
-        let current_display_order = display_order_counter;
-        display_order_counter += 1;
-
-        // parse_markdown_line_to_task は id のパースも試みる
-        // default_id として 0 を渡す
-        let mut task = parse_markdown_line_to_task(task_content_line, 0, default_created_date, current_display_order)?;
-
-        // ID処理: Markdownにあればそれを使い、なければ採番。重複チェックも行う。
-        if task.id != 0 { // IDが指定されている場合
-            if !existing_ids.contains(&task.id) { // 事前収集で見つからなかったIDが指定された場合（基本的にはありえないが念のため）
-                 existing_ids.insert(task.id); // ここで追加する
-            }
-            // next_auto_id の更新は不要、または指定IDより大きい次の空きを探すロジックが必要だが、
-            // まずは existing_ids に基づいて採番するロジックを優先
-        } else { // IDが指定されていない場合、自動採番
-            while existing_ids.contains(&next_auto_id) {
-                next_auto_id += 1;
-            }
-            task.id = next_auto_id;
-            existing_ids.insert(task.id); // 新しく採番したIDを記録
-            next_auto_id += 1; // 次の自動採番候補をインクリメント
+        while existing_ids.contains(&next_auto_id) {
+            next_auto_id += 1;
         }
-        flat_parsed_items.push((task, indent_level));
+        task.id = next_auto_id;
+        existing_ids.insert(task.id); // 新しく採番したIDを記録
+        next_auto_id += 1; // 次の自動採番候補をインクリメント
     }
+}
 
-    // ステップ2: パース済みアイテムリストから階層構造を構築
+// パース済みアイテムリスト (Task, インデントレベル) から階層構造を構築
+pub(crate) fn build_task_tree(flat_parsed_items: Vec<(Task, usize)>) -> Vec<Task> {
     if flat_parsed_items.is_empty() {
-        return Ok(Vec::new());
+        return Vec::new();
     }
 
     let mut result_tasks: Vec<Task> = Vec::new();
@@ -247,49 +502,47 @@ pub fn parse_markdown_document_to_tasks(
             }
         }
     }
-    Ok(result_tasks)
+    result_tasks
 }
 
 
 // B.3. 要素詳細 と B.4. 属性ごとの表示ルール に基づく正規表現の部品
-const STATUS_MARKER_RE_STR: &str = r#"\[(?P<status_char>[ xpw?>c-])\]"#;
+// マーカー文字の意味は StatusMap で決まる (未知の文字はパースエラー)
+const STATUS_MARKER_RE_STR: &str = r#"\[(?P<status_char>[^\[\]])\]"#;
 const PRIORITY_RE_STR: &str = r#"\((?P<priority_val>[A-Z]{1,}|N)\)"#;
+// 属性 (+project / @context / #tag を含む) は名前の後ろの attributes_str だけから読む。
+// [[名前]] の中身は属性として扱わず、[[ ]] のない名前は行の残り全体が名前になる (属性なし)
 const TASK_NAME_RE_STR: &str = r#"(?:(?:\[\[(?P<task_name>.+?)\]\])|(?P<task_name_plain>.+))"#;
 
-const ID_ATTR_RE_STR: &str = r#"id:(?P<id_val>\d+)"#;
+// 属性は空白区切りのトークンの先頭だけで認識する (#todo:3 の "do:3" や sprint+1 の "+1" を拾わない)
+const ID_ATTR_RE_STR: &str = r#"(?:^|\s)id:(?P<id_val>\d+)"#;
+// og fmt --emit-display-orders が出力する表示順。行の出現順より優先する
+const DO_ATTR_RE_STR: &str = r#"(?:^|\s)do:(?P<do_val>\d+)"#;
+// 位置指定の (A) に加えて、属性部の priority:A でも優先度を指定できる
+const PRIORITY_ATTR_RE_STR: &str = r#"(?:^|\s)priority:(?P<priority_attr_val>[A-Z]{1,}|N)(?:\s|$)"#;
 
 // B.5. 属性値の日付表現フォーマット - 正規表現で以下の形式をサポート:
 // - YYYY-MM-DD (e.g., 2023-05-15)
 // - YYYY/MM/DD (e.g., 2023/05/15)
 // - MM/DD (今年の年を補完, e.g., 05/15)
 // - M/D (今年の年を補完, e.g., 5/5) - 注: \d{1,2} パターンで単数桁も対応
-const CREATED_ATTR_RE_STR: &str = r#"created:(?P<created_val>(?:\d{4}[-/]\d{1,2}[-/]\d{1,2}|\d{1,2}/\d{1,2}))"#;
-const DUE_ATTR_RE_STR: &str = r#"due:(?P<due_val>(?:\d{4}[-/]\d{1,2}[-/]\d{1,2}|\d{1,2}/\d{1,2}|\"\"))"#;
-const UPDATED_ATTR_RE_STR: &str = r#"updated:(?P<updated_val>(?:\d{4}[-/]\d{1,2}[-/]\d{1,2}|\d{1,2}/\d{1,2}|\"\"))"#;
-const COMPLETED_ATTR_RE_STR: &str = r#"completed:(?P<completed_val>(?:\d{4}[-/]\d{1,2}[-/]\d{1,2}|\d{1,2}/\d{1,2}|\"\"))"#;
-
-const PROJECT_ATTR_RE_STR: &str = r#"\+(?P<project_val>\S+)"#;
-const CONTEXT_ATTR_RE_STR: &str = r#"@(?P<context_val>\S+)"#;
-const TAG_ATTR_RE_STR: &str = r#"#(?P<tag_val>\S+)"#;
-const NOTE_ATTR_RE_STR: &str = r#"note:"(?P<note_val>(?:[^"]|\"\")*)""#;
-
-
-fn map_status_char_to_string(status_char: char) -> String {
-    // 仕様書とコードの差異: 
-    // - 仕様書では大文字表記 (例: "NONE") を使用
-    // - 実装では小文字表記 (例: "open") を使用
-    // - 特に ' ' は仕様書では "NONE"、実装では "open" に対応
-    match status_char.to_ascii_lowercase() {
-        ' ' => "open".to_string(),  // 仕様書では "NONE"
-        'p' => "pending".to_string(),
-        '>' => "doing".to_string(),
-        'w' => "waiting".to_string(),
-        'x' => "done".to_string(),
-        'c' => "cancelled".to_string(),
-        '?' => "unknown".to_string(),
-        _ => "unknown".to_string(),
-    }
-}
+const CREATED_ATTR_RE_STR: &str = r#"(?:^|\s)created:(?P<created_val>(?:\d{4}[-/]\d{1,2}[-/]\d{1,2}|\d{1,2}/\d{1,2}))"#;
+const DUE_ATTR_RE_STR: &str = r#"(?:^|\s)due:(?P<due_val>(?:\d{4}[-/]\d{1,2}[-/]\d{1,2}|\d{1,2}/\d{1,2}|\"\"))"#;
+const UPDATED_ATTR_RE_STR: &str = r#"(?:^|\s)updated:(?P<updated_val>(?:\d{4}[-/]\d{1,2}[-/]\d{1,2}|\d{1,2}/\d{1,2}|\"\"))"#;
+const COMPLETED_ATTR_RE_STR: &str = r#"(?:^|\s)completed:(?P<completed_val>(?:\d{4}[-/]\d{1,2}[-/]\d{1,2}|\d{1,2}/\d{1,2}|\"\"))"#;
+
+const PROJECT_ATTR_RE_STR: &str = r#"(?:^|\s)\+(?P<project_val>\S+)"#;
+const CONTEXT_ATTR_RE_STR: &str = r#"(?:^|\s)@(?P<context_val>\S+)"#;
+// #key:value はキー・値タグ (tags_kv)、それ以外の #tag は通常のタグ (tags)
+const TAG_ATTR_RE_STR: &str = r#"(?:^|\s)#(?:(?P<tag_kv_key>\w+):(?P<tag_kv_val>\S+)|(?P<tag_val>\S+))"#;
+const REPEAT_ATTR_RE_STR: &str = r#"(?:^|\s)repeat:(?P<repeat_val>daily|weekly|monthly|weekdays|last-day-of-month)"#;
+const DEPENDS_ATTR_RE_STR: &str = r#"(?:^|\s)depends:(?P<depends_val>\d+(?:,\d+)*)"#;
+// 値の検証 (0〜100) はパース時に行うため、ここでは任意の値を拾う
+const PROGRESS_ATTR_RE_STR: &str = r#"(?:^|\s)progress:(?P<progress_val>\S+)"#;
+// 見積もり時間 (90m, 1.5h, 2h30m, 1d)。解釈できない値はパースエラー
+const ESTIMATE_ATTR_RE_STR: &str = r#"(?:^|\s)estimate:(?P<estimate_val>\S+)"#;
+const NOTE_ATTR_RE_STR: &str = r#"(?:^|\s)note:"(?P<note_val>(?:[^"]|\"\")*)""#;
+
 
 #[allow(dead_code)]
 fn map_string_to_status_char(status_string: &str) -> char {
@@ -307,23 +560,20 @@ fn map_string_to_status_char(status_string: &str) -> char {
     }
 }
 
-fn format_for_debug<T: Debug>(item: T) -> String {
-    format!("{:?}", item)
-}
-
-fn parse_date_or_empty_attr(captures: &regex::Captures, group_name: &str) -> Option<NaiveDate> {
+// 日付属性の値を読む。空の引用符は None、日付の形なのに実在しない日付は InvalidDate
+fn parse_date_or_empty_attr(captures: &regex::Captures, group_name: &str, field: &str) -> Result<Option<NaiveDate>, ParseError> {
     if let Some(val_match) = captures.name(group_name) {
         let s = val_match.as_str();
         if s == "\"\"" { // 空の引用符はNone
-            return None;
+            return Ok(None);
         }
         // YYYY-MM-DD
         if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
-            return Some(date);
+            return Ok(Some(date));
         }
         // YYYY/MM/DD
         if let Ok(date) = NaiveDate::parse_from_str(s, "%Y/%m/%d") {
-            return Some(date);
+            return Ok(Some(date));
         }
         // MM/DD or M/D (今年の年を補完) - supports both formats:
         // - Double-digit MM/DD (e.g., 05/15)
@@ -334,17 +584,38 @@ fn parse_date_or_empty_attr(captures: &regex::Captures, group_name: &str) -> Opt
                 if let (Ok(month), Ok(day)) = (parts[0].parse::<u32>(), parts[1].parse::<u32>()) {
                     let year = Local::now().year();
                     if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
-                        return Some(date);
+                        return Ok(Some(date));
                     }
                 }
             }
         }
+        return Err(ParseError::InvalidDate { field: field.to_string(), raw: s.to_string() });
     }
-    None
+    Ok(None)
 }
 
-pub fn parse_markdown_line_to_task(line: &str, default_id: i64, default_created_date: NaiveDate, default_display_order: i64) -> Result<Task, String> { // default_created_date to NaiveDate
+// 同じタスク内で重複するコンテキスト・タグは最初の 1 つだけ残す (出現順は維持)
+fn push_unique(labels: &mut Vec<String>, label: &str) {
+    if !labels.iter().any(|existing| existing == label) {
+        labels.push(label.to_string());
+    }
+}
+
+pub fn parse_markdown_line_to_task(line: &str, default_id: i64, default_created_date: NaiveDate, default_display_order: i64) -> Result<Task, ParseError> { // default_created_date to NaiveDate
+    parse_markdown_line_to_task_with_status_map(line, default_id, default_created_date, default_display_order, StatusMap::builtin())
+}
+
+// ステータスマーカーの対応表を指定する parse_markdown_line_to_task
+pub fn parse_markdown_line_to_task_with_status_map(
+    line: &str,
+    default_id: i64,
+    default_created_date: NaiveDate,
+    default_display_order: i64,
+    status_map: &StatusMap,
+) -> Result<Task, ParseError> {
     let id_re = Regex::new(ID_ATTR_RE_STR).unwrap();
+    let do_re = Regex::new(DO_ATTR_RE_STR).unwrap();
+    let priority_attr_re = Regex::new(PRIORITY_ATTR_RE_STR).unwrap();
     let created_re = Regex::new(CREATED_ATTR_RE_STR).unwrap();
     let due_re = Regex::new(DUE_ATTR_RE_STR).unwrap();
     let updated_re = Regex::new(UPDATED_ATTR_RE_STR).unwrap();
@@ -353,8 +624,17 @@ pub fn parse_markdown_line_to_task(line: &str, default_id: i64, default_created_
     let context_re = Regex::new(CONTEXT_ATTR_RE_STR).unwrap();
     let tag_re = Regex::new(TAG_ATTR_RE_STR).unwrap();
     let note_re = Regex::new(NOTE_ATTR_RE_STR).unwrap();
-
-    let trimmed_line = line.trim_start_matches("- ").trim();
+    let repeat_re = Regex::new(REPEAT_ATTR_RE_STR).unwrap();
+    let depends_re = Regex::new(DEPENDS_ATTR_RE_STR).unwrap();
+    let progress_re = Regex::new(PROGRESS_ATTR_RE_STR).unwrap();
+    let estimate_re = Regex::new(ESTIMATE_ATTR_RE_STR).unwrap();
+
+    let trimmed_line = line.trim_start_matches("- ").trim_start_matches("* ").trim();
+    // og fmt --show-progress が付ける行末の <!-- 75% --> は読み飛ばす
+    let trimmed_line = match trimmed_line.rfind("<!--") {
+        Some(pos) if trimmed_line.ends_with("-->") => trimmed_line[..pos].trim_end(),
+        _ => trimmed_line,
+    };
 
     let base_re_str = format!(
         r#"^\s*{}\s*(?:{}\s*)?{}\s*(?P<attributes_str>.*)"#,
@@ -362,14 +642,16 @@ pub fn parse_markdown_line_to_task(line: &str, default_id: i64, default_created_
         PRIORITY_RE_STR,
         TASK_NAME_RE_STR
     );
-    let base_re = Regex::new(&base_re_str).map_err(|e| format!("Failed to compile base regex: {}", e))?;
+    let base_re = Regex::new(&base_re_str).map_err(|e| ParseError::RegexError(e.to_string()))?;
 
-    let caps = base_re.captures(trimmed_line).ok_or_else(|| format!("Line '{}' does not match base task format", format_for_debug(trimmed_line)))?;
+    let caps = base_re.captures(trimmed_line).ok_or_else(|| ParseError::NoMatch { line: trimmed_line.to_string() })?;
 
     let status_char = caps.name("status_char").unwrap().as_str().chars().next().unwrap_or(' ');
-    let status = map_status_char_to_string(status_char);
+    let status = status_map.status_for(status_char)
+        .ok_or_else(|| ParseError::UnknownStatus { line: trimmed_line.to_string(), marker: status_char })?
+        .to_string();
     
-    let priority = caps.name("priority_val").map_or("N".to_string(), |m| m.as_str().to_string());
+    let positional_priority = caps.name("priority_val").map(|m| m.as_str().to_string());
     let name = if let Some(m) = caps.name("task_name") {
         m.as_str()
     } else if let Some(m) = caps.name("task_name_plain") {
@@ -381,28 +663,48 @@ pub fn parse_markdown_line_to_task(line: &str, default_id: i64, default_created_
     
     let attributes_str = caps.name("attributes_str").map_or("", |m| m.as_str()).trim();
 
+    // note:"..." の中身は他の属性として拾わないよう、先に取り出して属性部から除く
+    let task_notes: Option<String> = note_re.captures(attributes_str)
+        .and_then(|cap| cap.name("note_val"))
+        .map(|m| unescape_note(m.as_str()));
+    let attributes_without_note = note_re.replace(attributes_str, " ");
+    let attributes_str = attributes_without_note.trim();
+
+    let attribute_priority = priority_attr_re.captures(attributes_str)
+        .and_then(|cap| cap.name("priority_attr_val"))
+        .map(|m| m.as_str().to_string());
+    let priority = match (positional_priority, attribute_priority) {
+        (Some(positional), Some(attribute)) if positional != attribute => {
+            return Err(ParseError::ConflictingPriorities { line: trimmed_line.to_string(), positional, attribute });
+        }
+        (Some(p), _) | (None, Some(p)) => p,
+        (None, None) => "N".to_string(),
+    };
+
     let mut task_id = default_id;
     let mut task_created = default_created_date; // Initialize with NaiveDate
     
     if let Some(cap) = id_re.captures(attributes_str) {
         if let Some(val_str) = cap.name("id_val") {
-            task_id = val_str.as_str().parse().unwrap_or(default_id);
+            task_id = val_str.as_str().parse().map_err(|_| ParseError::InvalidId { raw: val_str.as_str().to_string() })?;
         }
     }
     // Parse created attribute. If present and valid, use it. Otherwise, default_created_date (already set to task_created) is used.
     if let Some(cap) = created_re.captures(attributes_str) {
-        if let Some(parsed_date) = parse_date_or_empty_attr(&cap, "created_val") {
+        if let Some(parsed_date) = parse_date_or_empty_attr(&cap, "created_val", "created")? {
             task_created = parsed_date;
         }
     }
     
-    let task_due = due_re.captures(attributes_str).and_then(|cap| {
-        parse_date_or_empty_attr(&cap, "due_val")
-    });
-
-    let task_updated = updated_re.captures(attributes_str).and_then(|cap| {
-        parse_date_or_empty_attr(&cap, "updated_val")
-    });
+    let task_due = match due_re.captures(attributes_str) {
+        Some(cap) => parse_date_or_empty_attr(&cap, "due_val", "due")?,
+        None => None,
+    };
+
+    let task_updated = match updated_re.captures(attributes_str) {
+        Some(cap) => parse_date_or_empty_attr(&cap, "updated_val", "updated")?,
+        None => None,
+    };
     
     // デバッグコード残骸削除
     // let direct_test_str_ok = "completed:2024-07-01"; 
@@ -414,9 +716,10 @@ pub fn parse_markdown_line_to_task(line: &str, default_id: i64, default_created_
     // if let Some(_) = temp_completed_re.captures(attributes_str) {}
     // if completed_re.is_match(attributes_str) {}
 
-    let task_completed = completed_re.captures(attributes_str).and_then(|cap| {
-        parse_date_or_empty_attr(&cap, "completed_val") 
-    });
+    let task_completed = match completed_re.captures(attributes_str) {
+        Some(cap) => parse_date_or_empty_attr(&cap, "completed_val", "completed")?,
+        None => None,
+    };
 
     let mut task_project: Option<String> = None;
     // (以下変更なし) ...
@@ -429,23 +732,55 @@ pub fn parse_markdown_line_to_task(line: &str, default_id: i64, default_created_
     let mut task_contexts: Vec<String> = Vec::new();
     for cap in context_re.captures_iter(attributes_str) {
         if let Some(val_str) = cap.name("context_val") {
-            task_contexts.push(val_str.as_str().to_string());
+            push_unique(&mut task_contexts, val_str.as_str());
         }
     }
 
     let mut task_tags: Vec<String> = Vec::new();
+    let mut task_tags_kv: HashMap<String, String> = HashMap::new();
     for cap in tag_re.captures_iter(attributes_str) {
-        if let Some(val_str) = cap.name("tag_val") {
-            task_tags.push(val_str.as_str().to_string());
+        if let (Some(key), Some(val)) = (cap.name("tag_kv_key"), cap.name("tag_kv_val")) {
+            task_tags_kv.insert(key.as_str().to_string(), val.as_str().to_string());
+        } else if let Some(val_str) = cap.name("tag_val") {
+            push_unique(&mut task_tags, val_str.as_str());
         }
     }
     
-    let mut task_notes: Option<String> = None;
-    if let Some(cap) = note_re.captures(attributes_str) {
-        if let Some(val_str) = cap.name("note_val") {
-            task_notes = Some(val_str.as_str().to_string().replace("\"\"", "\""));
-        }
-    }
+    let task_repeat = repeat_re.captures(attributes_str)
+        .and_then(|cap| cap.name("repeat_val"))
+        .and_then(|m| Frequency::parse(m.as_str()))
+        .map(|frequency| RepeatInfo { frequency: Some(frequency) });
+
+    let task_depends: Option<Vec<i64>> = depends_re.captures(attributes_str)
+        .and_then(|cap| cap.name("depends_val"))
+        .map(|m| m.as_str().split(',').filter_map(|id| id.parse().ok()).collect());
+
+    let display_order = do_re.captures(attributes_str)
+        .and_then(|cap| cap.name("do_val"))
+        .and_then(|m| m.as_str().parse().ok())
+        .unwrap_or(default_display_order);
+
+    let task_progress: Option<u8> = match progress_re.captures(attributes_str).and_then(|cap| cap.name("progress_val")) {
+        Some(m) => match m.as_str().parse::<u8>() {
+            Ok(value) if value <= 100 => Some(value),
+            _ => return Err(ParseError::InvalidValue { line: trimmed_line.to_string(), field: "progress", raw: m.as_str().to_string(), expected: "0-100" }),
+        },
+        None => None,
+    };
+
+    let task_estimate: Option<u32> = match estimate_re.captures(attributes_str).and_then(|cap| cap.name("estimate_val")) {
+        Some(m) => match crate::task_model::parse_duration_minutes(m.as_str(), crate::task_model::hours_per_day()) {
+            Some(minutes) => Some(minutes),
+            None => return Err(ParseError::InvalidValue { line: trimmed_line.to_string(), field: "estimate", raw: m.as_str().to_string(), expected: "e.g. 90m, 1.5h, 2h30m or 1d" }),
+        },
+        None => None,
+    };
+
+    // 未知の key:value (sprint:12 など) は捨てずに extra に残す
+    let task_extra: HashMap<String, serde_json::Value> = unknown_attributes(attributes_str)
+        .into_iter()
+        .map(|(key, value)| (key, extra_value(&value)))
+        .collect();
 
     Ok(Task {
         name,
@@ -453,7 +788,7 @@ pub fn parse_markdown_line_to_task(line: &str, default_id: i64, default_created_
         priority,
         id: task_id,
         created: task_created,
-        display_order: default_display_order,
+        display_order,
         due: task_due,
         updated: task_updated,
         completed: task_completed,
@@ -461,9 +796,14 @@ pub fn parse_markdown_line_to_task(line: &str, default_id: i64, default_created_
         contexts: if task_contexts.is_empty() { None } else { Some(task_contexts) },
         notes: task_notes,
         tags: if task_tags.is_empty() { None } else { Some(task_tags) },
+        tags_kv: if task_tags_kv.is_empty() { None } else { Some(task_tags_kv) },
         subtasks: None,
-        extra: None,
-        repeat: None,
+        extra: if task_extra.is_empty() { None } else { Some(task_extra) },
+        repeat: task_repeat,
+        depends: task_depends,
+        progress: task_progress,
+        estimate_minutes: task_estimate,
+        unknown_fields: Default::default(),
     })
 }
 
@@ -531,6 +871,272 @@ mod tests {
 
     // --- parse_markdown_document_to_tasks のテスト ---
 
+    #[test]
+    fn test_parse_priority_attribute_only() {
+        let line = "- [ ] [[Attribute priority]] id:1 priority:B";
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let task = parse_markdown_line_to_task(line, 0, default_date, 1).unwrap();
+        assert_eq!(task.priority, "B");
+        assert_eq!(task.id, 1);
+    }
+
+    #[test]
+    fn test_parse_priority_positional_and_attribute_conflict() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let conflicting = "- [ ] (A) [[Conflicting priority]] priority:B";
+        let err = parse_markdown_line_to_task(conflicting, 0, default_date, 1).unwrap_err();
+        assert_eq!(err, ParseError::ConflictingPriorities { line: "[ ] (A) [[Conflicting priority]] priority:B".to_string(), positional: "A".to_string(), attribute: "B".to_string() });
+        assert!(err.to_string().contains("conflicting priorities"));
+
+        // 同じ値の重複指定は許容する
+        let consistent = "- [ ] (A) [[Consistent priority]] priority:A";
+        let task = parse_markdown_line_to_task(consistent, 0, default_date, 1).unwrap();
+        assert_eq!(task.priority, "A");
+    }
+
+    #[test]
+    fn test_parse_error_variants() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let parse = |line: &str| parse_markdown_line_to_task(line, 0, default_date, 1).unwrap_err();
+
+        assert_eq!(parse("- [ab] [[Two markers]]"), ParseError::NoMatch { line: "[ab] [[Two markers]]".to_string() });
+        assert_eq!(parse("- [ ] [[Leap]] due:2023-02-29"), ParseError::InvalidDate { field: "due".to_string(), raw: "2023-02-29".to_string() });
+        assert_eq!(parse("- [ ] [[Month]] created:2024-13-01"), ParseError::InvalidDate { field: "created".to_string(), raw: "2024-13-01".to_string() });
+        assert_eq!(parse("- [x] [[Day]] completed:4/31"), ParseError::InvalidDate { field: "completed".to_string(), raw: "4/31".to_string() });
+        assert_eq!(parse("- [ ] [[Huge]] id:99999999999999999999"), ParseError::InvalidId { raw: "99999999999999999999".to_string() });
+
+        // 基本形の正規表現は定数なので RegexError は表示と行番号の付け方だけ確かめる
+        let err = ParseError::RegexError("unclosed group".to_string()).at_line(3);
+        assert!(matches!(err, OgError::Parse { line: 3, ref msg } if msg == "Failed to compile base regex: unclosed group"));
+        assert_eq!(OgError::from(parse("- [ ] [[Leap]] due:2023-02-29")).to_string(), "line 1: invalid due date '2023-02-29'");
+    }
+
+    #[test]
+    fn test_parse_error_policies() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let doc = "- [ ] [[First]] id:1\n- [ ] [[Bad date]] id:2 due:2024-02-30\n- [ ] [[Third]] id:3\n- [!] [[Bad marker]]\n";
+        let options = |error_policy| ParseOptions { error_policy, ..Default::default() };
+
+        let err = parse_markdown_document_to_tasks_with_options(doc, default_date, &options(ErrorPolicy::FailFast)).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: invalid due date '2024-02-30'");
+
+        for policy in [ErrorPolicy::SkipBad, ErrorPolicy::CollectAll] {
+            let (tasks, errors) = parse_markdown_document_collecting_errors(doc, default_date, &options(policy)).unwrap();
+            assert_eq!(tasks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 3]);
+            let lines: Vec<usize> = errors.iter().map(|(line, _)| *line).collect();
+            assert_eq!(lines, if policy == ErrorPolicy::CollectAll { vec![2, 4] } else { vec![] });
+        }
+
+        // SkipBad は並列版でも同じ結果。CollectAll を受け取れない関数は最初のエラーを返す
+        let tasks = parse_markdown_document_to_tasks_parallel_with_options(doc, default_date, &options(ErrorPolicy::SkipBad)).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert!(parse_markdown_document_to_tasks_parallel_with_options(doc, default_date, &options(ErrorPolicy::CollectAll)).is_err());
+
+        // 本文を残すパースでは読み飛ばした行を本文として残す
+        let document = parse_markdown_document_with_text(doc, default_date, &options(ErrorPolicy::SkipBad)).unwrap();
+        assert_eq!(document.tasks().count(), 2);
+        assert!(matches!(&document.blocks[1], Block::Text(text) if text.contains("Bad date")));
+    }
+
+    #[test]
+    fn test_parse_document_error_reports_document_line() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let markdown = "# Tasks\n- [ ] [[Fine]]\n\n- [ ] (A) [[Broken]] priority:B\n";
+        for result in [
+            parse_markdown_document_to_tasks(markdown, default_date),
+            parse_markdown_document_to_tasks_parallel(markdown, default_date),
+        ] {
+            match result {
+                Err(OgError::Parse { line, .. }) => assert_eq!(line, 4),
+                other => panic!("expected a parse error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_with_custom_status_map() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let options = ParseOptions {
+            status_map: StatusMap::from_toml("\"/\" = \"doing\"\n\"!\" = \"waiting\"\n").unwrap(),
+            ..Default::default()
+        };
+        let tasks = parse_markdown_document_to_tasks_with_options("- [/] [[Review PR]]\n- [!] [[Vendor reply]]\n", default_date, &options).unwrap();
+        assert_eq!(tasks[0].status, "doing");
+        assert_eq!(tasks[1].status, "waiting");
+
+        // 上書きされた既定の文字は未知の文字としてエラーになる
+        let err = parse_markdown_document_to_tasks_with_options("- [ ] [[Fine]]\n- [>] [[Old marker]]\n", default_date, &options).unwrap_err();
+        assert!(matches!(err, OgError::Parse { line: 2, ref msg } if msg.contains("unknown status marker")));
+    }
+
+    #[test]
+    fn test_strict_rejects_unknown_attributes() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let doc = "- [ ] [[Fine]] id:1 due:2024-02-01\n- [ ] [[Typo]] id:2 duee:2024-01-01";
+        let strict = ParseOptions { strict: true, ..Default::default() };
+
+        // 通常モードでは due としては読まれず、extra に入る
+        let tasks = parse_markdown_document_to_tasks_with_options(doc, default_date, &ParseOptions::default()).unwrap();
+        assert_eq!(tasks[1].due, None);
+        assert_eq!(tasks[1].extra.as_ref().unwrap()["duee"], serde_json::json!("2024-01-01"));
+
+        let err = parse_markdown_document_to_tasks_with_options(doc, default_date, &strict).unwrap_err();
+        assert!(matches!(err, OgError::Parse { line: 2, ref msg } if msg.contains("'duee:2024-01-01'")), "{:?}", err);
+
+        // 既知の属性・タグ・ノート内やタスク名内の key:value はエラーにしない
+        let ok = "- [ ] [[Read foo:bar]] id:1 #area:home +proj @ctx note:\"see x:y\" https://example.com/a progress:10 <!-- 10% -->";
+        assert!(parse_markdown_document_to_tasks_with_options(ok, default_date, &strict).is_ok());
+        assert!(parse_markdown_document_to_tasks_parallel_with_options(doc, default_date, &strict).is_err());
+    }
+
+    #[test]
+    fn test_parse_document_parallel_matches_sequential() {
+        // 10k 行での速度比較は benches/parse.rs
+        let mut md_doc = String::new();
+        for i in 0..36 {
+            if i % 3 == 0 {
+                md_doc.push_str(&format!("- [ ] [[Task {}]] created:2024-01-01\n", i));
+            } else {
+                md_doc.push_str(&format!("    - [x] (A) [[Task {}]] id:{} due:2024-02-01\n", i, i + 5000));
+            }
+        }
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let sequential = parse_markdown_document_to_tasks(&md_doc, default_date).unwrap();
+        let parallel = parse_markdown_document_to_tasks_parallel(&md_doc, default_date).unwrap();
+        assert_eq!(
+            serde_json::to_string(&sequential).unwrap(),
+            serde_json::to_string(&parallel).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_repeat_attribute() {
+        let line = "- [ ] [[Pay rent]] due:2023-01-31 repeat:last-day-of-month";
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let task = parse_markdown_line_to_task(line, 0, default_date, 1).unwrap();
+        assert_eq!(task.repeat.unwrap().frequency, Some(Frequency::LastDayOfMonth));
+
+        let line = "- [ ] [[Standup]] repeat:weekdays";
+        let task = parse_markdown_line_to_task(line, 0, default_date, 1).unwrap();
+        assert_eq!(task.repeat.unwrap().frequency, Some(Frequency::Weekdays));
+    }
+
+    #[test]
+    fn test_normalize_labels_strips_trailing_punctuation() {
+        let md_doc = "- [ ] [[Normalize]] +Proj. @Home, #done, #Urgent #,";
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        let raw = parse_markdown_document_to_tasks(md_doc, default_date).unwrap();
+        assert_eq!(raw[0].tags, Some(vec!["done,".to_string(), "Urgent".to_string(), ",".to_string()]));
+
+        let options = ParseOptions { strip_label_punctuation: true, ..Default::default() };
+        let tasks = parse_markdown_document_to_tasks_with_options(md_doc, default_date, &options).unwrap();
+        assert_eq!(tasks[0].tags, Some(vec!["done".to_string(), "Urgent".to_string()]));
+        assert_eq!(tasks[0].contexts, Some(vec!["Home".to_string()]));
+        assert_eq!(tasks[0].project, Some("Proj".to_string()));
+    }
+
+    #[test]
+    fn test_normalize_labels_lowercase_toggle() {
+        let md_doc = "\
+- [ ] [[Parent]] +Work #Urgent
+    - [ ] [[Child]] @Office #URGENT";
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let options = ParseOptions { lowercase_labels: true, ..Default::default() };
+        let tasks = parse_markdown_document_to_tasks_with_options(md_doc, default_date, &options).unwrap();
+        assert_eq!(tasks[0].project, Some("work".to_string()));
+        assert_eq!(tasks[0].tags, Some(vec!["urgent".to_string()]));
+        let child = &tasks[0].subtasks.as_ref().unwrap()[0];
+        assert_eq!(child.contexts, Some(vec!["office".to_string()]));
+        assert_eq!(child.tags, Some(vec!["urgent".to_string()]));
+    }
+
+    #[test]
+    fn test_parse_dedups_contexts_and_tags_in_order() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let task = parse_markdown_line_to_task("- [ ] [[Dup]] @home @work @home #a #a #b #c #b", 0, default_date, 1).unwrap();
+        assert_eq!(task.contexts, Some(vec!["home".to_string(), "work".to_string()]));
+        assert_eq!(task.tags, Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]));
+
+        // 別の行の同じタグはそれぞれのタスクに残る
+        let tasks = parse_markdown_document_to_tasks("- [ ] [[One]] #a #a\n- [ ] [[Two]] #b #a", default_date).unwrap();
+        assert_eq!(tasks[0].tags, Some(vec!["a".to_string()]));
+        assert_eq!(tasks[1].tags, Some(vec!["b".to_string(), "a".to_string()]));
+
+        // 小文字化で同じになったタグもまとめる
+        let options = ParseOptions { lowercase_labels: true, ..Default::default() };
+        let tasks = parse_markdown_document_to_tasks_with_options("- [ ] [[Case]] #Urgent #urgent", default_date, &options).unwrap();
+        assert_eq!(tasks[0].tags, Some(vec!["urgent".to_string()]));
+    }
+
+    #[test]
+    fn test_read_next_id_hint() {
+        assert_eq!(read_next_id_hint("<!-- og:next_id:42 -->\n- [ ] [[A]]"), Some(42));
+        assert_eq!(read_next_id_hint("  <!-- og:next_id: 7 -->"), Some(7));
+        // 1行目以外や壊れた値は無視
+        assert_eq!(read_next_id_hint("# Tasks\n<!-- og:next_id:42 -->"), None);
+        assert_eq!(read_next_id_hint("<!-- og:next_id:abc -->"), None);
+        assert_eq!(read_next_id_hint("<!-- og:next_id:0 -->"), None);
+        assert_eq!(read_next_id_hint(""), None);
+    }
+
+    #[test]
+    fn test_next_id_hint_numbers_new_tasks() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let doc = "<!-- og:next_id:10 -->\n- [ ] [[A]] id:3\n- [ ] [[New]]\n    - [ ] [[Child]]";
+        let tasks = parse_markdown_document_to_tasks(doc, default_date).unwrap();
+        assert_eq!(tasks[0].id, 3);
+        assert_eq!(tasks[1].id, 10);
+        assert_eq!(tasks[1].subtasks.as_ref().unwrap()[0].id, 11);
+
+        // ヒント以上の ID が手で書かれていれば、通常どおり欠番から振る
+        let stale = "<!-- og:next_id:2 -->\n- [ ] [[A]] id:5\n- [ ] [[New]]";
+        let tasks = parse_markdown_document_to_tasks(stale, default_date).unwrap();
+        assert_eq!(tasks[1].id, 1);
+    }
+
+    #[test]
+    fn test_parse_estimate_attribute() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        for (text, minutes) in [("90m", 90), ("1.5h", 90), ("2h30m", 150), ("1d", 480)] {
+            let task = parse_markdown_line_to_task(&format!("- [ ] [[Plan]] estimate:{}", text), 0, default_date, 1).unwrap();
+            assert_eq!(task.estimate_minutes, Some(minutes), "{}", text);
+        }
+        let err = parse_markdown_line_to_task("- [ ] [[Plan]] estimate:2hours", 0, default_date, 1).unwrap_err();
+        assert!(err.to_string().contains("invalid estimate '2hours'"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_progress_attribute() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let task = parse_markdown_line_to_task("- [>] [[Long]] id:1 progress:75", 0, default_date, 1).unwrap();
+        assert_eq!(task.progress, Some(75));
+        let task = parse_markdown_line_to_task("- [ ] [[Edge]] progress:0", 0, default_date, 1).unwrap();
+        assert_eq!(task.progress, Some(0));
+        let task = parse_markdown_line_to_task("- [ ] [[None]] id:2", 0, default_date, 1).unwrap();
+        assert_eq!(task.progress, None);
+
+        for invalid in ["progress:101", "progress:-5", "progress:half"] {
+            let line = format!("- [ ] [[Bad]] {}", invalid);
+            let err = parse_markdown_line_to_task(&line, 0, default_date, 1).unwrap_err();
+            assert!(err.to_string().contains("invalid progress"), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_depends_attribute() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let task = parse_markdown_line_to_task("- [ ] [[Blocked]] id:9 depends:1,4,7", 0, default_date, 1).unwrap();
+        assert_eq!(task.depends, Some(vec![1, 4, 7]));
+        assert_eq!(task.id, 9);
+
+        let task = parse_markdown_line_to_task("- [ ] [[Single]] depends:3", 0, default_date, 1).unwrap();
+        assert_eq!(task.depends, Some(vec![3]));
+
+        let task = parse_markdown_line_to_task("- [ ] [[Free]] id:2", 0, default_date, 1).unwrap();
+        assert_eq!(task.depends, None);
+    }
+
     #[test]
     fn test_parse_document_empty() {
         let md_doc = "";
@@ -640,6 +1246,55 @@ mod tests {
         assert_eq!(task.notes, Some(r#"A note with "escaped" quotes."#.to_string()));
     }
 
+    #[test]
+    fn test_parse_line_attributes_only_at_token_start() {
+        // note 内の属性風テキストや、トークン途中の do: / +1 は属性として拾わない
+        let line = r#"- [ ] [[Task]] id:7 #todo:3 #sprint+1 note:"see due:2024-01-01 +other @home""#;
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let task = parse_markdown_line_to_task(line, 0, default_date, 1).unwrap();
+        assert_eq!(task.id, 7);
+        assert_eq!(task.display_order, 1);
+        assert_eq!(task.due, None);
+        assert_eq!(task.project, None);
+        assert_eq!(task.contexts, None);
+        assert_eq!(task.tags, Some(vec!["sprint+1".to_string()]));
+        assert_eq!(task.tags_kv.unwrap().get("todo").map(String::as_str), Some("3"));
+        assert_eq!(task.notes, Some("see due:2024-01-01 +other @home".to_string()));
+    }
+
+    #[test]
+    fn test_parse_line_name_mentions_are_not_attributes() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        // [[ ]] のない名前の中の @x / +y / #z はコンテキスト・プロジェクト・タグにならない
+        let task = parse_markdown_line_to_task("- [ ] Ask @x about +budget #later", 3, default_date, 1).unwrap();
+        assert_eq!(task.name, "Ask @x about +budget #later");
+        assert_eq!((task.contexts, task.project, task.tags), (None, None, None));
+        assert_eq!(task.id, 3);
+
+        // [[ ]] の中は属性として読まず、後ろの属性だけを読む
+        let task = parse_markdown_line_to_task("- [ ] [[Email @john about +budget]] id:5 @office", 0, default_date, 1).unwrap();
+        assert_eq!(task.name, "Email @john about +budget");
+        assert_eq!(task.contexts, Some(vec!["office".to_string()]));
+        assert_eq!(task.project, None);
+    }
+
+    #[test]
+    fn test_parse_document_asterisk_list_marker() {
+        // "* [ ]" も "- [ ]" と同じくタスク行として読む (入れ子と混在を含む)
+        // チェックボックスのないリンクの箇条書き ("* [Docs](...)") はタスク行ではない
+        let md_doc = "* [ ] [[Parent]] id:1\n    * [x] [[Child]] id:2\n- [ ] [[Dash]] id:3\n* plain bullet\n* [Docs](http://example.com)\n- [[Wiki]] link";
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let tasks = parse_markdown_document_to_tasks(md_doc, default_date).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].name, "Parent");
+        assert_eq!(tasks[0].subtasks.as_ref().unwrap()[0].name, "Child");
+        assert_eq!(tasks[0].subtasks.as_ref().unwrap()[0].status, "done");
+        assert_eq!(tasks[1].name, "Dash");
+
+        let task = parse_markdown_line_to_task("* [ ] (B) [[Star]] id:4", 0, default_date, 1).unwrap();
+        assert_eq!((task.name.as_str(), task.priority.as_str(), task.id), ("Star", "B", 4));
+    }
+
     #[test]
     fn test_parse_document_multiple_level_subtasks() {
         let md_doc = " \\\n\
@@ -733,4 +1388,42 @@ mod tests {
         assert_eq!(tasks[1].id, 3);
         assert!(tasks[1].subtasks.is_none());
     }
+
+    #[test]
+    fn test_continuation_lines_join_task_line() {
+        let md = "- [ ] [[Wrapped]] id:1 \\\n        due:2024-02-01 #a \\\n        #b\n    - [ ] [[Child]] id:2";
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let tasks = parse_markdown_document_to_tasks(md, default_date).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].due, NaiveDate::from_ymd_opt(2024, 2, 1));
+        assert_eq!(tasks[0].tags, Some(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(tasks[0].subtasks.as_ref().unwrap()[0].name, "Child");
+
+        // 継続行を含むタスクのエラーはタスク行の行番号で報告する
+        let bad = format!("{}\n- [ ] [[Bad]] id:3 \\\n    estimate:soon", md);
+        let err = parse_markdown_document_to_tasks(&bad, default_date).unwrap_err().to_string();
+        assert!(err.starts_with("line 5:"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_line_note_escapes() {
+        let line = r#"- [ ] [[Issue]] note:"Steps:\n1. open\r\n- [ ] do X\\ C:\dir""#;
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let task = parse_markdown_line_to_task(line, 0, default_date, 1).unwrap();
+        // \d のような未知のエスケープはそのまま残す
+        assert_eq!(task.notes.as_deref(), Some("Steps:\n1. open\r\n- [ ] do X\\ C:\\dir"));
+    }
+
+    #[test]
+    fn test_trailing_backslash_before_task_line_is_not_continuation() {
+        // 手書きの行末の ` \` の次がタスク行なら、次のタスクを飲み込まない
+        let md = "- [ ] [[Backup C:\\ dir \\]] id:1 \\\n- [ ] [[Second]] id:2";
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let tasks = parse_markdown_document_to_tasks(md, default_date).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].name, "Backup C:\\ dir \\");
+        assert_eq!(tasks[0].id, 1);
+        assert_eq!(tasks[1].name, "Second");
+        assert_eq!(tasks[1].id, 2);
+    }
 }
@@ -0,0 +1,275 @@
+use crate::task_model::Task;
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+// タスク内容のフィンガープリント
+// name, status, priority, due, project, tags が同一なら、IDが違っても同じタスクとみなす
+pub fn content_fingerprint(task: &Task) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    task.name.hash(&mut hasher);
+    task.status.hash(&mut hasher);
+    task.priority.hash(&mut hasher);
+    task.due.hash(&mut hasher);
+    task.project.hash(&mut hasher);
+    task.tags.hash(&mut hasher);
+    hasher.finish()
+}
+
+// 重複とみなすタスクのキー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupKey {
+    Content,              // content_fingerprint と同じ (name, status, priority, due, project, tags)。og merge --dedup
+    NameDueProjectStatus, // priority や tags の違いは無視する。og merge --dedupe / og apply --dedupe
+}
+
+impl DedupKey {
+    fn fingerprint(self, task: &Task) -> u64 {
+        match self {
+            DedupKey::Content => content_fingerprint(task),
+            DedupKey::NameDueProjectStatus => {
+                let mut hasher = DefaultHasher::new();
+                (&task.name, task.due, &task.project, &task.status).hash(&mut hasher);
+                hasher.finish()
+            }
+        }
+    }
+}
+
+// 重複タスクを除去する (DedupKey::Content)
+pub fn dedup_tasks(tasks: Vec<Task>) -> (Vec<Task>, Vec<(i64, i64)>) {
+    dedup_tasks_by(tasks, DedupKey::Content)
+}
+
+// key が同じタスクの重複を除去する
+// - created が早い方を残す (同日の場合は先に出現した方)。ID も残した方のものを使う。
+// - extra は残す側を優先し、捨てる側にしかないキーを補完する。
+// - サブタスクは同じ親の中でだけ比較する。捨てたタスクのサブタスクは一緒に捨てる。
+// 戻り値の2番目は (残したID, 捨てたID) の組。
+pub fn dedup_tasks_by(tasks: Vec<Task>, key: DedupKey) -> (Vec<Task>, Vec<(i64, i64)>) {
+    let mut result: Vec<Task> = Vec::new();
+    let mut index_by_fingerprint: HashMap<u64, usize> = HashMap::new();
+    let mut pairs: Vec<(i64, i64)> = Vec::new();
+
+    for task in tasks {
+        let fingerprint = key.fingerprint(&task);
+        match index_by_fingerprint.get(&fingerprint) {
+            Some(&index) => {
+                let (mut kept, discarded) = if task.created < result[index].created {
+                    (task, result[index].clone())
+                } else {
+                    (result[index].clone(), task)
+                };
+                if let Some(discarded_extra) = discarded.extra {
+                    let kept_extra = kept.extra.get_or_insert_with(HashMap::new);
+                    for (key, value) in discarded_extra {
+                        kept_extra.entry(key).or_insert(value);
+                    }
+                }
+                pairs.push((kept.id, discarded.id));
+                result[index] = kept;
+            }
+            None => {
+                index_by_fingerprint.insert(fingerprint, result.len());
+                result.push(task);
+            }
+        }
+    }
+
+    for task in &mut result {
+        if let Some(subtasks) = task.subtasks.take() {
+            let (subtasks, subtask_pairs) = dedup_tasks_by(subtasks, key);
+            task.subtasks = Some(subtasks);
+            pairs.extend(subtask_pairs);
+        }
+    }
+
+    (result, pairs)
+}
+
+// og apply --dedupe: DedupKey::NameDueProjectStatus で重複を除去し、取り除いたタスク数を返す
+// (取り除いたタスクのサブタスクは数えない)
+pub fn dedupe_tasks(tasks: &mut Vec<Task>) -> usize {
+    let (deduped, pairs) = dedup_tasks_by(std::mem::take(tasks), DedupKey::NameDueProjectStatus);
+    *tasks = deduped;
+    pairs.len()
+}
+
+// 複数のタスクリストをファイル順に連結する。
+// ID が衝突した場合は未使用の最小IDに振り直し (サブタスク含む)、同じリスト内の depends も追従させる。
+// 同名タスクはそのまま残す。display_order は連結後の順序で振り直す。
+pub fn merge_task_lists(lists: Vec<Vec<Task>>) -> Vec<Task> {
+    let mut used_ids: HashSet<i64> = HashSet::new();
+    let mut merged: Vec<Task> = Vec::new();
+
+    for mut tasks in lists {
+        // 深さ優先順に新しいIDを決める。後続タスクの元IDは、そのタスクが使えるよう振り直し先から除外する。
+        let original_ids: Vec<i64> = crate::task_model::flatten_all(&tasks).map(|t| t.id).collect();
+        let mut new_ids: Vec<i64> = Vec::with_capacity(original_ids.len());
+        let mut id_map: HashMap<i64, i64> = HashMap::new(); // depends の付け替え用 (元ID → 新ID)
+        for (position, &old_id) in original_ids.iter().enumerate() {
+            let new_id = if !used_ids.contains(&old_id) {
+                old_id
+            } else {
+                let mut candidate = 1;
+                while used_ids.contains(&candidate) || original_ids[position + 1..].contains(&candidate) {
+                    candidate += 1;
+                }
+                candidate
+            };
+            used_ids.insert(new_id);
+            new_ids.push(new_id);
+            id_map.entry(old_id).or_insert(new_id);
+        }
+
+        let mut next_new_id = new_ids.into_iter();
+        for task in tasks.iter_mut() {
+            task.flatten_mut(&mut |t| {
+                t.id = next_new_id.next().unwrap();
+                if let Some(depends) = &mut t.depends {
+                    for id in depends.iter_mut() {
+                        if let Some(new_id) = id_map.get(id) {
+                            *id = *new_id;
+                        }
+                    }
+                }
+            });
+        }
+        merged.extend(tasks);
+    }
+
+    for (index, task) in merged.iter_mut().enumerate() {
+        task.display_order = (index + 1) as i64;
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn create_sample_task(id: i64, name: &str, created: NaiveDate) -> Task {
+        Task {
+            id,
+            name: name.to_string(),
+            status: "open".to_string(),
+            priority: "N".to_string(),
+            created,
+            display_order: id,
+            due: None,
+            updated: None,
+            completed: None,
+            project: Some("proj".to_string()),
+            contexts: None,
+            notes: None,
+            tags: Some(vec!["tag".to_string()]),
+            tags_kv: None,
+            subtasks: None,
+            extra: None,
+            repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_id_and_notes() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let a = create_sample_task(1, "Same", date);
+        let mut b = create_sample_task(2, "Same", date);
+        b.notes = Some("different note".to_string());
+        assert_eq!(content_fingerprint(&a), content_fingerprint(&b));
+
+        b.priority = "A".to_string();
+        assert_ne!(content_fingerprint(&a), content_fingerprint(&b));
+    }
+
+    #[test]
+    fn test_dedup_keeps_earlier_created_and_merges_extra() {
+        let newer = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let older = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        let mut first = create_sample_task(1, "Dup", newer);
+        let mut first_extra = HashMap::new();
+        first_extra.insert("a".to_string(), serde_json::json!(1));
+        first_extra.insert("shared".to_string(), serde_json::json!("from newer"));
+        first.extra = Some(first_extra);
+
+        let mut second = create_sample_task(5, "Dup", older);
+        let mut second_extra = HashMap::new();
+        second_extra.insert("shared".to_string(), serde_json::json!("from older"));
+        second.extra = Some(second_extra);
+
+        let other = create_sample_task(2, "Other", newer);
+
+        let (result, pairs) = dedup_tasks(vec![first, other, second]);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].id, 5);
+        assert_eq!(result[0].created, older);
+        let extra = result[0].extra.as_ref().unwrap();
+        assert_eq!(extra.get("a").unwrap(), &serde_json::json!(1));
+        assert_eq!(extra.get("shared").unwrap(), &serde_json::json!("from older"));
+        assert_eq!(result[1].id, 2);
+        assert_eq!(pairs, vec![(5, 1)]);
+    }
+
+    #[test]
+    fn test_merge_renumbers_conflicting_ids() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut parent = create_sample_task(1, "Parent A", date);
+        parent.subtasks = Some(vec![create_sample_task(2, "Child A", date)]);
+        let file_a = vec![parent, create_sample_task(3, "Same name", date)];
+
+        let mut blocked = create_sample_task(2, "Blocked B", date);
+        blocked.depends = Some(vec![1]);
+        let file_b = vec![create_sample_task(1, "Same name", date), blocked, create_sample_task(4, "Free B", date)];
+
+        let merged = merge_task_lists(vec![file_a, file_b]);
+        let ids: Vec<i64> = crate::task_model::flatten_all(&merged).map(|t| t.id).collect();
+        let unique: HashSet<i64> = ids.iter().copied().collect();
+        assert_eq!(ids.len(), unique.len());
+        assert_eq!(ids, vec![1, 2, 3, 5, 6, 4]);
+
+        // 同名タスクは両方残る
+        assert_eq!(merged.iter().filter(|t| t.name == "Same name").count(), 2);
+        // depends は振り直し後のIDを指す
+        assert_eq!(merged[3].depends, Some(vec![5]));
+        let orders: Vec<i64> = merged.iter().map(|t| t.display_order).collect();
+        assert_eq!(orders, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_dedupe_keeps_first_and_compares_subtasks_within_parent() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut first = create_sample_task(1, "Dup", date);
+        first.subtasks = Some(vec![create_sample_task(2, "Step", date), create_sample_task(3, "Step", date)]);
+        let mut later = create_sample_task(4, "Dup", date);
+        // priority や tags が違っても (name, due, project, status) が同じなら重複
+        later.priority = "A".to_string();
+        let mut other = create_sample_task(5, "Other", date);
+        other.subtasks = Some(vec![create_sample_task(6, "Step", date)]);
+        let mut tasks = vec![first, later, other];
+
+        // Content のキーでは priority が違うので Dup は残り、サブタスクの Step だけが重複
+        let (by_content, pairs) = dedup_tasks_by(tasks.clone(), DedupKey::Content);
+        assert_eq!(by_content.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 4, 5]);
+        assert_eq!(pairs, vec![(2, 3)]);
+
+        assert_eq!(dedupe_tasks(&mut tasks), 2);
+        assert_eq!(tasks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 5]);
+        assert_eq!(tasks[0].subtasks.as_ref().unwrap().len(), 1);
+        // 別の親のサブタスクとは比べない
+        assert_eq!(tasks[1].subtasks.as_ref().unwrap()[0].id, 6);
+    }
+
+    #[test]
+    fn test_dedupe_keeps_earlier_created_like_dedup() {
+        let newer = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let older = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut tasks = vec![create_sample_task(1, "Dup", newer), create_sample_task(2, "Dup", older)];
+        assert_eq!(dedupe_tasks(&mut tasks), 1);
+        assert_eq!((tasks[0].id, tasks[0].created), (2, older));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::error::ParseError;
+use crate::markdown_parser::{build_task_tree, calculate_indent_level, is_task_line};
+use crate::status_map::StatusMap;
+use crate::task_model::Task;
+use chrono::NaiveDate;
+use regex::Regex;
+
+// Obsidian (Tasks プラグイン) 形式のタスク行: "- [ ] 名前 📅 2024-01-15 ⏫"
+// 日付と優先度は絵文字の属性で書く。絵文字の後ろに異体字セレクタ (U+FE0F) が付いていてもよい
+const OBSIDIAN_TASK_RE_STR: &str = r#"^\[(?P<status_char>[^\[\]])\]\s*(?P<rest>.*)$"#;
+const OBSIDIAN_DATE_RE_STR: &str = r#"(?P<emoji>📅|➕|✅)\x{FE0F}?\s*(?P<date>\S*)"#;
+const OBSIDIAN_PRIORITY_RE_STR: &str = r#"(?P<emoji>⏫|🔼|🔽)\x{FE0F}?"#;
+// 名前の中の Obsidian のリンク [[ページ]] / [[ページ|表示名]]。og の [[名前]] と衝突するので表示される文字だけ残す
+const OBSIDIAN_WIKILINK_RE_STR: &str = r#"\[\[(?:[^\[\]|]*\|)?(?P<text>[^\[\]]*)\]\]"#;
+
+// Obsidian の慣習で og の既定と意味が違うマーカー。それ以外は StatusMap の既定の対応に従う
+fn obsidian_status(marker: char) -> Option<&'static str> {
+    match marker {
+        'X' => Some("done"),
+        '/' => Some("doing"),
+        '-' => Some("cancelled"), // og の既定では skipped
+        _ => StatusMap::builtin().status_for(marker),
+    }
+}
+
+fn date_field(emoji: &str) -> &'static str {
+    match emoji {
+        "📅" => "due",
+        "➕" => "created",
+        _ => "completed", // ✅
+    }
+}
+
+fn priority_for(emoji: &str) -> &'static str {
+    match emoji {
+        "⏫" => "A",
+        "🔼" => "B",
+        _ => "C", // 🔽
+    }
+}
+
+// Obsidian 形式の Markdown からタスクを読む (og --from obsidian)。
+// 📅 → due、➕ → created、✅ → completed、⏫ / 🔼 / 🔽 → 優先度 A / B / C。
+// 優先度の絵文字が複数あれば最初のものを使い、ない場合は N。それ以外の絵文字や #タグ は名前に残す。
+// タスク行以外は読み飛ばし、ID と表示順は行の出現順に 1 から振る。インデントのタブは空白 4 つとみなす
+pub fn parse_obsidian_tasks(input: &str, default_date: NaiveDate) -> Result<Vec<Task>, ParseError> {
+    let task_re = Regex::new(OBSIDIAN_TASK_RE_STR).map_err(|e| ParseError::RegexError(e.to_string()))?;
+    let date_re = Regex::new(OBSIDIAN_DATE_RE_STR).map_err(|e| ParseError::RegexError(e.to_string()))?;
+    let priority_re = Regex::new(OBSIDIAN_PRIORITY_RE_STR).map_err(|e| ParseError::RegexError(e.to_string()))?;
+    let wikilink_re = Regex::new(OBSIDIAN_WIKILINK_RE_STR).map_err(|e| ParseError::RegexError(e.to_string()))?;
+
+    let mut flat_parsed_items: Vec<(Task, usize)> = Vec::new();
+    for line in input.lines().filter(|line| is_task_line(line)) {
+        let indent_width = line.len() - line.trim_start().len();
+        let indent = line[..indent_width].replace('\t', "    ");
+        let content = line.trim_start().trim_start_matches(['-', '*']).trim_start();
+        let caps = task_re.captures(content).ok_or_else(|| ParseError::NoMatch { line: line.to_string() })?;
+
+        let marker = caps["status_char"].chars().next().unwrap();
+        let status = obsidian_status(marker).ok_or_else(|| ParseError::UnknownStatus { line: line.to_string(), marker })?;
+
+        let rest = &caps["rest"];
+        let mut due = None;
+        let mut created = None;
+        let mut completed = None;
+        for date_caps in date_re.captures_iter(rest) {
+            let field = date_field(&date_caps["emoji"]);
+            let raw = &date_caps["date"];
+            let date = NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .map_err(|_| ParseError::InvalidDate { field: field.to_string(), raw: raw.to_string() })?;
+            match field {
+                "due" => due = Some(date),
+                "created" => created = Some(date),
+                _ => completed = Some(date),
+            }
+        }
+        let priority = priority_re.captures(rest).map_or("N", |priority_caps| priority_for(&priority_caps["emoji"]));
+
+        let without_dates = date_re.replace_all(rest, " ");
+        let without_priority = priority_re.replace_all(&without_dates, " ");
+        // 対になっていない [[ / ]] も og の名前の区切りと紛らわしいので取り除く
+        let without_links = wikilink_re.replace_all(&without_priority, "$text").replace("[[", "").replace("]]", "");
+        let name = without_links.split_whitespace().collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            return Err(ParseError::NoMatch { line: line.to_string() });
+        }
+
+        let order = flat_parsed_items.len() as i64 + 1;
+        let task = Task {
+            name,
+            status: status.to_string(),
+            priority: priority.to_string(),
+            id: order,
+            created: created.unwrap_or(default_date),
+            display_order: order,
+            due,
+            updated: None,
+            completed,
+            project: None,
+            contexts: None,
+            notes: None,
+            tags: None,
+            tags_kv: None,
+            subtasks: None,
+            extra: None,
+            repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
+        };
+        flat_parsed_items.push((task, calculate_indent_level(&indent)));
+    }
+    Ok(build_task_tree(flat_parsed_items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+    }
+
+    #[test]
+    fn test_parse_obsidian_emoji_attributes() {
+        let input = "# Inbox\n- [ ] Write report 📅 2024-01-15 ➕ 2024-01-02 ⏫\n- [x] Pay rent ✅ 2024-01-03 🔼\n- [ ] Stretch 🔽 📅 2024-02-01\n- [ ] Read a book\n";
+        let tasks = parse_obsidian_tasks(input, default_date()).unwrap();
+        assert_eq!(tasks.len(), 4);
+
+        assert_eq!(tasks[0].name, "Write report");
+        assert_eq!(tasks[0].due, NaiveDate::from_ymd_opt(2024, 1, 15));
+        assert_eq!(tasks[0].created, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(tasks[0].priority, "A");
+        assert_eq!(tasks[0].status, "open");
+
+        assert_eq!(tasks[1].name, "Pay rent");
+        assert_eq!(tasks[1].status, "done");
+        assert_eq!(tasks[1].completed, NaiveDate::from_ymd_opt(2024, 1, 3));
+        assert_eq!(tasks[1].priority, "B");
+
+        assert_eq!(tasks[2].name, "Stretch");
+        assert_eq!(tasks[2].priority, "C");
+        assert_eq!(tasks[2].due, NaiveDate::from_ymd_opt(2024, 2, 1));
+
+        // 属性のないタスクは優先度 N、created は既定の日付
+        assert_eq!(tasks[3].priority, "N");
+        assert_eq!(tasks[3].created, default_date());
+        assert_eq!(tasks[3].due, None);
+        assert_eq!(tasks.iter().map(|task| task.id).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_parse_obsidian_nesting_and_statuses() {
+        let input = "- [/] Project ⏫\n\t- [X] Step one ✅ 2024-01-05\n\t- [-] Step two\n        - [ ] Detail #later\n";
+        let tasks = parse_obsidian_tasks(input, default_date()).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].status, "doing");
+        let steps = tasks[0].subtasks.as_ref().unwrap();
+        assert_eq!(steps[0].status, "done");
+        assert_eq!(steps[1].status, "cancelled");
+        assert_eq!(steps[1].subtasks.as_ref().unwrap()[0].name, "Detail #later");
+        assert_eq!(steps[1].subtasks.as_ref().unwrap()[0].id, 4);
+    }
+
+    #[test]
+    fn test_parse_obsidian_wikilinks_in_name() {
+        let input = "- [ ] Read [[Book]] notes 📅 2024-01-15\n- [ ] See [[Projects/Plan|the plan]] and ]] stray\n";
+        let tasks = parse_obsidian_tasks(input, default_date()).unwrap();
+        assert_eq!(tasks[0].name, "Read Book notes");
+        assert_eq!(tasks[1].name, "See the plan and stray");
+
+        // og の Markdown に書き出して読み直しても名前が変わらない
+        let markdown = crate::markdown_formatter::format_tasks_to_markdown_document(&tasks);
+        let reparsed = crate::markdown_parser::parse_markdown_document_to_tasks(&markdown, default_date()).unwrap();
+        assert_eq!(reparsed[0].name, "Read Book notes");
+        assert_eq!(reparsed[1].name, "See the plan and stray");
+    }
+
+    #[test]
+    fn test_parse_obsidian_errors() {
+        assert_eq!(
+            parse_obsidian_tasks("- [ ] Report 📅 2024-02-30", default_date()).unwrap_err(),
+            ParseError::InvalidDate { field: "due".to_string(), raw: "2024-02-30".to_string() }
+        );
+        assert!(matches!(
+            parse_obsidian_tasks("- [!] Report", default_date()),
+            Err(ParseError::UnknownStatus { marker: '!', .. })
+        ));
+        assert!(matches!(parse_obsidian_tasks("- [ ] ⏫", default_date()), Err(ParseError::NoMatch { .. })));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::task_model::{Frequency, Task};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+// 繰り返しタスクの次回期日を計算する
+// 基準日は due (なければ完了日)。repeat が未設定なら None。
+pub fn next_occurrence(task: &Task, completed_on: NaiveDate) -> Option<NaiveDate> {
+    let frequency = task.repeat.as_ref()?.frequency.as_ref()?;
+    let base = task.due.unwrap_or(completed_on);
+    let next = match frequency {
+        Frequency::Daily => base + Duration::days(1),
+        Frequency::Weekly => base + Duration::days(7),
+        Frequency::Monthly => {
+            let (year, month) = next_month(base.year(), base.month());
+            // 月末を超える日 (例: 1/31 → 2/31) はその月の末日に丸める
+            let day = base.day().min(last_day_of_month(year, month).day());
+            NaiveDate::from_ymd_opt(year, month, day)?
+        }
+        Frequency::Weekdays => {
+            let mut candidate = base + Duration::days(1);
+            while candidate.weekday() == Weekday::Sat || candidate.weekday() == Weekday::Sun {
+                candidate += Duration::days(1);
+            }
+            candidate
+        }
+        Frequency::LastDayOfMonth => {
+            let (year, month) = next_month(base.year(), base.month());
+            last_day_of_month(year, month)
+        }
+    };
+    Some(next)
+}
+
+fn next_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 12 { (year + 1, 1) } else { (year, month + 1) }
+}
+
+fn last_day_of_month(year: i32, month: u32) -> NaiveDate {
+    let (next_year, next_month) = next_month(year, month);
+    NaiveDate::from_ymd_opt(next_year, next_month, 1).unwrap() - Duration::days(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_model::RepeatInfo;
+
+    fn create_repeating_task(frequency: Frequency, due: Option<NaiveDate>) -> Task {
+        Task {
+            name: "Repeating".to_string(),
+            status: "done".to_string(),
+            priority: "N".to_string(),
+            id: 1,
+            created: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+            display_order: 1,
+            due,
+            updated: None,
+            completed: None,
+            project: None,
+            contexts: None,
+            notes: None,
+            tags: None,
+            tags_kv: None,
+            subtasks: None,
+            extra: None,
+            repeat: Some(RepeatInfo { frequency: Some(frequency) }),
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_last_day_of_month_rollover_non_leap_year() {
+        let task = create_repeating_task(Frequency::LastDayOfMonth, Some(date(2023, 1, 31)));
+        assert_eq!(next_occurrence(&task, date(2023, 1, 31)), Some(date(2023, 2, 28)));
+
+        let task = create_repeating_task(Frequency::LastDayOfMonth, Some(date(2023, 12, 31)));
+        assert_eq!(next_occurrence(&task, date(2023, 12, 31)), Some(date(2024, 1, 31)));
+    }
+
+    #[test]
+    fn test_monthly_clamps_to_month_end() {
+        let task = create_repeating_task(Frequency::Monthly, Some(date(2023, 1, 31)));
+        assert_eq!(next_occurrence(&task, date(2023, 1, 31)), Some(date(2023, 2, 28)));
+    }
+
+    #[test]
+    fn test_weekdays_skips_weekend() {
+        // 2024-06-07 is a Friday
+        let task = create_repeating_task(Frequency::Weekdays, Some(date(2024, 6, 7)));
+        assert_eq!(next_occurrence(&task, date(2024, 6, 7)), Some(date(2024, 6, 10)));
+
+        // 2024-06-04 is a Tuesday
+        let task = create_repeating_task(Frequency::Weekdays, Some(date(2024, 6, 4)));
+        assert_eq!(next_occurrence(&task, date(2024, 6, 4)), Some(date(2024, 6, 5)));
+    }
+
+    #[test]
+    fn test_falls_back_to_completed_date_without_due() {
+        let task = create_repeating_task(Frequency::Weekly, None);
+        assert_eq!(next_occurrence(&task, date(2024, 6, 4)), Some(date(2024, 6, 11)));
+    }
+
+    #[test]
+    fn test_no_repeat_returns_none() {
+        let mut task = create_repeating_task(Frequency::Daily, None);
+        task.repeat = Some(RepeatInfo::default());
+        assert_eq!(next_occurrence(&task, date(2024, 6, 4)), None);
+        task.repeat = None;
+        assert_eq!(next_occurrence(&task, date(2024, 6, 4)), None);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::color::{colorize, AnsiColor};
+use crate::filter::is_overdue;
+use crate::task_model::{flatten_all, Task};
+use chrono::NaiveDate;
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+// 端末表示用の色付け。ファイルに書き出す Markdown は常にプレーンなまま。
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Always,
+    Never,
+    Auto, // 標準出力が端末で、NO_COLOR が未設定なら色付け
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            "auto" => Ok(ColorChoice::Auto),
+            _ => Err(format!("Invalid color choice '{}' (expected always, never or auto)", s)),
+        }
+    }
+}
+
+impl ColorChoice {
+    pub fn should_color_stdout(&self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal(),
+        }
+    }
+}
+
+fn style_for(task: &Task, today: NaiveDate) -> Option<AnsiColor> {
+    if task.status.eq_ignore_ascii_case("done") || task.status.eq_ignore_ascii_case("skipped") {
+        Some(AnsiColor::Dim)
+    } else if is_overdue(task, today) {
+        Some(AnsiColor::Red)
+    } else if task.status.eq_ignore_ascii_case("doing") {
+        Some(AnsiColor::Bold)
+    } else {
+        None
+    }
+}
+
+// format_tasks_to_markdown_document の出力を行ごとに色付けする。
+// フォーマッタは1タスク1行を深さ優先で出力するため、行と flatten_all の順序が一致する。
+pub fn colorize_markdown_document(tasks: &[Task], markdown: &str, today: NaiveDate) -> String {
+    let mut flat_tasks = flatten_all(tasks);
+    markdown
+        .split('\n')
+        .map(|line| match flat_tasks.next().and_then(|task| style_for(task, today)) {
+            Some(style) => colorize(line, style),
+            None => line.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// --format table: GitHub などに貼るための Markdown の表。サブタスクは親の直後の行に置き、
+// 深さ分の字下げと "↳ " を Name の先頭に付ける。列幅は各列の最長のセルにそろえる
+const TABLE_HEADER: [&str; 5] = ["Status", "Priority", "Name", "Due", "Project"];
+
+pub fn render_table(tasks: &[Task]) -> String {
+    let mut rows: Vec<[String; 5]> = vec![TABLE_HEADER.map(String::from)];
+    collect_table_rows(tasks, 0, &mut rows);
+
+    let mut widths = [3; 5]; // 区切り行の "---" より狭くしない
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: &[String]| -> String {
+        let padded: Vec<String> = cells.iter().zip(widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        format!("| {} |\n", padded.join(" | "))
+    };
+    let separator: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+
+    let mut output = format_row(&rows[0]);
+    output.push_str(&format_row(&separator));
+    for row in &rows[1..] {
+        output.push_str(&format_row(row));
+    }
+    output
+}
+
+fn collect_table_rows(tasks: &[Task], depth: usize, rows: &mut Vec<[String; 5]>) {
+    for task in tasks {
+        let prefix = if depth == 0 { String::new() } else { format!("{}↳ ", "  ".repeat(depth - 1)) };
+        rows.push([
+            escape_table_cell(&task.status),
+            escape_table_cell(&task.priority),
+            format!("{}{}", prefix, escape_table_cell(&task.name)),
+            task.due.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+            task.project.as_deref().map(escape_table_cell).unwrap_or_default(),
+        ]);
+        if let Some(subtasks) = &task.subtasks {
+            collect_table_rows(subtasks, depth + 1, rows);
+        }
+    }
+}
+
+// セル内の "|" は列の区切りと解釈されないよう "\|" に、改行は空白にする
+fn escape_table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_formatter::format_tasks_to_markdown_document;
+    use crate::markdown_parser::parse_markdown_document_to_tasks;
+
+    #[test]
+    fn test_colorize_by_status() {
+        let md_doc = "\
+- [x] [[Done]] id:1
+    - [>] [[Doing]] id:2
+- [ ] [[Overdue]] id:3 due:2024-06-01
+- [ ] [[Plain]] id:4";
+        let today = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+        let tasks = parse_markdown_document_to_tasks(md_doc, today).unwrap();
+        let markdown = format_tasks_to_markdown_document(&tasks);
+        let colored = colorize_markdown_document(&tasks, &markdown, today);
+        let lines: Vec<&str> = colored.lines().collect();
+        assert!(lines[0].starts_with(AnsiColor::Dim.code()) && lines[0].ends_with("\x1b[0m"));
+        assert!(lines[1].starts_with(AnsiColor::Bold.code()));
+        assert!(lines[2].starts_with(AnsiColor::Red.code()));
+        assert!(!lines[3].contains('\x1b'));
+    }
+
+    #[test]
+    fn test_color_choice_from_str() {
+        assert_eq!("always".parse::<ColorChoice>(), Ok(ColorChoice::Always));
+        assert_eq!("Never".parse::<ColorChoice>(), Ok(ColorChoice::Never));
+        assert_eq!("auto".parse::<ColorChoice>(), Ok(ColorChoice::Auto));
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+        assert!(!ColorChoice::Never.should_color_stdout());
+    }
+
+    #[test]
+    fn test_render_table() {
+        let md_doc = "\
+- [ ] (A) [[Write a|b report]] id:1 due:2024-06-01 +work
+    - [x] (N) [[Outline]] id:2";
+        let tasks = parse_markdown_document_to_tasks(md_doc, NaiveDate::from_ymd_opt(2024, 6, 10).unwrap()).unwrap();
+        let table = render_table(&tasks);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "| Status | Priority | Name              | Due        | Project |");
+        assert_eq!(lines[1], "| ------ | -------- | ----------------- | ---------- | ------- |");
+        assert_eq!(lines[2], format!("| {:<6} | A        | Write a\\|b report | 2024-06-01 | work    |", tasks[0].status));
+        assert_eq!(lines[3], format!("| {:<6} | N        | ↳ Outline         |            |         |", tasks[0].subtasks.as_ref().unwrap()[0].status));
+    }
+}
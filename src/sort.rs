@@ -0,0 +1,221 @@
+use crate::task_model::Task;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+// og fmt --sort-by のソートキー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,     // 大文字小文字を区別しない
+    Priority, // A < B < ... < Z < N (優先度なしは最後)
+    Due,      // 期限なしは最後
+    Id,
+    Created,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "name" => Ok(SortKey::Name),
+            "priority" => Ok(SortKey::Priority),
+            "due" => Ok(SortKey::Due),
+            "id" => Ok(SortKey::Id),
+            "created" => Ok(SortKey::Created),
+            _ => Err(format!("Invalid sort key '{}' (expected name, priority, due, id or created)", s)),
+        }
+    }
+}
+
+// key による 2 タスクの比較 (sort_tasks と同じ順序)
+pub fn compare(a: &Task, b: &Task, key: SortKey) -> Ordering {
+    match key {
+        SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        SortKey::Priority => {
+            // (N) は優先度なし。長さ優先で比較し AA を Z の後ろにする
+            let rank = |p: &str| (p == "N", p.len(), p.to_string());
+            rank(&a.priority).cmp(&rank(&b.priority))
+        }
+        SortKey::Due => match (a.due, b.due) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        },
+        SortKey::Id => a.id.cmp(&b.id),
+        SortKey::Created => a.created.cmp(&b.created),
+    }
+}
+
+// トップレベルのタスクのみを安定ソートし、display_order を新しい並びの 1, 2, 3, ... に振り直す
+pub fn sort_tasks(tasks: &mut [Task], key: SortKey) {
+    tasks.sort_by(|a, b| compare(a, b, key));
+    for (index, task) in tasks.iter_mut().enumerate() {
+        task.display_order = (index + 1) as i64;
+    }
+}
+
+// 全階層の subtasks を同じキーで安定ソートする (subtasks: None のタスクはそのまま)。
+// display_order は兄弟の中での順 (親ごとに 1 から) に振り直す。og apply のサブタスクの採番と同じ
+pub fn sort_tasks_recursive(tasks: &mut [Task], key: SortKey) {
+    sort_tasks(tasks, key);
+    for task in tasks.iter_mut() {
+        if let Some(subtasks) = &mut task.subtasks {
+            sort_tasks_recursive(subtasks, key);
+        }
+    }
+}
+
+// 0 -> A, 12 -> M, 13 -> O, 24 -> Z, 25 -> AA (compare の順序と一致する)。
+// 1 文字の N は「優先度なし」なので飛ばす (NN などの 2 文字以上は使う)
+fn priority_for_rank(rank: usize) -> String {
+    let letter = |index: usize| (b'A' + (index % 26) as u8) as char;
+    match rank {
+        0..=12 => letter(rank).to_string(),
+        13..=24 => letter(rank + 1).to_string(),
+        _ => letter(rank - 25).to_string().repeat((rank - 25) / 26 + 2),
+    }
+}
+
+// トップレベルの (N) 以外の優先度を A, B, C, ... に詰め直す (例: A, C, E -> A, B, C)。
+// 同じ優先度同士は同じ優先度のまま、順序関係も保つ。タスクの並び順と subtasks は変えない
+pub fn normalize_priorities(tasks: &mut [Task]) {
+    let rank = |p: &str| (p.len(), p.to_string());
+    let mut used: Vec<(usize, String)> = tasks.iter().filter(|t| t.priority != "N").map(|t| rank(&t.priority)).collect();
+    used.sort();
+    used.dedup();
+    for task in tasks.iter_mut().filter(|t| t.priority != "N") {
+        let index = used.binary_search(&rank(&task.priority)).unwrap();
+        task.priority = priority_for_rank(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn create_named_task(id: i64, name: &str, subtasks: Option<Vec<Task>>) -> Task {
+        Task {
+            name: name.to_string(),
+            status: "open".to_string(),
+            priority: "N".to_string(),
+            id,
+            created: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            display_order: id,
+            due: None,
+            updated: None,
+            completed: None,
+            project: None,
+            contexts: None,
+            notes: None,
+            tags: None,
+            tags_kv: None,
+            subtasks,
+            extra: None,
+            repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
+        }
+    }
+
+    fn names(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    fn three_level_tree() -> Vec<Task> {
+        let grandchildren = vec![create_named_task(4, "zeta", None), create_named_task(5, "Alpha", None)];
+        let children = vec![create_named_task(3, "mike", Some(grandchildren)), create_named_task(6, "bravo", None)];
+        vec![create_named_task(1, "Yankee", Some(children)), create_named_task(2, "charlie", None)]
+    }
+
+    #[test]
+    fn test_sort_tasks_recursive_by_name() {
+        let mut tasks = three_level_tree();
+        sort_tasks_recursive(&mut tasks, SortKey::Name);
+        assert_eq!(names(&tasks), vec!["charlie", "Yankee"]);
+        let children = tasks[1].subtasks.as_ref().unwrap();
+        assert_eq!(names(children), vec!["bravo", "mike"]);
+        assert_eq!(names(children[1].subtasks.as_ref().unwrap()), vec!["Alpha", "zeta"]);
+        assert!(tasks[0].subtasks.is_none());
+        assert!(children[0].subtasks.is_none());
+    }
+
+    #[test]
+    fn test_sort_tasks_recursive_renumbers_per_sibling_group() {
+        let mut tasks = vec![
+            create_named_task(1, "Home", Some(vec![create_named_task(2, "wash", None), create_named_task(3, "cook", None), create_named_task(4, "buy", None)])),
+            create_named_task(5, "Work", Some(vec![create_named_task(6, "report", None), create_named_task(7, "email", None)])),
+        ];
+        sort_tasks_recursive(&mut tasks, SortKey::Name);
+        let orders = |tasks: &[Task]| tasks.iter().map(|t| (t.name.clone(), t.display_order)).collect::<Vec<_>>();
+        assert_eq!(orders(&tasks), vec![("Home".to_string(), 1), ("Work".to_string(), 2)]);
+        // 親子関係は保ったまま、子はそれぞれの親の中で 1 から
+        assert_eq!(
+            orders(tasks[0].subtasks.as_ref().unwrap()),
+            vec![("buy".to_string(), 1), ("cook".to_string(), 2), ("wash".to_string(), 3)]
+        );
+        assert_eq!(orders(tasks[1].subtasks.as_ref().unwrap()), vec![("email".to_string(), 1), ("report".to_string(), 2)]);
+        assert_eq!(tasks[1].subtasks.as_ref().unwrap()[0].id, 7);
+    }
+
+    #[test]
+    fn test_sort_tasks_top_level_only() {
+        let mut tasks = three_level_tree();
+        sort_tasks(&mut tasks, SortKey::Name);
+        assert_eq!(names(&tasks), vec!["charlie", "Yankee"]);
+        assert_eq!(names(tasks[1].subtasks.as_ref().unwrap()), vec!["mike", "bravo"]);
+    }
+
+    #[test]
+    fn test_priority_order_puts_none_last() {
+        let mut tasks = vec![create_named_task(1, "a", None), create_named_task(2, "b", None), create_named_task(3, "c", None)];
+        tasks[0].priority = "N".to_string();
+        tasks[1].priority = "B".to_string();
+        tasks[2].priority = "A".to_string();
+        sort_tasks(&mut tasks, SortKey::Priority);
+        assert_eq!(names(&tasks), vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_normalize_priorities_compresses_gaps() {
+        let mut tasks: Vec<Task> = ["E", "A", "N", "C"].iter().enumerate()
+            .map(|(i, p)| Task { priority: p.to_string(), ..create_named_task(i as i64 + 1, &format!("was {}", p), None) })
+            .collect();
+        normalize_priorities(&mut tasks);
+        let pairs: Vec<(&str, &str)> = tasks.iter().map(|t| (t.name.as_str(), t.priority.as_str())).collect();
+        assert_eq!(pairs, vec![("was E", "C"), ("was A", "A"), ("was N", "N"), ("was C", "B")]);
+    }
+
+    #[test]
+    fn test_normalize_priorities_keeps_ties_and_subtasks() {
+        let mut child = create_named_task(3, "child", None);
+        child.priority = "D".to_string();
+        let mut tasks = vec![create_named_task(1, "a", Some(vec![child])), create_named_task(2, "b", None), create_named_task(4, "c", None)];
+        tasks[0].priority = "C".to_string();
+        tasks[1].priority = "Z".to_string();
+        tasks[2].priority = "C".to_string();
+        normalize_priorities(&mut tasks);
+        assert_eq!(tasks.iter().map(|t| t.priority.as_str()).collect::<Vec<_>>(), vec!["A", "B", "A"]);
+        assert_eq!(tasks[0].subtasks.as_ref().unwrap()[0].priority, "D");
+        assert_eq!(priority_for_rank(25), "AA");
+    }
+
+    #[test]
+    fn test_normalize_priorities_never_produces_n() {
+        // 14 種類の優先度を詰めても (N) にならず、順序も保つ
+        let priorities = ["A", "C", "E", "G", "I", "K", "M", "O", "Q", "S", "U", "W", "Y", "AA"];
+        let mut tasks: Vec<Task> = (1..=14).map(|id| create_named_task(id, "t", None)).collect();
+        for (task, priority) in tasks.iter_mut().zip(priorities) {
+            task.priority = priority.to_string();
+        }
+        normalize_priorities(&mut tasks);
+        let normalized: Vec<&str> = tasks.iter().map(|t| t.priority.as_str()).collect();
+        assert_eq!(normalized, vec!["A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "O"]);
+        assert_eq!(priority_for_rank(24), "Z");
+        assert_eq!(priority_for_rank(26), "BB");
+        assert_eq!(priority_for_rank(51), "AAA");
+    }
+}
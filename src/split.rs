@@ -0,0 +1,287 @@
+use crate::task_model::{compact_ids, Task};
+use chrono::NaiveDate;
+use regex::{Captures, Regex};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+// og split --by の分割キー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitKey {
+    Project,
+    Tag,    // 複数タグを持つタスクは各タグのファイルに出力する
+    Status,
+}
+
+impl FromStr for SplitKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "project" => Ok(SplitKey::Project),
+            "tag" => Ok(SplitKey::Tag),
+            "status" => Ok(SplitKey::Status),
+            _ => Err(format!("Invalid split key '{}' (expected project, tag or status)", s)),
+        }
+    }
+}
+
+impl SplitKey {
+    // --name-template でこのキーの値を表すプレースホルダー名
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            SplitKey::Project => "project",
+            SplitKey::Tag => "tag",
+            SplitKey::Status => "status",
+        }
+    }
+}
+
+// キーを持たないタスクの出力先 (_none.md)
+pub const NO_KEY_NAME: &str = "_none";
+
+// トップレベルのタスクをキーごとに振り分ける。サブタスクは親と同じグループに入る。
+// 各グループ内で ID を 1 から (深さ優先順に) 振り直し、display_order も 1..N にする。
+// depends は同じグループ内のタスクのみ付け替え、グループ外への参照は削除する。
+pub fn split_tasks(tasks: &[Task], key: SplitKey) -> BTreeMap<String, Vec<Task>> {
+    let mut groups: BTreeMap<String, Vec<Task>> = BTreeMap::new();
+    for task in tasks {
+        for name in group_names(task, key) {
+            groups.entry(name).or_default().push(task.clone());
+        }
+    }
+    for group in groups.values_mut() {
+        renumber(group);
+    }
+    groups
+}
+
+// 出力ファイル名のテンプレート (og split / og archive --output-dir の --name-template)。
+// {project} / {status} / {tag} / {date} を置き換え、値の中のファイル名に使えない文字は '_' にする
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate(String);
+
+// グループを表すプレースホルダーの値 ([("project", "home")] など)
+pub type NameValues = Vec<(&'static str, String)>;
+
+const NAME_TEMPLATE_PLACEHOLDERS: [&str; 4] = ["project", "status", "tag", "date"];
+const PLACEHOLDER_RE_STR: &str = r"\{(?P<name>[^{}]*)\}";
+
+impl FromStr for NameTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() || s.contains('/') || s.contains('\\') {
+            return Err(format!("Invalid name template '{}' (expected a file name such as '{{project}}-tasks.md')", s));
+        }
+        let placeholder_re = Regex::new(PLACEHOLDER_RE_STR).unwrap();
+        if let Some(caps) = placeholder_re.captures_iter(s).find(|caps| !NAME_TEMPLATE_PLACEHOLDERS.contains(&&caps["name"])) {
+            return Err(format!("Unknown placeholder '{}' in name template '{}' (expected {{project}}, {{status}}, {{tag}} or {{date}})", &caps[0], s));
+        }
+        Ok(NameTemplate(s.to_string()))
+    }
+}
+
+impl NameTemplate {
+    // og split で --name-template がないときの "<キー>.md"
+    pub fn for_split_key(key: SplitKey) -> NameTemplate {
+        NameTemplate(format!("{{{}}}.md", key.placeholder()))
+    }
+
+    pub fn uses(&self, placeholder: &str) -> bool {
+        self.0.contains(&format!("{{{}}}", placeholder))
+    }
+
+    // values にないプレースホルダー (og split --by project での {status} など) はエラー
+    pub fn render(&self, values: &[(&str, String)]) -> Result<String, String> {
+        let mut missing: Option<String> = None;
+        let name = Regex::new(PLACEHOLDER_RE_STR).unwrap().replace_all(&self.0, |caps: &Captures| {
+            match values.iter().find(|(placeholder, _)| *placeholder == &caps["name"]) {
+                Some((_, value)) => sanitize_file_stem(value),
+                None => {
+                    missing.get_or_insert_with(|| caps[0].to_string());
+                    String::new()
+                }
+            }
+        });
+        match missing {
+            Some(placeholder) => Err(format!("Placeholder '{}' in name template '{}' is not available here", placeholder, self.0)),
+            None => Ok(name.into_owned()),
+        }
+    }
+}
+
+// グループごとの出力ファイル名を作る ({date} は date)。
+// サニタイズ後に別のグループと同じ名前になる場合 ("a/b" と "a_b" など) は、黙って混ぜずにエラーにする
+pub fn assign_file_names(template: &NameTemplate, groups: &[NameValues], date: NaiveDate) -> Result<Vec<String>, String> {
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let mut file_names = Vec::new();
+    for values in groups {
+        let label = values.iter().map(|(placeholder, value)| format!("{} '{}'", placeholder, value)).collect::<Vec<_>>().join(", ");
+        let mut values = values.clone();
+        values.push(("date", date.format("%Y-%m-%d").to_string()));
+        let file_name = template.render(&values)?;
+        if let Some(other) = seen.insert(file_name.clone(), label.clone()) {
+            return Err(format!("{} and {} would both be written to '{}'; use a --name-template that tells them apart", other, label, file_name));
+        }
+        file_names.push(file_name);
+    }
+    Ok(file_names)
+}
+
+// og archive --output-dir: トップレベルのタスクを、テンプレートが使う {project} / {status} の値ごとにまとめる。
+// project のないタスクは _none、status は小文字にする
+pub fn group_by_name_template(tasks: Vec<Task>, template: &NameTemplate) -> Vec<(NameValues, Vec<Task>)> {
+    let mut groups: BTreeMap<NameValues, Vec<Task>> = BTreeMap::new();
+    for task in tasks {
+        let mut values = Vec::new();
+        if template.uses("project") {
+            let project = task.project.as_deref().map(str::trim).filter(|p| !p.is_empty()).unwrap_or(NO_KEY_NAME);
+            values.push(("project", project.to_string()));
+        }
+        if template.uses("status") {
+            values.push(("status", task.status.to_lowercase()));
+        }
+        groups.entry(values).or_default().push(task);
+    }
+    groups.into_iter().collect()
+}
+
+fn group_names(task: &Task, key: SplitKey) -> Vec<String> {
+    let names: Vec<String> = match key {
+        SplitKey::Project => task.project.iter().cloned().collect(),
+        SplitKey::Tag => {
+            let mut tags = task.tags.clone().unwrap_or_default();
+            tags.dedup();
+            tags
+        }
+        SplitKey::Status => vec![task.status.to_lowercase()],
+    };
+    // ファイル名へのサニタイズは assign_file_names で行う (サニタイズ後の衝突を検出するため)
+    let names: Vec<String> = names.iter().map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect();
+    if names.is_empty() {
+        vec![NO_KEY_NAME.to_string()]
+    } else {
+        names
+    }
+}
+
+// ファイル名に使えない文字を '_' に置き換える
+pub(crate) fn sanitize_file_stem(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| if c == '/' || c == '\\' || c == ':' || c.is_control() { '_' } else { c })
+        .collect()
+}
+
+fn renumber(tasks: &mut [Task]) {
+    compact_ids(tasks);
+    for (index, task) in tasks.iter_mut().enumerate() {
+        task.display_order = (index + 1) as i64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_sample_task(id: i64, project: Option<&str>, tags: Option<Vec<&str>>, subtasks: Option<Vec<Task>>) -> Task {
+        Task {
+            name: format!("Task {}", id),
+            status: "open".to_string(),
+            priority: "N".to_string(),
+            id,
+            created: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            display_order: id,
+            due: None,
+            updated: None,
+            completed: None,
+            project: project.map(|p| p.to_string()),
+            contexts: None,
+            notes: None,
+            tags: tags.map(|t| t.into_iter().map(|s| s.to_string()).collect()),
+            tags_kv: None,
+            subtasks,
+            extra: None,
+            repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_split_by_project_renumbers_and_keeps_subtasks() {
+        let child = create_sample_task(3, None, None, None);
+        let tasks = vec![
+            create_sample_task(1, Some("home"), None, None),
+            create_sample_task(2, Some("work"), None, Some(vec![child])),
+            create_sample_task(4, None, None, None),
+        ];
+        let groups = split_tasks(&tasks, SplitKey::Project);
+        assert_eq!(groups.keys().cloned().collect::<Vec<_>>(), vec!["_none", "home", "work"]);
+
+        let work = &groups["work"];
+        assert_eq!(work.len(), 1);
+        assert_eq!(work[0].id, 1);
+        assert_eq!(work[0].name, "Task 2");
+        assert_eq!(work[0].subtasks.as_ref().unwrap()[0].id, 2);
+        assert_eq!(groups["_none"][0].id, 1);
+    }
+
+    #[test]
+    fn test_split_by_tag_duplicates_multi_tag_tasks() {
+        let mut tasks = vec![
+            create_sample_task(1, None, Some(vec!["a", "b"]), None),
+            create_sample_task(2, None, Some(vec!["b"]), None),
+        ];
+        tasks[1].depends = Some(vec![1]);
+        let groups = split_tasks(&tasks, SplitKey::Tag);
+        assert_eq!(groups["a"].len(), 1);
+        assert_eq!(groups["b"].len(), 2);
+        assert_eq!(groups["b"][1].id, 2);
+        assert_eq!(groups["b"][1].depends, Some(vec![1]));
+        assert_eq!(groups["b"][1].display_order, 2);
+    }
+
+    #[test]
+    fn test_name_template_sanitizes_and_rejects_collisions() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let tasks = vec![create_sample_task(1, Some("a/b"), None, None), create_sample_task(2, Some("c:d"), None, None)];
+        let groups = split_tasks(&tasks, SplitKey::Project);
+        assert_eq!(groups.keys().cloned().collect::<Vec<_>>(), vec!["a/b", "c:d"]);
+
+        let template: NameTemplate = "{project}-{date}.md".parse().unwrap();
+        let values: Vec<_> = groups.keys().map(|name| vec![("project", name.clone())]).collect();
+        assert_eq!(assign_file_names(&template, &values, date).unwrap(), vec!["a_b-2024-06-01.md", "c_d-2024-06-01.md"]);
+
+        // サニタイズ後に同じ名前になるグループはエラー
+        let values = vec![vec![("project", "a/b".to_string())], vec![("project", "a_b".to_string())]];
+        let err = assign_file_names(&template, &values, date).unwrap_err();
+        assert!(err.contains("'a_b-2024-06-01.md'"), "{}", err);
+
+        // --by project のグループに {status} はない。未知のプレースホルダーとパスは受け付けない
+        let template: NameTemplate = "{status}.md".parse().unwrap();
+        assert!(assign_file_names(&template, &[vec![("project", "x".to_string())]], date).is_err());
+        assert!("{owner}.md".parse::<NameTemplate>().is_err());
+        assert!("out/{project}.md".parse::<NameTemplate>().is_err());
+    }
+
+    #[test]
+    fn test_group_by_name_template() {
+        let mut tasks = vec![
+            create_sample_task(1, Some("home"), None, None),
+            create_sample_task(2, None, None, None),
+            create_sample_task(3, Some("home"), None, None),
+        ];
+        tasks[2].status = "CANCELLED".to_string();
+        let template: NameTemplate = "{project}-{status}.md".parse().unwrap();
+        let groups = group_by_name_template(tasks, &template);
+        let keys: Vec<_> = groups.iter().map(|(values, group)| (values.clone(), group.len())).collect();
+        assert_eq!(keys, vec![
+            (vec![("project", "_none".to_string()), ("status", "open".to_string())], 1),
+            (vec![("project", "home".to_string()), ("status", "cancelled".to_string())], 1),
+            (vec![("project", "home".to_string()), ("status", "open".to_string())], 1),
+        ]);
+    }
+}
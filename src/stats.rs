@@ -0,0 +1,50 @@
+use crate::task_model::{flatten_all, Task};
+use std::collections::HashMap;
+
+// 全タスク (サブタスク含む) のタグ出現数を数える。#key:value のタグは "key:value" として数える
+pub fn count_tags(tasks: &[Task]) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for task in flatten_all(tasks) {
+        for tag in task.tags.iter().flatten() {
+            *counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+        for (key, value) in task.tags_kv.iter().flatten() {
+            *counts.entry(format!("{}:{}", key, value)).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+// "#tag: N" 形式の集計表。出現数の多い順 (同数ならタグ名順)、min_count 未満のタグは省く
+pub fn format_tag_counts(counts: &HashMap<String, usize>, min_count: usize) -> String {
+    let mut entries: Vec<(&String, &usize)> = counts.iter().filter(|(_, &count)| count >= min_count).collect();
+    entries.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    entries.iter().map(|(tag, count)| format!("#{}: {}\n", tag, count)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_parser::parse_markdown_document_to_tasks;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_count_tags_across_tasks_and_subtasks() {
+        let md_doc = "\
+- [ ] [[One]] #work #urgent
+- [ ] [[Two]] #work
+    - [ ] [[Two child]] #urgent #home
+- [x] [[Three]] #work #sprint:42
+- [ ] [[Four]] #home #sprint:42";
+        let tasks = parse_markdown_document_to_tasks(md_doc, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
+        let counts = count_tags(&tasks);
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts["work"], 3);
+        assert_eq!(counts["urgent"], 2);
+        assert_eq!(counts["home"], 2);
+        assert_eq!(counts["sprint:42"], 2);
+
+        assert_eq!(format_tag_counts(&counts, 1), "#work: 3\n#home: 2\n#sprint:42: 2\n#urgent: 2\n");
+        assert_eq!(format_tag_counts(&counts, 3), "#work: 3\n");
+    }
+}
@@ -0,0 +1,126 @@
+use crate::error::OgError;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// ステータスマーカー文字 ([x] の x) とステータス文字列の対応表。
+// 既定値は仕様書 B.3 のとおり。~/.config/og/status-map.toml で上書きできる:
+//   "/" = "doing"
+//   "!" = "waiting"
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusMap {
+    char_to_status: HashMap<char, String>,
+}
+
+const BUILTIN_MAPPING: [(char, &str); 8] = [
+    (' ', "open"), // 仕様書では "NONE"
+    ('p', "pending"),
+    ('>', "doing"),
+    ('w', "waiting"),
+    ('x', "done"),
+    ('c', "cancelled"),
+    ('-', "skipped"), // コメントアウト扱い: 行は残すが作業対象外
+    ('?', "unknown"),
+];
+
+impl Default for StatusMap {
+    fn default() -> Self {
+        StatusMap {
+            char_to_status: BUILTIN_MAPPING.iter().map(|(c, s)| (*c, s.to_string())).collect(),
+        }
+    }
+}
+
+impl StatusMap {
+    // 既定の対応表 (パーサー/フォーマッタの既定値として共有する)
+    pub fn builtin() -> &'static StatusMap {
+        static BUILTIN: OnceLock<StatusMap> = OnceLock::new();
+        BUILTIN.get_or_init(StatusMap::default)
+    }
+
+    // TOML の上書き設定を既定の対応表に適用する。
+    // 上書きしたステータスの既定の文字は無効になる ("/" = "doing" なら ">" は未知の文字)。
+    pub fn from_toml(content: &str) -> Result<StatusMap, OgError> {
+        let overrides: HashMap<String, String> = toml::from_str(content)
+            .map_err(|e| OgError::Validation(format!("Invalid status map: {}", e)))?;
+        let mut map = StatusMap::default();
+        for (marker, status) in overrides {
+            let mut chars = marker.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                return Err(OgError::Validation(format!("Invalid status marker '{}' in status map: must be a single character", marker)));
+            };
+            if c == '[' || c == ']' {
+                return Err(OgError::Validation(format!("Invalid status marker '{}' in status map", marker)));
+            }
+            let status = status.to_lowercase();
+            map.char_to_status.retain(|_, s| *s != status);
+            map.char_to_status.insert(c, status);
+        }
+        Ok(map)
+    }
+
+    // ~/.config/og/status-map.toml を読み込む。ファイルがなければ既定の対応表。
+    pub fn load_config() -> Result<StatusMap, OgError> {
+        match config_path() {
+            Some(path) if path.exists() => StatusMap::from_toml(&fs::read_to_string(&path)?),
+            _ => Ok(StatusMap::default()),
+        }
+    }
+
+    // マーカー文字 → ステータス。大文字・小文字は区別しない。未知の文字は None。
+    pub fn status_for(&self, marker: char) -> Option<&str> {
+        self.char_to_status
+            .get(&marker)
+            .or_else(|| self.char_to_status.get(&marker.to_ascii_lowercase()))
+            .map(|s| s.as_str())
+    }
+
+    // ステータス → マーカー文字。"none" は "open" と同じ扱い。対応がなければ '?'。
+    pub fn char_for(&self, status: &str) -> char {
+        let status = status.to_ascii_lowercase();
+        let status = if status == "none" { "open".to_string() } else { status };
+        self.char_to_status
+            .iter()
+            .filter(|(_, s)| **s == status)
+            .map(|(c, _)| *c)
+            .min() // 同じステータスに複数の文字がある場合も出力を決定的にする
+            .unwrap_or('?')
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    Some(dirs::home_dir()?.join(".config").join("og").join("status-map.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_mapping() {
+        let map = StatusMap::builtin();
+        assert_eq!(map.status_for('x'), Some("done"));
+        assert_eq!(map.status_for('X'), Some("done"));
+        assert_eq!(map.status_for('!'), None);
+        assert_eq!(map.char_for("DOING"), '>');
+        assert_eq!(map.char_for("NONE"), ' ');
+        assert_eq!(map.char_for("bogus"), '?');
+    }
+
+    #[test]
+    fn test_custom_map_overrides_chars() {
+        let map = StatusMap::from_toml("\"/\" = \"doing\"\n\"!\" = \"waiting\"\n").unwrap();
+        assert_eq!(map.status_for('/'), Some("doing"));
+        assert_eq!(map.status_for('!'), Some("waiting"));
+        assert_eq!(map.status_for('>'), None);
+        assert_eq!(map.status_for('w'), None);
+        assert_eq!(map.char_for("doing"), '/');
+        assert_eq!(map.status_for('x'), Some("done"));
+    }
+
+    #[test]
+    fn test_custom_map_rejects_multi_char_marker() {
+        assert!(matches!(StatusMap::from_toml("\"ab\" = \"doing\"\n"), Err(OgError::Validation(_))));
+    }
+}
@@ -1,9 +1,11 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use chrono::NaiveDate;
+use chrono::{Local, NaiveDate};
+use crate::color::{colorize, AnsiColor};
 
 // A.2.1. 必須キー
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Task {
     pub name: String,
     pub status: String, // TODO: Enum (NONE, PENDING, DOING, WAITING, DONE, CANCELLED, UNKNOWN)
@@ -30,16 +32,746 @@ pub struct Task {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags_kv: Option<HashMap<String, String>>, // キー・値タグ (#area:health, #sprint:42)
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub subtasks: Option<Vec<Task>>, // 再帰的な構造
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extra: Option<HashMap<String, serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub repeat: Option<RepeatInfo>, // 初期仕様では空オブジェクト {}
+    #[serde(default, skip_serializing_if = "is_none_or_empty")]
+    pub depends: Option<Vec<i64>>, // 先行タスクのID (depends:1,4,7)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<u8>, // 進捗率 0〜100 (progress:75)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate_minutes: Option<u32>, // 見積もり時間 (分)。Markdown では estimate:2h30m
+
+    // 上記以外の未知のキー (他ツールが追加したフィールド) をそのまま保持し、JSON 書き出し時に復元する。
+    // extra はユーザー定義情報を入れる明示的なキー、こちらはトップレベルの未知キーの退避先。
+    // Markdown には出力しない。
+    #[serde(flatten)]
+    pub unknown_fields: HashMap<String, serde_json::Value>,
+}
+
+fn is_none_or_empty<T>(value: &Option<Vec<T>>) -> bool {
+    value.as_ref().is_none_or(|v| v.is_empty())
 }
 
 // repeat フィールド用の構造体 (A.2.3)
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
 pub struct RepeatInfo {
-    // 将来的に頻度等のルールを格納
-    // 初期仕様ではフィールドなし
+    // 初期仕様の空オブジェクト {} との互換のため省略可能
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<Frequency>,
+}
+
+// 繰り返しの頻度 (Markdown では repeat:daily のように記述)
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Weekdays,         // 次の平日 (月〜金)
+    LastDayOfMonth,   // 翌月の末日
+}
+
+impl Frequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Frequency::Daily => "daily",
+            Frequency::Weekly => "weekly",
+            Frequency::Monthly => "monthly",
+            Frequency::Weekdays => "weekdays",
+            Frequency::LastDayOfMonth => "last-day-of-month",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Frequency> {
+        match s {
+            "daily" => Some(Frequency::Daily),
+            "weekly" => Some(Frequency::Weekly),
+            "monthly" => Some(Frequency::Monthly),
+            "weekdays" => Some(Frequency::Weekdays),
+            "last-day-of-month" => Some(Frequency::LastDayOfMonth),
+            _ => None,
+        }
+    }
+}
+
+// 見出し・空行・本文などタスク以外の行も保持した Markdown ドキュメント (og fmt --keep-text 用)
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Block {
+    Text(String),     // タスク以外の 1 行 (そのまま出力する)
+    Tasks(Vec<Task>), // 連続するタスク行 (トップレベルのタスク列)
+}
+
+impl Document {
+    // 全ブロックのトップレベルタスクを出現順に列挙する
+    pub fn tasks(&self) -> impl Iterator<Item = &Task> {
+        self.blocks.iter().flat_map(|block| match block {
+            Block::Tasks(tasks) => tasks.iter(),
+            Block::Text(_) => [].iter(),
+        })
+    }
+}
+
+const COMPACT_NAME_WIDTH: usize = 40;
+
+impl Task {
+    // タスク自身とその子孫を深さ優先 (親→子の順) で列挙する
+    pub fn flatten(&self) -> impl Iterator<Item = &Task> {
+        let mut stack: Vec<&Task> = vec![self];
+        std::iter::from_fn(move || {
+            let task = stack.pop()?;
+            if let Some(subtasks) = &task.subtasks {
+                stack.extend(subtasks.iter().rev());
+            }
+            Some(task)
+        })
+    }
+
+    // og list --format compact の 1 行表示。各欄は固定幅:
+    // [ID 5 桁] [優先度] [ステータス 10 文字] [名前 40 文字] [期限 10 文字]
+    // 名前が 40 文字を超える場合は 39 文字 + "…" に切り詰める。期限がなければ "(none)"
+    pub fn to_compact_line(&self) -> String {
+        let name = if self.name.chars().count() > COMPACT_NAME_WIDTH {
+            format!("{}…", self.name.chars().take(COMPACT_NAME_WIDTH - 1).collect::<String>())
+        } else {
+            self.name.clone()
+        };
+        let status: String = self.status.chars().take(10).collect();
+        let due = self.due.map_or("(none)".to_string(), |due| due.format("%Y-%m-%d").to_string());
+        format!("[{:05}] [{}] [{:<10}] [{:<width$}] [{:<10}]", self.id, self.priority, status, name, due, width = COMPACT_NAME_WIDTH)
+    }
+
+    // to_compact_line の端末向け。期限切れの行は赤、優先度 A は太字
+    pub fn to_compact_line_colored(&self, today: NaiveDate) -> String {
+        let line = self.to_compact_line();
+        let line = if crate::filter::is_overdue(self, today) { colorize(&line, AnsiColor::Red) } else { line };
+        if self.priority == "A" {
+            colorize(&line, AnsiColor::Bold)
+        } else {
+            line
+        }
+    }
+
+    // 既定値 (status open、priority N、created は今日、id と display_order は 1) から Task を組み立てる
+    pub fn builder() -> TaskBuilder {
+        TaskBuilder::default()
+    }
+
+    // flatten と同じ順序で各タスクを可変参照で訪問する
+    // (親と子を同時に &mut で保持できないため、イテレータではなくコールバック形式)
+    pub fn flatten_mut<F: FnMut(&mut Task)>(&mut self, f: &mut F) {
+        f(self);
+        if let Some(subtasks) = &mut self.subtasks {
+            for subtask in subtasks {
+                subtask.flatten_mut(f);
+            }
+        }
+    }
+}
+
+/// 20 個のフィールドを全部書かずに Task を作るためのビルダー (Task::builder())。
+/// 指定しなかったフィールドは status open、priority N、created は今日、id と display_order は 1、それ以外は None。
+///
+/// ```
+/// let task = og::task_model::Task::builder().name("Write report").build();
+/// assert_eq!(task.name, "Write report");
+/// assert_eq!((task.status.as_str(), task.priority.as_str(), task.id), ("open", "N", 1));
+/// assert!(task.due.is_none() && task.subtasks.is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct TaskBuilder {
+    task: Task,
+}
+
+impl Default for TaskBuilder {
+    fn default() -> Self {
+        TaskBuilder {
+            task: Task {
+                name: String::new(),
+                status: "open".to_string(),
+                priority: "N".to_string(),
+                id: 1,
+                created: Local::now().date_naive(),
+                display_order: 1,
+                due: None,
+                updated: None,
+                completed: None,
+                project: None,
+                contexts: None,
+                notes: None,
+                tags: None,
+                tags_kv: None,
+                subtasks: None,
+                extra: None,
+                repeat: None,
+                depends: None,
+                progress: None,
+                estimate_minutes: None,
+                unknown_fields: HashMap::new(),
+            },
+        }
+    }
+}
+
+fn strings<I, S>(values: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    values.into_iter().map(Into::into).collect()
+}
+
+impl TaskBuilder {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.task.name = name.into();
+        self
+    }
+
+    pub fn status(mut self, status: impl Into<String>) -> Self {
+        self.task.status = status.into();
+        self
+    }
+
+    pub fn priority(mut self, priority: impl Into<String>) -> Self {
+        self.task.priority = priority.into();
+        self
+    }
+
+    pub fn id(mut self, id: i64) -> Self {
+        self.task.id = id;
+        self
+    }
+
+    pub fn created(mut self, created: NaiveDate) -> Self {
+        self.task.created = created;
+        self
+    }
+
+    pub fn display_order(mut self, display_order: i64) -> Self {
+        self.task.display_order = display_order;
+        self
+    }
+
+    pub fn due(mut self, due: NaiveDate) -> Self {
+        self.task.due = Some(due);
+        self
+    }
+
+    pub fn updated(mut self, updated: NaiveDate) -> Self {
+        self.task.updated = Some(updated);
+        self
+    }
+
+    pub fn completed(mut self, completed: NaiveDate) -> Self {
+        self.task.completed = Some(completed);
+        self
+    }
+
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.task.project = Some(project.into());
+        self
+    }
+
+    pub fn contexts<I, S>(mut self, contexts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.task.contexts = Some(strings(contexts));
+        self
+    }
+
+    pub fn notes(mut self, notes: impl Into<String>) -> Self {
+        self.task.notes = Some(notes.into());
+        self
+    }
+
+    pub fn tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.task.tags = Some(strings(tags));
+        self
+    }
+
+    pub fn tag_kv(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.task.tags_kv.get_or_insert_with(HashMap::new).insert(key.into(), value.into());
+        self
+    }
+
+    pub fn subtasks(mut self, subtasks: Vec<Task>) -> Self {
+        self.task.subtasks = Some(subtasks);
+        self
+    }
+
+    pub fn extra(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.task.extra.get_or_insert_with(HashMap::new).insert(key.into(), value);
+        self
+    }
+
+    pub fn repeat(mut self, frequency: Frequency) -> Self {
+        self.task.repeat = Some(RepeatInfo { frequency: Some(frequency) });
+        self
+    }
+
+    pub fn depends(mut self, depends: Vec<i64>) -> Self {
+        self.task.depends = Some(depends);
+        self
+    }
+
+    pub fn progress(mut self, progress: u8) -> Self {
+        self.task.progress = Some(progress);
+        self
+    }
+
+    pub fn estimate_minutes(mut self, estimate_minutes: u32) -> Self {
+        self.task.estimate_minutes = Some(estimate_minutes);
+        self
+    }
+
+    pub fn build(self) -> Task {
+        self.task
+    }
+}
+
+// Task の JSON 形式を記述する JSON Schema (og schema で出力)
+pub fn task_json_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(Task)).expect("JSON Schema is always serializable")
+}
+
+// display_order を現在の並び順で 1 から振り直す。サブタスクは兄弟ごとに独立して振り直す
+pub fn compact_display_orders(tasks: &mut [Task]) {
+    for (index, task) in tasks.iter_mut().enumerate() {
+        task.display_order = (index + 1) as i64;
+        if let Some(subtasks) = &mut task.subtasks {
+            compact_display_orders(subtasks);
+        }
+    }
+}
+
+// 空のサブタスクリスト (Some(vec![])) を None にそろえる。比較やハッシュの前の正規化にも使う
+pub fn prune_empty_subtasks(tasks: &mut [Task]) {
+    for task in tasks.iter_mut() {
+        if let Some(subtasks) = &mut task.subtasks {
+            prune_empty_subtasks(subtasks);
+            if subtasks.is_empty() {
+                task.subtasks = None;
+            }
+        }
+    }
+}
+
+// ID を 1 から振り直す (og id --compact)。番号はリストの並び順に深さ優先で、
+// 親→子→孫→次の兄弟の順に振る (親の ID は常に子より小さい)。
+// depends も新しい ID に付け替え、リスト内に存在しない ID への参照は削除する。
+// 戻り値は 元ID → 新ID の対応表。
+pub fn compact_ids(tasks: &mut [Task]) -> HashMap<i64, i64> {
+    let mut id_map: HashMap<i64, i64> = HashMap::new();
+    let mut next_id = 1;
+    for task in tasks.iter_mut() {
+        task.flatten_mut(&mut |t| {
+            id_map.entry(t.id).or_insert(next_id);
+            t.id = next_id;
+            next_id += 1;
+        });
+    }
+    for task in tasks.iter_mut() {
+        task.flatten_mut(&mut |t| {
+            if let Some(depends) = &mut t.depends {
+                *depends = depends.iter().filter_map(|id| id_map.get(id).copied()).collect();
+            }
+        });
+    }
+    id_map
+}
+
+// サブタスクを持たない子孫 (葉) のうち done の割合 (0.0〜1.0)。
+// サブタスクのないタスクは自身が葉なので、done なら 1.0、それ以外は 0.0。
+pub fn task_completion_percentage(task: &Task) -> f32 {
+    let leaves: Vec<&Task> = task
+        .flatten()
+        .filter(|t| t.subtasks.as_ref().is_none_or(|subtasks| subtasks.is_empty()))
+        .collect();
+    let done = leaves.iter().filter(|t| t.status.eq_ignore_ascii_case("done")).count();
+    done as f32 / leaves.len() as f32
+}
+
+// task_completion_percentage を "75%" の形式にする
+pub fn completion_percentage_str(task: &Task) -> String {
+    format!("{}%", (task_completion_percentage(task) * 100.0).round() as i64)
+}
+
+// 1d を何時間とみなすか ($OG_HOURS_PER_DAY、既定 8)
+pub fn hours_per_day() -> f64 {
+    std::env::var("OG_HOURS_PER_DAY")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|h| *h > 0.0)
+        .unwrap_or(8.0)
+}
+
+// 見積もり時間の文字列 (90m, 1.5h, 2h30m, 1d など) を分に変換する。
+// 単位は d・h・m をこの順に各 1 回まで並べられ、数値は小数も可。解釈できなければ None
+pub fn parse_duration_minutes(text: &str, hours_per_day: f64) -> Option<u32> {
+    let mut rest = text;
+    let mut total = 0.0;
+    let mut units = ['d', 'h', 'm'].iter();
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let value: f64 = rest[..number_len].parse().ok()?;
+        let unit = rest[number_len..].chars().next()?;
+        // 単位の重複や順序違い (30m2h など) は受け付けない
+        units.find(|u| **u == unit)?;
+        total += value * match unit {
+            'd' => hours_per_day * 60.0,
+            'h' => 60.0,
+            _ => 1.0,
+        };
+        rest = &rest[number_len + 1..];
+    }
+    if text.is_empty() || total > u32::MAX as f64 {
+        return None;
+    }
+    Some(total.round() as u32)
+}
+
+// 分を正規形 XhYm (例: 150 → 2h30m、45 → 0h45m) にする
+pub fn format_duration_minutes(minutes: u32) -> String {
+    format!("{}h{}m", minutes / 60, minutes % 60)
+}
+
+// トップレベルのタスク列に対する flatten
+pub fn flatten_all(tasks: &[Task]) -> impl Iterator<Item = &Task> {
+    tasks.iter().flat_map(|task| task.flatten())
+}
+
+// 階層を持たないタスク管理向けに、サブタスクツリーを平坦化する。
+// 各サブタスクの contexts 先頭に、祖先の名前を separator で連結したコンテキストを追加する
+// (例: 孫タスクには "Grandparent/Parent")。返り値のタスクは subtasks を持たない。
+pub fn flatten_subtasks_to_contexts(tasks: Vec<Task>, separator: &str) -> Vec<Task> {
+    fn walk(mut task: Task, ancestors: &mut Vec<String>, separator: &str, out: &mut Vec<Task>) {
+        let subtasks = task.subtasks.take().unwrap_or_default();
+        if !ancestors.is_empty() {
+            let mut contexts = vec![ancestors.join(separator)];
+            contexts.extend(task.contexts.take().unwrap_or_default());
+            task.contexts = Some(contexts);
+        }
+        let name = task.name.clone();
+        out.push(task);
+
+        ancestors.push(name);
+        for subtask in subtasks {
+            walk(subtask, ancestors, separator, out);
+        }
+        ancestors.pop();
+    }
+
+    let mut out = Vec::new();
+    let mut ancestors = Vec::new();
+    for task in tasks {
+        walk(task, &mut ancestors, separator, &mut out);
+    }
+    out
+}
+
+// オブジェクトのキーを辞書順に並べ直した JSON 値 (serde_json の preserve_order の有無に依存しない)
+fn canonical_json(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<(String, serde_json::Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            serde_json::Value::Object(entries.into_iter().map(|(k, v)| (k, canonical_json(v))).collect())
+        }
+        serde_json::Value::Array(items) => serde_json::Value::Array(items.into_iter().map(canonical_json).collect()),
+        other => other,
+    }
+}
+
+// タスクをキー順をそろえた JSON にし、その SHA-256 を 16 進文字列で返す。
+// 全フィールド (サブタスク・extra を含む) が同じなら同じ値になるので、変更の有無の判定に使う
+pub fn content_hash(task: &Task) -> String {
+    use sha2::{Digest, Sha256};
+    let value = serde_json::to_value(task).expect("Task is always serializable");
+    let bytes = serde_json::to_vec(&canonical_json(value)).expect("JSON values are always serializable");
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+// 全階層のタスクの id → content_hash (og apply --content-hash-file)
+pub fn content_hashes(tasks: &[Task]) -> std::collections::BTreeMap<i64, String> {
+    flatten_all(tasks).map(|task| (task.id, content_hash(task))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_task(id: i64, subtasks: Option<Vec<Task>>) -> Task {
+        let builder = Task::builder().name(format!("Task {}", id)).id(id).display_order(id).created(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+        match subtasks {
+            Some(subtasks) => builder.subtasks(subtasks).build(),
+            None => builder.build(),
+        }
+    }
+
+    #[test]
+    fn test_task_builder_defaults_and_setters() {
+        let task = Task::builder().name("Plain").build();
+        assert_eq!((task.status.as_str(), task.priority.as_str(), task.id, task.display_order), ("open", "N", 1, 1));
+        assert_eq!(task.created, Local::now().date_naive());
+        assert!(task.project.is_none() && task.tags.is_none() && task.extra.is_none() && task.unknown_fields.is_empty());
+
+        let due = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let task = Task::builder()
+            .name("Review")
+            .status("doing")
+            .priority("A")
+            .id(7)
+            .due(due)
+            .project("Work")
+            .contexts(["office"])
+            .tags(vec!["urgent".to_string()])
+            .tag_kv("sprint", "42")
+            .extra("ticket", serde_json::json!("OG-1"))
+            .repeat(Frequency::Weekly)
+            .depends(vec![3])
+            .estimate_minutes(90)
+            .subtasks(vec![Task::builder().name("Child").id(8).build()])
+            .build();
+        assert_eq!((task.status.as_str(), task.priority.as_str(), task.id, task.due), ("doing", "A", 7, Some(due)));
+        assert_eq!(task.contexts, Some(vec!["office".to_string()]));
+        assert_eq!(task.tags_kv.as_ref().unwrap()["sprint"], "42");
+        assert_eq!(task.extra.as_ref().unwrap()["ticket"], "OG-1");
+        assert_eq!(task.repeat.as_ref().unwrap().frequency, Some(Frequency::Weekly));
+        assert_eq!(task.flatten().map(|t| t.id).collect::<Vec<_>>(), vec![7, 8]);
+    }
+
+    fn three_level_tree() -> Vec<Task> {
+        let grandchild = create_test_task(3, None);
+        let child1 = create_test_task(2, Some(vec![grandchild]));
+        let child2 = create_test_task(4, None);
+        let root1 = create_test_task(1, Some(vec![child1, child2]));
+        let root2 = create_test_task(5, Some(vec![]));
+        vec![root1, root2]
+    }
+
+    #[test]
+    fn test_to_compact_line_columns() {
+        let mut task = create_test_task(5, None);
+        task.priority = "A".to_string();
+        task.due = NaiveDate::from_ymd_opt(2024, 6, 1);
+        let line = task.to_compact_line();
+        assert_eq!(line, format!("[00005] [A] [open      ] [Task 5{}] [2024-06-01]", " ".repeat(34)));
+
+        // 40 文字ちょうどは切り詰めず、41 文字なら 40 文字目が "…"。期限なしは "(none)"
+        task.name = "x".repeat(40);
+        task.due = None;
+        assert_eq!(task.to_compact_line(), format!("[00005] [A] [open      ] [{}] [(none)    ]", "x".repeat(40)));
+        task.name = format!("{}yz", "é".repeat(39));
+        let name_column = task.to_compact_line().split("] [").nth(3).unwrap().to_string();
+        assert_eq!(name_column.chars().count(), 40);
+        assert_eq!(name_column, format!("{}…", "é".repeat(39)));
+    }
+
+    #[test]
+    fn test_to_compact_line_colored() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+        let mut task = create_test_task(1, None);
+        assert_eq!(task.to_compact_line_colored(today), task.to_compact_line());
+        task.due = NaiveDate::from_ymd_opt(2024, 6, 1);
+        assert_eq!(task.to_compact_line_colored(today), format!("\x1b[31m{}\x1b[0m", task.to_compact_line()));
+        task.priority = "A".to_string();
+        task.status = "done".to_string();
+        assert_eq!(task.to_compact_line_colored(today), format!("\x1b[1m{}\x1b[0m", task.to_compact_line()));
+    }
+
+    #[test]
+    fn test_flatten_depth_first_order() {
+        let tasks = three_level_tree();
+        let ids: Vec<i64> = tasks[0].flatten().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+
+        let all_ids: Vec<i64> = flatten_all(&tasks).map(|t| t.id).collect();
+        assert_eq!(all_ids, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_flatten_mut_visits_every_task() {
+        let mut tasks = three_level_tree();
+        let mut visited = Vec::new();
+        for task in &mut tasks {
+            task.flatten_mut(&mut |t| {
+                visited.push(t.id);
+                t.priority = "A".to_string();
+            });
+        }
+        assert_eq!(visited, vec![1, 2, 3, 4, 5]);
+        assert!(flatten_all(&tasks).all(|t| t.priority == "A"));
+    }
+
+    #[test]
+    fn test_compact_ids_rewrites_depends() {
+        let mut tasks = three_level_tree();
+        // 疎な ID にしてから振り直す
+        for task in &mut tasks {
+            task.flatten_mut(&mut |t| t.id *= 1000);
+        }
+        tasks[1].depends = Some(vec![3000, 1000]);
+        tasks[0].subtasks.as_mut().unwrap()[1].depends = Some(vec![5000, 9999]);
+
+        let id_map = compact_ids(&mut tasks);
+        assert_eq!(flatten_all(&tasks).map(|t| t.id).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(id_map[&3000], 3);
+        assert_eq!(tasks[1].depends, Some(vec![3, 1]));
+        // 存在しない ID への参照は削除される
+        assert_eq!(tasks[0].subtasks.as_ref().unwrap()[1].depends, Some(vec![5]));
+    }
+
+    #[test]
+    fn test_prune_empty_subtasks() {
+        let mut tasks = three_level_tree();
+        tasks.push(create_test_task(6, Some(vec![create_test_task(7, Some(vec![]))])));
+        prune_empty_subtasks(&mut tasks);
+
+        // トップレベルも深い階層も Some(vec![]) は None になり、中身のあるリストは残る
+        assert!(tasks[1].subtasks.is_none());
+        assert!(tasks[2].subtasks.as_ref().unwrap()[0].subtasks.is_none());
+        assert_eq!(tasks[0].subtasks.as_ref().unwrap().len(), 2);
+        assert!(!serde_json::to_string(&tasks).unwrap().contains("\"subtasks\":[]"));
+    }
+
+    #[test]
+    fn test_parse_duration_minutes() {
+        assert_eq!(parse_duration_minutes("90m", 8.0), Some(90));
+        assert_eq!(parse_duration_minutes("1.5h", 8.0), Some(90));
+        assert_eq!(parse_duration_minutes("2h30m", 8.0), Some(150));
+        assert_eq!(parse_duration_minutes("1d", 8.0), Some(480));
+        assert_eq!(parse_duration_minutes("1d", 6.0), Some(360));
+        assert_eq!(parse_duration_minutes("1d2h", 8.0), Some(600));
+        for invalid in ["", "2", "h", "2x", "30m2h", "1h1h", "1..5h", "-1h"] {
+            assert_eq!(parse_duration_minutes(invalid, 8.0), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_format_duration_minutes() {
+        assert_eq!(format_duration_minutes(150), "2h30m");
+        assert_eq!(format_duration_minutes(45), "0h45m");
+        assert_eq!(format_duration_minutes(120), "2h0m");
+        // 正規形は同じ値に戻る
+        assert_eq!(parse_duration_minutes(&format_duration_minutes(90), 8.0), Some(90));
+    }
+
+    #[test]
+    fn test_completion_percentage_two_levels() {
+        let mut tasks = three_level_tree();
+        // 葉は 3, 4 (id:1 の子孫) と 5 (サブタスクが空なので自身が葉)
+        tasks[0].subtasks.as_mut().unwrap()[0].subtasks.as_mut().unwrap()[0].status = "done".to_string();
+        assert_eq!(task_completion_percentage(&tasks[0]), 0.5);
+        assert_eq!(completion_percentage_str(&tasks[0]), "50%");
+        assert_eq!(completion_percentage_str(&tasks[0].subtasks.as_ref().unwrap()[0]), "100%");
+
+        tasks[0].subtasks.as_mut().unwrap()[1].status = "done".to_string();
+        assert_eq!(completion_percentage_str(&tasks[0]), "100%");
+        assert_eq!(task_completion_percentage(&tasks[1]), 0.0);
+        tasks[1].status = "done".to_string();
+        assert_eq!(task_completion_percentage(&tasks[1]), 1.0);
+    }
+
+    #[test]
+    fn test_task_json_schema() {
+        let schema = task_json_schema();
+        let required: Vec<&str> = schema["required"].as_array().unwrap().iter().map(|v| v.as_str().unwrap()).collect();
+        for key in ["name", "status", "priority", "id", "created", "display_order"] {
+            assert!(required.contains(&key), "{} should be required", key);
+        }
+        assert!(!required.contains(&"due"));
+        assert_eq!(schema["properties"]["created"]["format"], "date");
+        assert_eq!(schema["properties"]["due"]["type"], serde_json::json!(["string", "null"]));
+        assert_eq!(schema["properties"]["due"]["format"], "date");
+        assert_eq!(schema["properties"]["tags"]["type"], serde_json::json!(["array", "null"]));
+        assert!(schema["definitions"]["RepeatInfo"].is_object());
+        // subtasks は Task 自身を参照する再帰構造
+        let subtasks = serde_json::to_string(&schema["properties"]["subtasks"]).unwrap();
+        assert!(subtasks.contains("#/definitions/Task"));
+    }
+
+    #[test]
+    fn test_flatten_subtasks_to_contexts() {
+        let mut tasks = three_level_tree();
+        tasks[0].name = "Grandparent".to_string();
+        tasks[0].subtasks.as_mut().unwrap()[0].name = "Parent".to_string();
+        tasks[0].subtasks.as_mut().unwrap()[0].subtasks.as_mut().unwrap()[0].contexts = Some(vec!["home".to_string()]);
+
+        let flat = flatten_subtasks_to_contexts(tasks, "/");
+        let ids: Vec<i64> = flat.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+        assert!(flat.iter().all(|t| t.subtasks.is_none()));
+
+        assert_eq!(flat[0].contexts, None);
+        assert_eq!(flat[1].contexts, Some(vec!["Grandparent".to_string()]));
+        assert_eq!(flat[2].contexts, Some(vec!["Grandparent/Parent".to_string(), "home".to_string()]));
+        assert_eq!(flat[3].contexts, Some(vec!["Grandparent".to_string()]));
+        assert_eq!(flat[4].contexts, None);
+    }
+
+    #[test]
+    fn test_unknown_fields_survive_round_trip() {
+        let line = r#"{"name":"Custom","status":"open","priority":"N","id":1,"created":"2024-01-01","display_order":1,"extra":{"k":"v"},"color":"red","meta":{"source":"other-tool","rank":3}}"#;
+        let task: Task = serde_json::from_str(line).unwrap();
+        assert_eq!(task.unknown_fields.len(), 2);
+        assert_eq!(task.unknown_fields["color"], serde_json::json!("red"));
+        assert_eq!(task.extra.as_ref().unwrap()["k"], serde_json::json!("v"));
+        assert!(!task.unknown_fields.contains_key("extra"));
+
+        let written: serde_json::Value = serde_json::to_value(&task).unwrap();
+        let original: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(written, original);
+
+        let markdown = crate::markdown_formatter::format_tasks_to_markdown_document(&[task]);
+        assert!(!markdown.contains("red"));
+        assert!(!markdown.contains("other-tool"));
+    }
+
+    #[test]
+    fn test_content_hash_identical_and_changed_fields() {
+        let task = create_test_task(1, Some(vec![create_test_task(2, None)]));
+        let hash = content_hash(&task);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, content_hash(&task.clone()));
+
+        // extra のキーの挿入順は結果に影響しない
+        let mut a = task.clone();
+        a.extra = Some(HashMap::from([("x".to_string(), serde_json::json!(1)), ("y".to_string(), serde_json::json!(2))]));
+        let mut b = task.clone();
+        b.extra = Some(HashMap::from([("y".to_string(), serde_json::json!(2)), ("x".to_string(), serde_json::json!(1))]));
+        assert_eq!(content_hash(&a), content_hash(&b));
+
+        let changes: Vec<fn(&mut Task)> = vec![
+            |t| t.name.push('!'),
+            |t| t.status = "done".to_string(),
+            |t| t.priority = "A".to_string(),
+            |t| t.display_order += 1,
+            |t| t.due = NaiveDate::from_ymd_opt(2024, 2, 1),
+            |t| t.updated = NaiveDate::from_ymd_opt(2024, 2, 1),
+            |t| t.project = Some("work".to_string()),
+            |t| t.estimate_minutes = Some(30),
+            |t| t.subtasks.as_mut().unwrap()[0].name.push('!'),
+            |t| { t.unknown_fields.insert("color".to_string(), serde_json::json!("red")); },
+        ];
+        for change in changes {
+            let mut changed = task.clone();
+            change(&mut changed);
+            assert_ne!(content_hash(&changed), hash);
+        }
+    }
 }
@@ -0,0 +1,61 @@
+use crate::task_model::Task;
+use tera::{Context, Tera};
+
+// og fmt --template: Tera テンプレートでタスク一覧を描画する
+// テンプレートには tasks (Task の配列) と --template-vars の変数が渡される
+pub fn render_tasks_with_template(template: &str, tasks: &[Task], vars: &[(String, String)]) -> Result<String, String> {
+    let mut context = Context::new();
+    context.insert("tasks", tasks);
+    for (key, value) in vars {
+        context.insert(key.as_str(), value);
+    }
+    Tera::one_off(template, &context, false).map_err(|e| format!("Error rendering template: {}", format_tera_error(&e)))
+}
+
+// --template-vars の KEY=VALUE をパースする
+pub fn parse_template_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(format!("Invalid template variable '{}' (expected KEY=VALUE)", s)),
+    }
+}
+
+// Tera のエラーは原因が source チェーンに入っているため連結して表示する
+fn format_tera_error(error: &tera::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_parser::parse_markdown_document_to_tasks;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_render_minimal_template() {
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let tasks = parse_markdown_document_to_tasks("- [ ] (A) [[Write report]] due:2024-02-01\n- [x] [[Ship it]]", default_date).unwrap();
+        let template = "# {{ title }}\n{% for task in tasks %}* {{ task.name }} [{{ task.status }}] ({{ task.priority }}) {{ task.due | default(value=\"\") }}\n{% endfor %}";
+        let vars = vec![parse_template_var("title=My Tasks").unwrap()];
+        let output = render_tasks_with_template(template, &tasks, &vars).unwrap();
+        assert_eq!(output, "# My Tasks\n* Write report [open] (A) 2024-02-01\n* Ship it [done] (N) \n");
+    }
+
+    #[test]
+    fn test_parse_template_var() {
+        assert_eq!(parse_template_var("a=b=c").unwrap(), ("a".to_string(), "b=c".to_string()));
+        assert!(parse_template_var("novalue").is_err());
+        assert!(parse_template_var("=value").is_err());
+    }
+
+    #[test]
+    fn test_render_invalid_template_is_error() {
+        assert!(render_tasks_with_template("{% for %}", &[], &[]).is_err());
+    }
+}
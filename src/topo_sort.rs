@@ -0,0 +1,123 @@
+use crate::task_model::{flatten_all, Task};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// depends が循環しているタスクの ID (リスト内の出現順)
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("dependency cycle among tasks {}", involved_ids.iter().map(|id| format!("id:{}", id)).collect::<Vec<_>>().join(", "))]
+pub struct CycleError {
+    pub involved_ids: Vec<i64>,
+}
+
+// depends に従ってタスクを並べる (Kahn のアルゴリズム)。B が A に依存していれば A が先に来る。
+// サブタスクも含めた全タスクが対象で、順序に制約のないタスク同士は元の出現順を保つ。
+// リストにない ID への依存は無視する
+pub fn topological_sort(tasks: &[Task]) -> Result<Vec<&Task>, CycleError> {
+    let nodes: Vec<&Task> = flatten_all(tasks).collect();
+    let index_by_id: HashMap<i64, usize> = nodes.iter().enumerate().rev().map(|(index, task)| (task.id, index)).collect();
+
+    // dependents[i]: タスク i に依存しているタスク
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    let mut in_degree: Vec<usize> = vec![0; nodes.len()];
+    for (index, task) in nodes.iter().enumerate() {
+        for dep in task.depends.iter().flatten() {
+            if let Some(&dep_index) = index_by_id.get(dep) {
+                dependents[dep_index].push(index);
+                in_degree[index] += 1;
+            }
+        }
+    }
+
+    // 依存のなくなったタスクのうち、元の位置が最も前のものから取り出す
+    let mut ready: BinaryHeap<Reverse<usize>> = (0..nodes.len()).filter(|&i| in_degree[i] == 0).map(Reverse).collect();
+    let mut sorted: Vec<&Task> = Vec::with_capacity(nodes.len());
+    while let Some(Reverse(index)) = ready.pop() {
+        sorted.push(nodes[index]);
+        for &dependent in &dependents[index] {
+            in_degree[dependent] -= 1;
+            if in_degree[dependent] == 0 {
+                ready.push(Reverse(dependent));
+            }
+        }
+    }
+    if sorted.len() == nodes.len() {
+        return Ok(sorted);
+    }
+
+    // 残ったタスクから、循環の先にあるだけのタスク (残りのどれからも依存されていないもの) を除いていく
+    let mut remaining: Vec<bool> = in_degree.iter().map(|&degree| degree > 0).collect();
+    loop {
+        let peel: Vec<usize> = (0..nodes.len())
+            .filter(|&i| remaining[i] && !dependents[i].iter().any(|&d| remaining[d]))
+            .collect();
+        if peel.is_empty() {
+            break;
+        }
+        for i in peel {
+            remaining[i] = false;
+        }
+    }
+    let involved_ids = (0..nodes.len()).filter(|&i| remaining[i]).map(|i| nodes[i].id).collect();
+    Err(CycleError { involved_ids })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn task_depending_on(id: i64, depends: &[i64]) -> Task {
+        Task {
+            name: format!("Task {}", id),
+            status: "open".to_string(),
+            priority: "N".to_string(),
+            id,
+            created: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            display_order: id,
+            due: None,
+            updated: None,
+            completed: None,
+            project: None,
+            contexts: None,
+            notes: None,
+            tags: None,
+            tags_kv: None,
+            subtasks: None,
+            extra: None,
+            repeat: None,
+            depends: if depends.is_empty() { None } else { Some(depends.to_vec()) },
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
+        }
+    }
+
+    fn ids(sorted: &[&Task]) -> Vec<i64> {
+        sorted.iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn test_linear_chain() {
+        // 3 → 2 → 1 の順に依存 (リスト上は逆順)
+        let tasks = vec![task_depending_on(3, &[2]), task_depending_on(2, &[1]), task_depending_on(1, &[])];
+        assert_eq!(ids(&topological_sort(&tasks).unwrap()), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_diamond_keeps_original_order_between_independent_tasks() {
+        // 4 は 2 と 3 に、2 と 3 は 1 に依存。5 は独立でサブタスクとして含まれる
+        let mut top = task_depending_on(4, &[3, 2]);
+        top.subtasks = Some(vec![task_depending_on(5, &[])]);
+        let tasks = vec![top, task_depending_on(3, &[1]), task_depending_on(2, &[1]), task_depending_on(1, &[99])];
+        assert_eq!(ids(&topological_sort(&tasks).unwrap()), vec![5, 1, 3, 2, 4]);
+    }
+
+    #[test]
+    fn test_cycle_reports_only_tasks_in_the_cycle() {
+        // 1 ⇄ 2 が循環し、3 はその先にあるだけ
+        let tasks = vec![task_depending_on(1, &[2]), task_depending_on(2, &[1]), task_depending_on(3, &[2]), task_depending_on(4, &[])];
+        let err = topological_sort(&tasks).unwrap_err();
+        assert_eq!(err.involved_ids, vec![1, 2]);
+        assert_eq!(err.to_string(), "dependency cycle among tasks id:1, id:2");
+    }
+}
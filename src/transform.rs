@@ -0,0 +1,90 @@
+use crate::split::sanitize_file_stem;
+use crate::task_model::Task;
+use std::collections::HashMap;
+
+// すべてのタスク (サブタスクを含む) の ID を 0 (未採番) にする (og fmt --remove-ids)。
+// ID 0 のタスクは id:N を出力しないため、再パース時に 1 から振り直される。
+// depends は古い ID を指したままになるので一緒に消す
+pub fn clear_ids(tasks: &mut [Task]) {
+    for task in tasks {
+        task.flatten_mut(&mut |t: &mut Task| {
+            t.id = 0;
+            t.depends = None;
+        });
+    }
+}
+
+// og fmt --split-by-project で project のないタスクの出力先 (inbox.md)
+pub const INBOX_NAME: &str = "inbox";
+
+// トップレベルのタスクを project ごとに振り分ける (サブタスクは親と同じファイル)。
+// split_tasks と違い ID や display_order は振り直さず、project のないタスクは inbox に入れる。
+// 別々の project がサニタイズ後に同じファイル名になる場合 ("a/b" と "a_b"、project "inbox" と project なしなど) は、
+// 黙って混ぜずにエラーにする
+pub fn split_tasks_by_project(tasks: Vec<Task>) -> Result<HashMap<String, Vec<Task>>, String> {
+    let mut groups: HashMap<String, Vec<Task>> = HashMap::new();
+    let mut sources: HashMap<String, String> = HashMap::new();
+    for task in tasks {
+        let stem = task.project.as_deref().map(sanitize_file_stem).filter(|n| !n.is_empty());
+        let (name, source) = match (stem, task.project.as_deref()) {
+            (Some(stem), Some(project)) => (stem, format!("project '{}'", project.trim())),
+            _ => (INBOX_NAME.to_string(), "tasks without a project".to_string()),
+        };
+        match sources.get(&name) {
+            Some(other) if *other != source => {
+                return Err(format!("Error: {} and {} would both be written to '{}.md'; rename one of the projects", other, source, name));
+            }
+            Some(_) => {}
+            None => {
+                sources.insert(name.clone(), source);
+            }
+        }
+        groups.entry(name).or_default().push(task);
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_ids_recurses_and_drops_depends() {
+        let child = Task::builder().name("Child").id(8).depends(vec![3]).build();
+        let mut tasks = vec![Task::builder().name("Parent").id(7).subtasks(vec![child]).build(), Task::builder().name("Other").id(3).build()];
+        clear_ids(&mut tasks);
+        assert!(tasks.iter().flat_map(|t| t.flatten()).all(|t| t.id == 0 && t.depends.is_none()));
+    }
+
+    #[test]
+    fn test_split_tasks_by_project() {
+        let child = Task::builder().name("Child").id(2).build();
+        let tasks = vec![
+            Task::builder().name("Report").id(1).project("work").subtasks(vec![child]).build(),
+            Task::builder().name("Loose").id(3).build(),
+            Task::builder().name("Deploy").id(4).project("work").build(),
+        ];
+        let groups = split_tasks_by_project(tasks).unwrap();
+        let ids = |name: &str| groups[name].iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(ids("work"), vec![1, 4]);
+        assert_eq!(ids(INBOX_NAME), vec![3]);
+        assert_eq!(groups["work"][0].subtasks.as_ref().unwrap()[0].id, 2);
+    }
+
+    #[test]
+    fn test_split_tasks_by_project_rejects_collisions() {
+        let err = split_tasks_by_project(vec![
+            Task::builder().name("A").id(1).project("a/b").build(),
+            Task::builder().name("B").id(2).project("a_b").build(),
+        ])
+        .unwrap_err();
+        assert!(err.contains("project 'a/b' and project 'a_b'"), "{}", err);
+
+        let err = split_tasks_by_project(vec![
+            Task::builder().name("Loose").id(1).build(),
+            Task::builder().name("Named").id(2).project("inbox").build(),
+        ])
+        .unwrap_err();
+        assert!(err.contains("'inbox.md'"), "{}", err);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::task_model::{flatten_all, Task};
+use std::collections::{HashMap, HashSet};
+
+// og validate: JSON タスクリストの整合性を検査する (ファイルは変更しない)
+pub fn validate_tasks(tasks: &[Task]) -> Vec<String> {
+    let mut issues: Vec<String> = Vec::new();
+
+    let mut seen_ids: HashSet<i64> = HashSet::new();
+    for task in flatten_all(tasks) {
+        if !seen_ids.insert(task.id) {
+            issues.push(format!("duplicate id:{}", task.id));
+        }
+    }
+
+    issues.extend(display_order_issues(tasks));
+    issues
+}
+
+// display_order の重複と欠番を検出する。兄弟タスクごとに 1 からの連番であることを期待する
+pub fn display_order_issues(tasks: &[Task]) -> Vec<String> {
+    fn check(siblings: &[Task], parent_id: Option<i64>, issues: &mut Vec<String>) {
+        let scope = match parent_id {
+            Some(id) => format!("subtasks of id:{}", id),
+            None => "top-level tasks".to_string(),
+        };
+        let mut ids_by_order: HashMap<i64, Vec<i64>> = HashMap::new();
+        for task in siblings {
+            ids_by_order.entry(task.display_order).or_default().push(task.id);
+        }
+        let mut orders: Vec<i64> = ids_by_order.keys().copied().collect();
+        orders.sort();
+        for order in &orders {
+            let ids = &ids_by_order[order];
+            if ids.len() > 1 {
+                let ids_str = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
+                issues.push(format!("{}: duplicate display_order {} (ids {})", scope, order, ids_str));
+            }
+        }
+        let mut expected = 1;
+        for &order in &orders {
+            if order != expected {
+                issues.push(format!("{}: display_order gap (expected {}, found {})", scope, expected, order));
+            }
+            expected = order + 1;
+        }
+
+        for task in siblings {
+            if let Some(subtasks) = &task.subtasks {
+                check(subtasks, Some(task.id), issues);
+            }
+        }
+    }
+
+    let mut issues = Vec::new();
+    check(tasks, None, &mut issues);
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_model::compact_display_orders;
+    use chrono::NaiveDate;
+
+    fn task(id: i64, display_order: i64) -> Task {
+        Task {
+            id,
+            name: format!("Task {}", id),
+            status: "open".to_string(),
+            priority: "N".to_string(),
+            created: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            display_order,
+            due: None,
+            updated: None,
+            completed: None,
+            project: None,
+            contexts: None,
+            notes: None,
+            tags: None,
+            tags_kv: None,
+            subtasks: None,
+            extra: None,
+            repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_display_order_gaps_and_duplicates() {
+        let mut parent = task(1, 1);
+        parent.subtasks = Some(vec![task(10, 2), task(11, 2)]);
+        let tasks = vec![parent, task(2, 2), task(3, 5), task(4, 7)];
+
+        let issues = display_order_issues(&tasks);
+        assert_eq!(issues, vec![
+            "top-level tasks: display_order gap (expected 3, found 5)".to_string(),
+            "top-level tasks: display_order gap (expected 6, found 7)".to_string(),
+            "subtasks of id:1: duplicate display_order 2 (ids 10, 11)".to_string(),
+            "subtasks of id:1: display_order gap (expected 1, found 2)".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_compacted_tasks_are_valid() {
+        let mut parent = task(1, 3);
+        parent.subtasks = Some(vec![task(10, 4), task(11, 9)]);
+        let mut tasks = vec![task(2, 1), parent, task(3, 8)];
+        assert!(!validate_tasks(&tasks).is_empty());
+
+        compact_display_orders(&mut tasks);
+        assert!(validate_tasks(&tasks).is_empty());
+        // 現在の並び順を保ったまま振り直す
+        assert_eq!(tasks.iter().map(|t| (t.id, t.display_order)).collect::<Vec<_>>(), vec![(2, 1), (1, 2), (3, 3)]);
+        let subtasks = tasks[1].subtasks.as_ref().unwrap();
+        assert_eq!(subtasks.iter().map(|t| t.display_order).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_duplicate_ids_across_levels() {
+        let mut parent = task(1, 1);
+        parent.subtasks = Some(vec![task(1, 1)]);
+        assert_eq!(validate_tasks(&[parent]), vec!["duplicate id:1".to_string()]);
+    }
+}
@@ -0,0 +1,112 @@
+// og fmt --watch: 入力ファイルを監視し、変更のたびに整形して書き戻す
+use chrono::Local;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+const DEBOUNCE: Duration = Duration::from_millis(200);
+// 停止要求 (SIGINT/SIGTERM) を確認する間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+fn timestamp() -> String {
+    Local::now().format("%H:%M:%S").to_string()
+}
+
+// SIGINT (Ctrl-C) / SIGTERM を受けたら true になるフラグを返す。tokio ランタイム内で呼ぶこと
+fn stop_on_signal() -> Arc<AtomicBool> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = stop.clone();
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    tokio::select! {
+                        _ = tokio::signal::ctrl_c() => {}
+                        _ = terminate.recv() => {}
+                    }
+                }
+                Err(_) => {
+                    let _ = tokio::signal::ctrl_c().await;
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+        flag.store(true, Ordering::SeqCst);
+    });
+    stop
+}
+
+// 初回の整形のあと、SIGINT/SIGTERM を受けるまで変更を監視し続ける。
+// パースエラーなどは表示するだけで監視は続ける。
+pub fn watch_and_format(path: &str, format: impl Fn(&str) -> Result<String, String>) -> Result<(), String> {
+    let target = fs::canonicalize(path).map_err(|e| format!("Error resolving '{}': {}", path, e))?;
+    let file_name = target.file_name().map(|n| n.to_os_string());
+    // エディタの保存はリネームで置き換えることが多いので、親ディレクトリを監視してファイル名で絞る
+    let watch_dir = target.parent().ok_or_else(|| format!("Error: cannot watch '{}'.", path))?;
+
+    // 直前に自分で書き込んだ内容。同じ内容への変更通知は自分の書き込みとみなして無視する
+    let mut last_written: Option<String> = None;
+    let reformat = |last_written: &mut Option<String>| {
+        let content = match fs::read_to_string(&target) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("[{}] Error reading '{}': {}", timestamp(), path, e);
+                return;
+            }
+        };
+        if last_written.as_deref() == Some(content.as_str()) {
+            return;
+        }
+        match format(&content) {
+            Ok(formatted) => {
+                if formatted != content {
+                    if let Err(e) = og::fs_util::write_atomic(&target, &formatted) {
+                        eprintln!("[{}] Error writing back to file '{}': {}", timestamp(), path, e);
+                        return;
+                    }
+                }
+                eprintln!("[{}] Reformatted {}", timestamp(), path);
+                *last_written = Some(formatted);
+            }
+            Err(e) => eprintln!("[{}] {}", timestamp(), e),
+        }
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| format!("Error starting file watcher: {}", e))?;
+    watcher.watch(watch_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Error watching '{}': {}", path, e))?;
+    let stop = stop_on_signal();
+
+    reformat(&mut last_written);
+    eprintln!("Watching {} for changes (Ctrl-C to stop)", path);
+
+    // 書き込みは Modify、リネームによる置き換えは Create として届く
+    let is_relevant = |event: &notify::Result<notify::Event>| match event {
+        Ok(event) => matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_))
+            && event.paths.iter().any(|p| p.file_name().map(|n| n.to_os_string()) == file_name),
+        Err(_) => false,
+    };
+    while !stop.load(Ordering::SeqCst) {
+        let event = match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(event) => event,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+        if !is_relevant(&event) {
+            continue;
+        }
+        // 連続した保存はまとめて 1 回だけ整形する
+        while rx.recv_timeout(DEBOUNCE).is_ok() {}
+        reformat(&mut last_written);
+    }
+    eprintln!("Stopped watching {}", path);
+    Ok(())
+}
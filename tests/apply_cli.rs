@@ -45,7 +45,7 @@ fn apply_update_json_file_and_output_markdown() {
         "extra": null,
         "repeat": null
     });
-    writeln!(json_file, "{}", existing.to_string()).unwrap();
+    writeln!(json_file, "{}", existing).unwrap();
 
     // Apply markdown change (rename task)
     cmd.arg("apply")
@@ -59,4 +59,126 @@ fn apply_update_json_file_and_output_markdown() {
     // JSON file should be updated
     let contents = std::fs::read_to_string(json_file.path()).unwrap();
     assert!(contents.contains("\"name\":\"NewName\""));
-}
\ No newline at end of file
+}
+
+/// `og apply` replaces the JSON file atomically: every line is complete and no temp file is left behind
+#[test]
+fn apply_writes_complete_json_without_leftover_temp_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join("tasks.jsonl");
+    std::fs::write(&json_path, "").unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("apply")
+        .arg("--from").arg("markdown")
+        .arg("--target-json").arg(&json_path)
+        .write_stdin("- [ ] [[First]] id:1\n- [ ] [[Second]] id:2\n    - [ ] [[Child]] id:3\n")
+        .assert()
+        .success();
+
+    let contents = std::fs::read_to_string(&json_path).unwrap();
+    assert!(contents.ends_with('\n'));
+    let names: Vec<String> = contents.lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["name"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(names, vec!["First", "Second"]);
+    assert!(contents.contains("\"name\":\"Child\""));
+
+    let entries: Vec<_> = std::fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(entries, vec![std::ffi::OsString::from("tasks.jsonl")]);
+}
+
+/// `--backup` copies the target JSON to `<path>.bak` before apply overwrites it
+#[test]
+fn apply_backup_keeps_pre_edit_contents() {
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join("tasks.jsonl");
+    let original = "{\"id\":1,\"name\":\"OldTask\",\"status\":\"NONE\",\"created\":\"2024-01-01\",\"tags\":[],\"priority\":\"N\",\"display_order\":1}\n";
+    std::fs::write(&json_path, original).unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("apply")
+        .arg("--from").arg("markdown")
+        .arg("--target-json").arg(&json_path)
+        .arg("--backup")
+        .write_stdin("- [ ] [[NewName]] id:1\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Backed up"));
+
+    assert_eq!(std::fs::read_to_string(dir.path().join("tasks.jsonl.bak")).unwrap(), original);
+    assert!(std::fs::read_to_string(&json_path).unwrap().contains("\"name\":\"NewName\""));
+}
+
+/// `--backup=timestamped` names the backup after the current time
+#[test]
+fn apply_timestamped_backup() {
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join("tasks.jsonl");
+    std::fs::write(&json_path, "").unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("apply")
+        .arg("--from").arg("markdown")
+        .arg("--target-json").arg(&json_path)
+        .arg("--backup=timestamped")
+        .write_stdin("- [ ] Task\n")
+        .assert()
+        .success();
+
+    let backups: Vec<String> = std::fs::read_dir(dir.path()).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".bak"))
+        .collect();
+    assert_eq!(backups.len(), 1);
+    assert!(backups[0].starts_with("tasks.jsonl.20"), "{}", backups[0]);
+}
+
+/// `--content-hash-file` writes an id → hash map and reports how many tasks changed on the next apply
+#[test]
+fn apply_content_hash_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join("tasks.jsonl");
+    let hash_path = dir.path().join("hashes.json");
+    std::fs::write(&json_path, "").unwrap();
+    let apply = |markdown: &str| {
+        Command::cargo_bin("og").unwrap()
+            .arg("apply")
+            .arg("--from").arg("markdown")
+            .arg("--target-json").arg(&json_path)
+            .arg("--content-hash-file").arg(&hash_path)
+            .write_stdin(markdown.to_string())
+            .assert()
+            .success()
+    };
+
+    apply("- [ ] First id:1 created:2024-01-01\n- [ ] Second id:2 created:2024-01-01\n")
+        .stderr(predicate::str::contains("changed since the last apply").not());
+    let hashes: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&hash_path).unwrap()).unwrap();
+    assert_eq!(hashes.as_object().unwrap().len(), 2);
+    assert_eq!(hashes["1"].as_str().unwrap().len(), 64);
+
+    apply("- [ ] First id:1 created:2024-01-01\n- [x] Second id:2 created:2024-01-01\n")
+        .stderr(predicate::str::contains("1 of 2 task(s) changed since the last apply"));
+}
+
+/// `--conflict-strategy ours` keeps the JSON fields updated after the Markdown was written and reports them
+#[test]
+fn apply_conflict_strategy_ours_keeps_newer_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join("tasks.jsonl");
+    std::fs::write(&json_path, r#"{"id":1,"name":"Report","status":"open","priority":"N","created":"2024-01-01","updated":"2024-05-03","due":"2024-05-10","completed":null,"display_order":1}"#).unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("apply")
+        .arg("--from").arg("markdown")
+        .arg("--target-json").arg(&json_path)
+        .arg("--conflict-strategy").arg("ours")
+        .write_stdin("- [ ] (N) [[Report]] id:1 due:2024-05-07 created:2024-01-01 updated:2024-05-01\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Conflict: task id:1 field 'due' (JSON updated 2024-05-03, Markdown 2024-05-01): kept JSON value"));
+
+    let contents = std::fs::read_to_string(&json_path).unwrap();
+    assert!(contents.contains("\"due\":\"2024-05-10\""), "{}", contents);
+}
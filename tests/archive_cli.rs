@@ -0,0 +1,98 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::NamedTempFile;
+
+/// Closed tasks move to the archive with their ids; partially done parents stay
+#[test]
+fn archive_moves_closed_tasks_and_appends() {
+    let tasks = NamedTempFile::new().unwrap();
+    fs::write(tasks.path(), "\
+- [x] [[Shipped]] id:4
+    - [x] [[Tested]] id:9
+- [x] [[Partly done]] id:5
+    - [ ] [[Still open]] id:6
+- [ ] [[Next]] id:7
+- [c] [[Dropped]] id:8
+").unwrap();
+    let archive = NamedTempFile::new().unwrap();
+    fs::write(archive.path(), "- [x] (N) [[Older]] id:1").unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("archive")
+        .arg(tasks.path())
+        .arg("--to-file").arg(archive.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Archived 3 task(s)"));
+
+    let active = fs::read_to_string(tasks.path()).unwrap();
+    assert!(active.contains("[[Partly done]] id:5"));
+    assert!(active.contains("[[Still open]] id:6"));
+    assert!(active.contains("[[Next]] id:7"));
+    assert!(!active.contains("Shipped"));
+    assert!(!active.contains("Dropped"));
+
+    let archived = fs::read_to_string(archive.path()).unwrap();
+    assert!(archived.starts_with("- [x] (N) [[Older]] id:1\n"));
+    assert!(archived.contains("[[Shipped]] id:4"));
+    assert!(archived.contains("    - [x] (N) [[Tested]] id:9"));
+    assert!(archived.contains("[[Dropped]] id:8"));
+}
+
+/// `--output-dir` with `--name-template` appends archived tasks to one file per project
+#[test]
+fn archive_output_dir_groups_by_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let tasks = dir.path().join("tasks.md");
+    fs::write(&tasks, "- [x] [[Fence]] id:1 +home\n- [x] [[Report]] id:2 +work/q3\n- [ ] [[Next]] id:3 +home\n").unwrap();
+    let out_dir = dir.path().join("archive");
+
+    Command::cargo_bin("og").unwrap()
+        .arg("archive")
+        .arg(&tasks)
+        .arg("--output-dir").arg(&out_dir)
+        .arg("--name-template").arg("{project}-done.md")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Archived 2 task(s)"));
+
+    assert!(fs::read_to_string(out_dir.join("home-done.md")).unwrap().contains("[[Fence]] id:1"));
+    assert!(fs::read_to_string(out_dir.join("work_q3-done.md")).unwrap().contains("[[Report]] id:2"));
+    assert!(fs::read_to_string(&tasks).unwrap().contains("[[Next]] id:3"));
+}
+
+/// Closed subtrees under an open parent are archived whole, and `-o` receives the remaining tasks
+#[test]
+fn archive_moves_nested_closed_subtrees_and_honors_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let tasks = dir.path().join("tasks.md");
+    let original = "\
+- [ ] [[Project]] id:1
+    - [x] [[Phase one]] id:2
+        - [x] [[Step]] id:3
+    - [ ] [[Phase two]] id:4
+";
+    fs::write(&tasks, original).unwrap();
+    let archive = dir.path().join("archive.md");
+    let remaining = dir.path().join("remaining.md");
+
+    Command::cargo_bin("og").unwrap()
+        .arg("archive")
+        .arg(&tasks)
+        .arg("--to-file").arg(&archive)
+        .arg("-o").arg(&remaining)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Archived 2 task(s)"));
+
+    assert_eq!(fs::read_to_string(&tasks).unwrap(), original);
+    let archived = fs::read_to_string(&archive).unwrap();
+    assert!(archived.starts_with("- [x] (N) [[Phase one]] id:2"));
+    assert!(archived.contains("    - [x] (N) [[Step]] id:3"));
+    let active = fs::read_to_string(&remaining).unwrap();
+    assert!(active.contains("[[Project]] id:1"));
+    assert!(active.contains("    - [ ] (N) [[Phase two]] id:4"));
+    assert!(!active.contains("Phase one"));
+    assert!(!active.contains("Step"));
+}
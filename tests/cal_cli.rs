@@ -0,0 +1,148 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+/// `og cal --no-auth` skips OAuth and prints the empty schedule
+#[test]
+fn cal_no_auth_prints_empty_schedule() {
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .arg("--no-auth")
+        .env("OG_CREDENTIALS_PATH", "/nonexistent/credentials.json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("予定はありません"))
+        .stderr(predicate::str::contains("offline mode"));
+}
+
+/// `OG_CAL_NO_AUTH=1` implies offline mode when credentials are missing
+#[test]
+fn cal_env_no_auth_without_credentials_is_offline() {
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .env("OG_CAL_NO_AUTH", "1")
+        .env("OG_CREDENTIALS_PATH", "/nonexistent/credentials.json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("予定はありません"));
+}
+
+/// `--color` forces ANSI codes even when piped; `--no-color` keeps the plain output
+#[test]
+fn cal_color_flags() {
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .arg("--no-auth")
+        .arg("--color")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("\x1b[1m### 予定\x1b[0m\n"));
+
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .arg("--no-auth")
+        .arg("--no-color")
+        .assert()
+        .success()
+        .stdout("### 予定\n予定はありません。\n");
+}
+
+/// `--format json` prints a JSON array (empty when offline)
+#[test]
+fn cal_format_json_offline_is_empty_array() {
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .arg("--no-auth")
+        .arg("--format").arg("json")
+        .assert()
+        .success()
+        .stdout("[]\n");
+}
+
+/// `--next` rejects a count of zero
+#[test]
+fn cal_next_requires_positive_count() {
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .arg("--no-auth")
+        .arg("--next").arg("0")
+        .assert()
+        .failure();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .arg("--no-auth")
+        .arg("--next").arg("2")
+        .arg("--format").arg("json")
+        .assert()
+        .success()
+        .stdout("[]\n");
+}
+
+/// `og doctor` lists each setup problem with a hint and exits non-zero
+#[test]
+fn doctor_reports_missing_credentials_and_token() {
+    Command::cargo_bin("og").unwrap()
+        .arg("doctor")
+        .env("OG_CREDENTIALS_PATH", "/nonexistent/credentials.json")
+        .env("OG_TOKEN_PATH", "/nonexistent/token.json")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("[FAIL] Credentials file: /nonexistent/credentials.json not found"))
+        .stdout(predicate::str::contains("[FAIL] Token file: /nonexistent/token.json not found"))
+        .stdout(predicate::str::contains("hint: Run 'og cal' to authenticate"))
+        .stdout(predicate::str::contains("2 of 3 checks failed"));
+}
+
+/// `og cal --remind-minutes` prints nothing and exits 0 when no event is about to start
+#[test]
+fn cal_remind_minutes_without_events_is_silent() {
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .arg("--no-auth")
+        .arg("--remind-minutes").arg("15")
+        .env("OG_CREDENTIALS_PATH", "/nonexistent/credentials.json")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+}
+
+/// `og cal --remind-minutes` exits 2 on errors so that they are not mistaken for upcoming events
+#[test]
+fn cal_remind_minutes_error_exits_two() {
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .arg("--no-auth")
+        .arg("--remind-minutes").arg("15")
+        .arg("--format").arg("bogus")
+        .env("OG_CREDENTIALS_PATH", "/nonexistent/credentials.json")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::is_empty());
+}
+
+/// `og cal --format compact` works with `--remind-minutes` and prints nothing when no event is upcoming
+#[test]
+fn cal_remind_minutes_with_compact_format() {
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .arg("--no-auth")
+        .arg("--remind-minutes").arg("15")
+        .arg("--format").arg("compact")
+        .env("OG_CREDENTIALS_PATH", "/nonexistent/credentials.json")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+}
+
+/// Missing credentials exit 2 with the path and a pointer to `og doctor`
+#[test]
+fn cal_missing_credentials_explains_next_step() {
+    Command::cargo_bin("og").unwrap()
+        .arg("cal")
+        .env("OG_CREDENTIALS_PATH", "/nonexistent/credentials.json")
+        .env_remove("OG_CAL_NO_AUTH")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("credentials not found at /nonexistent/credentials.json"))
+        .stderr(predicate::str::contains("Run 'og doctor' to check the calendar setup."));
+}
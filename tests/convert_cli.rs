@@ -0,0 +1,109 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+/// Piped markdown converts with only `--stdin-format` and `--to`
+#[test]
+fn stdin_format_replaces_from_when_piping() {
+    Command::cargo_bin("og").unwrap()
+        .arg("--stdin-format").arg("markdown")
+        .arg("--to").arg("json")
+        .write_stdin("- [ ] [[task]]\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"name\":\"task\""));
+}
+
+/// Conflicting `--from` and `--stdin-format` values are rejected
+#[test]
+fn stdin_format_conflicting_with_from_is_error() {
+    Command::cargo_bin("og").unwrap()
+        .arg("--from").arg("json")
+        .arg("--stdin-format").arg("markdown")
+        .arg("--to").arg("json")
+        .write_stdin("- [ ] [[task]]\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("conflicts with --stdin-format"));
+}
+
+/// Subcommands reading stdin honor `--stdin-format`; subcommands without an input format reject it
+#[test]
+fn stdin_format_applies_to_subcommands() {
+    Command::cargo_bin("og").unwrap()
+        .arg("list")
+        .arg("--stdin-format").arg("json")
+        .write_stdin("{\"id\":1,\"name\":\"From JSON\",\"status\":\"open\",\"priority\":\"A\",\"created\":\"2024-01-01\",\"display_order\":1}\n")
+        .assert()
+        .success()
+        .stdout("[1] (A) From JSON\n");
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--stdin-format").arg("json")
+        .write_stdin("- [ ] [[task]]\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--stdin-format is only supported"));
+}
+
+/// A custom `sprint:12` attribute survives markdown -> json -> markdown via `extra`
+#[test]
+fn custom_attribute_round_trips_through_json() {
+    let json = Command::cargo_bin("og").unwrap()
+        .arg("--from").arg("markdown")
+        .arg("--to").arg("json")
+        .write_stdin("- [ ] [[Plan]] id:1 created:2024-01-01 sprint:12\n")
+        .output()
+        .unwrap();
+    let json = String::from_utf8(json.stdout).unwrap();
+    assert!(json.contains("\"extra\":{\"sprint\":12}"), "{}", json);
+
+    Command::cargo_bin("og").unwrap()
+        .arg("--from").arg("json")
+        .arg("--to").arg("markdown")
+        .write_stdin(json)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[[Plan]] id:1 "))
+        .stdout(predicate::str::contains(" sprint:12"));
+}
+
+/// `--from obsidian` reads Obsidian Tasks emoji attributes into og's Markdown format
+#[test]
+fn obsidian_to_markdown_maps_emoji_attributes() {
+    Command::cargo_bin("og").unwrap()
+        .arg("--from").arg("obsidian")
+        .arg("--to").arg("markdown")
+        .write_stdin("- [ ] Write report 📅 2024-01-15 ➕ 2024-01-02 ⏫\n- [x] Pay rent ✅ 2024-01-03\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- [ ] (A) [[Write report]] id:1"))
+        .stdout(predicate::str::contains("due:2024-01-15"))
+        .stdout(predicate::str::contains("created:2024-01-02"))
+        .stdout(predicate::str::contains("- [x] (N) [[Pay rent]] id:2"))
+        .stdout(predicate::str::contains("completed:2024-01-03"));
+}
+
+/// A multi-line note (e.g. an imported issue body) survives json -> markdown -> json on one task line
+#[test]
+fn multi_line_note_round_trips_through_markdown() {
+    let json = "{\"name\":\"Bug\",\"status\":\"open\",\"priority\":\"N\",\"id\":1,\"created\":\"2024-01-01\",\"display_order\":1,\"notes\":\"Steps:\\r\\n1. open\\n- [ ] do X\\nC:\\\\dir \\\"quoted\\\"\"}\n";
+    let markdown = Command::cargo_bin("og").unwrap()
+        .arg("--from").arg("json")
+        .arg("--to").arg("markdown")
+        .write_stdin(json)
+        .output()
+        .unwrap();
+    let markdown = String::from_utf8(markdown.stdout).unwrap();
+    assert_eq!(markdown.lines().count(), 1, "{}", markdown);
+    assert!(markdown.contains(r#"note:"Steps:\r\n1. open\n- [ ] do X\nC:\\dir ""quoted""""#), "{}", markdown);
+
+    Command::cargo_bin("og").unwrap()
+        .arg("--from").arg("markdown")
+        .arg("--to").arg("json")
+        .write_stdin(markdown)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""notes":"Steps:\r\n1. open\n- [ ] do X\nC:\\dir \"quoted\"""#))
+        .stdout(predicate::function(|out: &str| out.lines().count() == 1));
+}
@@ -0,0 +1,54 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+const INPUT: &str = "\
+- [x] [[Ship release]] id:1 completed:2024-06-03
+- [x] [[Fix login bug]] id:2 completed:2024-06-07
+- [x] [[Old cleanup]] id:3 completed:2024-05-20
+- [ ] [[Still open]] id:4
+";
+
+/// `og done` keeps only tasks completed inside the inclusive window
+#[test]
+fn done_filters_by_completed_date_window() {
+    Command::cargo_bin("og").unwrap()
+        .arg("done")
+        .arg("--since").arg("2024-06-03")
+        .arg("--until").arg("2024-06-09")
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[[Ship release]]"))
+        .stdout(predicate::str::contains("[[Fix login bug]]"))
+        .stdout(predicate::str::contains("[[Old cleanup]]").not())
+        .stdout(predicate::str::contains("[[Still open]]").not());
+}
+
+/// `--group-by project` prints a heading per project, with project-less tasks under `(none)`
+#[test]
+fn done_group_by_project() {
+    let input = "\
+- [x] [[Ship release]] id:1 +alpha completed:2024-06-03
+- [x] [[Write notes]] id:2 +beta completed:2024-06-04
+- [x] [[Fix bug]] id:3 +alpha completed:2024-06-05
+- [x] [[Inbox zero]] id:4 completed:2024-06-06
+";
+    let output = Command::cargo_bin("og").unwrap()
+        .arg("done")
+        .arg("--group-by").arg("project")
+        .write_stdin(input)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    let heading_at = |heading: &str| lines.iter().position(|l| *l == heading).unwrap();
+    let task_at = |name: &str| lines.iter().position(|l| l.contains(name)).unwrap();
+
+    let (alpha, beta, none) = (heading_at("### +alpha"), heading_at("### +beta"), heading_at("### (none)"));
+    assert!(alpha < beta && beta < none);
+    assert!((alpha..beta).contains(&task_at("[[Ship release]]")));
+    assert!((alpha..beta).contains(&task_at("[[Fix bug]]")));
+    assert!((beta..none).contains(&task_at("[[Write notes]]")));
+    assert!(task_at("[[Inbox zero]]") > none);
+}
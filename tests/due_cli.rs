@@ -0,0 +1,167 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+
+// Tasks due today, tomorrow and in three days, written relative to the real today
+fn write_tasks(dir: &tempfile::TempDir) -> std::path::PathBuf {
+    let today = chrono::Local::now().date_naive();
+    let day = |offset: i64| (today + chrono::Duration::days(offset)).format("%Y-%m-%d").to_string();
+    let task = |id: i64, name: &str, priority: &str, due: String| {
+        format!(
+            "{{\"name\":\"{}\",\"status\":\"open\",\"priority\":\"{}\",\"id\":{},\"created\":\"2024-01-01\",\"display_order\":{},\"due\":\"{}\"}}\n",
+            name, priority, id, id, due
+        )
+    };
+    let content = [
+        task(1, "In three days", "A", day(3)),
+        task(2, "Today low", "C", day(0)),
+        task(3, "Tomorrow", "N", day(1)),
+        task(4, "Today high", "A", day(0)),
+    ]
+    .concat();
+    let path = dir.path().join("tasks.jsonl");
+    fs::write(&path, content).unwrap();
+    path
+}
+
+/// Without options only tasks due today are listed, highest priority first
+#[test]
+fn due_lists_tasks_due_today() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_tasks(&dir);
+    let today = chrono::Local::now().date_naive().format("%Y-%m-%d").to_string();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("due")
+        .arg("--target-json").arg(&path)
+        .assert()
+        .success()
+        .stdout(format!("[4] (A) Today high \u{2013} due {today}\n[2] (C) Today low \u{2013} due {today}\n"));
+}
+
+/// `--date 1d` lists tomorrow's task only; `--days-ahead 3` covers today through three days out
+#[test]
+fn due_date_and_days_ahead() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_tasks(&dir);
+
+    Command::cargo_bin("og").unwrap()
+        .arg("due")
+        .arg("--target-json").arg(&path)
+        .arg("--date").arg("1d")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("[3] (N) Tomorrow"))
+        .stdout(predicate::str::contains("Today").not())
+        .stdout(predicate::str::contains("In three days").not());
+
+    let output = Command::cargo_bin("og").unwrap()
+        .arg("due")
+        .arg("--target-json").arg(&path)
+        .arg("--days-ahead").arg("2")
+        .output()
+        .unwrap();
+    let ids: Vec<String> = String::from_utf8(output.stdout).unwrap().lines().map(|l| l[..3].to_string()).collect();
+    assert_eq!(ids, vec!["[4]", "[2]", "[3]"]);
+
+    let output = Command::cargo_bin("og").unwrap()
+        .arg("due")
+        .arg("--target-json").arg(&path)
+        .arg("--days-ahead").arg("3")
+        .output()
+        .unwrap();
+    let ids: Vec<String> = String::from_utf8(output.stdout).unwrap().lines().map(|l| l[..3].to_string()).collect();
+    assert_eq!(ids, vec!["[4]", "[2]", "[3]", "[1]"]);
+}
+
+/// A `--days-ahead` past the latest representable date is an error, not a panic
+#[test]
+fn due_days_ahead_overflow_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_tasks(&dir);
+
+    Command::cargo_bin("og").unwrap()
+        .arg("due")
+        .arg("--target-json").arg(&path)
+        .arg("--days-ahead").arg("4294967295")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("--days-ahead 4294967295"));
+}
+
+/// `--format markdown` prints the matching tasks as a markdown task list
+#[test]
+fn due_markdown_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_tasks(&dir);
+
+    Command::cargo_bin("og").unwrap()
+        .arg("due")
+        .arg("--target-json").arg(&path)
+        .arg("--format").arg("markdown")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("- [ ] (A) [[Today high]] id:4"))
+        .stdout(predicate::str::contains("- [ ] (C) [[Today low]] id:2"));
+}
+
+/// `--show-notes` prints each note under its task as `  > ` lines, keeping the note's own newlines
+#[test]
+fn due_show_notes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tasks.jsonl");
+    let today = chrono::Local::now().date_naive().format("%Y-%m-%d").to_string();
+    fs::write(
+        &path,
+        format!("{{\"name\":\"Call\",\"status\":\"open\",\"priority\":\"A\",\"id\":1,\"created\":\"2024-01-01\",\"display_order\":1,\"due\":\"{today}\",\"notes\":\"Ask about\\nthe invoice\"}}\n"),
+    )
+    .unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("due")
+        .arg("--target-json").arg(&path)
+        .arg("--show-notes")
+        .assert()
+        .success()
+        .stdout(format!("[1] (A) Call \u{2013} due {today}\n  > Ask about\n  > the invoice\n"));
+}
+
+/// `--format table` prints a Markdown table with padded columns
+#[test]
+fn due_table_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_tasks(&dir);
+    let today = chrono::Local::now().date_naive().format("%Y-%m-%d").to_string();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("due")
+        .arg("--target-json").arg(&path)
+        .arg("--format").arg("table")
+        .assert()
+        .success()
+        .stdout(format!(
+            "| Status | Priority | Name       | Due        | Project |\n\
+             | ------ | -------- | ---------- | ---------- | ------- |\n\
+             | open   | A        | Today high | {today} |         |\n\
+             | open   | C        | Today low  | {today} |         |\n"
+        ));
+}
+
+/// `--due-week 2024-W32` lists tasks due Monday 2024-08-05 through Sunday 2024-08-11
+#[test]
+fn due_week_iso() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tasks.jsonl");
+    let task = |id: i64, due: &str| {
+        format!("{{\"name\":\"Task {id}\",\"status\":\"open\",\"priority\":\"N\",\"id\":{id},\"created\":\"2024-01-01\",\"display_order\":{id},\"due\":\"{due}\"}}\n")
+    };
+    fs::write(&path, [task(1, "2024-08-04"), task(2, "2024-08-05"), task(3, "2024-08-11"), task(4, "2024-08-12")].concat()).unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("due")
+        .arg("--target-json").arg(&path)
+        .arg("--due-week").arg("2024-W32")
+        .assert()
+        .success()
+        .stdout("[2] (N) Task 2 \u{2013} due 2024-08-05\n[3] (N) Task 3 \u{2013} due 2024-08-11\n");
+}
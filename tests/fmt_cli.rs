@@ -0,0 +1,398 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+const INPUT: &str = "- [x] [[Done]] id:1\n- [>] [[Doing]] id:2\n- [ ] [[Overdue]] id:3 due:2000-01-01\n";
+
+/// `og fmt --color never` must not emit escape codes
+#[test]
+fn fmt_color_never_is_plain() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--color").arg("never")
+        .arg("--overdue-action").arg("warn")
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[[Done]]"))
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+/// `--color auto` stays plain when stdout is not a terminal
+#[test]
+fn fmt_color_auto_is_plain_when_piped() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+/// `--color always` colors by status
+#[test]
+fn fmt_color_always_colors_status() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--color").arg("always")
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[2m- [x]"))
+        .stdout(predicate::str::contains("\x1b[1m- [>]"))
+        .stdout(predicate::str::contains("\x1b[31m- [ ]"));
+}
+
+/// `--max-depth` hides tasks, so colors could land on the wrong lines; the output stays plain
+#[test]
+fn fmt_color_always_is_plain_with_max_depth() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--color").arg("always")
+        .arg("--max-depth").arg("0")
+        .write_stdin("- [ ] [[Root]] id:1\n    - [x] [[Hidden done]] id:2\n- [ ] [[Open sibling]] id:3\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- [ ] (N) [[Open sibling]] id:3"))
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+/// The next-id hint line is not a task, so `--emit-next-id-hint` output stays plain
+#[test]
+fn fmt_color_always_is_plain_with_next_id_hint() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--color").arg("always")
+        .arg("--emit-next-id-hint")
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("<!-- og:next_id:4 -->\n"))
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+/// Continuation lines from `--wrap` / `--max-line-length` are not tasks, so the output stays plain
+#[test]
+fn fmt_color_always_is_plain_with_wrapped_lines() {
+    for args in [["--wrap", "30"], ["--max-line-length", "30"]] {
+        Command::cargo_bin("og").unwrap()
+            .arg("fmt")
+            .arg("--color").arg("always")
+            .args(args)
+            .write_stdin(INPUT)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("\x1b[").not());
+    }
+}
+
+/// `--watch` implies `--in-place`, so it needs a named file
+#[test]
+fn fmt_watch_requires_named_file() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--watch")
+        .write_stdin(INPUT)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--in-place requires a named input file"));
+}
+
+/// `--keep-text` leaves headings and prose between task groups untouched
+#[test]
+fn fmt_keep_text_preserves_document_structure() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--keep-text")
+        .write_stdin("## Today\n\n- [ ] [[A]] id:1\n\nSome notes.\n- [x] [[B]] id:2\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("## Today\n\n- [ ] (N) [[A]] id:1"))
+        .stdout(predicate::str::contains("\n\nSome notes.\n- [x] (N) [[B]] id:2"));
+}
+
+/// `--aggregate-tags` replaces the document with tag counts, most used first
+#[test]
+fn fmt_aggregate_tags_prints_counts() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--aggregate-tags")
+        .arg("--min-count").arg("2")
+        .write_stdin("- [ ] [[A]] #work #once\n- [ ] [[B]] #work #home\n- [ ] [[C]] #home #work\n")
+        .assert()
+        .success()
+        .stdout("#work: 3\n#home: 2\n");
+}
+
+/// `--strip-completed-older-than` drops only done tasks completed before the cutoff
+#[test]
+fn fmt_strip_completed_older_than() {
+    let today = chrono::Local::now().date_naive();
+    let old = today - chrono::Duration::days(30);
+    let recent = today - chrono::Duration::days(1);
+    let input = format!(
+        "- [x] [[Old]] id:1 completed:{}\n- [x] [[Recent]] id:2 completed:{}\n- [ ] [[Open]] id:3\n",
+        old.format("%Y-%m-%d"),
+        recent.format("%Y-%m-%d"),
+    );
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--strip-completed-older-than").arg("29")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[[Old]]").not())
+        .stdout(predicate::str::contains("[[Recent]]"))
+        .stdout(predicate::str::contains("[[Open]]"))
+        .stderr(predicate::str::contains("Removed 1 completed task(s)"));
+}
+
+/// `--emit-display-orders` writes do:N, and re-parsing keeps those values as display_order
+#[test]
+fn fmt_emit_display_orders_round_trip() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--emit-display-orders")
+        .write_stdin("- [ ] [[A]] id:1\n- [ ] [[B]] id:2 do:5\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[[A]] id:1 do:1 "))
+        .stdout(predicate::str::contains("[[B]] id:2 do:5 "));
+
+    let output = Command::cargo_bin("og").unwrap()
+        .arg("--from").arg("markdown")
+        .arg("--to").arg("json")
+        .write_stdin("- [ ] [[B]] id:2 do:5\n- [ ] [[A]] id:1 do:1\n")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let orders: Vec<(String, i64)> = stdout.lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .map(|v| (v["name"].as_str().unwrap().to_string(), v["display_order"].as_i64().unwrap()))
+        .collect();
+    assert_eq!(orders, vec![("B".to_string(), 5), ("A".to_string(), 1)]);
+}
+
+/// `--strict` rejects a misspelled attribute with its line number; without it the line is accepted
+#[test]
+fn fmt_strict_rejects_unknown_attribute() {
+    let input = "- [ ] [[Ok]] id:1\n- [ ] [[Typo]] id:2 duee:2024-01-01\n";
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[[Typo]]"));
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--strict")
+        .write_stdin(input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("line 2"))
+        .stderr(predicate::str::contains("duee:2024-01-01"));
+}
+
+/// `--add-missing-created` writes today's date for a task without created:, `--suppress-created-when-default` leaves it out
+#[test]
+fn fmt_created_when_default() {
+    let today = chrono::Local::now().date_naive().format("%Y-%m-%d").to_string();
+    let input = "- [ ] [[New Task]] id:1\n- [ ] [[Old Task]] id:2 created:2024-01-01\n";
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--add-missing-created")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("[[New Task]] id:1 due:\"\" created:{}", today)))
+        .stdout(predicate::str::contains("created:2024-01-01"));
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--suppress-created-when-default")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("created:{}", today)).not())
+        .stdout(predicate::str::contains("[[New Task]] id:1 due:\"\" updated:\"\""))
+        .stdout(predicate::str::contains("created:2024-01-01"));
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--add-missing-created")
+        .arg("--suppress-created-when-default")
+        .write_stdin(input)
+        .assert()
+        .failure();
+}
+
+/// `--split-by-project` writes one file per project plus inbox.md and summarizes them on stdout
+#[test]
+fn fmt_split_by_project_writes_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().join("split");
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--split-by-project").arg(&out_dir)
+        .write_stdin("- [ ] [[Report]] id:1 +work\n    - [ ] [[Draft]] id:2\n- [ ] [[Paint fence]] id:3 +home\n- [ ] [[Call mom]] id:4\n- [ ] [[Review]] id:5 +work\n")
+        .assert()
+        .success()
+        .stdout("Wrote 3 files: home.md (1 tasks), work.md (3 tasks), inbox.md (1 tasks)\n");
+
+    let work = std::fs::read_to_string(out_dir.join("work.md")).unwrap();
+    assert!(work.starts_with("- [ ] (N) [[Report]] id:1"));
+    assert!(work.contains("    - [ ] (N) [[Draft]] id:2"));
+    assert!(work.contains("[[Review]] id:5"));
+    assert!(std::fs::read_to_string(out_dir.join("home.md")).unwrap().contains("[[Paint fence]] id:3"));
+    assert!(std::fs::read_to_string(out_dir.join("inbox.md")).unwrap().contains("[[Call mom]] id:4"));
+}
+
+/// `--split-by-project` fails without writing anything when a project would share inbox.md
+#[test]
+fn fmt_split_by_project_rejects_inbox_collision() {
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().join("split");
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--split-by-project").arg(&out_dir)
+        .write_stdin("- [ ] [[Triage]] id:1 +inbox
+- [ ] [[Call mom]] id:2
+")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'inbox.md'"));
+    assert!(!out_dir.exists());
+}
+
+/// `--max-line-length` moves attributes onto `↳` lines that `og fmt` reads back unchanged
+#[test]
+fn fmt_max_line_length_round_trips() {
+    let input = "- [ ] (A) [[Write the quarterly report]] id:1 due:2024-05-01 +work @office created:2024-01-01\n";
+    let plain = Command::cargo_bin("og").unwrap().arg("fmt").write_stdin(input).output().unwrap();
+    let wrapped = Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--max-line-length").arg("50")
+        .write_stdin(input)
+        .output()
+        .unwrap();
+    let wrapped = String::from_utf8(wrapped.stdout).unwrap();
+    assert!(wrapped.contains("[[Write the quarterly report]]\n  ↳ id:1"), "{}", wrapped);
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .write_stdin(wrapped)
+        .assert()
+        .success()
+        .stdout(String::from_utf8(plain.stdout).unwrap());
+}
+
+/// `--add-week-of-year` groups tasks under ISO week comments that `og fmt` ignores on the next run
+#[test]
+fn fmt_add_week_of_year_groups_by_due_week() {
+    let input = "- [ ] [[Later]] id:1 due:2024-06-19 created:2024-01-01\n- [ ] [[Someday]] id:2 created:2024-01-01\n- [ ] [[Soon]] id:3 due:2024-06-14 created:2024-01-01\n";
+    let annotated = Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--add-week-of-year")
+        .write_stdin(input)
+        .output()
+        .unwrap();
+    let annotated = String::from_utf8(annotated.stdout).unwrap();
+    let week_24 = annotated.find("<!-- W24 2024-06-10 – 2024-06-16 -->\n- [ ] (N) [[Soon]]").expect(&annotated);
+    let week_25 = annotated.find("<!-- W25 2024-06-17 – 2024-06-23 -->\n- [ ] (N) [[Later]]").expect(&annotated);
+    let no_due = annotated.find("<!-- No Due Date -->\n- [ ] (N) [[Someday]]").expect(&annotated);
+    assert!(week_24 < week_25 && week_25 < no_due, "{}", annotated);
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--add-week-of-year")
+        .write_stdin(annotated.clone())
+        .assert()
+        .success()
+        .stdout(annotated);
+}
+
+/// Tasks written with `* [ ]` bullets are read and rewritten with `- `
+#[test]
+fn fmt_normalizes_asterisk_bullets() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .write_stdin("* [ ] [[Parent]] id:1 created:2024-01-01\n    * [x] [[Child]] id:2 created:2024-01-01\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- [ ] (N) [[Parent]] id:1"))
+        .stdout(predicate::str::contains("    - [x] (N) [[Child]] id:2"))
+        .stdout(predicate::str::contains("* [").not());
+}
+
+/// Link bullets such as `* [Docs](url)` are ordinary Markdown, not malformed tasks
+#[test]
+fn fmt_ignores_link_bullets() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .write_stdin("* [Docs](http://example.com)\n- [ ] [[Task]] id:1 created:2024-01-01\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- [ ] (N) [[Task]] id:1"));
+}
+
+/// `--max-depth 1` shows children but not grandchildren
+#[test]
+fn fmt_max_depth_hides_grandchildren() {
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--max-depth").arg("1")
+        .write_stdin("- [ ] [[Root]] id:1\n    - [ ] [[Child]] id:2\n        - [ ] [[Grandchild]] id:3\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("    - [ ] (N) [[Child]] id:2"))
+        .stdout(predicate::str::contains("(… 1 more)"))
+        .stdout(predicate::str::contains("Grandchild").not());
+}
+
+/// `--in-place --preserve-header-lines` keeps front matter and a title and ends the file with a newline
+#[test]
+fn fmt_in_place_preserves_header_and_trailing_newline() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tasks.md");
+    std::fs::write(&path, "---\ntitle: x\n---\n# Tasks\n- [ ] [[A]] id:1 created:2024-01-01").unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--in-place")
+        .arg("--preserve-header-lines")
+        .arg(&path)
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("---\ntitle: x\n---\n# Tasks\n- [ ] (N) [[A]] id:1"), "{}", content);
+    assert!(content.ends_with('\n') && !content.ends_with("\n\n"), "{:?}", content);
+}
+
+/// `--remove-ids` drops every id, and formatting the output again assigns fresh ones
+#[test]
+fn fmt_remove_ids_reassigns_on_reparse() {
+    let stripped = Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .arg("--remove-ids")
+        .write_stdin("- [ ] [[A]] id:12 created:2024-01-01\n    - [ ] [[B]] id:30 depends:12 created:2024-01-01\n- [ ] [[C]] id:7 created:2024-01-01\n")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stripped = String::from_utf8(stripped).unwrap();
+    assert!(!stripped.contains("id:") && !stripped.contains("depends:"), "{}", stripped);
+
+    Command::cargo_bin("og").unwrap()
+        .arg("fmt")
+        .write_stdin(stripped)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[[A]] id:1"))
+        .stdout(predicate::str::contains("    - [ ] (N) [[B]] id:2"))
+        .stdout(predicate::str::contains("[[C]] id:3"));
+}
@@ -0,0 +1,67 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::NamedTempFile;
+
+const INPUT: &str = "\
+- [ ] [[Parent]] id:10423 created:2024-01-01
+    - [ ] [[Child]] id:20 created:2024-01-01
+- [ ] [[Blocked]] id:777 created:2024-01-01 depends:20,10423
+";
+
+/// `og id --compact` renumbers depth-first and keeps depends pointing at the same tasks
+#[test]
+fn id_compact_rewrites_depends() {
+    Command::cargo_bin("og").unwrap()
+        .arg("id")
+        .arg("--compact")
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[[Parent]] id:1 "))
+        .stdout(predicate::str::contains("[[Child]] id:2 "))
+        .stdout(predicate::str::contains("[[Blocked]] id:3 "))
+        .stdout(predicate::str::contains("depends:2,1"));
+}
+
+/// A named input file is rewritten in place in its own format; --to json needs -o
+#[test]
+fn id_compact_in_place_keeps_input_format() {
+    let file = NamedTempFile::new().unwrap();
+    fs::write(file.path(), INPUT).unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("id")
+        .arg("--compact")
+        .arg("--to").arg("json")
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("use -o"));
+    assert_eq!(fs::read_to_string(file.path()).unwrap(), INPUT);
+
+    let json_file = NamedTempFile::new().unwrap();
+    Command::cargo_bin("og").unwrap()
+        .arg("id")
+        .arg("--compact")
+        .arg("--to").arg("json")
+        .arg("-o").arg(json_file.path())
+        .arg(file.path())
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(file.path()).unwrap(), INPUT);
+    let converted = fs::read_to_string(json_file.path()).unwrap();
+    assert!(converted.contains("\"id\":3"));
+    assert!(converted.contains("\"depends\":[2,1]"));
+    assert!(!converted.contains("10423"));
+
+    Command::cargo_bin("og").unwrap()
+        .arg("id")
+        .arg("--compact")
+        .arg(file.path())
+        .assert()
+        .success();
+    let rewritten = fs::read_to_string(file.path()).unwrap();
+    assert!(rewritten.contains("[[Blocked]] id:3 "));
+    assert!(!rewritten.contains("10423"));
+}
@@ -0,0 +1,52 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+
+const ISSUES: &str = r#"[
+    {"number": 7, "title": "Crash on start", "body": "Stack trace attached", "created_at": "2024-05-01T09:00:00Z",
+     "milestone": {"due_on": "2024-06-30T07:00:00Z"}, "labels": [{"name": "bug"}], "assignees": [{"login": "octocat"}]},
+    {"number": 8, "title": "Bump deps", "body": null, "created_at": "2024-05-02T09:00:00Z", "milestone": null,
+     "labels": [], "assignees": [], "pull_request": {"url": "https://api.github.com/repos/acme/app/pulls/8"}}
+]"#;
+
+/// `og import --from-github-issues` adds open issues (not pull requests) once, sending $GITHUB_TOKEN
+#[test]
+fn import_github_issues_from_stubbed_api() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/repos/acme/app/issues")
+        .match_query(mockito::Matcher::AllOf(vec![
+            mockito::Matcher::UrlEncoded("state".into(), "open".into()),
+            mockito::Matcher::UrlEncoded("page".into(), "1".into()),
+        ]))
+        .match_header("authorization", "Bearer secret")
+        .with_header("content-type", "application/json")
+        .with_body(ISSUES)
+        .expect(2)
+        .create();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tasks.jsonl");
+    let import = || {
+        Command::cargo_bin("og").unwrap()
+            .arg("import")
+            .arg("--target-json").arg(&path)
+            .arg("--from-github-issues").arg("acme/app")
+            .env("OG_GITHUB_API_URL", server.url())
+            .env("GITHUB_TOKEN", "secret")
+            .assert()
+            .success()
+    };
+
+    import().stderr(predicate::str::contains("Imported 1 new issue(s) from acme/app"));
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.contains("\"name\":\"Crash on start\""), "{}", content);
+    assert!(content.contains("\"due\":\"2024-06-30\""), "{}", content);
+    assert!(content.contains("\"github_issue_number\":7"), "{}", content);
+    assert!(!content.contains("Bump deps"), "{}", content);
+
+    // A second run must not add the same issue again
+    import().stderr(predicate::str::contains("Imported 0 new issue(s)"));
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+    mock.assert();
+}
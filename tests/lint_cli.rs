@@ -0,0 +1,46 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::NamedTempFile;
+use std::io::Write;
+
+/// `og lint` reports a duplicate id with its line number and exits non-zero
+#[test]
+fn lint_reports_duplicate_id() {
+    let mut md_file = NamedTempFile::new().unwrap();
+    writeln!(md_file, "- [ ] [[Task A]] id:1").unwrap();
+    writeln!(md_file, "- [ ] [[Task B]] id:1").unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("lint")
+        .arg(md_file.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("line 2: duplicate id:1 (first used on line 1)"));
+
+    // The file must be left untouched
+    let contents = std::fs::read_to_string(md_file.path()).unwrap();
+    assert_eq!(contents, "- [ ] [[Task A]] id:1\n- [ ] [[Task B]] id:1\n");
+}
+
+/// `og lint --format json` emits machine-readable issues
+#[test]
+fn lint_json_format() {
+    Command::cargo_bin("og").unwrap()
+        .arg("lint")
+        .arg("--format").arg("json")
+        .write_stdin("- [ ] [[Task A]] id:1\n- [ ] [[Task B]] id:1\n")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("\"line\":2"));
+}
+
+/// A clean file passes
+#[test]
+fn lint_clean_file_succeeds() {
+    Command::cargo_bin("og").unwrap()
+        .arg("lint")
+        .write_stdin("- [ ] [[Task A]] id:1\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+}
@@ -0,0 +1,91 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+/// `og list` prints one line per task, subtasks included, in document order
+#[test]
+fn list_prints_tasks_in_document_order() {
+    Command::cargo_bin("og").unwrap()
+        .arg("list")
+        .write_stdin("- [ ] (A) [[Ship]] id:1 depends:2\n    - [ ] [[Tag release]] id:3\n- [ ] [[Build]] id:2\n")
+        .assert()
+        .success()
+        .stdout("[1] (A) Ship\n[3] (N) Tag release\n[2] (N) Build\n");
+}
+
+/// `--topological-order` puts dependencies first and shows them as an `(after: ...)` suffix
+#[test]
+fn list_topological_order() {
+    Command::cargo_bin("og").unwrap()
+        .arg("list")
+        .arg("--topological-order")
+        .write_stdin("- [ ] [[Deploy]] id:1 depends:2,3\n- [ ] [[Test]] id:2 depends:3\n- [ ] [[Build]] id:3\n")
+        .assert()
+        .success()
+        .stdout("[3] (N) Build\n[2] (N) Test  (after: 3)\n[1] (N) Deploy  (after: 2, 3)\n");
+}
+
+/// A dependency cycle is reported with the ids involved
+#[test]
+fn list_topological_order_rejects_cycle() {
+    Command::cargo_bin("og").unwrap()
+        .arg("list")
+        .arg("--topological-order")
+        .write_stdin("- [ ] [[A]] id:1 depends:2\n- [ ] [[B]] id:2 depends:1\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("dependency cycle among tasks id:1, id:2"));
+}
+
+/// `--format compact` prints fixed-width columns with `(none)` for a missing due date
+#[test]
+fn list_compact_format() {
+    Command::cargo_bin("og").unwrap()
+        .arg("list")
+        .arg("--format").arg("compact")
+        .arg("--color").arg("never")
+        .write_stdin("- [>] (B) [[Ship]] id:12 due:2024-06-01\n    - [ ] [[Tag release]] id:3\n")
+        .assert()
+        .success()
+        .stdout(format!(
+            "[00012] [B] [doing     ] [Ship{}] [2024-06-01]\n[00003] [N] [open      ] [Tag release{}] [(none)    ]\n",
+            " ".repeat(36),
+            " ".repeat(29)
+        ));
+}
+
+/// `--tag tag:KEY:VALUE` lists only tasks with that key-value tag; a bare name matches plain tags
+#[test]
+fn list_filters_by_tag_query() {
+    let input = "- [ ] [[Run]] id:1 #area:health #daily\n    - [ ] [[Stretch]] id:2 #area:health\n- [ ] [[Report]] id:3 #area:work\n";
+    Command::cargo_bin("og").unwrap()
+        .arg("list")
+        .arg("--tag").arg("tag:area:health")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("[1] (N) Run\n[2] (N) Stretch\n");
+
+    Command::cargo_bin("og").unwrap()
+        .arg("list")
+        .arg("--tag").arg("#daily")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("[1] (N) Run\n");
+}
+
+/// `--show-notes` prints each note under its task as `  > ` lines, keeping the note's own newlines
+#[test]
+fn list_show_notes() {
+    Command::cargo_bin("og").unwrap()
+        .arg("list")
+        .arg("--show-notes")
+        .arg("--from").arg("json")
+        .write_stdin(concat!(
+            "{\"name\":\"Call\",\"status\":\"open\",\"priority\":\"N\",\"id\":1,\"created\":\"2024-01-01\",\"display_order\":1,\"notes\":\"first line\\nsecond line\"}\n",
+            "{\"name\":\"Plain\",\"status\":\"open\",\"priority\":\"N\",\"id\":2,\"created\":\"2024-01-01\",\"display_order\":2}\n",
+        ))
+        .assert()
+        .success()
+        .stdout("[1] (N) Call\n  > first line\n  > second line\n[2] (N) Plain\n");
+}
@@ -0,0 +1,115 @@
+use assert_cmd::Command;
+use std::collections::HashSet;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+fn markdown_file(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    write!(file, "{}", content).unwrap();
+    file
+}
+
+/// `og merge` renumbers overlapping ids so every merged task has a unique id
+#[test]
+fn merge_conflicting_files_yields_unique_ids() {
+    let file_a = markdown_file("- [ ] [[Alpha]] id:1\n    - [ ] [[Alpha child]] id:2\n- [ ] [[Shared]] id:3\n");
+    let file_b = markdown_file("- [ ] [[Shared]] id:1\n- [ ] [[Beta]] id:2\n");
+
+    let output = Command::cargo_bin("og").unwrap()
+        .arg("merge")
+        .arg("--to").arg("json")
+        .arg(file_a.path())
+        .arg(file_b.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let tasks: Vec<serde_json::Value> = stdout.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(tasks.len(), 4);
+
+    let mut ids = HashSet::new();
+    for task in &tasks {
+        assert!(ids.insert(task["id"].as_i64().unwrap()));
+        for subtask in task["subtasks"].as_array().into_iter().flatten() {
+            assert!(ids.insert(subtask["id"].as_i64().unwrap()));
+        }
+    }
+    assert_eq!(ids.len(), 5);
+
+    // Duplicate names across files are preserved
+    assert_eq!(tasks.iter().filter(|t| t["name"] == "Shared").count(), 2);
+}
+
+/// `og merge --dedup` collapses identical tasks
+#[test]
+fn merge_dedup_collapses_identical_tasks() {
+    let file_a = markdown_file("- [ ] [[Shared]] id:1 created:2024-01-01\n");
+    let file_b = markdown_file("- [ ] [[Shared]] id:1 created:2024-02-01\n");
+
+    let output = Command::cargo_bin("og").unwrap()
+        .arg("merge")
+        .arg("--dedup")
+        .arg(file_a.path())
+        .arg(file_b.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("created:2024-01-01"));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("duplicate"));
+}
+
+/// `og merge --dedupe` keeps a single survivor of an exact duplicate and reports the removal
+#[test]
+fn merge_dedupe_keeps_single_survivor() {
+    let file_a = markdown_file("- [ ] [[Write report]] id:1 due:2024-03-01 +work\n- [ ] [[Other]] id:2\n");
+    let file_b = markdown_file("- [ ] [[Write report]] id:1 due:2024-03-01 +work\n");
+
+    let output = Command::cargo_bin("og").unwrap()
+        .arg("merge")
+        .arg("--dedupe")
+        .arg("--to").arg("json")
+        .arg(file_a.path())
+        .arg(file_b.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Removed 1 duplicate task(s)"));
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let tasks: Vec<serde_json::Value> = stdout.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks.iter().filter(|t| t["name"] == "Write report").count(), 1);
+    assert_eq!(tasks[0]["id"], 1);
+}
+
+/// `--dedup` and `--dedupe` select different duplicate keys and cannot be combined
+#[test]
+fn merge_dedup_and_dedupe_are_exclusive() {
+    let file_a = markdown_file("- [ ] (A) [[Shared]] id:1\n");
+    let file_b = markdown_file("- [ ] (B) [[Shared]] id:1\n");
+
+    Command::cargo_bin("og").unwrap()
+        .arg("merge")
+        .arg("--dedup")
+        .arg("--dedupe")
+        .arg(file_a.path())
+        .arg(file_b.path())
+        .assert()
+        .failure();
+
+    // The priorities differ, so --dedup keeps both and --dedupe keeps one
+    for (flag, count) in [("--dedup", 2), ("--dedupe", 1)] {
+        let output = Command::cargo_bin("og").unwrap()
+            .arg("merge")
+            .arg(flag)
+            .arg(file_a.path())
+            .arg(file_b.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), count, "{}", flag);
+    }
+}
@@ -0,0 +1,121 @@
+//! Property test: formatting any valid task tree to Markdown and parsing it back gives the same tasks.
+//!
+//! The generator only produces tasks already in the form the parser returns (documented normalizations):
+//! - names have no `[`, `]`, `<`, `\` or line breaks and no surrounding whitespace (`]]` ends the name)
+//! - empty contexts, tags, tags_kv, depends and subtask lists are `None`, and labels within a task are unique
+//! - ids and display orders are numbered depth-first from 1, as the parser assigns display orders by line
+//! - `extra` is `None` (string extra values that read as numbers or booleans come back as those types)
+
+use chrono::NaiveDate;
+use og::markdown_formatter::format_tasks_to_markdown_document;
+use og::markdown_parser::parse_markdown_document_to_tasks;
+use og::task_model::{Frequency, RepeatInfo, Task};
+use proptest::prelude::*;
+use std::collections::HashMap;
+
+const STATUSES: [&str; 8] = ["open", "pending", "doing", "waiting", "done", "cancelled", "skipped", "unknown"];
+const FREQUENCIES: [Frequency; 5] = [Frequency::Daily, Frequency::Weekly, Frequency::Monthly, Frequency::Weekdays, Frequency::LastDayOfMonth];
+
+fn date() -> impl Strategy<Value = NaiveDate> {
+    (2000i32..2100, 1u32..=365).prop_map(|(year, ordinal)| NaiveDate::from_yo_opt(year, ordinal).unwrap())
+}
+
+fn labels() -> impl Strategy<Value = Option<Vec<String>>> {
+    prop::collection::btree_set("[a-z0-9_]{1,8}", 0..4).prop_map(|set| if set.is_empty() { None } else { Some(set.into_iter().collect()) })
+}
+
+// 子タスクを持たない 1 タスク分 (id と display_order は後で振る)
+fn task_fields() -> impl Strategy<Value = Task> {
+    let core = (
+        "[A-Za-z0-9]([A-Za-z0-9 ,.!?:#@+'\"()/-]{0,30}[A-Za-z0-9.!?)])?",
+        prop::sample::select(STATUSES.to_vec()),
+        prop_oneof![Just("N".to_string()), "[A-Z]{1,2}"],
+        date(),
+        prop::option::of(date()),
+        prop::option::of(date()),
+        prop::option::of(date()),
+    );
+    let attributes = (
+        prop::option::of("[a-z0-9_]{1,8}"),
+        labels(),
+        labels(),
+        prop::collection::btree_map("[a-z][a-z0-9_]{0,6}", "[a-z0-9-]{1,6}", 0..3),
+        prop::option::of("[A-Za-z0-9 ,.!?:#@+'\"()/\\\\\\n\\r-]{0,40}"),
+        prop::option::of(prop::sample::select(FREQUENCIES.to_vec())),
+        prop::option::of(prop::collection::vec(1i64..100, 1..4)),
+        prop::option::of(0u8..=100),
+        prop::option::of(1u32..6000),
+    );
+    (core, attributes).prop_map(
+        |((name, status, priority, created, due, updated, completed), (project, contexts, tags, tags_kv, notes, frequency, depends, progress, estimate_minutes))| Task {
+            name,
+            status: status.to_string(),
+            priority,
+            id: 0,
+            created,
+            display_order: 0,
+            due,
+            updated,
+            completed,
+            project,
+            contexts,
+            notes,
+            tags,
+            tags_kv: if tags_kv.is_empty() { None } else { Some(tags_kv.into_iter().collect::<HashMap<_, _>>()) },
+            subtasks: None,
+            extra: None,
+            repeat: frequency.map(|frequency| RepeatInfo { frequency: Some(frequency) }),
+            depends,
+            progress,
+            estimate_minutes,
+            unknown_fields: Default::default(),
+        },
+    )
+}
+
+fn task_tree() -> impl Strategy<Value = Task> {
+    task_fields().prop_recursive(3, 24, 4, |inner| {
+        (task_fields(), prop::collection::vec(inner, 1..4)).prop_map(|(mut task, subtasks)| {
+            task.subtasks = Some(subtasks);
+            task
+        })
+    })
+}
+
+// パーサーと同じく、行の出現順 (深さ優先) に id と display_order を振る
+fn number_depth_first(tasks: &mut [Task], next: &mut i64) {
+    for task in tasks {
+        task.id = *next;
+        task.display_order = *next;
+        *next += 1;
+        if let Some(subtasks) = &mut task.subtasks {
+            number_depth_first(subtasks, next);
+        }
+    }
+}
+
+fn task_list() -> impl Strategy<Value = Vec<Task>> {
+    prop::collection::vec(task_tree(), 1..6).prop_map(|mut tasks| {
+        number_depth_first(&mut tasks, &mut 1);
+        tasks
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    /// `parse(format(tasks)) == tasks` for any task tree in normalized form
+    #[test]
+    fn format_then_parse_round_trips(tasks in task_list()) {
+        let markdown = format_tasks_to_markdown_document(&tasks);
+        let default_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let parsed = parse_markdown_document_to_tasks(&markdown, default_date)
+            .map_err(|e| TestCaseError::fail(format!("{}\n{}", e, markdown)))?;
+        prop_assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&tasks).unwrap(),
+            "markdown:\n{}",
+            markdown
+        );
+    }
+}
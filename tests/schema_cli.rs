@@ -0,0 +1,43 @@
+use assert_cmd::Command;
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+
+fn print_schema() -> Value {
+    let output = Command::cargo_bin("og").unwrap().arg("print-schema").output().unwrap();
+    assert!(output.status.success());
+    serde_json::from_slice(&output.stdout).expect("schema should be valid JSON")
+}
+
+/// The emitted schema validates a known-good task, including nested subtasks and repeat
+#[test]
+fn print_schema_validates_known_good_task() {
+    let schema = JSONSchema::compile(&print_schema()).expect("schema should compile");
+    let task = json!({
+        "name": "Write report",
+        "status": "open",
+        "priority": "A",
+        "id": 1,
+        "created": "2024-01-01",
+        "display_order": 1,
+        "due": null,
+        "tags": ["work"],
+        "repeat": { "frequency": "weekly" },
+        "subtasks": [{
+            "name": "Outline",
+            "status": "done",
+            "priority": "N",
+            "id": 2,
+            "created": "2024-01-01",
+            "display_order": 1,
+            "completed": "2024-01-02"
+        }]
+    });
+    assert!(schema.is_valid(&task));
+}
+
+/// Tasks missing required keys are rejected
+#[test]
+fn print_schema_rejects_task_without_required_keys() {
+    let schema = JSONSchema::compile(&print_schema()).expect("schema should compile");
+    assert!(!schema.is_valid(&json!({ "name": "No id", "status": "open" })));
+}
@@ -25,9 +25,14 @@ mod spec_compliance_tests {
             contexts: None,
             notes: None,
             tags: None,
+            tags_kv: None,
             subtasks: None,
             extra: None,
             repeat: None,
+            depends: None,
+            progress: None,
+            estimate_minutes: None,
+            unknown_fields: Default::default(),
         }
     }
 
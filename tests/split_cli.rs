@@ -0,0 +1,86 @@
+use assert_cmd::Command;
+use std::fs;
+use tempfile::tempdir;
+
+const INPUT: &str = "- [ ] [[Paint fence]] id:3 +home\n    - [ ] [[Buy paint]] id:4\n- [ ] [[Write report]] id:7 +work #urgent #q3\n- [ ] [[Call mom]] id:9 #urgent\n";
+
+/// `og split --by project` writes one file per project, creating the output directory
+#[test]
+fn split_by_project_creates_out_dir_and_files() {
+    let dir = tempdir().unwrap();
+    let out_dir = dir.path().join("nested").join("projects");
+
+    Command::cargo_bin("og").unwrap()
+        .arg("split")
+        .arg("--by").arg("project")
+        .arg("--out-dir").arg(&out_dir)
+        .write_stdin(INPUT)
+        .assert()
+        .success();
+
+    let home = fs::read_to_string(out_dir.join("home.md")).unwrap();
+    assert!(home.contains("[[Paint fence]] id:1"));
+    // Subtasks travel with their parent and are renumbered within the file
+    assert!(home.contains("    - [ ] (N) [[Buy paint]] id:2"));
+
+    let work = fs::read_to_string(out_dir.join("work.md")).unwrap();
+    assert!(work.contains("[[Write report]] id:1"));
+    assert!(!work.contains("Paint fence"));
+
+    let none = fs::read_to_string(out_dir.join("_none.md")).unwrap();
+    assert!(none.contains("[[Call mom]] id:1"));
+}
+
+/// `og split --by tag` puts multi-tag tasks in every matching file
+#[test]
+fn split_by_tag_duplicates_multi_tag_tasks() {
+    let dir = tempdir().unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("split")
+        .arg("--by").arg("tag")
+        .arg("--out-dir").arg(dir.path())
+        .write_stdin(INPUT)
+        .assert()
+        .success();
+
+    let urgent = fs::read_to_string(dir.path().join("urgent.md")).unwrap();
+    assert!(urgent.contains("[[Write report]] id:1"));
+    assert!(urgent.contains("[[Call mom]] id:2"));
+    let q3 = fs::read_to_string(dir.path().join("q3.md")).unwrap();
+    assert!(q3.contains("[[Write report]] id:1"));
+    assert!(fs::read_to_string(dir.path().join("_none.md")).unwrap().contains("[[Paint fence]]"));
+}
+
+/// `--name-template` names the files, replacing `/` in a project name with `_`
+#[test]
+fn split_name_template_sanitizes_project() {
+    let dir = tempdir().unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("split")
+        .arg("--output-dir").arg(dir.path())
+        .arg("--name-template").arg("{project}-tasks.md")
+        .write_stdin("- [ ] [[Nested]] id:1 +a/b\n- [ ] [[Loose]] id:2\n")
+        .assert()
+        .success();
+
+    assert!(fs::read_to_string(dir.path().join("a_b-tasks.md")).unwrap().contains("[[Nested]] id:1"));
+    assert!(fs::read_to_string(dir.path().join("_none-tasks.md")).unwrap().contains("[[Loose]] id:1"));
+}
+
+/// Groups that sanitize to the same file name are an error, and nothing is written
+#[test]
+fn split_name_collision_fails() {
+    let dir = tempdir().unwrap();
+    let out_dir = dir.path().join("out");
+
+    Command::cargo_bin("og").unwrap()
+        .arg("split")
+        .arg("--out-dir").arg(&out_dir)
+        .write_stdin("- [ ] [[One]] id:1 +a/b\n- [ ] [[Two]] id:2 +a_b\n")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("would both be written to 'a_b.md'"));
+    assert!(!out_dir.exists());
+}
@@ -0,0 +1,60 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+
+const TASKS: &str = "\
+{\"name\":\"A\",\"status\":\"open\",\"priority\":\"N\",\"id\":1,\"created\":\"2024-01-01\",\"display_order\":1}
+{\"name\":\"B\",\"status\":\"open\",\"priority\":\"N\",\"id\":2,\"created\":\"2024-01-01\",\"display_order\":5}
+";
+
+/// Gaps are reported, and `--compact-display-order` renumbers the file in place
+#[test]
+fn validate_reports_and_compacts_display_order_gaps() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tasks.jsonl");
+    fs::write(&path, TASKS).unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("validate")
+        .arg("--target-json").arg(&path)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("display_order gap (expected 2, found 5)"));
+
+    Command::cargo_bin("og").unwrap()
+        .arg("validate")
+        .arg("--target-json").arg(&path)
+        .arg("--compact-display-order")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    let compacted = fs::read_to_string(&path).unwrap();
+    assert!(compacted.contains("\"display_order\":2"));
+    assert!(!compacted.contains("\"display_order\":5"));
+}
+
+/// A target matching `--ignore-pattern` is skipped, unless a `!` pattern keeps it
+#[test]
+fn validate_ignore_pattern_skips_matching_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tasks.jsonl.bak");
+    fs::write(&path, TASKS).unwrap();
+
+    Command::cargo_bin("og").unwrap()
+        .arg("validate")
+        .arg("--target-json").arg(&path)
+        .arg("--ignore-pattern").arg("*.bak")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("Skipping"));
+
+    Command::cargo_bin("og").unwrap()
+        .arg("validate")
+        .arg("--target-json").arg(&path)
+        .arg("--ignore-pattern").arg("*.bak")
+        .arg("--ignore-pattern").arg("!tasks.*")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("display_order gap"));
+}